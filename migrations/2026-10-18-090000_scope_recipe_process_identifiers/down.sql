@@ -0,0 +1,11 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE recipe_process_relations
+DROP CONSTRAINT unique_recipe_process_relation;
+
+ALTER TABLE recipe_process_flows
+DROP CONSTRAINT recipe_process_flows_process_identifier_key,
+ADD CONSTRAINT recipe_process_flows_identifier_key UNIQUE (identifier);
+
+ALTER TABLE recipe_processes
+DROP CONSTRAINT recipe_processes_recipe_identifier_key,
+ADD CONSTRAINT recipe_processes_identifier_key UNIQUE (identifier);
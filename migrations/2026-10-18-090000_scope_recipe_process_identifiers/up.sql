@@ -0,0 +1,12 @@
+-- Process and flow identifiers come from the templates, so they only need to be
+-- unique within the recipe (or process) they were instantiated in.
+ALTER TABLE recipe_processes
+DROP CONSTRAINT recipe_processes_identifier_key,
+ADD CONSTRAINT recipe_processes_recipe_identifier_key UNIQUE (recipe_id, identifier);
+
+ALTER TABLE recipe_process_flows
+DROP CONSTRAINT recipe_process_flows_identifier_key,
+ADD CONSTRAINT recipe_process_flows_process_identifier_key UNIQUE (recipe_process_id, identifier);
+
+ALTER TABLE recipe_process_relations
+ADD CONSTRAINT unique_recipe_process_relation UNIQUE (recipe_process_id, output_of);
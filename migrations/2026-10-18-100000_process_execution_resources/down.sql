@@ -0,0 +1,13 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE economic_resources
+DROP COLUMN custodian_id;
+
+-- Only numeric lots fit the integer columns, lot codes like ACME-20261018-0001 are dropped
+ALTER TABLE process_executions
+DROP COLUMN batch_id,
+DROP COLUMN to_economic_resource_id,
+DROP COLUMN economic_resource_id,
+ALTER COLUMN to_resource_lot_number TYPE INTEGER
+    USING CASE WHEN to_resource_lot_number ~ '^-?[0-9]{1,9}$' THEN to_resource_lot_number::INTEGER END,
+ALTER COLUMN resource_lot_number TYPE INTEGER
+    USING CASE WHEN resource_lot_number ~ '^-?[0-9]{1,9}$' THEN resource_lot_number::INTEGER END;
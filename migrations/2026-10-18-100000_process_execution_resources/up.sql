@@ -0,0 +1,11 @@
+-- Lots are stored as text on economic_resources, executions record the same value
+ALTER TABLE process_executions
+ALTER COLUMN resource_lot_number TYPE TEXT,
+ALTER COLUMN to_resource_lot_number TYPE TEXT,
+ADD COLUMN economic_resource_id UUID REFERENCES economic_resources(id),
+ADD COLUMN to_economic_resource_id UUID REFERENCES economic_resources(id),
+ADD COLUMN batch_id UUID;
+
+-- Agent holding the resource when it differs from the resource specification agent
+ALTER TABLE economic_resources
+ADD COLUMN custodian_id UUID REFERENCES agents(id);
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE counters
+    DROP COLUMN lot_period,
+    DROP COLUMN lot_prefix,
+    DROP COLUMN lot_format,
+    DROP CONSTRAINT counters_agent_id_key;
@@ -0,0 +1,22 @@
+-- One counter row per agent, keeping the highest values handed out so far
+UPDATE counters
+SET lot_code = latest.lot_code,
+    reference_number = latest.reference_number
+FROM (
+    SELECT agent_id, MAX(lot_code) AS lot_code, MAX(reference_number) AS reference_number
+    FROM counters
+    GROUP BY agent_id
+) AS latest
+WHERE counters.agent_id = latest.agent_id;
+
+DELETE FROM counters
+USING counters AS duplicate
+WHERE counters.agent_id = duplicate.agent_id
+  AND counters.id < duplicate.id;
+
+ALTER TABLE counters
+    ADD CONSTRAINT counters_agent_id_key UNIQUE (agent_id),
+    ADD COLUMN lot_format TEXT NOT NULL DEFAULT '{AGENT}-{YYYYMMDD}-{SEQ:4}',
+    ADD COLUMN lot_prefix TEXT,
+    -- Rendered lot format without the sequence, the sequence restarts when it changes
+    ADD COLUMN lot_period TEXT;
@@ -0,0 +1,25 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM process_execution_custom_values
+WHERE field_id IN (SELECT id FROM recipe_process_flow_data_fields WHERE field_class = 'unitOfMeasure');
+DELETE FROM recipe_process_flow_data_fields WHERE field_class = 'unitOfMeasure';
+DELETE FROM recipe_flow_template_data_fields WHERE field_class = 'unitOfMeasure';
+
+ALTER TYPE field_class_enum RENAME TO field_class_enum_old;
+CREATE TYPE field_class_enum AS ENUM (
+    'resourceSpecification',
+    'economicResource',
+    'quantity',
+    'hasPointInTime',
+    'agent',
+    'location',
+    'note',
+    'trackingIdentifier',
+    'custom',
+    'referenceDocumentNumber',
+    'referenceDocumentType'
+);
+ALTER TABLE recipe_flow_template_data_fields
+    ALTER COLUMN field_class TYPE field_class_enum USING field_class::text::field_class_enum;
+ALTER TABLE recipe_process_flow_data_fields
+    ALTER COLUMN field_class TYPE field_class_enum USING field_class::text::field_class_enum;
+DROP TYPE field_class_enum_old;
@@ -0,0 +1,2 @@
+-- Unit of measure of a quantity, a Key Data Element of FDA Critical Tracking Events
+ALTER TYPE field_class_enum ADD VALUE IF NOT EXISTS 'unitOfMeasure';
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX process_executions_epcis_event_id_idx;
+ALTER TABLE process_executions DROP COLUMN epcis_event_id;
@@ -0,0 +1,3 @@
+-- EPCIS eventID of imported executions, so a document can be imported again safely
+ALTER TABLE process_executions ADD COLUMN epcis_event_id TEXT;
+CREATE INDEX process_executions_epcis_event_id_idx ON process_executions (epcis_event_id);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX recipe_templates_overriden_by_idx;
+ALTER TABLE recipe_templates DROP CONSTRAINT recipe_templates_identifier_version_key;
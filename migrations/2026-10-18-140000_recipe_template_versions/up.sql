@@ -0,0 +1,20 @@
+-- Templates sharing an identifier within a map before versioning become
+-- consecutive versions of it, each overridden by the next
+WITH numbered AS (
+    SELECT
+        id,
+        ROW_NUMBER() OVER lineage AS version,
+        LEAD(id) OVER lineage AS next_id
+    FROM recipe_templates
+    WINDOW lineage AS (PARTITION BY map_template_id, identifier ORDER BY version, id)
+)
+UPDATE recipe_templates
+SET version = numbered.version,
+    overriden_by = COALESCE(recipe_templates.overriden_by, numbered.next_id)
+FROM numbered
+WHERE recipe_templates.id = numbered.id;
+
+-- Versions of a template share its identifier within the map
+ALTER TABLE recipe_templates
+ADD CONSTRAINT recipe_templates_identifier_version_key UNIQUE (map_template_id, identifier, version);
+CREATE INDEX recipe_templates_overriden_by_idx ON recipe_templates (overriden_by);
@@ -0,0 +1,13 @@
+-- This file should undo anything in `up.sql`
+-- Deleted templates give up their identifier, so they don't clash with the templates that reused it
+DROP INDEX recipe_templates_identifier_version_key;
+UPDATE recipe_templates
+SET identifier = identifier || '-deleted-' || id
+WHERE deleted_at IS NOT NULL;
+ALTER TABLE recipe_templates
+ADD CONSTRAINT recipe_templates_identifier_version_key UNIQUE (map_template_id, identifier, version);
+
+ALTER TABLE recipe_flow_template_data_fields DROP COLUMN deleted_at;
+ALTER TABLE recipe_flow_templates DROP COLUMN deleted_at;
+ALTER TABLE recipe_templates DROP COLUMN deleted_at;
+ALTER TABLE map_templates DROP COLUMN deleted_at;
@@ -0,0 +1,10 @@
+ALTER TABLE map_templates ADD COLUMN deleted_at TIMESTAMP;
+ALTER TABLE recipe_templates ADD COLUMN deleted_at TIMESTAMP;
+ALTER TABLE recipe_flow_templates ADD COLUMN deleted_at TIMESTAMP;
+ALTER TABLE recipe_flow_template_data_fields ADD COLUMN deleted_at TIMESTAMP;
+
+-- A deleted template frees its identifier
+ALTER TABLE recipe_templates DROP CONSTRAINT recipe_templates_identifier_version_key;
+CREATE UNIQUE INDEX recipe_templates_identifier_version_key
+ON recipe_templates (map_template_id, identifier, version)
+WHERE deleted_at IS NULL;
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE recipe_process_flow_data_fields DROP COLUMN option_set_id;
+ALTER TABLE recipe_flow_template_data_fields DROP COLUMN option_set_id;
+DROP TABLE IF EXISTS option_set_values;
+DROP TABLE IF EXISTS option_sets;
+DROP TYPE IF EXISTS option_source_enum;
@@ -0,0 +1,24 @@
+-- Where the choices of a Select field come from, dynamic sources are resolved per agent
+CREATE TYPE option_source_enum AS ENUM ('Static', 'AgentLocations', 'ResourceSpecifications', 'LotsOnHand');
+
+CREATE TABLE IF NOT EXISTS option_sets (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    name TEXT NOT NULL,
+    source option_source_enum NOT NULL,
+    -- Only for ResourceSpecifications, all types when empty
+    resource_type resource_type_enum,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS option_set_values (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    option_set_id UUID NOT NULL REFERENCES option_sets(id) ON DELETE CASCADE,
+    value TEXT NOT NULL,
+    label TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    CONSTRAINT option_set_values_value_key UNIQUE (option_set_id, value)
+);
+
+-- Process fields copy the option set of their template field, or override it
+ALTER TABLE recipe_flow_template_data_fields ADD COLUMN option_set_id UUID REFERENCES option_sets(id);
+ALTER TABLE recipe_process_flow_data_fields ADD COLUMN option_set_id UUID REFERENCES option_sets(id);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS recipe_flow_template_data_field_rules;
+DROP TYPE IF EXISTS rule_kind_enum;
@@ -0,0 +1,13 @@
+-- Conditions and constraints on template data fields, written in the rule language
+-- of the templates and evaluated against the other fields of the same flow
+CREATE TYPE rule_kind_enum AS ENUM ('RequiredIf', 'VisibleIf', 'Constraint');
+
+CREATE TABLE IF NOT EXISTS recipe_flow_template_data_field_rules (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    data_field_id UUID NOT NULL REFERENCES recipe_flow_template_data_fields(id) ON DELETE CASCADE,
+    kind rule_kind_enum NOT NULL,
+    expression TEXT NOT NULL,
+    -- Shown when the rule is broken instead of the expression
+    message TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE process_execution_custom_values DROP COLUMN from_default;
+ALTER TABLE agents DROP COLUMN primary_location_id;
@@ -0,0 +1,5 @@
+-- Location the agent.primaryLocation default resolves to
+ALTER TABLE agents ADD COLUMN primary_location_id UUID REFERENCES locations(id) ON DELETE SET NULL;
+
+-- Values the execution took from the default of the field instead of the submitted ones
+ALTER TABLE process_execution_custom_values ADD COLUMN from_default BOOLEAN NOT NULL DEFAULT false;
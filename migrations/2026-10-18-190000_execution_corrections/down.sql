@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS process_executions_corrects_idx;
+ALTER TABLE process_executions DROP COLUMN reversal;
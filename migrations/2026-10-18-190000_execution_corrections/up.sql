@@ -0,0 +1,5 @@
+-- A correction records a reversal of the corrected execution and, unless the
+-- execution is voided, a replacement. Both point to it through corrects
+ALTER TABLE process_executions ADD COLUMN reversal BOOLEAN NOT NULL DEFAULT false;
+
+CREATE INDEX IF NOT EXISTS process_executions_corrects_idx ON process_executions (corrects);
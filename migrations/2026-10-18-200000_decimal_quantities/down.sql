@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE process_executions
+    ALTER COLUMN resource_quantity TYPE INTEGER USING ROUND(resource_quantity)::INTEGER;
+
+ALTER TABLE economic_resources
+    ALTER COLUMN accounting_quantity TYPE INTEGER USING ROUND(accounting_quantity)::INTEGER,
+    ALTER COLUMN on_hand_quantity TYPE INTEGER USING ROUND(on_hand_quantity)::INTEGER;
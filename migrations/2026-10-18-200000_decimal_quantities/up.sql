@@ -0,0 +1,7 @@
+-- Quantities are exact decimals, so fractional amounts like 12.5 kg can be recorded
+ALTER TABLE economic_resources
+    ALTER COLUMN accounting_quantity TYPE NUMERIC USING accounting_quantity::NUMERIC,
+    ALTER COLUMN on_hand_quantity TYPE NUMERIC USING on_hand_quantity::NUMERIC;
+
+ALTER TABLE process_executions
+    ALTER COLUMN resource_quantity TYPE NUMERIC USING resource_quantity::NUMERIC;
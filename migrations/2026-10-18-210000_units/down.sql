@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE process_executions DROP COLUMN unit_id;
+ALTER TABLE resource_specifications DROP COLUMN unit_id;
+DROP TABLE IF EXISTS units;
+DROP TYPE IF EXISTS unit_dimension_enum;
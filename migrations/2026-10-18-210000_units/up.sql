@@ -0,0 +1,54 @@
+-- Units of measure after the OM2 vocabulary used by ValueFlows, each unit converts
+-- to the base unit of its dimension by its conversion factor
+CREATE TYPE unit_dimension_enum AS ENUM ('Mass', 'Length', 'Area', 'Volume', 'Time', 'Count');
+
+CREATE TABLE IF NOT EXISTS units (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    label TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    dimension unit_dimension_enum NOT NULL,
+    conversion_factor NUMERIC NOT NULL CHECK (conversion_factor > 0),
+    om2_uri TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS units_symbol_key ON units (LOWER(symbol));
+
+INSERT INTO units (label, symbol, dimension, conversion_factor, om2_uri) VALUES
+    ('kilogram', 'kg', 'Mass', 1, 'http://www.ontology-of-units-of-measure.org/resource/om-2/kilogram'),
+    ('gram', 'g', 'Mass', 0.001, 'http://www.ontology-of-units-of-measure.org/resource/om-2/gram'),
+    ('milligram', 'mg', 'Mass', 0.000001, 'http://www.ontology-of-units-of-measure.org/resource/om-2/milligram'),
+    ('tonne', 't', 'Mass', 1000, 'http://www.ontology-of-units-of-measure.org/resource/om-2/tonne'),
+    ('pound', 'lb', 'Mass', 0.45359237, 'http://www.ontology-of-units-of-measure.org/resource/om-2/pound-Avoirdupois'),
+    ('ounce', 'oz', 'Mass', 0.028349523125, 'http://www.ontology-of-units-of-measure.org/resource/om-2/ounce-Avoirdupois'),
+    ('metre', 'm', 'Length', 1, 'http://www.ontology-of-units-of-measure.org/resource/om-2/metre'),
+    ('centimetre', 'cm', 'Length', 0.01, 'http://www.ontology-of-units-of-measure.org/resource/om-2/centimetre'),
+    ('millimetre', 'mm', 'Length', 0.001, 'http://www.ontology-of-units-of-measure.org/resource/om-2/millimetre'),
+    ('kilometre', 'km', 'Length', 1000, 'http://www.ontology-of-units-of-measure.org/resource/om-2/kilometre'),
+    ('inch', 'in', 'Length', 0.0254, 'http://www.ontology-of-units-of-measure.org/resource/om-2/inch-International'),
+    ('foot', 'ft', 'Length', 0.3048, 'http://www.ontology-of-units-of-measure.org/resource/om-2/foot-International'),
+    ('square metre', 'm2', 'Area', 1, 'http://www.ontology-of-units-of-measure.org/resource/om-2/squareMetre'),
+    ('hectare', 'ha', 'Area', 10000, 'http://www.ontology-of-units-of-measure.org/resource/om-2/hectare'),
+    ('acre', 'ac', 'Area', 4046.8564224, 'http://www.ontology-of-units-of-measure.org/resource/om-2/acre-International'),
+    ('cubic metre', 'm3', 'Volume', 1, 'http://www.ontology-of-units-of-measure.org/resource/om-2/cubicMetre'),
+    ('litre', 'l', 'Volume', 0.001, 'http://www.ontology-of-units-of-measure.org/resource/om-2/litre'),
+    ('millilitre', 'ml', 'Volume', 0.000001, 'http://www.ontology-of-units-of-measure.org/resource/om-2/millilitre'),
+    ('gallon', 'gal', 'Volume', 0.003785411784, 'http://www.ontology-of-units-of-measure.org/resource/om-2/gallon-US'),
+    ('second', 's', 'Time', 1, 'http://www.ontology-of-units-of-measure.org/resource/om-2/second-Time'),
+    ('minute', 'min', 'Time', 60, 'http://www.ontology-of-units-of-measure.org/resource/om-2/minute-Time'),
+    ('hour', 'h', 'Time', 3600, 'http://www.ontology-of-units-of-measure.org/resource/om-2/hour'),
+    ('day', 'd', 'Time', 86400, 'http://www.ontology-of-units-of-measure.org/resource/om-2/day'),
+    ('each', 'ea', 'Count', 1, 'http://www.ontology-of-units-of-measure.org/resource/om-2/one'),
+    ('dozen', 'dz', 'Count', 12, NULL);
+
+ALTER TABLE resource_specifications ADD COLUMN unit_id UUID REFERENCES units(id);
+
+-- Specifications whose free text names a known unit take it, and its symbol
+UPDATE resource_specifications s
+SET unit_id = u.id, unit_of_measure = u.symbol
+FROM units u
+WHERE LOWER(TRIM(s.unit_of_measure)) IN (LOWER(u.symbol), LOWER(u.label), LOWER(u.label) || 's');
+
+-- Unit the quantity of the execution was recorded in, the unit of the
+-- resource specification when empty
+ALTER TABLE process_executions ADD COLUMN unit_id UUID REFERENCES units(id);
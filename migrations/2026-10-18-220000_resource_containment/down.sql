@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS economic_resources_contained_in_idx;
+ALTER TABLE process_executions DROP COLUMN container_id;
@@ -0,0 +1,5 @@
+-- Load and Unload events pack a resource into a container and unpack it, the
+-- execution keeps the container so a correction can undo the packing
+ALTER TABLE process_executions ADD COLUMN container_id UUID REFERENCES economic_resources(id);
+
+CREATE INDEX IF NOT EXISTS economic_resources_contained_in_idx ON economic_resources (contained_in);
@@ -0,0 +1,23 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS economic_resources_current_location_idx;
+ALTER TABLE economic_resources RENAME COLUMN current_location TO current_location_id;
+ALTER TABLE economic_resources ADD COLUMN current_location TEXT NOT NULL DEFAULT '';
+UPDATE economic_resources SET current_location = current_location_id::text WHERE current_location_id IS NOT NULL;
+ALTER TABLE economic_resources ALTER COLUMN current_location DROP DEFAULT;
+ALTER TABLE economic_resources DROP COLUMN current_location_id;
+
+DROP INDEX IF EXISTS locations_parent_id_idx;
+DROP INDEX IF EXISTS locations_gln_idx;
+ALTER TABLE locations
+    DROP CONSTRAINT locations_coordinates_check,
+    DROP COLUMN longitude,
+    DROP COLUMN latitude,
+    DROP COLUMN country_code,
+    DROP COLUMN postal_code,
+    DROP COLUMN region,
+    DROP COLUMN locality,
+    DROP COLUMN street_address,
+    DROP COLUMN parent_id,
+    DROP COLUMN location_type,
+    DROP COLUMN gln;
+DROP TYPE location_type_enum;
@@ -0,0 +1,53 @@
+-- Structured locations: a GS1 GLN, a postal address, coordinates and a type,
+-- nested through parent_id (warehouse, zone, bin)
+CREATE TYPE location_type_enum AS ENUM ('Farm', 'Packhouse', 'DistributionCenter', 'Retail', 'Warehouse', 'Zone', 'Bin', 'Other');
+
+ALTER TABLE locations
+    ADD COLUMN gln VARCHAR(13) CHECK (gln ~ '^[0-9]{13}$'),
+    ADD COLUMN location_type location_type_enum NOT NULL DEFAULT 'Other',
+    ADD COLUMN parent_id UUID REFERENCES locations(id),
+    ADD COLUMN street_address TEXT,
+    ADD COLUMN locality TEXT,
+    ADD COLUMN region TEXT,
+    ADD COLUMN postal_code TEXT,
+    ADD COLUMN country_code CHAR(2),
+    ADD COLUMN latitude DOUBLE PRECISION CHECK (latitude BETWEEN -90 AND 90),
+    ADD COLUMN longitude DOUBLE PRECISION CHECK (longitude BETWEEN -180 AND 180),
+    ADD CONSTRAINT locations_coordinates_check CHECK ((latitude IS NULL) = (longitude IS NULL));
+
+CREATE UNIQUE INDEX IF NOT EXISTS locations_gln_idx ON locations (gln);
+CREATE INDEX IF NOT EXISTS locations_parent_id_idx ON locations (parent_id);
+
+-- current_location held the id of a location as text, or free text for
+-- resources created by hand
+ALTER TABLE economic_resources ADD COLUMN current_location_id UUID REFERENCES locations(id);
+
+UPDATE economic_resources r
+SET current_location_id = l.id
+FROM locations l
+WHERE r.current_location = l.id::text;
+
+-- Free text names a location of whoever holds the resource, created when missing
+INSERT INTO locations (agent_id, name, value)
+SELECT DISTINCT COALESCE(r.custodian_id, s.agent_id), TRIM(r.current_location), TRIM(r.current_location)
+FROM economic_resources r
+JOIN resource_specifications s ON s.id = r.resource_specification_id
+WHERE r.current_location_id IS NULL
+  AND TRIM(r.current_location) <> ''
+  AND NOT EXISTS (
+      SELECT 1 FROM locations l
+      WHERE l.agent_id = COALESCE(r.custodian_id, s.agent_id) AND l.name = TRIM(r.current_location)
+  );
+
+UPDATE economic_resources r
+SET current_location_id = l.id
+FROM resource_specifications s, locations l
+WHERE s.id = r.resource_specification_id
+  AND r.current_location_id IS NULL
+  AND l.agent_id = COALESCE(r.custodian_id, s.agent_id)
+  AND l.name = TRIM(r.current_location);
+
+ALTER TABLE economic_resources DROP COLUMN current_location;
+ALTER TABLE economic_resources RENAME COLUMN current_location_id TO current_location;
+
+CREATE INDEX IF NOT EXISTS economic_resources_current_location_idx ON economic_resources (current_location);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER IF EXISTS inventory_ledger_entries_append_only ON inventory_ledger_entries;
+DROP FUNCTION IF EXISTS inventory_ledger_append_only();
+DROP TABLE IF EXISTS inventory_ledger_entries;
@@ -0,0 +1,41 @@
+-- Append-only ledger of the quantity changes of economic resources. Every
+-- entry holds the resource, specification, lot, location and holder at the
+-- time of the change, so stock at any moment is the sum of the earlier entries
+CREATE TABLE inventory_ledger_entries (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    economic_resource_id UUID NOT NULL REFERENCES economic_resources(id),
+    resource_specification_id UUID NOT NULL REFERENCES resource_specifications(id),
+    lot TEXT,
+    location_id UUID REFERENCES locations(id),
+    agent_id UUID NOT NULL REFERENCES agents(id),
+    accounting_delta NUMERIC NOT NULL,
+    on_hand_delta NUMERIC NOT NULL,
+    -- Entries are written while the execution is applied, before its row exists
+    process_execution_id UUID REFERENCES process_executions(id) DEFERRABLE INITIALLY DEFERRED,
+    recorded_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS inventory_ledger_entries_recorded_at_idx ON inventory_ledger_entries (recorded_at);
+CREATE INDEX IF NOT EXISTS inventory_ledger_entries_economic_resource_id_idx ON inventory_ledger_entries (economic_resource_id);
+
+CREATE FUNCTION inventory_ledger_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'inventory_ledger_entries is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER inventory_ledger_entries_append_only
+    BEFORE UPDATE OR DELETE ON inventory_ledger_entries
+    FOR EACH ROW EXECUTE FUNCTION inventory_ledger_append_only();
+
+-- The history before the ledger isn't known, existing resources open with
+-- their current quantities at the time they were created
+INSERT INTO inventory_ledger_entries (
+    economic_resource_id, resource_specification_id, lot, location_id, agent_id,
+    accounting_delta, on_hand_delta, recorded_at
+)
+SELECT r.id, r.resource_specification_id, r.lot, r.current_location, COALESCE(r.custodian_id, s.agent_id),
+       r.accounting_quantity, r.on_hand_quantity, r.created_at
+FROM economic_resources r
+JOIN resource_specifications s ON s.id = r.resource_specification_id
+WHERE r.accounting_quantity <> 0 OR r.on_hand_quantity <> 0;
@@ -0,0 +1,13 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE inventory_ledger_entries DISABLE TRIGGER inventory_ledger_entries_append_only;
+DELETE FROM inventory_ledger_entries
+    WHERE process_execution_id IN (SELECT id FROM process_executions WHERE operation IS NOT NULL);
+ALTER TABLE inventory_ledger_entries ENABLE TRIGGER inventory_ledger_entries_append_only;
+DELETE FROM process_executions WHERE operation IS NOT NULL;
+
+ALTER TABLE process_executions
+    DROP CONSTRAINT process_executions_flow_or_operation,
+    DROP COLUMN operation,
+    ALTER COLUMN process_flow_id SET NOT NULL;
+
+DROP TYPE resource_operation_enum;
@@ -0,0 +1,8 @@
+-- Split and merge of resources are recorded as executions outside of any recipe
+CREATE TYPE resource_operation_enum AS ENUM ('Split', 'Merge');
+
+ALTER TABLE process_executions
+    ALTER COLUMN process_flow_id DROP NOT NULL,
+    ADD COLUMN operation resource_operation_enum,
+    ADD CONSTRAINT process_executions_flow_or_operation
+        CHECK ((process_flow_id IS NULL) <> (operation IS NULL));
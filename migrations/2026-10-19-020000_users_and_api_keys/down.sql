@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE recipe_templates DROP COLUMN IF EXISTS created_by;
+ALTER TABLE recipe_templates RENAME CONSTRAINT recipe_templates_created_by_agent_fkey TO recipe_templates_created_by_fkey;
+ALTER TABLE recipe_templates RENAME COLUMN created_by_agent TO created_by;
+
+DROP TABLE IF EXISTS api_keys;
+DROP TABLE IF EXISTS agent_memberships;
+DROP TABLE IF EXISTS users;
@@ -0,0 +1,35 @@
+-- People acting for agents, and the API keys they authenticate with
+CREATE TABLE IF NOT EXISTS users (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    name TEXT NOT NULL,
+    email TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT now()
+);
+CREATE UNIQUE INDEX users_email_idx ON users (lower(email));
+
+CREATE TABLE IF NOT EXISTS agent_memberships (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    agent_id UUID NOT NULL REFERENCES agents(id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    UNIQUE (user_id, agent_id)
+);
+CREATE INDEX agent_memberships_agent_id_idx ON agent_memberships (agent_id);
+
+-- Only the SHA-256 of a key is kept, the key itself is shown once when it is issued
+CREATE TABLE IF NOT EXISTS api_keys (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    prefix TEXT NOT NULL,
+    key_hash TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    last_used_at TIMESTAMP,
+    revoked_at TIMESTAMP
+);
+CREATE INDEX api_keys_user_id_idx ON api_keys (user_id);
+
+-- Templates keep the agent they were created for, the user who created them is recorded apart
+ALTER TABLE recipe_templates RENAME COLUMN created_by TO created_by_agent;
+ALTER TABLE recipe_templates RENAME CONSTRAINT recipe_templates_created_by_fkey TO recipe_templates_created_by_agent_fkey;
+ALTER TABLE recipe_templates ADD COLUMN created_by UUID REFERENCES users(id) ON DELETE SET NULL;
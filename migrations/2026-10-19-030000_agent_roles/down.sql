@@ -0,0 +1,10 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX process_executions_performed_by_idx;
+
+ALTER TABLE process_executions DROP COLUMN performed_by;
+
+ALTER TABLE users DROP COLUMN deployment_admin;
+
+ALTER TABLE agent_memberships DROP COLUMN role;
+
+DROP TYPE agent_role_enum;
@@ -0,0 +1,13 @@
+-- Roles of the members of an agent, members from before roles may operate until an admin appoints them
+CREATE TYPE agent_role_enum AS ENUM ('Admin', 'TemplateDesigner', 'Operator', 'Auditor');
+
+ALTER TABLE agent_memberships ADD COLUMN role agent_role_enum NOT NULL DEFAULT 'Operator';
+ALTER TABLE agent_memberships ALTER COLUMN role DROP DEFAULT;
+
+-- Deployment admins manage what all agents share, like units. The first one signs up with the bootstrap secret
+ALTER TABLE users ADD COLUMN deployment_admin BOOLEAN NOT NULL DEFAULT false;
+
+-- The user who performed the execution, none for executions recorded before users
+ALTER TABLE process_executions ADD COLUMN performed_by UUID REFERENCES users(id) ON DELETE SET NULL;
+
+CREATE INDEX process_executions_performed_by_idx ON process_executions(performed_by);
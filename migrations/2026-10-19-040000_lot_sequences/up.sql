@@ -0,0 +1,14 @@
+-- Last sequence handed out for each lot code without its sequence, so
+-- switching the lot format away and back continues the earlier sequence
+CREATE TABLE lot_sequences (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    agent_id UUID NOT NULL REFERENCES agents(id),
+    period TEXT NOT NULL,
+    sequence INTEGER NOT NULL,
+    UNIQUE (agent_id, period)
+);
+
+INSERT INTO lot_sequences (agent_id, period, sequence)
+SELECT agent_id, lot_period, lot_code
+FROM counters
+WHERE lot_period IS NOT NULL;
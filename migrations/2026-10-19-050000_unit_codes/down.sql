@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX units_unece_code_key;
+
+ALTER TABLE units DROP COLUMN unece_code;
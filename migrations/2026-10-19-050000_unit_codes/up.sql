@@ -0,0 +1,16 @@
+-- UN/ECE Recommendation 20 code of the unit, EPCIS quantities carry it as their uom
+ALTER TABLE units ADD COLUMN unece_code TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS units_unece_code_key ON units (unece_code);
+
+UPDATE units u
+SET unece_code = codes.unece_code
+FROM (VALUES
+    ('kg', 'KGM'), ('g', 'GRM'), ('mg', 'MGM'), ('t', 'TNE'), ('lb', 'LBR'), ('oz', 'ONZ'),
+    ('m', 'MTR'), ('cm', 'CMT'), ('mm', 'MMT'), ('km', 'KMT'), ('in', 'INH'), ('ft', 'FOT'),
+    ('m2', 'MTK'), ('ha', 'HAR'), ('ac', 'ACR'),
+    ('m3', 'MTQ'), ('l', 'LTR'), ('ml', 'MLT'), ('gal', 'GLL'),
+    ('s', 'SEC'), ('min', 'MIN'), ('h', 'HUR'), ('d', 'DAY'),
+    ('ea', 'H87'), ('dz', 'DZN')
+) AS codes (symbol, unece_code)
+WHERE LOWER(u.symbol) = codes.symbol;
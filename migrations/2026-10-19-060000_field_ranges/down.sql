@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE recipe_process_flow_data_fields DROP COLUMN min_value, DROP COLUMN max_value;
+ALTER TABLE recipe_flow_template_data_fields DROP COLUMN min_value, DROP COLUMN max_value;
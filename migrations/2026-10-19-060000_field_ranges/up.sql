@@ -0,0 +1,3 @@
+-- Bounds of Number fields, both inclusive. Process fields copy the range of their template field, or override it
+ALTER TABLE recipe_flow_template_data_fields ADD COLUMN min_value NUMERIC, ADD COLUMN max_value NUMERIC;
+ALTER TABLE recipe_process_flow_data_fields ADD COLUMN min_value NUMERIC, ADD COLUMN max_value NUMERIC;
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE map_templates DROP COLUMN created_by_agent;
@@ -0,0 +1,3 @@
+-- Maps keep the agent they were created for, whose designers change them. Existing
+-- maps have no owner and are left to the deployment admins
+ALTER TABLE map_templates ADD COLUMN created_by_agent UUID REFERENCES agents(id) ON DELETE SET NULL;
@@ -14,6 +14,8 @@ pub struct Agent {
     pub name: String,
     pub note: Option<String>,
     pub created_at: NaiveDateTime,
+    /// Location the agent.primaryLocation default resolves to
+    pub primary_location_id: Option<Uuid>,
 }
 
 #[derive(Insertable)]
@@ -0,0 +1,79 @@
+use juniper::GraphQLObject;
+
+use super::economic_resource::EconomicResource;
+
+/// A resource with everything packed into it, cases in a pallet, units in a case
+#[derive(GraphQLObject, Debug)]
+pub struct ContainmentNode {
+    pub resource: EconomicResource,
+    /// Levels below the root of the tree, the root is at 0
+    pub depth: i32,
+    pub contents: Vec<ContainmentNode>,
+}
+
+impl ContainmentNode {
+    /// Nests the contained resources under the root, resources that don't
+    /// descend from it are left out
+    pub fn build(root: EconomicResource, contained: &[EconomicResource]) -> Self {
+        Self::build_at(root, contained, 0)
+    }
+
+    fn build_at(resource: EconomicResource, contained: &[EconomicResource], depth: i32) -> Self {
+        let contents = contained
+            .iter()
+            .filter(|r| r.contained_in == Some(resource.id))
+            .map(|r| Self::build_at(r.clone(), contained, depth + 1))
+            .collect();
+
+        ContainmentNode { resource, depth, contents }
+    }
+
+    /// Number of resources packed in the tree, at any depth
+    pub fn count(&self) -> i32 {
+        self.contents.iter().map(|c| 1 + c.count()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::common::economic_resource::Decimal;
+
+    fn resource(name: &str, contained_in: Option<Uuid>) -> EconomicResource {
+        EconomicResource {
+            id: Uuid::new_v4(),
+            resource_specification_id: Uuid::nil(),
+            name: name.to_string(),
+            note: None,
+            accounting_quantity: Decimal::from(1),
+            on_hand_quantity: Decimal::from(1),
+            tracking_identifier: None,
+            current_location: None,
+            lot: None,
+            contained_in,
+            created_at: NaiveDateTime::default(),
+            reference_number: 0,
+            custodian_id: None,
+        }
+    }
+
+    #[test]
+    fn nests_contents_under_their_container() {
+        let pallet = resource("pallet", None);
+        let case_a = resource("case a", Some(pallet.id));
+        let case_b = resource("case b", Some(pallet.id));
+        let unit = resource("unit", Some(case_a.id));
+        let elsewhere = resource("elsewhere", Some(Uuid::new_v4()));
+
+        let tree = ContainmentNode::build(pallet, &[case_a.clone(), case_b, unit, elsewhere]);
+
+        assert_eq!(tree.count(), 3);
+        assert_eq!(tree.contents.len(), 2);
+        let case = tree.contents.iter().find(|c| c.resource.id == case_a.id).unwrap();
+        assert_eq!((case.depth, case.contents.len()), (1, 1));
+        assert_eq!(case.contents[0].depth, 2);
+    }
+}
@@ -0,0 +1,196 @@
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use diesel::{Insertable, Queryable};
+use juniper::GraphQLObject;
+use uuid::Uuid;
+
+use crate::db::schema::{counters, lot_sequences};
+
+#[derive(Queryable, GraphQLObject, Debug)]
+#[diesel(table_name = counters)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Counter {
+    pub id: Uuid,
+    pub agent_id: Uuid,
+    pub lot_code: i32,
+    pub reference_number: i32,
+    pub created_at: NaiveDateTime,
+    pub lot_format: String,
+    pub lot_prefix: Option<String>,
+    pub lot_period: Option<String>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = counters)]
+pub struct NewCounter<'a> {
+    pub agent_id: &'a Uuid,
+}
+
+impl<'a> NewCounter<'a> {
+    pub fn new(agent_id: &'a Uuid) -> Self {
+        NewCounter { agent_id }
+    }
+}
+
+/// Sequence of the lot codes sharing everything but their sequence
+#[derive(Insertable)]
+#[diesel(table_name = lot_sequences)]
+pub struct NewLotSequence<'a> {
+    pub agent_id: &'a Uuid,
+    pub period: &'a str,
+    pub sequence: i32,
+}
+
+impl<'a> NewLotSequence<'a> {
+    pub fn new(agent_id: &'a Uuid, period: &'a str) -> Self {
+        NewLotSequence {
+            agent_id,
+            period,
+            sequence: 1,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum LotToken {
+    Literal(String),
+    Agent,
+    Date,
+    Year,
+    Month,
+    Day,
+    Sequence(usize),
+}
+
+/// Lot code format such as `{AGENT}-{YYYYMMDD}-{SEQ:4}`
+///
+/// Supported tokens are `{AGENT}`, `{YYYYMMDD}`, `{YYYY}`, `{MM}`, `{DD}` and
+/// `{SEQ}` or `{SEQ:n}` for a sequence zero padded to n digits. The sequence
+/// is mandatory and kept apart for every rendering of the rest of the code,
+/// so `{YYYYMMDD}` gives a daily sequence and `{YYYY}` a yearly one.
+#[derive(Debug, PartialEq, Eq)]
+pub struct LotFormat {
+    tokens: Vec<LotToken>,
+}
+
+impl LotFormat {
+    pub fn parse(format: &str) -> Result<Self, String> {
+        let mut tokens = Vec::new();
+        let mut rest = format;
+
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                tokens.push(LotToken::Literal(rest[..start].to_string()));
+            }
+            let end = rest[start..]
+                .find('}')
+                .map(|end| start + end)
+                .ok_or_else(|| format!("Unclosed token in lot format {}", format))?;
+
+            let token = match &rest[start + 1..end] {
+                "AGENT" => LotToken::Agent,
+                "YYYYMMDD" => LotToken::Date,
+                "YYYY" => LotToken::Year,
+                "MM" => LotToken::Month,
+                "DD" => LotToken::Day,
+                "SEQ" => LotToken::Sequence(1),
+                other => match other.strip_prefix("SEQ:").map(|width| width.parse::<usize>()) {
+                    Some(Ok(width)) if (1..=10).contains(&width) => LotToken::Sequence(width),
+                    _ => return Err(format!("Unknown token {{{}}} in lot format {}", other, format)),
+                },
+            };
+            tokens.push(token);
+            rest = &rest[end + 1..];
+        }
+
+        if !rest.is_empty() {
+            tokens.push(LotToken::Literal(rest.to_string()));
+        }
+
+        let sequences = tokens
+            .iter()
+            .filter(|token| matches!(token, LotToken::Sequence(_)))
+            .count();
+        if sequences != 1 {
+            return Err(format!("Lot format {} must contain exactly one {{SEQ}} token", format));
+        }
+
+        Ok(LotFormat { tokens })
+    }
+
+    /// The code without its sequence, used to know when the sequence restarts
+    pub fn period(&self, agent_code: &str, date: NaiveDate) -> String {
+        self.render_with(agent_code, date, None)
+    }
+
+    pub fn render(&self, agent_code: &str, date: NaiveDate, sequence: i32) -> String {
+        self.render_with(agent_code, date, Some(sequence))
+    }
+
+    fn render_with(&self, agent_code: &str, date: NaiveDate, sequence: Option<i32>) -> String {
+        self.tokens
+            .iter()
+            .map(|token| match token {
+                LotToken::Literal(literal) => literal.clone(),
+                LotToken::Agent => agent_code.to_string(),
+                LotToken::Date => date.format("%Y%m%d").to_string(),
+                LotToken::Year => format!("{:04}", date.year()),
+                LotToken::Month => format!("{:02}", date.month()),
+                LotToken::Day => format!("{:02}", date.day()),
+                LotToken::Sequence(width) => match sequence {
+                    Some(sequence) => format!("{:0width$}", sequence, width = *width),
+                    None => String::new(),
+                },
+            })
+            .collect()
+    }
+}
+
+/// Code used for the {AGENT} token when the counter has no lot prefix
+pub fn agent_code(agent_name: &str) -> String {
+    let code: String = agent_name
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .take(8)
+        .collect();
+
+    if code.is_empty() {
+        "AGENT".to_string()
+    } else {
+        code
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_lot_codes() {
+        let format = LotFormat::parse("{AGENT}-{YYYYMMDD}-{SEQ:4}").unwrap();
+        let date = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+
+        assert_eq!(format.render("ACME", date, 1), "ACME-20261018-0001");
+        assert_eq!(format.render("ACME", date, 12345), "ACME-20261018-12345");
+        assert_eq!(format.period("ACME", date), "ACME-20261018-");
+
+        let format = LotFormat::parse("L{YYYY}{MM}{DD}/{SEQ}").unwrap();
+        assert_eq!(format.render("ACME", date, 7), "L20261018/7");
+    }
+
+    #[test]
+    fn rejects_invalid_formats() {
+        assert!(LotFormat::parse("{AGENT}-{YYYYMMDD}").is_err());
+        assert!(LotFormat::parse("{SEQ}-{SEQ}").is_err());
+        assert!(LotFormat::parse("{AGENT}-{SEQ:0}").is_err());
+        assert!(LotFormat::parse("{WEEK}-{SEQ}").is_err());
+        assert!(LotFormat::parse("{AGENT-{SEQ}").is_err());
+        assert!(LotFormat::parse("{SEQ").is_err());
+    }
+
+    #[test]
+    fn agent_codes() {
+        assert_eq!(agent_code("Acme Farms Inc."), "ACMEFARM");
+        assert_eq!(agent_code("  "), "AGENT");
+    }
+}
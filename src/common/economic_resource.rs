@@ -1,18 +1,98 @@
 
+use std::{fmt, str::FromStr};
+
+use bigdecimal::BigDecimal;
 use chrono::NaiveDateTime;
-use diesel::{prelude::Insertable, Queryable};
-use juniper::GraphQLObject;
+use diesel::{
+    deserialize::{self, FromSql, FromSqlRow},
+    expression::AsExpression,
+    pg::{Pg, PgValue},
+    prelude::Insertable,
+    serialize::{self, Output, ToSql},
+    sql_types::Numeric,
+    Queryable,
+};
+use juniper::{
+    graphql_scalar, parser::ScalarToken, GraphQLObject, ParseScalarResult, ParseScalarValue, Value,
+};
 use uuid::Uuid;
 
 use crate::db::schema::economic_resources;
 
 use super::resource_specification::ResourceSpecification;
 
-// Wrapper around BigDecimal
+/// Wrapper around BigDecimal, exact quantities stored as NUMERIC
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, FromSqlRow, AsExpression)]
+#[diesel(sql_type = Numeric)]
+pub struct Decimal(pub BigDecimal);
 
+impl From<BigDecimal> for Decimal {
+    fn from(value: BigDecimal) -> Self {
+        Decimal(value)
+    }
+}
 
+impl From<i32> for Decimal {
+    fn from(value: i32) -> Self {
+        Decimal(BigDecimal::from(value))
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = bigdecimal::ParseBigDecimalError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        BigDecimal::from_str(value.trim()).map(Decimal)
+    }
+}
 
-#[derive(Queryable, GraphQLObject, Debug)]
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl ToSql<Numeric, Pg> for Decimal {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        ToSql::<Numeric, Pg>::to_sql(&self.0, out)
+    }
+}
+
+impl FromSql<Numeric, Pg> for Decimal {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        BigDecimal::from_sql(bytes).map(Decimal)
+    }
+}
+
+/// Serialized as a string so no precision is lost, numbers are accepted as input
+#[graphql_scalar(description = "Decimal")]
+impl<S> GraphQLScalar for Decimal
+where
+    S: ScalarValue,
+{
+    fn resolve(&self) -> Value {
+        Value::scalar(self.to_string())
+    }
+
+    fn from_input_value(v: &InputValue) -> Option<Decimal> {
+        let scalar = v.as_scalar()?;
+        match (scalar.as_str(), scalar.as_int(), scalar.as_float()) {
+            (Some(value), _, _) => value.parse().ok(),
+            (_, Some(value), _) => Some(Decimal::from(value)),
+            (_, _, Some(value)) => value.to_string().parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn from_str<'a>(value: ScalarToken<'a>) -> ParseScalarResult<'a, S> {
+        match value {
+            ScalarToken::Int(value) | ScalarToken::Float(value) => Ok(S::from(value.to_owned())),
+            _ => <String as ParseScalarValue<S>>::from_str(value),
+        }
+    }
+}
+
+#[derive(Queryable, GraphQLObject, Debug, Clone)]
 #[diesel(table_name = economic_resources)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct EconomicResource {
@@ -20,14 +100,15 @@ pub struct EconomicResource {
     pub resource_specification_id: Uuid,
     pub name: String,
     pub note: Option<String>,
-    pub accounting_quantity: i32,
-    pub on_hand_quantity: i32,
+    pub accounting_quantity: Decimal,
+    pub on_hand_quantity: Decimal,
     pub tracking_identifier: Option<String>,
-    pub current_location: String,
+    pub current_location: Option<Uuid>,
     pub lot: Option<String>,
     pub contained_in: Option<Uuid>,
     pub created_at: NaiveDateTime,
     pub reference_number: i32,
+    pub custodian_id: Option<Uuid>,
 }
 
 #[derive(Insertable)]
@@ -36,36 +117,38 @@ pub struct NewEconomicResource<'a> {
     pub resource_specification_id: &'a Uuid,
     pub name: &'a str,
     pub note: Option<&'a str>,
-    pub accounting_quantity: &'a i32,
-    pub on_hand_quantity: &'a i32,
+    pub accounting_quantity: &'a Decimal,
+    pub on_hand_quantity: &'a Decimal,
     pub tracking_identifier: Option<&'a str>,
-    pub current_location: &'a str,
+    pub current_location: Option<&'a Uuid>,
     pub lot: Option<&'a str>,
     pub contained_in: Option<&'a Uuid>,
+    pub custodian_id: Option<&'a Uuid>,
+    /// Allocated from the agent's counter, from the table's sequence when left out
+    pub reference_number: Option<&'a i32>,
 }
 
 impl<'a> NewEconomicResource<'a> {
+    /// Note, tracking identifier, container, custodian and reference number are set on the result when known
     pub fn new(
         resource_specification_id: &'a Uuid,
         name: &'a str,
-        note: Option<&'a str>,
-        accounting_quantity: &'a i32,
-        tracking_identifier: Option<&'a str>,
-        current_location: &'a str,
-        lot: Option<&'a str>,
-        contained_in: Option<&'a Uuid>
-
+        accounting_quantity: &'a Decimal,
+        current_location: Option<&'a Uuid>,
+        lot: Option<&'a str>
     ) -> Self {
         NewEconomicResource {
             resource_specification_id,
             name,
-            note,
+            note: None,
             accounting_quantity,
             on_hand_quantity: accounting_quantity,
-            tracking_identifier, 
+            tracking_identifier: None,
             current_location,
             lot,
-            contained_in
+            contained_in: None,
+            custodian_id: None,
+            reference_number: None
         }
     }
 }
@@ -78,14 +161,15 @@ pub struct EconomicResourceWithSpec {
     pub resource_specification: ResourceSpecification,
     pub name: String,
     pub note: Option<String>,
-    pub accounting_quantity: i32,
-    pub on_hand_quantity: i32,
+    pub accounting_quantity: Decimal,
+    pub on_hand_quantity: Decimal,
     pub tracking_identifier: Option<String>,
-    pub current_location: String,
+    pub current_location: Option<Uuid>,
     pub lot: Option<String>,
     pub contained_in: Option<Uuid>,
     pub created_at: NaiveDateTime,
     pub reference_number: i32,
+    pub custodian_id: Option<Uuid>,
 }
 
 impl EconomicResourceWithSpec {
@@ -105,7 +189,23 @@ impl EconomicResourceWithSpec {
             lot: economic_resource.lot,
             contained_in: economic_resource.contained_in,
             created_at: economic_resource.created_at,
-            reference_number: economic_resource.reference_number
+            reference_number: economic_resource.reference_number,
+            custodian_id: economic_resource.custodian_id
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_prints_exact_quantities() {
+        let quantity: Decimal = " 0.1 ".parse().unwrap();
+        assert_eq!(quantity.to_string(), "0.1");
+        assert_eq!("12.50".parse::<Decimal>().unwrap(), "12.5".parse::<Decimal>().unwrap());
+        assert!("1.5.2".parse::<Decimal>().is_err());
+        assert!("".parse::<Decimal>().is_err());
+        assert!(Decimal::from(2) > "1.999999999999999999".parse::<Decimal>().unwrap());
+    }
+}
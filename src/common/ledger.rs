@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDateTime;
+use diesel::{Insertable, Queryable};
+use juniper::{GraphQLEnum, GraphQLObject};
+use uuid::Uuid;
+
+use crate::db::schema::inventory_ledger_entries;
+
+use super::economic_resource::{Decimal, EconomicResource};
+
+/// One quantity change of a resource, with where it was and who held it at the time
+#[derive(Queryable, GraphQLObject, Debug, Clone)]
+#[diesel(table_name = inventory_ledger_entries)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct InventoryLedgerEntry {
+    pub id: Uuid,
+    pub economic_resource_id: Uuid,
+    pub resource_specification_id: Uuid,
+    pub lot: Option<String>,
+    pub location_id: Option<Uuid>,
+    /// The custodian of the resource, or else the owner of its specification
+    pub agent_id: Uuid,
+    pub accounting_delta: Decimal,
+    pub on_hand_delta: Decimal,
+    /// Execution that changed the quantity, none for changes made by hand
+    pub process_execution_id: Option<Uuid>,
+    pub recorded_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = inventory_ledger_entries)]
+pub struct NewInventoryLedgerEntry<'a> {
+    pub economic_resource_id: &'a Uuid,
+    pub resource_specification_id: &'a Uuid,
+    pub lot: Option<&'a str>,
+    pub location_id: Option<&'a Uuid>,
+    pub agent_id: &'a Uuid,
+    pub accounting_delta: &'a Decimal,
+    pub on_hand_delta: &'a Decimal,
+    pub process_execution_id: Option<&'a Uuid>,
+}
+
+impl<'a> NewInventoryLedgerEntry<'a> {
+    /// Entry of the resource where it is now
+    pub fn new(
+        resource: &'a EconomicResource,
+        agent_id: &'a Uuid,
+        accounting_delta: &'a Decimal,
+        on_hand_delta: &'a Decimal,
+    ) -> Self {
+        NewInventoryLedgerEntry {
+            economic_resource_id: &resource.id,
+            resource_specification_id: &resource.resource_specification_id,
+            lot: resource.lot.as_deref(),
+            location_id: resource.current_location.as_ref(),
+            agent_id,
+            accounting_delta,
+            on_hand_delta,
+            process_execution_id: None,
+        }
+    }
+}
+
+#[derive(GraphQLEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StockGrouping {
+    Agent,
+    Location,
+    ResourceSpecification,
+    Lot,
+}
+
+/// Stock of one group, only the fields stock is grouped by are set
+#[derive(GraphQLObject, Debug, PartialEq)]
+pub struct StockLevel {
+    pub agent_id: Option<Uuid>,
+    pub location_id: Option<Uuid>,
+    pub resource_specification_id: Option<Uuid>,
+    pub lot: Option<String>,
+    pub accounting_quantity: Decimal,
+    pub on_hand_quantity: Decimal,
+}
+
+type StockKey = (Option<Uuid>, Option<Uuid>, Option<Uuid>, Option<String>);
+
+/// Sums the entries per group, groups holding nothing are left out
+pub fn stock_levels(entries: &[InventoryLedgerEntry], group_by: &[StockGrouping]) -> Vec<StockLevel> {
+    let mut totals: BTreeMap<StockKey, (BigDecimal, BigDecimal)> = BTreeMap::new();
+
+    for entry in entries {
+        let key = (
+            Some(entry.agent_id).filter(|_| group_by.contains(&StockGrouping::Agent)),
+            entry.location_id.filter(|_| group_by.contains(&StockGrouping::Location)),
+            Some(entry.resource_specification_id).filter(|_| group_by.contains(&StockGrouping::ResourceSpecification)),
+            entry.lot.clone().filter(|_| group_by.contains(&StockGrouping::Lot)),
+        );
+        let total = totals.entry(key).or_insert_with(|| (BigDecimal::zero(), BigDecimal::zero()));
+        total.0 += &entry.accounting_delta.0;
+        total.1 += &entry.on_hand_delta.0;
+    }
+
+    totals
+        .into_iter()
+        .filter(|(_, (accounting, on_hand))| !accounting.is_zero() || !on_hand.is_zero())
+        .map(|((agent_id, location_id, resource_specification_id, lot), (accounting, on_hand))| StockLevel {
+            agent_id,
+            location_id,
+            resource_specification_id,
+            lot,
+            accounting_quantity: Decimal(accounting),
+            on_hand_quantity: Decimal(on_hand),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(location_id: Uuid, lot: &str, on_hand_delta: i32) -> InventoryLedgerEntry {
+        InventoryLedgerEntry {
+            id: Uuid::new_v4(),
+            economic_resource_id: Uuid::nil(),
+            resource_specification_id: Uuid::nil(),
+            lot: Some(lot.to_string()),
+            location_id: Some(location_id),
+            agent_id: Uuid::nil(),
+            accounting_delta: Decimal::from(on_hand_delta),
+            on_hand_delta: Decimal::from(on_hand_delta),
+            process_execution_id: None,
+            recorded_at: NaiveDateTime::default(),
+        }
+    }
+
+    #[test]
+    fn sums_entries_per_group() {
+        let (farm, dock) = (Uuid::new_v4(), Uuid::new_v4());
+        let entries = [
+            entry(farm, "A", 10),
+            entry(farm, "A", -4),
+            entry(farm, "B", 5),
+            // Moved from the farm to the dock
+            entry(farm, "B", -5),
+            entry(dock, "B", 5),
+        ];
+
+        let by_location = stock_levels(&entries, &[StockGrouping::Location]);
+        assert_eq!(by_location.len(), 2);
+        let farm_stock = by_location.iter().find(|s| s.location_id == Some(farm)).unwrap();
+        assert_eq!((farm_stock.on_hand_quantity.to_string(), farm_stock.lot.clone()), ("6".to_string(), None));
+
+        let by_lot = stock_levels(&entries, &[StockGrouping::Lot, StockGrouping::Location]);
+        assert_eq!(by_lot.len(), 2, "lot B left the farm");
+
+        let total = stock_levels(&entries, &[]);
+        assert_eq!(total.len(), 1);
+        assert_eq!(total[0].accounting_quantity, Decimal::from(11));
+    }
+}
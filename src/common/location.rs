@@ -1,17 +1,167 @@
-use diesel::{Insertable, Queryable};
-use juniper::GraphQLObject;
+use std::io::Write;
+
+use diesel::{
+    deserialize::{self, FromSql, FromSqlRow},
+    expression::AsExpression,
+    pg::{Pg, PgValue},
+    serialize::{self, IsNull, Output, ToSql},
+    Insertable, Queryable,
+};
+use juniper::{GraphQLEnum, GraphQLInputObject, GraphQLObject};
 use uuid::Uuid;
 
-use crate::db::schema::locations;
+use crate::db::schema::{locations, sql_types::LocationTypeEnum};
+
+#[derive(Debug, PartialEq, FromSqlRow, AsExpression, Eq, GraphQLEnum, Clone, Copy)]
+#[diesel(sql_type = LocationTypeEnum)]
+pub enum LocationType {
+    Farm,
+    Packhouse,
+    DistributionCenter,
+    Retail,
+    Warehouse,
+    Zone,
+    Bin,
+    Other,
+}
+
+impl ToSql<LocationTypeEnum, Pg> for LocationType {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        match *self {
+            LocationType::Farm => out.write_all(b"Farm")?,
+            LocationType::Packhouse => out.write_all(b"Packhouse")?,
+            LocationType::DistributionCenter => out.write_all(b"DistributionCenter")?,
+            LocationType::Retail => out.write_all(b"Retail")?,
+            LocationType::Warehouse => out.write_all(b"Warehouse")?,
+            LocationType::Zone => out.write_all(b"Zone")?,
+            LocationType::Bin => out.write_all(b"Bin")?,
+            LocationType::Other => out.write_all(b"Other")?,
+        }
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<LocationTypeEnum, Pg> for LocationType {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"Farm" => Ok(LocationType::Farm),
+            b"Packhouse" => Ok(LocationType::Packhouse),
+            b"DistributionCenter" => Ok(LocationType::DistributionCenter),
+            b"Retail" => Ok(LocationType::Retail),
+            b"Warehouse" => Ok(LocationType::Warehouse),
+            b"Zone" => Ok(LocationType::Zone),
+            b"Bin" => Ok(LocationType::Bin),
+            b"Other" => Ok(LocationType::Other),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
 
-#[derive(Queryable, GraphQLObject, Debug)]
-#[diesel(table_name = agents)]
+#[derive(Queryable, GraphQLObject, Debug, Clone)]
+#[diesel(table_name = locations)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct Location {
     pub id: Uuid,
     pub agent_id: Uuid,
     pub name: String,
     pub value: String,
+    /// GS1 Global Location Number
+    pub gln: Option<String>,
+    pub location_type: LocationType,
+    /// Location this one is part of, the warehouse of a zone or the zone of a bin
+    pub parent_id: Option<Uuid>,
+    pub street_address: Option<String>,
+    pub locality: Option<String>,
+    pub region: Option<String>,
+    pub postal_code: Option<String>,
+    /// ISO 3166-1 alpha-2 code
+    pub country_code: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+impl Location {
+    /// Name and postal address, the name and value when there is no address
+    pub fn description(&self) -> String {
+        let locality = [self.postal_code.as_deref(), self.locality.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<&str>>()
+            .join(" ");
+        let address: Vec<&str> = [
+            self.street_address.as_deref(),
+            Some(locality.as_str()),
+            self.region.as_deref(),
+            self.country_code.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|part| !part.trim().is_empty())
+        .collect();
+
+        if address.is_empty() {
+            format!("{}, {}", self.name, self.value)
+        } else {
+            format!("{}, {}", self.name, address.join(", "))
+        }
+    }
+}
+
+/// GLNs are 13 digits, the last one a GS1 check digit over the others
+pub fn valid_gln(gln: &str) -> bool {
+    if gln.len() != 13 || !gln.chars().all(|c| c.is_ascii_digit()) {
+        return false;
+    }
+
+    let digits: Vec<u32> = gln.chars().filter_map(|c| c.to_digit(10)).collect();
+    // Weights alternate 3 and 1 from the digit next to the check digit
+    let sum: u32 = digits[..12]
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, digit)| if i % 2 == 0 { digit * 3 } else { *digit })
+        .sum();
+
+    (10 - sum % 10) % 10 == digits[12]
+}
+
+/// Structured part of a location, everything is optional
+#[derive(GraphQLInputObject, Debug, Default)]
+pub struct LocationDetails {
+    pub gln: Option<String>,
+    pub location_type: Option<LocationType>,
+    pub parent_id: Option<Uuid>,
+    pub street_address: Option<String>,
+    pub locality: Option<String>,
+    pub region: Option<String>,
+    pub postal_code: Option<String>,
+    pub country_code: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+/// A location with the locations nested in it
+#[derive(GraphQLObject, Debug)]
+pub struct LocationNode {
+    pub location: Location,
+    pub depth: i32,
+    pub children: Vec<LocationNode>,
+}
+
+impl LocationNode {
+    pub fn build(root: Location, nested: &[Location]) -> Self {
+        Self::build_at(root, nested, 0)
+    }
+
+    fn build_at(location: Location, nested: &[Location], depth: i32) -> Self {
+        let children = nested
+            .iter()
+            .filter(|l| l.parent_id == Some(location.id))
+            .map(|l| Self::build_at(l.clone(), nested, depth + 1))
+            .collect();
+
+        LocationNode { location, depth, children }
+    }
 }
 
 #[derive(Insertable)]
@@ -20,6 +170,16 @@ pub struct NewLocation<'a> {
     pub agent_id: &'a Uuid,
     pub name: &'a str,
     pub value: &'a str,
+    pub gln: Option<&'a str>,
+    pub location_type: LocationType,
+    pub parent_id: Option<&'a Uuid>,
+    pub street_address: Option<&'a str>,
+    pub locality: Option<&'a str>,
+    pub region: Option<&'a str>,
+    pub postal_code: Option<&'a str>,
+    pub country_code: Option<&'a str>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
 }
 
 impl<'a> NewLocation<'a> {
@@ -32,7 +192,30 @@ impl<'a> NewLocation<'a> {
             name,
             agent_id,
             value,
+            gln: None,
+            location_type: LocationType::Other,
+            parent_id: None,
+            street_address: None,
+            locality: None,
+            region: None,
+            postal_code: None,
+            country_code: None,
+            latitude: None,
+            longitude: None,
         }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_gln_check_digit() {
+        assert!(valid_gln("0614141000005"));
+        assert!(valid_gln("4006381333931"));
+        assert!(!valid_gln("0614141000006"));
+        assert!(!valid_gln("061414100000"));
+        assert!(!valid_gln("06141410000a5"));
+    }
+}
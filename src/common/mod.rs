@@ -1,4 +1,10 @@
 pub mod agent;
 pub mod resource_specification;
 pub mod economic_resource;
-pub mod location;
\ No newline at end of file
+pub mod location;
+pub mod counter;
+pub mod unit;
+pub mod containment;
+pub mod ledger;
+pub mod resource_operation;
+pub mod user;
\ No newline at end of file
@@ -0,0 +1,106 @@
+use std::io::Write;
+
+use bigdecimal::{BigDecimal, Signed, Zero};
+use diesel::{
+    deserialize::{self, FromSql, FromSqlRow},
+    expression::AsExpression,
+    pg::{Pg, PgValue},
+    serialize::{self, IsNull, Output, ToSql},
+};
+use juniper::{GraphQLEnum, GraphQLInputObject, GraphQLObject};
+use uuid::Uuid;
+
+use crate::{db::schema::sql_types::ResourceOperationEnum, recipe::process::execution::ProcessExecution};
+
+use super::economic_resource::{Decimal, EconomicResource};
+
+#[derive(Debug, PartialEq, FromSqlRow, AsExpression, Eq, GraphQLEnum, Clone, Copy)]
+#[diesel(sql_type = ResourceOperationEnum)]
+pub enum ResourceOperation {
+    /// One resource divided into several, a partial shipment of a lot
+    Split,
+    /// Several resources commingled into one
+    Merge,
+}
+
+impl ToSql<ResourceOperationEnum, Pg> for ResourceOperation {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        match *self {
+            ResourceOperation::Split => out.write_all(b"Split")?,
+            ResourceOperation::Merge => out.write_all(b"Merge")?,
+        }
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<ResourceOperationEnum, Pg> for ResourceOperation {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"Split" => Ok(ResourceOperation::Split),
+            b"Merge" => Ok(ResourceOperation::Merge),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
+
+/// One resource split off, in the unit of the resource specification
+#[derive(GraphQLInputObject, Debug)]
+pub struct SplitPart {
+    pub quantity: Decimal,
+    /// Lot of the new resource, the lot of the split resource when empty
+    pub lot: Option<String>,
+}
+
+/// The Consume and Produce executions of a split or a merge, run as one batch so
+/// traceability connects the consumed resources with the produced ones
+#[derive(GraphQLObject, Debug)]
+pub struct ResourceOperationResponse {
+    pub operation: ResourceOperation,
+    pub batch_id: Uuid,
+    pub executions: Vec<ProcessExecution>,
+    pub consumed: Vec<EconomicResource>,
+    pub produced: Vec<EconomicResource>,
+}
+
+/// What is left of the resource once the parts are split off
+pub fn split_remainder(available: &BigDecimal, quantities: &[BigDecimal]) -> Result<BigDecimal, String> {
+    if quantities.is_empty() {
+        return Err("At least one part is required".to_string());
+    }
+    if quantities.iter().any(|q| !q.is_positive()) {
+        return Err("Parts must be greater than zero".to_string());
+    }
+
+    let total = quantities.iter().fold(BigDecimal::zero(), |total, q| total + q);
+    if &total > available {
+        return Err(format!("The parts add up to {}, only {} is on hand", total, available));
+    }
+
+    Ok(available - total)
+}
+
+/// The lot the merged resources share, none when they differ
+pub fn shared_lot(lots: &[Option<String>]) -> Option<String> {
+    let first = lots.first()?.clone()?;
+    lots.iter().all(|lot| lot.as_ref() == Some(&first)).then_some(first)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_split_parts() {
+        let available = BigDecimal::from(10);
+        assert_eq!(split_remainder(&available, &[BigDecimal::from(4), BigDecimal::from(6)]), Ok(BigDecimal::zero()));
+        assert_eq!(split_remainder(&available, &[BigDecimal::from(3)]), Ok(BigDecimal::from(7)));
+        assert!(split_remainder(&available, &[BigDecimal::from(6), BigDecimal::from(5)]).is_err());
+        assert!(split_remainder(&available, &[BigDecimal::zero()]).is_err());
+        assert!(split_remainder(&available, &[]).is_err());
+
+        let a = Some("A".to_string());
+        assert_eq!(shared_lot(&[a.clone(), a.clone()]), a);
+        assert_eq!(shared_lot(&[a.clone(), Some("B".to_string())]), None);
+        assert_eq!(shared_lot(&[a, None]), None);
+    }
+}
@@ -56,7 +56,9 @@ pub struct ResourceSpecification {
     pub note: Option<String>,
     pub created_at: NaiveDateTime,
     pub resource_type: ResourceType,  
-    pub unit_of_measure: String
+    pub unit_of_measure: String,
+    /// Registered unit the quantities of the specification are kept in, unit_of_measure is its symbol
+    pub unit_id: Option<Uuid>
 }
 
 #[derive(Insertable, Debug)]
@@ -66,7 +68,8 @@ pub struct NewResourceSpecification<'a> {
     pub name: &'a str,
     pub note: Option<&'a str>,
     pub resource_type: &'a ResourceType,
-    pub unit_of_measure: &'a str
+    pub unit_of_measure: &'a str,
+    pub unit_id: Option<&'a Uuid>
 }
 
 impl<'a> NewResourceSpecification<'a> {
@@ -82,7 +85,8 @@ impl<'a> NewResourceSpecification<'a> {
             name,
             note,
             resource_type,
-            unit_of_measure
+            unit_of_measure,
+            unit_id: None
         }
     }
 }
@@ -0,0 +1,172 @@
+use std::io::Write;
+
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+use diesel::{
+    deserialize::{self, FromSql, FromSqlRow},
+    expression::AsExpression,
+    pg::{Pg, PgValue},
+    serialize::{self, IsNull, Output, ToSql},
+    Insertable, Queryable,
+};
+use juniper::{GraphQLEnum, GraphQLObject};
+use uuid::Uuid;
+
+use crate::db::schema::{sql_types::UnitDimensionEnum, units};
+
+use super::economic_resource::Decimal;
+
+/// Decimal places kept when a conversion doesn't terminate, like kilograms to pounds
+const CONVERSION_SCALE: i64 = 9;
+
+/// What a unit measures, only units of the same dimension convert into each other
+#[derive(Debug, PartialEq, FromSqlRow, AsExpression, Eq, GraphQLEnum, Clone, Copy)]
+#[diesel(sql_type = UnitDimensionEnum)]
+pub enum UnitDimension {
+    Mass,
+    Length,
+    Area,
+    Volume,
+    Time,
+    Count,
+}
+
+impl ToSql<UnitDimensionEnum, Pg> for UnitDimension {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        match *self {
+            UnitDimension::Mass => out.write_all(b"Mass")?,
+            UnitDimension::Length => out.write_all(b"Length")?,
+            UnitDimension::Area => out.write_all(b"Area")?,
+            UnitDimension::Volume => out.write_all(b"Volume")?,
+            UnitDimension::Time => out.write_all(b"Time")?,
+            UnitDimension::Count => out.write_all(b"Count")?,
+        }
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<UnitDimensionEnum, Pg> for UnitDimension {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"Mass" => Ok(UnitDimension::Mass),
+            b"Length" => Ok(UnitDimension::Length),
+            b"Area" => Ok(UnitDimension::Area),
+            b"Volume" => Ok(UnitDimension::Volume),
+            b"Time" => Ok(UnitDimension::Time),
+            b"Count" => Ok(UnitDimension::Count),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
+
+/// Unit of measure after OM2, the conversion factor gives the unit in the
+/// base unit of its dimension, kilograms for mass
+#[derive(Queryable, GraphQLObject, Debug, Clone)]
+#[diesel(table_name = units)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Unit {
+    pub id: Uuid,
+    pub label: String,
+    pub symbol: String,
+    pub dimension: UnitDimension,
+    pub conversion_factor: Decimal,
+    pub om2_uri: Option<String>,
+    pub created_at: NaiveDateTime,
+    /// UN/ECE Recommendation 20 code, EPCIS quantities carry it as their uom
+    pub unece_code: Option<String>,
+}
+
+impl Unit {
+    /// Units are named by their symbol or their label, in any case and in the plural
+    pub fn matches(&self, name: &str) -> bool {
+        let name = name.trim().to_lowercase();
+        let label = self.label.to_lowercase();
+        name == self.symbol.to_lowercase() || name == label || name == format!("{}s", label)
+    }
+
+    pub fn convert(&self, quantity: &BigDecimal, to: &Unit) -> Result<BigDecimal, String> {
+        if self.id == to.id {
+            return Ok(quantity.clone());
+        }
+        if self.dimension != to.dimension {
+            return Err(format!(
+                "Can't convert {} ({:?}) to {} ({:?})",
+                self.symbol, self.dimension, to.symbol, to.dimension
+            ));
+        }
+
+        let converted = (quantity * &self.conversion_factor.0 / &to.conversion_factor.0).round(CONVERSION_SCALE);
+        let normalized = converted.normalized();
+        // Whole numbers normalize to a negative scale, 1000 to 1E+3
+        if normalized.as_bigint_and_exponent().1 < 0 {
+            Ok(normalized.with_scale(0))
+        } else {
+            Ok(normalized)
+        }
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = units)]
+pub struct NewUnit<'a> {
+    pub label: &'a str,
+    pub symbol: &'a str,
+    pub dimension: &'a UnitDimension,
+    pub conversion_factor: &'a Decimal,
+    pub om2_uri: Option<&'a str>,
+    pub unece_code: Option<&'a str>,
+}
+
+impl<'a> NewUnit<'a> {
+    pub fn new(
+        label: &'a str,
+        symbol: &'a str,
+        dimension: &'a UnitDimension,
+        conversion_factor: &'a Decimal,
+        om2_uri: Option<&'a str>,
+    ) -> Self {
+        NewUnit {
+            label,
+            symbol,
+            dimension,
+            conversion_factor,
+            om2_uri,
+            unece_code: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit(label: &str, symbol: &str, dimension: UnitDimension, conversion_factor: &str) -> Unit {
+        Unit {
+            id: Uuid::new_v4(),
+            label: label.to_string(),
+            symbol: symbol.to_string(),
+            dimension,
+            conversion_factor: conversion_factor.parse().unwrap(),
+            om2_uri: None,
+            created_at: NaiveDateTime::default(),
+            unece_code: None,
+        }
+    }
+
+    #[test]
+    fn converts_within_a_dimension() {
+        let kilogram = unit("kilogram", "kg", UnitDimension::Mass, "1");
+        let gram = unit("gram", "g", UnitDimension::Mass, "0.001");
+        let pound = unit("pound", "lb", UnitDimension::Mass, "0.45359237");
+        let litre = unit("litre", "l", UnitDimension::Volume, "0.001");
+        let quantity = |q: &str| q.parse::<BigDecimal>().unwrap();
+
+        assert_eq!(gram.convert(&quantity("1250"), &kilogram), Ok(quantity("1.25")));
+        assert_eq!(kilogram.convert(&quantity("2"), &gram).unwrap().to_string(), "2000");
+        assert_eq!(kilogram.convert(&quantity("1"), &pound), Ok(quantity("2.204622622")));
+        assert!(gram.convert(&quantity("1"), &litre).is_err());
+
+        assert!(kilogram.matches("KG") && kilogram.matches("kilogram") && kilogram.matches(" Kilograms "));
+        assert!(!kilogram.matches("g"));
+    }
+}
@@ -0,0 +1,254 @@
+use std::io::Write;
+
+use chrono::NaiveDateTime;
+use diesel::{
+    deserialize::{self, FromSql, FromSqlRow},
+    expression::AsExpression,
+    pg::{Pg, PgValue},
+    serialize::{self, IsNull, Output, ToSql},
+    Insertable, Queryable,
+};
+use juniper::{GraphQLEnum, GraphQLObject};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::db::schema::{agent_memberships, api_keys, sql_types::AgentRoleEnum, users};
+
+/// Every key starts with it, so leaked keys are easy to search for
+pub const API_KEY_PREFIX: &str = "vf_";
+
+/// A person acting for the agents they are a member of
+#[derive(Queryable, GraphQLObject, Debug, Clone)]
+#[diesel(table_name = users)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct User {
+    pub id: Uuid,
+    pub name: String,
+    pub email: String,
+    pub created_at: NaiveDateTime,
+    /// Manages what all agents share, like units
+    pub deployment_admin: bool,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = users)]
+pub struct NewUser<'a> {
+    pub name: &'a str,
+    pub email: &'a str,
+    pub deployment_admin: bool,
+}
+
+impl<'a> NewUser<'a> {
+    /// Deployment admins are set on the result
+    pub fn new(name: &'a str, email: &'a str) -> Self {
+        NewUser { name, email, deployment_admin: false }
+    }
+}
+
+/// What a member may do within the agent, every member may read the agent's data
+#[derive(Debug, PartialEq, FromSqlRow, AsExpression, Eq, GraphQLEnum, Clone, Copy)]
+#[diesel(sql_type = AgentRoleEnum)]
+pub enum AgentRole {
+    /// Everything, including the members and the templates the agent has access to
+    Admin,
+    /// Templates, and the recipes and processes made from them
+    TemplateDesigner,
+    /// Events and the resources they act on
+    Operator,
+    /// Read-only
+    Auditor,
+}
+
+impl ToSql<AgentRoleEnum, Pg> for AgentRole {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        match *self {
+            AgentRole::Admin => out.write_all(b"Admin")?,
+            AgentRole::TemplateDesigner => out.write_all(b"TemplateDesigner")?,
+            AgentRole::Operator => out.write_all(b"Operator")?,
+            AgentRole::Auditor => out.write_all(b"Auditor")?,
+        }
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<AgentRoleEnum, Pg> for AgentRole {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"Admin" => Ok(AgentRole::Admin),
+            b"TemplateDesigner" => Ok(AgentRole::TemplateDesigner),
+            b"Operator" => Ok(AgentRole::Operator),
+            b"Auditor" => Ok(AgentRole::Auditor),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
+
+/// What members may do with the agent's data, each granted to some of the roles
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Permission {
+    /// Reading anything of the agent, every member may
+    Read,
+    /// Executing and correcting events, creating, packing, splitting and merging resources
+    Operate,
+    /// Editing templates, recipes, processes and the settings of their data fields
+    Design,
+    /// Managing members, locations, specifications, counters and template access
+    Administer,
+}
+
+impl AgentRole {
+    pub fn allows(self, permission: Permission) -> bool {
+        match self {
+            AgentRole::Admin => true,
+            AgentRole::TemplateDesigner => matches!(permission, Permission::Read | Permission::Design),
+            AgentRole::Operator => matches!(permission, Permission::Read | Permission::Operate),
+            AgentRole::Auditor => permission == Permission::Read,
+        }
+    }
+}
+
+#[derive(Queryable, GraphQLObject, Debug, Clone)]
+#[diesel(table_name = agent_memberships)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct AgentMembership {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub agent_id: Uuid,
+    pub created_at: NaiveDateTime,
+    pub role: AgentRole,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = agent_memberships)]
+pub struct NewAgentMembership<'a> {
+    pub user_id: &'a Uuid,
+    pub agent_id: &'a Uuid,
+    pub role: &'a AgentRole,
+}
+
+impl<'a> NewAgentMembership<'a> {
+    pub fn new(user_id: &'a Uuid, agent_id: &'a Uuid, role: &'a AgentRole) -> Self {
+        NewAgentMembership { user_id, agent_id, role }
+    }
+}
+
+/// A member of an agent with their role
+#[derive(GraphQLObject, Debug)]
+pub struct AgentMember {
+    pub user: User,
+    pub membership: AgentMembership,
+}
+
+#[derive(Queryable, GraphQLObject, Debug, Clone)]
+#[diesel(table_name = api_keys)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    /// First characters of the key, to tell keys apart
+    pub prefix: String,
+    #[graphql(skip)]
+    pub key_hash: String,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = api_keys)]
+pub struct NewApiKey<'a> {
+    pub user_id: &'a Uuid,
+    pub name: &'a str,
+    pub prefix: &'a str,
+    pub key_hash: &'a str,
+}
+
+impl<'a> NewApiKey<'a> {
+    pub fn new(user_id: &'a Uuid, name: &'a str, prefix: &'a str, key_hash: &'a str) -> Self {
+        NewApiKey { user_id, name, prefix, key_hash }
+    }
+}
+
+/// A newly issued key, the only time the key itself is returned
+#[derive(GraphQLObject, Debug)]
+pub struct IssuedApiKey {
+    pub user: User,
+    pub api_key: ApiKey,
+    /// Sent as `Authorization: Bearer <key>`
+    pub key: String,
+}
+
+pub fn generate_api_key() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    format!("{}{}", API_KEY_PREFIX, hex::encode(bytes))
+}
+
+/// Keys are looked up by their SHA-256, they are random enough not to need a salt
+pub fn hash_api_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// True when the deployment has a bootstrap secret and it was given, deployments
+/// without one can't be bootstrapped
+pub fn is_bootstrap_secret(configured: Option<&str>, given: &str) -> bool {
+    match configured.map(str::trim).filter(|secret| !secret.is_empty()) {
+        Some(secret) => hash_api_key(secret) == hash_api_key(given.trim()),
+        None => false,
+    }
+}
+
+/// The key of an `Authorization: Bearer <key>` header value
+pub fn bearer_key(header: &str) -> Option<&str> {
+    let (scheme, key) = header.trim().split_once(' ')?;
+    let key = key.trim();
+    (scheme.eq_ignore_ascii_case("bearer") && !key.is_empty()).then_some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn issues_and_reads_api_keys() {
+        let key = generate_api_key();
+        assert!(key.starts_with(API_KEY_PREFIX) && key.len() == API_KEY_PREFIX.len() + 64);
+        assert_ne!(key, generate_api_key());
+        assert_eq!(hash_api_key(&key), hash_api_key(&key));
+        assert_eq!(
+            hash_api_key("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        assert_eq!(bearer_key(&format!("Bearer {}", key)), Some(key.as_str()));
+        assert_eq!(bearer_key("bearer  vf_1 "), Some("vf_1"));
+        assert_eq!(bearer_key("Basic dXNlcg=="), None);
+        assert_eq!(bearer_key("Bearer "), None);
+    }
+
+    #[test]
+    fn bootstraps_with_the_configured_secret_only() {
+        assert!(is_bootstrap_secret(Some("s3cret"), " s3cret "));
+        assert!(!is_bootstrap_secret(Some("s3cret"), "secret"));
+        assert!(!is_bootstrap_secret(None, ""));
+        assert!(!is_bootstrap_secret(Some(" "), " "));
+    }
+
+    #[test]
+    fn roles_allow_their_permissions() {
+        use Permission::*;
+
+        let allowed = |role: AgentRole| {
+            [Read, Operate, Design, Administer]
+                .into_iter()
+                .filter(|p| role.allows(*p))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(allowed(AgentRole::Admin), vec![Read, Operate, Design, Administer]);
+        assert_eq!(allowed(AgentRole::TemplateDesigner), vec![Read, Design]);
+        assert_eq!(allowed(AgentRole::Operator), vec![Read, Operate]);
+        assert_eq!(allowed(AgentRole::Auditor), vec![Read]);
+    }
+}
@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const EPCIS_CONTEXT: &str = "https://ref.gs1.org/standards/epcis/epcis-context.jsonld";
+pub const EPCIS_DOCUMENT_TYPE: &str = "EPCISDocument";
+pub const EPCIS_SCHEMA_VERSION: &str = "2.0";
+
+/// GS1 EPCIS 2.0 document in its JSON-LD serialization
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EpcisDocument {
+    #[serde(rename = "@context", default)]
+    pub context: Value,
+    #[serde(rename = "type")]
+    pub document_type: String,
+    pub schema_version: String,
+    pub creation_date: String,
+    pub epcis_body: EpcisBody,
+}
+
+impl EpcisDocument {
+    pub fn new(creation_date: String, event_list: Vec<EpcisEvent>) -> Self {
+        EpcisDocument {
+            context: Value::Array(vec![Value::String(EPCIS_CONTEXT.to_string())]),
+            document_type: EPCIS_DOCUMENT_TYPE.to_string(),
+            schema_version: EPCIS_SCHEMA_VERSION.to_string(),
+            creation_date,
+            epcis_body: EpcisBody { event_list },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EpcisBody {
+    pub event_list: Vec<EpcisEvent>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum EpcisEventType {
+    #[serde(rename = "ObjectEvent")]
+    Object,
+    #[serde(rename = "AggregationEvent")]
+    Aggregation,
+    #[serde(rename = "TransformationEvent")]
+    Transformation,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum EpcisAction {
+    Add,
+    Observe,
+    Delete,
+}
+
+/// ObjectEvent, AggregationEvent or TransformationEvent, only the fields this
+/// project maps are kept. Objects are identified by class and lot, so events
+/// carry quantity lists rather than instance EPCs
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EpcisEvent {
+    #[serde(rename = "type")]
+    pub event_type: EpcisEventType,
+    pub event_time: String,
+    pub event_time_zone_offset: String,
+    #[serde(rename = "eventID", skip_serializing_if = "Option::is_none")]
+    pub event_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action: Option<EpcisAction>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub biz_step: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disposition: Option<String>,
+    #[serde(rename = "parentID", skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub quantity_list: Vec<QuantityElement>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub child_quantity_list: Vec<QuantityElement>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub input_quantity_list: Vec<QuantityElement>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub output_quantity_list: Vec<QuantityElement>,
+    #[serde(rename = "transformationID", skip_serializing_if = "Option::is_none")]
+    pub transformation_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_point: Option<Reference>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub biz_location: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub biz_transaction_list: Vec<BizTransaction>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub source_list: Vec<Source>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub destination_list: Vec<Destination>,
+}
+
+impl EpcisEvent {
+    pub fn new(event_type: EpcisEventType, event_time: String) -> Self {
+        EpcisEvent {
+            event_type,
+            event_time,
+            event_time_zone_offset: "+00:00".to_string(),
+            event_id: None,
+            action: None,
+            biz_step: None,
+            disposition: None,
+            parent_id: None,
+            quantity_list: Vec::new(),
+            child_quantity_list: Vec::new(),
+            input_quantity_list: Vec::new(),
+            output_quantity_list: Vec::new(),
+            transformation_id: None,
+            read_point: None,
+            biz_location: None,
+            biz_transaction_list: Vec::new(),
+            source_list: Vec::new(),
+            destination_list: Vec::new(),
+        }
+    }
+
+    /// Owning party the objects come from
+    pub fn source_party(&self) -> Option<&str> {
+        self.source_list
+            .iter()
+            .find(|s| s.source_type == SOURCE_DESTINATION_OWNING_PARTY)
+            .map(|s| s.source.as_str())
+    }
+
+    /// Owning party the objects go to
+    pub fn destination_party(&self) -> Option<&str> {
+        self.destination_list
+            .iter()
+            .find(|d| d.destination_type == SOURCE_DESTINATION_OWNING_PARTY)
+            .map(|d| d.destination.as_str())
+    }
+
+    pub fn source_location(&self) -> Option<&str> {
+        self.source_list
+            .iter()
+            .find(|s| s.source_type == SOURCE_DESTINATION_LOCATION)
+            .map(|s| s.source.as_str())
+    }
+
+    pub fn destination_location(&self) -> Option<&str> {
+        self.destination_list
+            .iter()
+            .find(|d| d.destination_type == SOURCE_DESTINATION_LOCATION)
+            .map(|d| d.destination.as_str())
+    }
+}
+
+pub const SOURCE_DESTINATION_OWNING_PARTY: &str = "owning_party";
+pub const SOURCE_DESTINATION_LOCATION: &str = "location";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct QuantityElement {
+    pub epc_class: String,
+    pub quantity: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uom: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Reference {
+    pub id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BizTransaction {
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub transaction_type: Option<String>,
+    pub biz_transaction: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Source {
+    #[serde(rename = "type")]
+    pub source_type: String,
+    pub source: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Destination {
+    #[serde(rename = "type")]
+    pub destination_type: String,
+    pub destination: String,
+}
@@ -0,0 +1,235 @@
+//! Mapping between ValueFlows flows and EPCIS 2.0 events
+//!
+//! Actions map to CBV 2.0 business steps where one exists. CBV has no step for
+//! a resource taking part in a process without being consumed or for a
+//! process referring to a resource, so `use` and `cite` are published in our
+//! own vocabulary, which EPCIS allows for bizStep as long as it is a URI:
+//!
+//! | bizStep               | action | meaning                                            |
+//! |-----------------------|--------|----------------------------------------------------|
+//! | `urn:vf:bizstep:use`  | Use    | equipment or a tool was used and is still there    |
+//! | `urn:vf:bizstep:cite` | Cite   | a design or specification was referred to, unused  |
+//!
+//! Agents, locations, resources and resource classes without a GS1 key are
+//! identified by `urn:vf:agent:`, `urn:vf:location:`, `urn:vf:resource:` and
+//! `urn:vf:class:` URNs followed by their id, classes adding `.lot`.
+
+use uuid::Uuid;
+
+use crate::templates::recipe_flow_template::{ActionType, RoleType};
+
+use super::document::{EpcisAction, EpcisEventType};
+
+/// EPCIS shape of a ValueFlows action, bizStep values are CBV 2.0 terms where one exists
+#[derive(Debug)]
+pub struct EventMapping {
+    pub action: ActionType,
+    pub role_type: RoleType,
+    pub event_type: EpcisEventType,
+    pub epcis_action: EpcisAction,
+    pub biz_step: &'static str,
+}
+
+/// One entry per action and role, Transfer is shipping for the provider and receiving for the receiver
+pub const EVENT_MAPPINGS: [EventMapping; 11] = [
+    EventMapping {
+        action: ActionType::Produce,
+        role_type: RoleType::Output,
+        event_type: EpcisEventType::Object,
+        epcis_action: EpcisAction::Add,
+        biz_step: "commissioning",
+    },
+    EventMapping {
+        action: ActionType::Consume,
+        role_type: RoleType::Input,
+        event_type: EpcisEventType::Object,
+        epcis_action: EpcisAction::Delete,
+        biz_step: "decommissioning",
+    },
+    EventMapping {
+        action: ActionType::Transfer,
+        role_type: RoleType::Output,
+        event_type: EpcisEventType::Object,
+        epcis_action: EpcisAction::Observe,
+        biz_step: "shipping",
+    },
+    EventMapping {
+        action: ActionType::Transfer,
+        role_type: RoleType::Input,
+        event_type: EpcisEventType::Object,
+        epcis_action: EpcisAction::Observe,
+        biz_step: "receiving",
+    },
+    EventMapping {
+        action: ActionType::Dispatch,
+        role_type: RoleType::Output,
+        event_type: EpcisEventType::Object,
+        epcis_action: EpcisAction::Observe,
+        biz_step: "departing",
+    },
+    EventMapping {
+        action: ActionType::Accept,
+        role_type: RoleType::Input,
+        event_type: EpcisEventType::Object,
+        epcis_action: EpcisAction::Observe,
+        biz_step: "accepting",
+    },
+    EventMapping {
+        action: ActionType::Modify,
+        role_type: RoleType::Output,
+        event_type: EpcisEventType::Object,
+        epcis_action: EpcisAction::Observe,
+        biz_step: "repairing",
+    },
+    EventMapping {
+        action: ActionType::Use,
+        role_type: RoleType::Input,
+        event_type: EpcisEventType::Object,
+        epcis_action: EpcisAction::Observe,
+        biz_step: "urn:vf:bizstep:use",
+    },
+    EventMapping {
+        action: ActionType::Cite,
+        role_type: RoleType::Input,
+        event_type: EpcisEventType::Object,
+        epcis_action: EpcisAction::Observe,
+        biz_step: "urn:vf:bizstep:cite",
+    },
+    EventMapping {
+        action: ActionType::Load,
+        role_type: RoleType::Input,
+        event_type: EpcisEventType::Aggregation,
+        epcis_action: EpcisAction::Add,
+        biz_step: "loading",
+    },
+    EventMapping {
+        action: ActionType::Unload,
+        role_type: RoleType::Output,
+        event_type: EpcisEventType::Aggregation,
+        epcis_action: EpcisAction::Delete,
+        biz_step: "unloading",
+    },
+];
+
+impl EventMapping {
+    pub fn for_flow(action: ActionType, role_type: RoleType) -> Option<&'static EventMapping> {
+        EVENT_MAPPINGS
+            .iter()
+            .find(|m| m.action == action && m.role_type == role_type)
+    }
+
+    /// Mapping of an incoming event, a bare or CBV URI bizStep matches the same term
+    pub fn for_event(
+        event_type: EpcisEventType,
+        epcis_action: EpcisAction,
+        biz_step: &str,
+    ) -> Option<&'static EventMapping> {
+        let biz_step = biz_step.trim_start_matches(CBV_BIZ_STEP_PREFIX);
+        EVENT_MAPPINGS
+            .iter()
+            .find(|m| m.event_type == event_type && m.epcis_action == epcis_action && m.biz_step == biz_step)
+    }
+}
+
+const CBV_BIZ_STEP_PREFIX: &str = "https://ref.gs1.org/cbv/BizStep-";
+
+const AGENT_PREFIX: &str = "urn:vf:agent:";
+const LOCATION_PREFIX: &str = "urn:vf:location:";
+const RESOURCE_PREFIX: &str = "urn:vf:resource:";
+const CLASS_PREFIX: &str = "urn:vf:class:";
+/// GS1 Digital Link of a GLN, locations with one are identified by it
+const GLN_PREFIX: &str = "https://id.gs1.org/414/";
+
+pub fn agent_urn(agent_id: &Uuid) -> String {
+    format!("{}{}", AGENT_PREFIX, agent_id)
+}
+
+pub fn location_urn(location_id: &Uuid) -> String {
+    format!("{}{}", LOCATION_PREFIX, location_id)
+}
+
+pub fn gln_uri(gln: &str) -> String {
+    format!("{}{}", GLN_PREFIX, gln)
+}
+
+pub fn resource_urn(resource_id: &Uuid) -> String {
+    format!("{}{}", RESOURCE_PREFIX, resource_id)
+}
+
+/// Class level identifier of a resource specification and lot, the
+/// counterpart of an LGTIN for products without a GTIN
+pub fn class_urn(resource_specification_id: &Uuid, lot: Option<&str>) -> String {
+    match lot {
+        Some(lot) => format!("{}{}.{}", CLASS_PREFIX, resource_specification_id, lot),
+        None => format!("{}{}", CLASS_PREFIX, resource_specification_id),
+    }
+}
+
+pub fn parse_agent_urn(urn: &str) -> Option<Uuid> {
+    parse_id(urn, AGENT_PREFIX)
+}
+
+pub fn parse_location_urn(urn: &str) -> Option<Uuid> {
+    parse_id(urn, LOCATION_PREFIX)
+}
+
+pub fn parse_gln_uri(uri: &str) -> Option<String> {
+    let gln = uri.strip_prefix(GLN_PREFIX)?;
+    (gln.len() == 13 && gln.chars().all(|c| c.is_ascii_digit())).then(|| gln.to_string())
+}
+
+pub fn parse_resource_urn(urn: &str) -> Option<Uuid> {
+    parse_id(urn, RESOURCE_PREFIX)
+}
+
+/// Returns the resource specification and lot of a class identifier
+pub fn parse_class_urn(urn: &str) -> Option<(Uuid, Option<String>)> {
+    let class = urn.strip_prefix(CLASS_PREFIX)?;
+    match class.split_once('.') {
+        Some((spec, lot)) if !lot.is_empty() => Some((Uuid::parse_str(spec).ok()?, Some(lot.to_string()))),
+        Some(_) => None,
+        None => Some((Uuid::parse_str(class).ok()?, None)),
+    }
+}
+
+fn parse_id(urn: &str, prefix: &str) -> Option<Uuid> {
+    Uuid::parse_str(urn.strip_prefix(prefix)?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_action_role_maps_to_a_distinct_event() {
+        for mapping in EVENT_MAPPINGS.iter() {
+            let found = EventMapping::for_event(mapping.event_type, mapping.epcis_action, mapping.biz_step)
+                .expect("Every mapping can be found back from its event");
+            assert_eq!((found.action, found.role_type), (mapping.action, mapping.role_type));
+        }
+
+        let shipping = EventMapping::for_event(
+            EpcisEventType::Object,
+            EpcisAction::Observe,
+            "https://ref.gs1.org/cbv/BizStep-shipping",
+        )
+        .unwrap();
+        assert_eq!((shipping.action, shipping.role_type), (ActionType::Transfer, RoleType::Output));
+    }
+
+    #[test]
+    fn class_urn_round_trip() {
+        let spec = Uuid::new_v4();
+
+        assert_eq!(
+            parse_class_urn(&class_urn(&spec, Some("ACME-20261018-0001.A"))),
+            Some((spec, Some("ACME-20261018-0001.A".to_string())))
+        );
+        assert_eq!(parse_class_urn(&class_urn(&spec, None)), Some((spec, None)));
+        assert_eq!(parse_class_urn("urn:epc:class:lgtin:4012345.012345.998877"), None);
+        assert_eq!(parse_agent_urn(&agent_urn(&spec)), Some(spec));
+        assert_eq!(parse_agent_urn(&location_urn(&spec)), None);
+        assert_eq!(parse_gln_uri(&gln_uri("0614141000005")), Some("0614141000005".to_string()));
+        assert_eq!(parse_gln_uri("https://id.gs1.org/414/061414"), None);
+    }
+}
@@ -0,0 +1,260 @@
+use crate::templates::{
+    recipe_flow_template::{ActionType, RoleType},
+    recipe_flow_template_data_field::{FieldClass, FieldType, FlowThrough},
+};
+
+/// Group holding the reference document type and number of a flow
+pub const REFERENCE_DOCUMENT_GROUP: &str = "Reference document";
+
+/// Data field of a Critical Tracking Event flow, most of them Key Data Elements
+pub struct CteDataField {
+    pub identifier: &'static str,
+    pub class: FieldClass,
+    pub field: &'static str,
+    pub field_type: FieldType,
+    pub required: bool,
+    pub flow_through: Option<FlowThrough>,
+    /// Field of another flow of the same event this field inherits, as (flow, field)
+    pub inherits: Option<(&'static str, &'static str)>,
+}
+
+pub struct CteFlow {
+    pub identifier: &'static str,
+    pub role_type: RoleType,
+    pub action: ActionType,
+    pub data_fields: &'static [CteDataField],
+}
+
+/// FSMA 204 Critical Tracking Event, installed as a recipe template of an FDA map
+pub struct CriticalTrackingEvent {
+    pub identifier: &'static str,
+    pub name: &'static str,
+    pub flows: &'static [CteFlow],
+}
+
+impl CteDataField {
+    const fn new(identifier: &'static str, class: FieldClass, field: &'static str, field_type: FieldType) -> Self {
+        CteDataField {
+            identifier,
+            class,
+            field,
+            field_type,
+            required: true,
+            flow_through: None,
+            inherits: None,
+        }
+    }
+
+    const fn optional(mut self) -> Self {
+        self.required = false;
+        self
+    }
+
+    const fn external(mut self) -> Self {
+        self.flow_through = Some(FlowThrough::External);
+        self
+    }
+
+    const fn internal(mut self) -> Self {
+        self.flow_through = Some(FlowThrough::Internal);
+        self
+    }
+
+    const fn inherits(mut self, flow: &'static str, field: &'static str) -> Self {
+        self.inherits = Some((flow, field));
+        self
+    }
+}
+
+const PRODUCT: CteDataField = CteDataField::new("product", FieldClass::ResourceSpecification, "Product", FieldType::Select);
+const LOT: CteDataField = CteDataField::new("lot", FieldClass::EconomicResource, "Traceability lot code", FieldType::Select);
+const QUANTITY: CteDataField = CteDataField::new("quantity", FieldClass::Quantity, "Quantity", FieldType::Number);
+const UNIT_OF_MEASURE: CteDataField = CteDataField::new("unit_of_measure", FieldClass::UnitOfMeasure, "Unit of measure", FieldType::Select);
+const DATE: CteDataField = CteDataField::new("date", FieldClass::HasPointInTime, "Date", FieldType::Date);
+const REFERENCE_DOCUMENT_TYPE: CteDataField = CteDataField::new("reference_document_type", FieldClass::ReferenceDocumentType, "Reference document type", FieldType::Select);
+const REFERENCE_DOCUMENT_NUMBER: CteDataField = CteDataField::new("reference_document_number", FieldClass::ReferenceDocumentNumber, "Reference document number", FieldType::Text);
+const NOTE: CteDataField = CteDataField::new("note", FieldClass::Note, "Note", FieldType::Text).optional();
+
+pub const CRITICAL_TRACKING_EVENTS: [CriticalTrackingEvent; 7] = [
+    CriticalTrackingEvent {
+        identifier: "fda_harvesting",
+        name: "Harvesting",
+        flows: &[CteFlow {
+            identifier: "harvest",
+            role_type: RoleType::Output,
+            action: ActionType::Produce,
+            data_fields: &[
+                PRODUCT,
+                QUANTITY,
+                UNIT_OF_MEASURE,
+                CteDataField::new("harvest_location", FieldClass::Location, "Harvest location", FieldType::Select).internal(),
+                DATE,
+                REFERENCE_DOCUMENT_TYPE,
+                REFERENCE_DOCUMENT_NUMBER,
+                NOTE,
+            ],
+        }],
+    },
+    CriticalTrackingEvent {
+        identifier: "fda_cooling",
+        name: "Cooling",
+        flows: &[
+            CteFlow {
+                identifier: "cooling_in",
+                role_type: RoleType::Input,
+                action: ActionType::Accept,
+                data_fields: &[LOT, QUANTITY, UNIT_OF_MEASURE],
+            },
+            CteFlow {
+                identifier: "cooling_out",
+                role_type: RoleType::Output,
+                action: ActionType::Modify,
+                data_fields: &[
+                    LOT.inherits("cooling_in", "lot"),
+                    QUANTITY.inherits("cooling_in", "quantity"),
+                    UNIT_OF_MEASURE.inherits("cooling_in", "unit_of_measure"),
+                    CteDataField::new("cooling_location", FieldClass::Location, "Cooling location", FieldType::Select).internal(),
+                    DATE,
+                    REFERENCE_DOCUMENT_TYPE,
+                    REFERENCE_DOCUMENT_NUMBER,
+                ],
+            },
+        ],
+    },
+    CriticalTrackingEvent {
+        identifier: "fda_initial_packing",
+        name: "Initial packing",
+        flows: &[
+            CteFlow {
+                identifier: "packing_in",
+                role_type: RoleType::Input,
+                action: ActionType::Consume,
+                data_fields: &[LOT, QUANTITY, UNIT_OF_MEASURE],
+            },
+            CteFlow {
+                identifier: "packing_out",
+                role_type: RoleType::Output,
+                action: ActionType::Produce,
+                data_fields: &[
+                    PRODUCT,
+                    QUANTITY,
+                    UNIT_OF_MEASURE,
+                    CteDataField::new("packing_location", FieldClass::Location, "Packing location", FieldType::Select).internal(),
+                    DATE,
+                    REFERENCE_DOCUMENT_TYPE,
+                    REFERENCE_DOCUMENT_NUMBER,
+                ],
+            },
+        ],
+    },
+    CriticalTrackingEvent {
+        identifier: "fda_first_land_based_receiving",
+        name: "First land-based receiving",
+        flows: &[CteFlow {
+            identifier: "landing",
+            role_type: RoleType::Output,
+            action: ActionType::Produce,
+            data_fields: &[
+                PRODUCT,
+                QUANTITY,
+                UNIT_OF_MEASURE,
+                CteDataField::new("landing_location", FieldClass::Location, "Landing location", FieldType::Select).internal(),
+                DATE,
+                REFERENCE_DOCUMENT_TYPE,
+                REFERENCE_DOCUMENT_NUMBER,
+                CteDataField::new("harvest_area", FieldClass::Note, "Harvest date and area", FieldType::Text).optional(),
+            ],
+        }],
+    },
+    CriticalTrackingEvent {
+        identifier: "fda_shipping",
+        name: "Shipping",
+        flows: &[CteFlow {
+            identifier: "shipping",
+            role_type: RoleType::Output,
+            action: ActionType::Transfer,
+            data_fields: &[
+                LOT,
+                QUANTITY,
+                UNIT_OF_MEASURE,
+                CteDataField::new("ship_to", FieldClass::Agent, "Ship to", FieldType::Select).external(),
+                CteDataField::new("ship_to_location", FieldClass::Location, "Ship to location", FieldType::Select).external(),
+                CteDataField::new("ship_from_location", FieldClass::Location, "Ship from location", FieldType::Select).internal(),
+                DATE,
+                REFERENCE_DOCUMENT_TYPE,
+                REFERENCE_DOCUMENT_NUMBER,
+            ],
+        }],
+    },
+    CriticalTrackingEvent {
+        identifier: "fda_receiving",
+        name: "Receiving",
+        flows: &[CteFlow {
+            identifier: "receiving",
+            role_type: RoleType::Input,
+            action: ActionType::Transfer,
+            data_fields: &[
+                LOT,
+                QUANTITY,
+                UNIT_OF_MEASURE,
+                CteDataField::new("shipped_by", FieldClass::Agent, "Immediate previous source", FieldType::Select).external(),
+                CteDataField::new("ship_from_location", FieldClass::Location, "Ship from location", FieldType::Select).external(),
+                CteDataField::new("receive_location", FieldClass::Location, "Receiving location", FieldType::Select).internal(),
+                DATE,
+                REFERENCE_DOCUMENT_TYPE,
+                REFERENCE_DOCUMENT_NUMBER,
+            ],
+        }],
+    },
+    CriticalTrackingEvent {
+        identifier: "fda_transformation",
+        name: "Transformation",
+        flows: &[
+            CteFlow {
+                identifier: "transformation_in",
+                role_type: RoleType::Input,
+                action: ActionType::Consume,
+                data_fields: &[LOT, QUANTITY, UNIT_OF_MEASURE],
+            },
+            CteFlow {
+                identifier: "transformation_out",
+                role_type: RoleType::Output,
+                action: ActionType::Produce,
+                data_fields: &[
+                    PRODUCT,
+                    QUANTITY,
+                    UNIT_OF_MEASURE,
+                    CteDataField::new("transformation_location", FieldClass::Location, "Transformation location", FieldType::Select).internal(),
+                    DATE,
+                    REFERENCE_DOCUMENT_TYPE,
+                    REFERENCE_DOCUMENT_NUMBER,
+                ],
+            },
+        ],
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flows_are_valid_for_their_actions() {
+        for cte in &CRITICAL_TRACKING_EVENTS {
+            for flow in cte.flows {
+                assert!(flow.action.allows_role(flow.role_type), "{}.{}", cte.identifier, flow.identifier);
+
+                for data_field in flow.data_fields {
+                    if let Some((inherited_flow, inherited_field)) = data_field.inherits {
+                        let inherited = cte
+                            .flows
+                            .iter()
+                            .find(|f| f.identifier == inherited_flow)
+                            .and_then(|f| f.data_fields.iter().find(|d| d.identifier == inherited_field));
+                        assert!(inherited.is_some(), "{}.{} inherits a missing field", cte.identifier, data_field.identifier);
+                    }
+                }
+            }
+        }
+    }
+}
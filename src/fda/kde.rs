@@ -0,0 +1,133 @@
+use uuid::Uuid;
+
+use crate::common::economic_resource::Decimal;
+
+/// FSMA 204 Key Data Elements checked when executing events against an FDA map
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum KeyDataElement {
+    TraceabilityLotCode,
+    TraceabilityLotCodeSource,
+    LocationDescription,
+    Quantity,
+    UnitOfMeasure,
+    ReferenceDocument,
+}
+
+impl KeyDataElement {
+    pub fn description(&self) -> &'static str {
+        match self {
+            KeyDataElement::TraceabilityLotCode => "traceability lot code",
+            KeyDataElement::TraceabilityLotCodeSource => "traceability lot code source",
+            KeyDataElement::LocationDescription => "location description",
+            KeyDataElement::Quantity => "quantity",
+            KeyDataElement::UnitOfMeasure => "unit of measure",
+            KeyDataElement::ReferenceDocument => "reference document",
+        }
+    }
+}
+
+/// Key Data Elements recorded by one flow of a Critical Tracking Event
+#[derive(Debug, Default)]
+pub struct CteRecord {
+    /// True when the flow moves a lot, the lot elements only apply to those flows
+    pub moves_lot: bool,
+    pub traceability_lot_code: Option<String>,
+    pub traceability_lot_code_source: Option<Uuid>,
+    pub location: Option<Uuid>,
+    pub quantity: Option<Decimal>,
+    pub unit_of_measure: Option<String>,
+    pub reference_document: bool,
+}
+
+/// Key Data Elements missing from the flows of one run of a Critical Tracking
+/// Event. Lot, quantity and unit apply to every flow moving a lot, location and
+/// reference document to the event as a whole.
+pub fn missing_key_data_elements(records: &[CteRecord]) -> Vec<KeyDataElement> {
+    let mut missing = Vec::new();
+    let lot_records: Vec<&CteRecord> = records.iter().filter(|r| r.moves_lot).collect();
+
+    let checks: [(KeyDataElement, bool); 6] = [
+        (
+            KeyDataElement::TraceabilityLotCode,
+            lot_records.iter().all(|r| r.traceability_lot_code.is_some()),
+        ),
+        (
+            KeyDataElement::TraceabilityLotCodeSource,
+            lot_records.iter().all(|r| r.traceability_lot_code_source.is_some()),
+        ),
+        (
+            KeyDataElement::LocationDescription,
+            records.iter().any(|r| r.location.is_some()),
+        ),
+        (
+            KeyDataElement::Quantity,
+            lot_records.iter().all(|r| r.quantity.is_some()),
+        ),
+        (
+            KeyDataElement::UnitOfMeasure,
+            lot_records.iter().all(|r| r.unit_of_measure.is_some()),
+        ),
+        (
+            KeyDataElement::ReferenceDocument,
+            records.iter().any(|r| r.reference_document),
+        ),
+    ];
+
+    for (key_data_element, present) in checks {
+        if !present {
+            missing.push(key_data_element);
+        }
+    }
+
+    missing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn complete() -> CteRecord {
+        CteRecord {
+            moves_lot: true,
+            traceability_lot_code: Some("ACME-20261018-0001".to_string()),
+            traceability_lot_code_source: Some(Uuid::new_v4()),
+            location: Some(Uuid::new_v4()),
+            quantity: Some(Decimal::from(10)),
+            unit_of_measure: Some("kg".to_string()),
+            reference_document: true,
+        }
+    }
+
+    #[test]
+    fn complete_event_has_nothing_missing() {
+        assert!(missing_key_data_elements(&[complete()]).is_empty());
+    }
+
+    #[test]
+    fn event_level_elements_can_come_from_any_flow() {
+        let input = CteRecord {
+            location: None,
+            reference_document: false,
+            ..complete()
+        };
+        assert!(missing_key_data_elements(&[input, complete()]).is_empty());
+    }
+
+    #[test]
+    fn reports_missing_elements() {
+        let record = CteRecord {
+            traceability_lot_code: None,
+            unit_of_measure: None,
+            reference_document: false,
+            ..complete()
+        };
+        assert_eq!(
+            missing_key_data_elements(&[record]),
+            vec![
+                KeyDataElement::TraceabilityLotCode,
+                KeyDataElement::UnitOfMeasure,
+                KeyDataElement::ReferenceDocument,
+            ]
+        );
+    }
+}
@@ -0,0 +1,3 @@
+pub mod cte;
+pub mod kde;
+pub mod spreadsheet;
\ No newline at end of file
@@ -0,0 +1,128 @@
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+use crate::common::economic_resource::Decimal;
+
+/// One Critical Tracking Event of the FDA sortable spreadsheet, columns follow the FSMA 204 KDEs
+#[derive(Debug, Default)]
+pub struct SpreadsheetRow {
+    pub traceability_lot_code: String,
+    pub traceability_lot_code_source: String,
+    pub product_description: String,
+    pub critical_tracking_event: String,
+    pub event_date: Option<NaiveDateTime>,
+    pub quantity: Option<Decimal>,
+    pub unit_of_measure: String,
+    pub location_description: String,
+    pub destination_location_description: String,
+    pub immediate_previous_source: String,
+    pub immediate_subsequent_recipient: String,
+    pub reference_document_type: String,
+    pub reference_document_number: String,
+    pub event_id: Uuid,
+}
+
+const HEADER: [&str; 14] = [
+    "Traceability Lot Code",
+    "Traceability Lot Code Source",
+    "Product Description",
+    "Critical Tracking Event",
+    "Event Date",
+    "Quantity",
+    "Unit of Measure",
+    "Location Description",
+    "Destination Location Description",
+    "Immediate Previous Source",
+    "Immediate Subsequent Recipient",
+    "Reference Document Type",
+    "Reference Document Number",
+    "Event ID",
+];
+
+impl SpreadsheetRow {
+    fn columns(&self) -> [String; 14] {
+        [
+            self.traceability_lot_code.clone(),
+            self.traceability_lot_code_source.clone(),
+            self.product_description.clone(),
+            self.critical_tracking_event.clone(),
+            self.event_date
+                .map(|date| date.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default(),
+            self.quantity.as_ref().map(|quantity| quantity.to_string()).unwrap_or_default(),
+            self.unit_of_measure.clone(),
+            self.location_description.clone(),
+            self.destination_location_description.clone(),
+            self.immediate_previous_source.clone(),
+            self.immediate_subsequent_recipient.clone(),
+            self.reference_document_type.clone(),
+            self.reference_document_number.clone(),
+            self.event_id.to_string(),
+        ]
+    }
+}
+
+/// Renders the rows as RFC 4180 CSV, which spreadsheet applications open as a sortable sheet
+pub fn to_csv(rows: &[SpreadsheetRow]) -> String {
+    let mut csv = String::new();
+    push_record(&mut csv, HEADER.iter().map(|column| column.to_string()));
+    for row in rows {
+        push_record(&mut csv, row.columns().into_iter());
+    }
+    csv
+}
+
+fn push_record(csv: &mut String, columns: impl Iterator<Item = String>) {
+    let record: Vec<String> = columns.map(|column| escape(&column)).collect();
+    csv.push_str(&record.join(","));
+    csv.push_str("\r\n");
+}
+
+/// Cells starting like a formula get a leading quote, so names, notes and lots
+/// entered by users open as text rather than run in the spreadsheet application
+fn escape(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_escaped_csv() {
+        let row = SpreadsheetRow {
+            traceability_lot_code: "ACME-20261018-0001".to_string(),
+            product_description: "Tomato, \"roma\"".to_string(),
+            quantity: Some(Decimal::from(10)),
+            ..Default::default()
+        };
+
+        let csv = to_csv(&[row]);
+        let lines: Vec<&str> = csv.split("\r\n").collect();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("Traceability Lot Code,Traceability Lot Code Source,"));
+        assert!(lines[1].starts_with("ACME-20261018-0001,,\"Tomato, \"\"roma\"\"\",,,10,"));
+    }
+
+    #[test]
+    fn neutralizes_formulas() {
+        assert_eq!(escape("=HYPERLINK(\"http://evil.test\")"), "\"'=HYPERLINK(\"\"http://evil.test\"\")\"");
+        assert_eq!(escape("+1-555-0100"), "'+1-555-0100");
+        assert_eq!(escape("-2+3"), "'-2+3");
+        assert_eq!(escape("@SUM(A1:A2)"), "'@SUM(A1:A2)");
+        assert_eq!(escape("\t=1"), "'\t=1");
+        assert_eq!(escape("Lot A-1 = 10 kg"), "Lot A-1 = 10 kg");
+        assert_eq!(escape("12.5"), "12.5");
+    }
+}
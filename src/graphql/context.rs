@@ -1,8 +1,85 @@
 use std::sync::Arc;
-use crate::db::conn::Pool;
+use juniper::{graphql_value, FieldError, FieldResult};
+use uuid::Uuid;
+
+use crate::{
+    common::user::{AgentMembership, AgentRole, Permission, User},
+    db::conn::Pool,
+};
 
 pub struct Context {
     pub pool: Arc<Pool>,
+    /// The user the API key of the request belongs to, none without a key
+    pub user: Option<User>,
+    /// Agents the user is a member of, with their role in each
+    pub memberships: Vec<AgentMembership>,
 }
 
 impl juniper::Context for Context {}
+
+impl Context {
+    pub fn user(&self) -> FieldResult<&User> {
+        self.user
+            .as_ref()
+            .ok_or_else(|| access_error("Authentication required, send an API key as a Bearer token".to_string()))
+    }
+
+    pub fn agent_ids(&self) -> Vec<Uuid> {
+        self.memberships.iter().map(|m| m.agent_id).collect()
+    }
+
+    pub fn role(&self, agent_id: Uuid) -> Option<AgentRole> {
+        self.memberships.iter().find(|m| m.agent_id == agent_id).map(|m| m.role)
+    }
+
+    pub fn is_member(&self, agent_id: Uuid) -> bool {
+        self.role(agent_id).is_some()
+    }
+
+    pub fn check_member(&self, agent_id: Uuid) -> FieldResult<()> {
+        let user = self.user()?;
+        if !self.is_member(agent_id) {
+            return Err(access_error(format!("{} is not a member of agent {}", user.email, agent_id)));
+        }
+        Ok(())
+    }
+
+    /// Members whose role in the agent allows the permission
+    pub fn check_permission(&self, agent_id: Uuid, permission: Permission) -> FieldResult<()> {
+        self.check_member(agent_id)?;
+        match self.role(agent_id) {
+            Some(role) if !role.allows(permission) => Err(access_error(format!(
+                "{} is {:?} of agent {}, which doesn't allow {:?}",
+                self.user()?.email,
+                role,
+                agent_id,
+                permission
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// What all agents share, like units, is managed by deployment admins
+    pub fn check_deployment_admin(&self) -> FieldResult<()> {
+        let user = self.user()?;
+        if !user.deployment_admin {
+            return Err(access_error(format!("{} is not an admin of the deployment", user.email)));
+        }
+        Ok(())
+    }
+
+    /// Creating a template takes a role allowing it in any agent, changing one
+    /// takes it in the agent owning the template, see `authorization`
+    pub fn check_any_permission(&self, permission: Permission) -> FieldResult<()> {
+        let user = self.user()?;
+        if !self.memberships.iter().any(|m| m.role.allows(permission)) {
+            return Err(access_error(format!("{} has no role that allows {:?}", user.email, permission)));
+        }
+        Ok(())
+    }
+}
+
+pub fn access_error(error_message: String) -> FieldError {
+    FieldError::new("Access denied", graphql_value!({ "code": error_message }))
+}
+
@@ -1,10 +1,18 @@
-use actix_web::{get, HttpResponse, Responder, web, route};
+use actix_web::{get, post, HttpRequest, HttpResponse, Responder, web, route};
+use actix_web::http::header;
 use juniper::http::GraphQLRequest;
 use juniper::http::graphiql::graphiql_source;
 use actix_web_lab::respond::Html;
 use crate::graphql::schema::{Schema, create_schema};
-use crate::graphql::context::Context;
+use crate::common::user::bearer_key;
+use crate::graphql::context::{access_error, Context};
 use crate::db::conn::Pool;
+use crate::graphql::modules::common::user;
+use crate::graphql::modules::fda::spreadsheet::{self, SpreadsheetParams};
+use crate::graphql::modules::epcis::{
+    export::{self, EpcisExportParams},
+    import::{self, EpcisImportParams},
+};
 
 use std::sync::Arc;
 use actix_web::{App, HttpServer};
@@ -19,16 +27,159 @@ pub async fn graphql_playground() -> impl Responder {
 pub async fn graphql(
     schema: web::Data<Schema>,
     pool: web::Data<Arc<Pool>>,
+    http_req: HttpRequest,
     req: web::Json<GraphQLRequest>
 ) -> impl Responder {
-    let ctx = Context {
-        pool: pool.get_ref().clone(),
+    let ctx = match request_context(pool.get_ref(), &http_req).await {
+        Ok(ctx) => ctx,
+        Err(res) => return res,
     };
 
     let res = req.execute(&schema, &ctx).await;
     HttpResponse::Ok().json(res)
 }
 
+/// FDA sortable spreadsheet of an FDA map, as a CSV download
+#[get("/fda/spreadsheet")]
+pub async fn fda_spreadsheet(
+    pool: web::Data<Arc<Pool>>,
+    http_req: HttpRequest,
+    params: web::Query<SpreadsheetParams>
+) -> impl Responder {
+    let ctx = match member_context(pool.get_ref(), &http_req).await {
+        Ok(ctx) => ctx,
+        Err(res) => return res,
+    };
+    let pool = pool.get_ref().clone();
+
+    let res = web::block(move || {
+        let conn = &mut pool.get().expect("Failed to get DB connection from pool");
+        spreadsheet::fda_spreadsheet(conn, &params, &ctx.agent_ids())
+    })
+    .await;
+
+    match res {
+        Ok(Ok(csv)) => HttpResponse::Ok()
+            .content_type("text/csv; charset=utf-8")
+            .insert_header(("Content-Disposition", "attachment; filename=\"fda_spreadsheet.csv\""))
+            .body(csv),
+        Ok(Err(e)) => bad_request(e),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+/// GS1 EPCIS 2.0 JSON-LD document of the events of a process or a lot
+#[get("/epcis/events")]
+pub async fn epcis_export(
+    pool: web::Data<Arc<Pool>>,
+    http_req: HttpRequest,
+    params: web::Query<EpcisExportParams>
+) -> impl Responder {
+    let ctx = match member_context(pool.get_ref(), &http_req).await {
+        Ok(ctx) => ctx,
+        Err(res) => return res,
+    };
+    let pool = pool.get_ref().clone();
+
+    let res = web::block(move || {
+        let conn = &mut pool.get().expect("Failed to get DB connection from pool");
+        export::export_events(conn, &params, &ctx.agent_ids())
+    })
+    .await;
+
+    match res {
+        Ok(Ok(document)) => HttpResponse::Ok()
+            .content_type("application/ld+json")
+            .json(document),
+        Ok(Err(e)) => bad_request(e),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+/// Records the events of a GS1 EPCIS 2.0 document as executions of a process
+#[post("/epcis/events")]
+pub async fn epcis_import(
+    pool: web::Data<Arc<Pool>>,
+    http_req: HttpRequest,
+    params: web::Query<EpcisImportParams>,
+    body: String
+) -> impl Responder {
+    let ctx = match member_context(pool.get_ref(), &http_req).await {
+        Ok(ctx) => ctx,
+        Err(res) => return res,
+    };
+    let pool = pool.get_ref().clone();
+
+    let res = web::block(move || {
+        let conn = &mut pool.get().expect("Failed to get DB connection from pool");
+        import::import_events(conn, &params, &body, &ctx)
+    })
+    .await;
+
+    match res {
+        Ok(Ok(result)) => HttpResponse::Ok().json(result),
+        Ok(Err(e)) => bad_request(e),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+/// Context of the request, with the user of its `Authorization: Bearer` API key.
+/// Requests without the header are anonymous, a key that doesn't authenticate is
+/// turned away before the request runs
+async fn request_context(pool: &Arc<Pool>, req: &HttpRequest) -> Result<Context, HttpResponse> {
+    let key = match req.headers().get(header::AUTHORIZATION) {
+        Some(value) => value.to_str().ok().and_then(bearer_key).map(str::to_string),
+        None => {
+            return Ok(Context {
+                pool: pool.clone(),
+                user: None,
+                memberships: Vec::new(),
+            })
+        }
+    };
+    let key = key.ok_or_else(|| {
+        unauthorized(access_error("Send the API key as `Authorization: Bearer <key>`".to_string()))
+    })?;
+
+    let auth_pool = pool.clone();
+    let res = web::block(move || {
+        let conn = &mut auth_pool.get().expect("Failed to get DB connection from pool");
+        user::authenticate(conn, &key)
+    })
+    .await;
+
+    match res {
+        Ok(Ok((user, memberships))) => Ok(Context {
+            pool: pool.clone(),
+            user: Some(user),
+            memberships,
+        }),
+        Ok(Err(e)) => Err(unauthorized(e)),
+        Err(_) => Err(HttpResponse::InternalServerError().finish()),
+    }
+}
+
+/// Context of a route that is only served to authenticated users
+async fn member_context(pool: &Arc<Pool>, req: &HttpRequest) -> Result<Context, HttpResponse> {
+    let ctx = request_context(pool, req).await?;
+    ctx.user().map_err(unauthorized)?;
+    Ok(ctx)
+}
+
+fn unauthorized(e: juniper::FieldError) -> HttpResponse {
+    HttpResponse::Unauthorized().json(serde_json::json!({
+        "message": e.message(),
+        "extensions": e.extensions(),
+    }))
+}
+
+fn bad_request(e: juniper::FieldError) -> HttpResponse {
+    HttpResponse::BadRequest().json(serde_json::json!({
+        "message": e.message(),
+        "extensions": e.extensions(),
+    }))
+}
+
 pub async fn start_server(pool: Arc<Pool>) -> std::io::Result<()> {
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
 
@@ -50,6 +201,9 @@ pub async fn start_server(pool: Arc<Pool>) -> std::io::Result<()> {
             .app_data(Data::new(pool.clone()))
             .service(crate::graphql::handler::graphql)
             .service(crate::graphql::handler::graphql_playground)
+            .service(crate::graphql::handler::fda_spreadsheet)
+            .service(crate::graphql::handler::epcis_export)
+            .service(crate::graphql::handler::epcis_import)
     })
     .workers(5)
     .bind(("127.0.0.1", 7878))?
@@ -1,12 +1,12 @@
 
 use crate::{
-    common::agent::{Agent, AgentWithLocations, NewAgent}, db::schema::agents, graphql::context::Context
+    common::{agent::{Agent, AgentWithLocations, NewAgent}, user::AgentRole}, db::schema::{agents, locations}, graphql::context::Context
 };
 use diesel::prelude::*;
-use juniper::FieldResult;
+use juniper::{graphql_value, FieldError, FieldResult};
 use uuid::Uuid;
 
-use super::location::locations_by_agent;
+use super::{location::locations_by_agent, user::add_membership};
 
 
 /**** Queries */
@@ -16,7 +16,9 @@ pub fn all_agents(context: &Context) -> FieldResult<Vec<Agent>> {
         .get()
         .expect("Failed to get DB connection from pool");
 
-    let results = agents::table.load::<Agent>(conn)?;
+    let results = agents::table
+        .filter(agents::id.eq_any(context.agent_ids()))
+        .load::<Agent>(conn)?;
     Ok(results)
 }
 
@@ -40,10 +42,12 @@ pub fn agents_with_location(context: &Context) -> FieldResult<Vec<AgentWithLocat
         .get()
         .expect("Failed to get DB connection from pool");
 
-    let agents = agents::table.load::<Agent>(conn)?;
+    let agents = agents::table
+        .filter(agents::id.eq_any(context.agent_ids()))
+        .load::<Agent>(conn)?;
     let mut results = Vec::new();
     for agent in agents {
-        let locations = locations_by_agent(&context, agent.id)?;
+        let locations = locations_by_agent(context, agent.id)?;
         let agent_with_locations = AgentWithLocations::new(agent.id, agent.name, locations);
         results.push(agent_with_locations);
     }
@@ -53,16 +57,49 @@ pub fn agents_with_location(context: &Context) -> FieldResult<Vec<AgentWithLocat
 
 /*** Mutations */
 
+/// The user creating the agent becomes its first admin
 pub fn create_agent(context: &Context, name: String, note: Option<String>) -> FieldResult<Agent> {
+    let user = context.user()?;
     let conn = &mut context.pool.get().expect("Failed to get DB connection from pool");
 
     // Create the new agent instance
     let new_agent = NewAgent::new(&name, note.as_deref());
 
-    // Insert the new agent into the database
-    let inserted_agent = diesel::insert_into(agents::table)
-        .values(&new_agent)
+    conn.transaction::<_, FieldError, _>(|conn| {
+        // Insert the new agent into the database
+        let inserted_agent: Agent = diesel::insert_into(agents::table)
+            .values(&new_agent)
+            .get_result(conn)?;
+
+        add_membership(conn, user.id, inserted_agent.id, AgentRole::Admin)?;
+
+        Ok(inserted_agent)
+    })
+}
+
+/// Location the agent.primaryLocation default resolves to, one of the agent's own
+pub fn set_agent_primary_location(context: &Context, agent_id: Uuid, location_id: Option<Uuid>) -> FieldResult<Agent> {
+    let conn = &mut context.pool.get().expect("Failed to get DB connection from pool");
+
+    if let Some(location_id) = location_id {
+        let owned: i64 = locations::table
+            .filter(locations::id.eq(location_id))
+            .filter(locations::agent_id.eq(agent_id))
+            .count()
+            .get_result(conn)?;
+        if owned == 0 {
+            let error_message = format!("Location {} is not a location of agent {}", location_id, agent_id);
+            return Err(FieldError::new(
+                "Unable to set primary location",
+                graphql_value!({ "code": error_message }),
+            ));
+        }
+    }
+
+    let updated_agent = diesel::update(agents::table)
+        .filter(agents::id.eq(agent_id))
+        .set(agents::primary_location_id.eq(location_id))
         .get_result(conn)?;
 
-    Ok(inserted_agent)
+    Ok(updated_agent)
 }
@@ -0,0 +1,275 @@
+use diesel::prelude::*;
+use juniper::FieldResult;
+use uuid::Uuid;
+
+use crate::{
+    common::{economic_resource::EconomicResource, user::Permission},
+    db::schema::{
+        economic_resources, locations, map_templates, process_executions, recipe_flow_template_data_fields,
+        recipe_flow_templates, recipe_process_flow_data_fields, recipe_process_flows, recipe_processes,
+        recipe_templates, recipes, resource_specifications,
+    },
+    graphql::context::{access_error, Context},
+};
+
+use super::containment;
+
+/// Checks the caller is a member of the agent each item belongs to, with a role
+/// allowing the permission, before the resolvers touch it. Items that don't exist
+/// are reported as denied, so ids of other agents' items can't be probed
+pub fn agent(context: &Context, agent_id: Uuid, permission: Permission) -> FieldResult<()> {
+    context.check_permission(agent_id, permission)
+}
+
+pub fn resource_specification(context: &Context, resource_specification_id: Uuid, permission: Permission) -> FieldResult<()> {
+    let agent_id = owner(context, |conn| {
+        resource_specifications::table
+            .filter(resource_specifications::id.eq(resource_specification_id))
+            .select(resource_specifications::agent_id)
+            .first::<Uuid>(conn)
+            .optional()
+    })?;
+    checked(context, agent_id, "Resource specification", resource_specification_id, permission)
+}
+
+/// Resources are the business of whoever holds them
+pub fn economic_resource(context: &Context, economic_resource_id: Uuid, permission: Permission) -> FieldResult<()> {
+    context.user()?;
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let resource: Option<EconomicResource> = economic_resources::table
+        .filter(economic_resources::id.eq(economic_resource_id))
+        .first::<EconomicResource>(conn)
+        .optional()?;
+    let agent_id = match resource {
+        Some(resource) => Some(containment::holder(conn, &resource)?),
+        None => None,
+    };
+    checked(context, agent_id, "Economic resource", economic_resource_id, permission)
+}
+
+pub fn location(context: &Context, location_id: Uuid, permission: Permission) -> FieldResult<()> {
+    let agent_id = owner(context, |conn| {
+        locations::table
+            .filter(locations::id.eq(location_id))
+            .select(locations::agent_id)
+            .first::<Uuid>(conn)
+            .optional()
+    })?;
+    checked(context, agent_id, "Location", location_id, permission)
+}
+
+pub fn recipe(context: &Context, recipe_id: Uuid, permission: Permission) -> FieldResult<()> {
+    let agent_id = owner(context, |conn| {
+        recipes::table
+            .filter(recipes::id.eq(recipe_id))
+            .select(recipes::agent_id)
+            .first::<Uuid>(conn)
+            .optional()
+    })?;
+    checked(context, agent_id, "Recipe", recipe_id, permission)
+}
+
+pub fn recipe_process(context: &Context, recipe_process_id: Uuid, permission: Permission) -> FieldResult<()> {
+    let agent_id = owner(context, |conn| {
+        recipe_processes::table
+            .inner_join(recipes::table)
+            .filter(recipe_processes::id.eq(recipe_process_id))
+            .select(recipes::agent_id)
+            .first::<Uuid>(conn)
+            .optional()
+    })?;
+    checked(context, agent_id, "Recipe process", recipe_process_id, permission)
+}
+
+pub fn process_flow(context: &Context, process_flow_id: Uuid, permission: Permission) -> FieldResult<()> {
+    let agent_id = owner(context, |conn| {
+        recipe_process_flows::table
+            .inner_join(recipe_processes::table.inner_join(recipes::table))
+            .filter(recipe_process_flows::id.eq(process_flow_id))
+            .select(recipes::agent_id)
+            .first::<Uuid>(conn)
+            .optional()
+    })?;
+    checked(context, agent_id, "Process flow", process_flow_id, permission)
+}
+
+pub fn process_flow_data_field(context: &Context, data_field_id: Uuid, permission: Permission) -> FieldResult<()> {
+    let agent_id = owner(context, |conn| {
+        recipe_process_flow_data_fields::table
+            .inner_join(recipe_process_flows::table.inner_join(recipe_processes::table.inner_join(recipes::table)))
+            .filter(recipe_process_flow_data_fields::id.eq(data_field_id))
+            .select(recipes::agent_id)
+            .first::<Uuid>(conn)
+            .optional()
+    })?;
+    checked(context, agent_id, "Data field", data_field_id, permission)
+}
+
+/// Executions belong to the owner of their recipe, splits and merges to the agent
+/// holding the resources
+pub fn process_execution(context: &Context, process_execution_id: Uuid, permission: Permission) -> FieldResult<()> {
+    let agent_id = owner(context, |conn| {
+        let execution: Option<(Option<Uuid>, Uuid)> = process_executions::table
+            .filter(process_executions::id.eq(process_execution_id))
+            .select((process_executions::process_flow_id, process_executions::provider_agent))
+            .first::<(Option<Uuid>, Uuid)>(conn)
+            .optional()?;
+
+        match execution {
+            Some((Some(process_flow_id), _)) => recipe_process_flows::table
+                .inner_join(recipe_processes::table.inner_join(recipes::table))
+                .filter(recipe_process_flows::id.eq(process_flow_id))
+                .select(recipes::agent_id)
+                .first::<Uuid>(conn)
+                .optional(),
+            Some((None, provider_agent)) => Ok(Some(provider_agent)),
+            None => Ok(None),
+        }
+    })?;
+    checked(context, agent_id, "Process execution", process_execution_id, permission)
+}
+
+/// Templates are shared by every agent, they are changed by the agent that created
+/// them. Deployment admins change any of them, and alone those no agent owns
+pub fn map_template(context: &Context, map_template_id: Uuid, permission: Permission) -> FieldResult<()> {
+    let agent_id = owner(context, |conn| {
+        map_templates::table
+            .filter(map_templates::id.eq(map_template_id))
+            .select(map_templates::created_by_agent)
+            .first::<Option<Uuid>>(conn)
+            .optional()
+    })?;
+    template_checked(context, agent_id, "Map template", map_template_id, permission)
+}
+
+pub fn recipe_template(context: &Context, recipe_template_id: Uuid, permission: Permission) -> FieldResult<()> {
+    let agent_id = owner(context, |conn| {
+        recipe_templates::table
+            .filter(recipe_templates::id.eq(recipe_template_id))
+            .select(recipe_templates::created_by_agent)
+            .first::<Option<Uuid>>(conn)
+            .optional()
+    })?;
+    template_checked(context, agent_id, "Recipe template", recipe_template_id, permission)
+}
+
+pub fn recipe_flow_template(context: &Context, recipe_flow_template_id: Uuid, permission: Permission) -> FieldResult<()> {
+    let agent_id = owner(context, |conn| {
+        recipe_flow_templates::table
+            .inner_join(recipe_templates::table)
+            .filter(recipe_flow_templates::id.eq(recipe_flow_template_id))
+            .select(recipe_templates::created_by_agent)
+            .first::<Option<Uuid>>(conn)
+            .optional()
+    })?;
+    template_checked(context, agent_id, "Recipe flow template", recipe_flow_template_id, permission)
+}
+
+pub fn template_data_field(context: &Context, data_field_id: Uuid, permission: Permission) -> FieldResult<()> {
+    let agent_id = owner(context, |conn| {
+        recipe_flow_template_data_fields::table
+            .inner_join(recipe_flow_templates::table.inner_join(recipe_templates::table))
+            .filter(recipe_flow_template_data_fields::id.eq(data_field_id))
+            .select(recipe_templates::created_by_agent)
+            .first::<Option<Uuid>>(conn)
+            .optional()
+    })?;
+    template_checked(context, agent_id, "Data field", data_field_id, permission)
+}
+
+fn owner<T>(
+    context: &Context,
+    lookup: impl FnOnce(&mut PgConnection) -> QueryResult<Option<T>>,
+) -> FieldResult<Option<T>> {
+    context.user()?;
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    Ok(lookup(conn)?)
+}
+
+fn checked(context: &Context, agent_id: Option<Uuid>, item: &str, id: Uuid, permission: Permission) -> FieldResult<()> {
+    match agent_id {
+        Some(agent_id) if context.is_member(agent_id) => context.check_permission(agent_id, permission),
+        _ => Err(access_error(format!("{} {} doesn't belong to an agent you are a member of", item, id))),
+    }
+}
+
+/// Templates that exist without an owning agent belong to the deployment
+fn template_checked(
+    context: &Context,
+    agent_id: Option<Option<Uuid>>,
+    item: &str,
+    id: Uuid,
+    permission: Permission,
+) -> FieldResult<()> {
+    if context.user()?.deployment_admin {
+        return Ok(());
+    }
+    match agent_id {
+        Some(None) => context.check_deployment_admin(),
+        agent_id => checked(context, agent_id.flatten(), item, id, permission),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::NaiveDateTime;
+    use diesel::r2d2::{ConnectionManager, Pool};
+
+    use super::*;
+    use crate::common::user::{AgentMembership, AgentRole, User};
+
+    fn context(deployment_admin: bool, memberships: &[(Uuid, AgentRole)]) -> Context {
+        let user = User {
+            id: Uuid::new_v4(),
+            name: "Designer".to_string(),
+            email: "designer@grower.test".to_string(),
+            created_at: NaiveDateTime::default(),
+            deployment_admin,
+        };
+        Context {
+            // Never connected to, the checks only read the memberships
+            pool: Arc::new(Pool::builder().build_unchecked(ConnectionManager::new("postgres://localhost/unused"))),
+            memberships: memberships
+                .iter()
+                .map(|(agent_id, role)| AgentMembership {
+                    id: Uuid::new_v4(),
+                    user_id: user.id,
+                    agent_id: *agent_id,
+                    created_at: NaiveDateTime::default(),
+                    role: *role,
+                })
+                .collect(),
+            user: Some(user),
+        }
+    }
+
+    #[test]
+    fn templates_are_changed_by_designers_of_their_owner() {
+        let (grower, packer) = (Uuid::new_v4(), Uuid::new_v4());
+        let id = Uuid::new_v4();
+        let check = |context: &Context, owner: Option<Option<Uuid>>| {
+            template_checked(context, owner, "Recipe template", id, Permission::Design).is_ok()
+        };
+
+        let designer = context(false, &[(grower, AgentRole::TemplateDesigner), (packer, AgentRole::Operator)]);
+        assert!(check(&designer, Some(Some(grower))));
+        assert!(!check(&designer, Some(Some(packer))));
+        assert!(!check(&designer, Some(Some(Uuid::new_v4()))));
+        assert!(!check(&designer, Some(None)));
+        assert!(!check(&designer, None));
+
+        let admin = context(true, &[]);
+        assert!(check(&admin, Some(Some(grower))));
+        assert!(check(&admin, Some(None)));
+    }
+}
@@ -0,0 +1,206 @@
+use diesel::prelude::*;
+use juniper::{graphql_value, FieldError, FieldResult};
+use uuid::Uuid;
+
+use crate::{
+    common::{containment::ContainmentNode, economic_resource::EconomicResource},
+    db::schema::{economic_resources, resource_specifications},
+    graphql::context::Context,
+};
+
+use super::ledger;
+
+/*** Queries */
+/// The resource with everything packed into it, at any depth
+pub fn containment_tree(context: &Context, economic_resource_id: Uuid) -> FieldResult<ContainmentNode> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let root = resource_by_id(conn, economic_resource_id)?;
+    let contained = contents(conn, root.id)?;
+
+    Ok(ContainmentNode::build(root, &contained))
+}
+
+/*** Mutations */
+pub fn pack_resource(context: &Context, economic_resource_id: Uuid, container_id: Uuid) -> FieldResult<EconomicResource> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    conn.transaction::<_, FieldError, _>(|conn| {
+        let resource = locked_resource(conn, economic_resource_id)?;
+        if let Some(current) = resource.contained_in {
+            return Err(containment_error(format!(
+                "{} is already packed in {}, repack it instead",
+                resource.name, current
+            )));
+        }
+
+        pack(conn, &resource, container_id, None)
+    })
+}
+
+/// Takes the resource out of its container, it stays where the container is
+pub fn unpack_resource(context: &Context, economic_resource_id: Uuid) -> FieldResult<EconomicResource> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    conn.transaction::<_, FieldError, _>(|conn| {
+        let resource = locked_resource(conn, economic_resource_id)?;
+        if resource.contained_in.is_none() {
+            return Err(containment_error(format!("{} is not packed", resource.name)));
+        }
+
+        unpack(conn, &resource)
+    })
+}
+
+/// Moves a packed resource, with its contents, into another container
+pub fn repack_resource(context: &Context, economic_resource_id: Uuid, container_id: Uuid) -> FieldResult<EconomicResource> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    conn.transaction::<_, FieldError, _>(|conn| {
+        let resource = locked_resource(conn, economic_resource_id)?;
+        match resource.contained_in {
+            None => Err(containment_error(format!("{} is not packed, pack it instead", resource.name))),
+            Some(current) if current == container_id => Err(containment_error(format!(
+                "{} is already packed in {}",
+                resource.name, container_id
+            ))),
+            Some(_) => pack(conn, &resource, container_id, None),
+        }
+    })
+}
+
+/// Packs the resource into the container, the resource and its contents move
+/// to the location of the container
+pub fn pack(
+    conn: &mut PgConnection,
+    resource: &EconomicResource,
+    container_id: Uuid,
+    process_execution_id: Option<Uuid>,
+) -> FieldResult<EconomicResource> {
+    let container = resource_by_id(conn, container_id)?;
+    if container.id == resource.id {
+        return Err(containment_error(format!("{} can't be packed into itself", resource.name)));
+    }
+
+    // Walking up from the container must not reach the resource
+    let mut ancestor = container.contained_in;
+    while let Some(ancestor_id) = ancestor {
+        if ancestor_id == resource.id {
+            return Err(containment_error(format!(
+                "{} is packed inside {}, it can't contain it",
+                container.name, resource.name
+            )));
+        }
+        ancestor = economic_resources::table
+            .filter(economic_resources::id.eq(ancestor_id))
+            .select(economic_resources::contained_in)
+            .first::<Option<Uuid>>(conn)?;
+    }
+
+    if holder(conn, resource)? != holder(conn, &container)? {
+        return Err(containment_error(format!(
+            "{} and {} are held by different agents",
+            resource.name, container.name
+        )));
+    }
+
+    let packed: EconomicResource = diesel::update(economic_resources::table)
+        .filter(economic_resources::id.eq(resource.id))
+        .set((
+            economic_resources::contained_in.eq(container.id),
+            economic_resources::current_location.eq(container.current_location),
+        ))
+        .get_result(conn)?;
+    ledger::record_move(conn, resource, &packed, process_execution_id)?;
+    move_contents(conn, packed.id, packed.current_location, process_execution_id)?;
+
+    Ok(packed)
+}
+
+pub fn unpack(conn: &mut PgConnection, resource: &EconomicResource) -> FieldResult<EconomicResource> {
+    let unpacked: EconomicResource = diesel::update(economic_resources::table)
+        .filter(economic_resources::id.eq(resource.id))
+        .set(economic_resources::contained_in.eq(None::<Uuid>))
+        .get_result(conn)?;
+
+    Ok(unpacked)
+}
+
+/// Moves everything packed in the container, at any depth, to the location
+pub fn move_contents(
+    conn: &mut PgConnection,
+    container_id: Uuid,
+    current_location: Option<Uuid>,
+    process_execution_id: Option<Uuid>,
+) -> FieldResult<()> {
+    for resource in contents(conn, container_id)? {
+        let moved: EconomicResource = diesel::update(economic_resources::table)
+            .filter(economic_resources::id.eq(resource.id))
+            .set(economic_resources::current_location.eq(current_location))
+            .get_result(conn)?;
+        ledger::record_move(conn, &resource, &moved, process_execution_id)?;
+    }
+
+    Ok(())
+}
+
+/// Resources packed in the container at any depth, level by level
+pub fn contents(conn: &mut PgConnection, container_id: Uuid) -> FieldResult<Vec<EconomicResource>> {
+    let mut contents = Vec::new();
+    let mut level = vec![container_id];
+
+    while !level.is_empty() {
+        let found: Vec<EconomicResource> = economic_resources::table
+            .filter(economic_resources::contained_in.eq_any(&level))
+            .order(economic_resources::created_at.asc())
+            .load::<EconomicResource>(conn)?;
+        level = found.iter().map(|r| r.id).collect();
+        contents.extend(found);
+    }
+
+    Ok(contents)
+}
+
+/// Resources without a custodian are held by the owner of their specification
+pub fn holder(conn: &mut PgConnection, resource: &EconomicResource) -> FieldResult<Uuid> {
+    match resource.custodian_id {
+        Some(custodian_id) => Ok(custodian_id),
+        None => Ok(resource_specifications::table
+            .filter(resource_specifications::id.eq(resource.resource_specification_id))
+            .select(resource_specifications::agent_id)
+            .first::<Uuid>(conn)?),
+    }
+}
+
+fn locked_resource(conn: &mut PgConnection, economic_resource_id: Uuid) -> FieldResult<EconomicResource> {
+    economic_resources::table
+        .filter(economic_resources::id.eq(economic_resource_id))
+        .for_update()
+        .first::<EconomicResource>(conn)
+        .optional()?
+        .ok_or_else(|| containment_error(format!("Economic resource {} not found", economic_resource_id)))
+}
+
+fn resource_by_id(conn: &mut PgConnection, economic_resource_id: Uuid) -> FieldResult<EconomicResource> {
+    economic_resources::table
+        .filter(economic_resources::id.eq(economic_resource_id))
+        .first::<EconomicResource>(conn)
+        .optional()?
+        .ok_or_else(|| containment_error(format!("Economic resource {} not found", economic_resource_id)))
+}
+
+fn containment_error(error_message: String) -> FieldError {
+    FieldError::new("Invalid containment", graphql_value!({ "code": error_message }))
+}
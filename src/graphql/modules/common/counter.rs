@@ -0,0 +1,157 @@
+use chrono::Utc;
+use diesel::prelude::*;
+use juniper::{graphql_value, FieldError, FieldResult};
+use uuid::Uuid;
+
+use crate::{
+    common::counter::{agent_code, Counter, LotFormat, NewCounter, NewLotSequence},
+    db::schema::{agents, counters, lot_sequences},
+    graphql::context::Context,
+};
+
+/**** Queries */
+pub fn counter_by_agent(context: &Context, agent_id: Uuid) -> FieldResult<Counter> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    conn.transaction::<_, FieldError, _>(|conn| {
+        ensure_counter(conn, &agent_id)?;
+
+        let counter = counters::table
+            .filter(counters::agent_id.eq(agent_id))
+            .first::<Counter>(conn)?;
+
+        Ok(counter)
+    })
+}
+
+/*** Mutations */
+pub fn allocate_lot_code(context: &Context, agent_id: Uuid) -> FieldResult<String> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    conn.transaction::<_, FieldError, _>(|conn| next_lot_code(conn, &agent_id))
+}
+
+pub fn allocate_reference_number(context: &Context, agent_id: Uuid) -> FieldResult<i32> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    conn.transaction::<_, FieldError, _>(|conn| next_reference_number(conn, &agent_id))
+}
+
+pub fn update_lot_format(
+    context: &Context,
+    agent_id: Uuid,
+    lot_format: String,
+    lot_prefix: Option<String>,
+) -> FieldResult<Counter> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    if let Err(error_message) = LotFormat::parse(&lot_format) {
+        return Err(FieldError::new(
+            "Invalid lot format",
+            graphql_value!({ "code": error_message }),
+        ));
+    }
+
+    conn.transaction::<_, FieldError, _>(|conn| {
+        ensure_counter(conn, &agent_id)?;
+
+        let counter = diesel::update(counters::table)
+            .filter(counters::agent_id.eq(agent_id))
+            .set((
+                counters::lot_format.eq(lot_format),
+                counters::lot_prefix.eq(lot_prefix),
+            ))
+            .get_result::<Counter>(conn)?;
+
+        Ok(counter)
+    })
+}
+
+/// Hands out the next lot code of the agent.
+///
+/// Must run inside a transaction, the counter row stays locked until it
+/// commits so concurrent allocations for the same agent are serialized.
+pub fn next_lot_code(conn: &mut PgConnection, agent_id: &Uuid) -> FieldResult<String> {
+    let counter = lock_counter(conn, agent_id)?;
+
+    let lot_format = LotFormat::parse(&counter.lot_format).map_err(|error_message| {
+        FieldError::new("Invalid lot format", graphql_value!({ "code": error_message }))
+    })?;
+
+    let code = match &counter.lot_prefix {
+        Some(lot_prefix) => lot_prefix.clone(),
+        None => {
+            let agent_name = agents::table
+                .filter(agents::id.eq(agent_id))
+                .select(agents::name)
+                .first::<String>(conn)?;
+            agent_code(&agent_name)
+        }
+    };
+
+    let today = Utc::now().date_naive();
+    let period = lot_format.period(&code, today);
+    let sequence = diesel::insert_into(lot_sequences::table)
+        .values(NewLotSequence::new(agent_id, &period))
+        .on_conflict((lot_sequences::agent_id, lot_sequences::period))
+        .do_update()
+        .set(lot_sequences::sequence.eq(lot_sequences::sequence + 1))
+        .returning(lot_sequences::sequence)
+        .get_result::<i32>(conn)?;
+
+    diesel::update(counters::table)
+        .filter(counters::id.eq(counter.id))
+        .set((
+            counters::lot_code.eq(sequence),
+            counters::lot_period.eq(period),
+        ))
+        .execute(conn)?;
+
+    Ok(lot_format.render(&code, today, sequence))
+}
+
+/// Hands out the next reference number of the agent, see `next_lot_code`
+pub fn next_reference_number(conn: &mut PgConnection, agent_id: &Uuid) -> FieldResult<i32> {
+    ensure_counter(conn, agent_id)?;
+
+    let reference_number = diesel::update(counters::table)
+        .filter(counters::agent_id.eq(agent_id))
+        .set(counters::reference_number.eq(counters::reference_number + 1))
+        .returning(counters::reference_number)
+        .get_result::<i32>(conn)?;
+
+    Ok(reference_number)
+}
+
+fn lock_counter(conn: &mut PgConnection, agent_id: &Uuid) -> FieldResult<Counter> {
+    ensure_counter(conn, agent_id)?;
+
+    let counter = counters::table
+        .filter(counters::agent_id.eq(agent_id))
+        .for_update()
+        .first::<Counter>(conn)?;
+
+    Ok(counter)
+}
+
+fn ensure_counter(conn: &mut PgConnection, agent_id: &Uuid) -> FieldResult<()> {
+    diesel::insert_into(counters::table)
+        .values(NewCounter::new(agent_id))
+        .on_conflict(counters::agent_id)
+        .do_nothing()
+        .execute(conn)?;
+
+    Ok(())
+}
@@ -1,12 +1,14 @@
 
 use crate::{
-    common::economic_resource::{EconomicResource, EconomicResourceWithSpec, NewEconomicResource}, db::schema::economic_resources, graphql::context::Context
+    common::{economic_resource::{EconomicResource, EconomicResourceWithSpec, NewEconomicResource}, resource_specification::ResourceSpecification}, 
+    db::schema::{economic_resources, resource_specifications}, 
+    graphql::context::Context
 };
 use diesel::prelude::*;
-use juniper::FieldResult;
+use juniper::{FieldError, FieldResult};
 use uuid::Uuid;
 
-use super::resource_specification::resource_specifications_by_agent;
+use super::{ledger, resource_specification::resource_specifications_by_agent};
 
 
 /*** Queries */
@@ -27,52 +29,58 @@ pub fn economic_resources_by_agent(
     context: &Context,
     agent_id: Uuid
 ) -> FieldResult<Vec<EconomicResourceWithSpec>> {
-    let resource_specifications_by_agent = resource_specifications_by_agent(&context, agent_id)?;
+    let resource_specifications_by_agent = resource_specifications_by_agent(context, agent_id)?;
 
     let mut result: Vec<EconomicResourceWithSpec> = Vec::new();
 
     for spec in resource_specifications_by_agent {
-        let economic_resources = economic_resources_by_specification_id(&context, spec.id)?;
+        let economic_resources = economic_resources_by_specification_id(context, spec.id)?;
         for economic_resource in economic_resources {
+            // Resources handed over to another agent are listed under their custodian
+            if economic_resource.custodian_id.is_some_and(|custodian_id| custodian_id != agent_id) {
+                continue;
+            }
             let economic_resource_with_spec = EconomicResourceWithSpec::build(economic_resource, spec.clone());
             result.push(economic_resource_with_spec);
         }
     }
 
+    let conn = &mut context.pool.get().expect("Failed to get DB connection from pool");
+
+    let in_custody: Vec<(EconomicResource, ResourceSpecification)> = economic_resources::table
+        .inner_join(resource_specifications::table)
+        .filter(economic_resources::custodian_id.eq(agent_id))
+        .filter(resource_specifications::agent_id.ne(agent_id))
+        .load::<(EconomicResource, ResourceSpecification)>(conn)?;
+
+    for (economic_resource, spec) in in_custody {
+        result.push(EconomicResourceWithSpec::build(economic_resource, spec));
+    }
+
     Ok(result)
 }
 
 /*** Mutations */
 pub fn create_economic_resource(
     context: &Context,
-    resource_specification_id: Uuid, 
-    name: String,
-    note: Option<String>,
-    accounting_quantity: i32,
-    tracking_identifier: Option<String>,
-    current_location: String,
-    lot: Option<String>,
-    contained_in: Option<Uuid>
+    new_economic_resource: &NewEconomicResource,
 ) -> FieldResult<EconomicResource> {
     let conn = &mut context.pool.get().expect("Failed to get DB connection from pool");
 
-    // Create the new resource specification instance
-    let new_economic_resource = NewEconomicResource::new(
-        &resource_specification_id, 
-        &name, 
-        note.as_deref(), 
-        &accounting_quantity, 
-        tracking_identifier.as_deref(), 
-        &current_location, 
-        lot.as_deref(), 
-        contained_in.as_ref()
-    ); 
-        
-    // Insert the new resource specification into the database
-    let inserted_resource_spec = diesel::insert_into(economic_resources::table)
-        .values(&new_economic_resource)
-        .get_result(conn)?;
-
-    Ok(inserted_resource_spec)
+    // Insert the new resource specification into the database, with its opening ledger entry
+    conn.transaction::<_, FieldError, _>(|conn| {
+        let inserted_resource_spec: EconomicResource = diesel::insert_into(economic_resources::table)
+            .values(new_economic_resource)
+            .get_result(conn)?;
+        ledger::record_change(
+            conn,
+            &inserted_resource_spec,
+            &inserted_resource_spec.accounting_quantity.0,
+            &inserted_resource_spec.on_hand_quantity.0,
+            None,
+        )?;
+
+        Ok(inserted_resource_spec)
+    })
 
 }
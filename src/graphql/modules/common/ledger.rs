@@ -0,0 +1,164 @@
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+use juniper::{graphql_value, FieldError, FieldResult};
+use uuid::Uuid;
+
+use crate::{
+    common::{
+        economic_resource::{Decimal, EconomicResource},
+        ledger::{stock_levels, InventoryLedgerEntry, NewInventoryLedgerEntry, StockGrouping, StockLevel},
+    },
+    db::schema::inventory_ledger_entries,
+    graphql::context::Context,
+    recipe::process::validation::parse_date,
+};
+
+use super::{containment, location};
+
+/*** Queries */
+/// Stock held at the moment, a date on its own means the end of that day.
+/// Filtering by location includes the locations nested in it
+pub fn stock_as_of(
+    context: &Context,
+    as_of: Option<String>,
+    group_by: Vec<StockGrouping>,
+    agent_id: Option<Uuid>,
+    resource_specification_id: Option<Uuid>,
+    location_id: Option<Uuid>,
+) -> FieldResult<Vec<StockLevel>> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    // Only entries of the agents the caller is a member of
+    let mut query = inventory_ledger_entries::table
+        .filter(inventory_ledger_entries::agent_id.eq_any(context.agent_ids()))
+        .into_boxed();
+    if let Some(as_of) = as_of {
+        query = query.filter(inventory_ledger_entries::recorded_at.le(as_of_bound(&as_of)?));
+    }
+    if let Some(agent_id) = agent_id {
+        query = query.filter(inventory_ledger_entries::agent_id.eq(agent_id));
+    }
+    if let Some(resource_specification_id) = resource_specification_id {
+        query = query.filter(inventory_ledger_entries::resource_specification_id.eq(resource_specification_id));
+    }
+    if let Some(location_id) = location_id {
+        let mut location_ids: Vec<Uuid> = location::nested_locations(conn, location_id)?
+            .into_iter()
+            .map(|l| l.id)
+            .collect();
+        location_ids.push(location_id);
+        query = query.filter(inventory_ledger_entries::location_id.eq_any(location_ids));
+    }
+
+    let entries: Vec<InventoryLedgerEntry> = query.load::<InventoryLedgerEntry>(conn)?;
+
+    Ok(stock_levels(&entries, &group_by))
+}
+
+/// Entries of a resource or a lot in the order they were recorded
+pub fn ledger_entries(
+    context: &Context,
+    economic_resource_id: Option<Uuid>,
+    lot: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+) -> FieldResult<Vec<InventoryLedgerEntry>> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    if economic_resource_id.is_none() && lot.is_none() {
+        return Err(ledger_error("An economic resource or a lot is required".to_string()));
+    }
+
+    // Only entries of the agents the caller is a member of
+    let mut query = inventory_ledger_entries::table
+        .filter(inventory_ledger_entries::agent_id.eq_any(context.agent_ids()))
+        .into_boxed();
+    if let Some(economic_resource_id) = economic_resource_id {
+        query = query.filter(inventory_ledger_entries::economic_resource_id.eq(economic_resource_id));
+    }
+    if let Some(lot) = lot {
+        query = query.filter(inventory_ledger_entries::lot.eq(lot));
+    }
+    if let Some(from) = from {
+        let from = parse_date(&from).map_err(ledger_error)?;
+        query = query.filter(inventory_ledger_entries::recorded_at.ge(from));
+    }
+    if let Some(to) = to {
+        query = query.filter(inventory_ledger_entries::recorded_at.le(as_of_bound(&to)?));
+    }
+
+    let entries = query
+        .order((inventory_ledger_entries::recorded_at.asc(), inventory_ledger_entries::id.asc()))
+        .load::<InventoryLedgerEntry>(conn)?;
+
+    Ok(entries)
+}
+
+/// Records a quantity change of the resource where it is now
+pub fn record_change(
+    conn: &mut PgConnection,
+    resource: &EconomicResource,
+    accounting_delta: &BigDecimal,
+    on_hand_delta: &BigDecimal,
+    process_execution_id: Option<Uuid>,
+) -> FieldResult<()> {
+    if accounting_delta.is_zero() && on_hand_delta.is_zero() {
+        return Ok(());
+    }
+
+    let agent_id = containment::holder(conn, resource)?;
+    let (accounting_delta, on_hand_delta) = (Decimal(accounting_delta.clone()), Decimal(on_hand_delta.clone()));
+    let mut new_entry = NewInventoryLedgerEntry::new(resource, &agent_id, &accounting_delta, &on_hand_delta);
+    new_entry.process_execution_id = process_execution_id.as_ref();
+
+    diesel::insert_into(inventory_ledger_entries::table)
+        .values(new_entry)
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Records the resource leaving where it was and arriving where it is now
+pub fn record_move(
+    conn: &mut PgConnection,
+    before: &EconomicResource,
+    after: &EconomicResource,
+    process_execution_id: Option<Uuid>,
+) -> FieldResult<()> {
+    if before.current_location == after.current_location {
+        return Ok(());
+    }
+
+    record_change(
+        conn,
+        before,
+        &-&before.accounting_quantity.0,
+        &-&before.on_hand_quantity.0,
+        process_execution_id,
+    )?;
+    record_change(
+        conn,
+        after,
+        &after.accounting_quantity.0,
+        &after.on_hand_quantity.0,
+        process_execution_id,
+    )
+}
+
+fn as_of_bound(value: &str) -> FieldResult<NaiveDateTime> {
+    if let Ok(date) = value.trim().parse::<NaiveDate>() {
+        return Ok(date.and_hms_micro_opt(23, 59, 59, 999_999).expect("End of day is a valid time"));
+    }
+    parse_date(value.trim()).map_err(ledger_error)
+}
+
+fn ledger_error(error_message: String) -> FieldError {
+    FieldError::new("Invalid ledger query", graphql_value!({ "code": error_message }))
+}
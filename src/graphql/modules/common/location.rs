@@ -1,9 +1,10 @@
-
 use crate::{
-    common::location::{Location, NewLocation}, db::schema::locations, graphql::context::Context
+    common::location::{valid_gln, Location, LocationDetails, LocationNode, NewLocation},
+    db::schema::locations,
+    graphql::context::Context
 };
 use diesel::prelude::*;
-use juniper::FieldResult;
+use juniper::{graphql_value, FieldError, FieldResult};
 use uuid::Uuid;
 
 
@@ -21,18 +22,168 @@ pub fn locations_by_agent(context: &Context, agent_id: Uuid) -> FieldResult<Vec<
     Ok(results)
 }
 
+/// The location with the locations nested in it, at any depth
+pub fn location_tree(context: &Context, location_id: Uuid) -> FieldResult<LocationNode> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let root = location_by_id(conn, location_id)?;
+    let nested = nested_locations(conn, root.id)?;
+
+    Ok(LocationNode::build(root, &nested))
+}
+
 
 /*** Mutations */
-pub fn create_location(context: &Context, agent_id: Uuid, name: String, value: String) -> FieldResult<Location> {
+pub fn create_location(
+    context: &Context,
+    agent_id: Uuid,
+    name: String,
+    value: String,
+    details: Option<LocationDetails>,
+) -> FieldResult<Location> {
     let conn = &mut context.pool.get().expect("Failed to get DB connection from pool");
 
-    // Create the new agent instance
-    let new_location = NewLocation::new(&name, &agent_id, &value);
+    let details = details.unwrap_or_default();
+    check_details(conn, agent_id, None, &details)?;
+    let country_code = details.country_code.as_deref().map(str::to_uppercase);
 
-    // Insert the new agent into the database
-    let inserted_agent = diesel::insert_into(locations::table)
+    let mut new_location = NewLocation::new(&name, &agent_id, &value);
+    new_location.gln = details.gln.as_deref();
+    new_location.location_type = details.location_type.unwrap_or(new_location.location_type);
+    new_location.parent_id = details.parent_id.as_ref();
+    new_location.street_address = details.street_address.as_deref();
+    new_location.locality = details.locality.as_deref();
+    new_location.region = details.region.as_deref();
+    new_location.postal_code = details.postal_code.as_deref();
+    new_location.country_code = country_code.as_deref();
+    new_location.latitude = details.latitude;
+    new_location.longitude = details.longitude;
+
+    let inserted_location = diesel::insert_into(locations::table)
         .values(&new_location)
         .get_result(conn)?;
 
-    Ok(inserted_agent)
-}
\ No newline at end of file
+    Ok(inserted_location)
+}
+
+/// Replaces the structured part of the location, fields left out are cleared
+pub fn set_location_details(context: &Context, location_id: Uuid, details: LocationDetails) -> FieldResult<Location> {
+    let conn = &mut context.pool.get().expect("Failed to get DB connection from pool");
+
+    let location = location_by_id(conn, location_id)?;
+    check_details(conn, location.agent_id, Some(location.id), &details)?;
+
+    let updated: Location = diesel::update(locations::table)
+        .filter(locations::id.eq(location.id))
+        .set((
+            locations::gln.eq(&details.gln),
+            locations::location_type.eq(details.location_type.unwrap_or(location.location_type)),
+            locations::parent_id.eq(details.parent_id),
+            locations::street_address.eq(&details.street_address),
+            locations::locality.eq(&details.locality),
+            locations::region.eq(&details.region),
+            locations::postal_code.eq(&details.postal_code),
+            locations::country_code.eq(details.country_code.as_deref().map(str::to_uppercase)),
+            locations::latitude.eq(details.latitude),
+            locations::longitude.eq(details.longitude),
+        ))
+        .get_result(conn)?;
+
+    Ok(updated)
+}
+
+pub fn location_by_id(conn: &mut PgConnection, location_id: Uuid) -> FieldResult<Location> {
+    locations::table
+        .filter(locations::id.eq(location_id))
+        .first::<Location>(conn)
+        .optional()?
+        .ok_or_else(|| location_error(format!("Location {} not found", location_id)))
+}
+
+/// Locations nested in the location at any depth, level by level
+pub fn nested_locations(conn: &mut PgConnection, location_id: Uuid) -> FieldResult<Vec<Location>> {
+    let mut nested = Vec::new();
+    let mut level = vec![location_id];
+
+    while !level.is_empty() {
+        let found: Vec<Location> = locations::table
+            .filter(locations::parent_id.eq_any(&level))
+            .order(locations::name.asc())
+            .load::<Location>(conn)?;
+        level = found.iter().map(|l| l.id).collect();
+        nested.extend(found);
+    }
+
+    Ok(nested)
+}
+
+/// Location with the GLN, for identifiers read from EPCIS documents
+pub fn location_by_gln(conn: &mut PgConnection, gln: &str) -> FieldResult<Option<Location>> {
+    let location = locations::table
+        .filter(locations::gln.eq(gln))
+        .first::<Location>(conn)
+        .optional()?;
+
+    Ok(location)
+}
+
+fn check_details(
+    conn: &mut PgConnection,
+    agent_id: Uuid,
+    location_id: Option<Uuid>,
+    details: &LocationDetails,
+) -> FieldResult<()> {
+    if let Some(gln) = &details.gln {
+        if !valid_gln(gln) {
+            return Err(location_error(format!("{} is not a valid GLN", gln)));
+        }
+        let taken: Option<Location> = location_by_gln(conn, gln)?;
+        if let Some(taken) = taken.filter(|l| Some(l.id) != location_id) {
+            return Err(location_error(format!("GLN {} is already used by {}", gln, taken.name)));
+        }
+    }
+
+    if details.latitude.is_some() != details.longitude.is_some() {
+        return Err(location_error("Latitude and longitude go together".to_string()));
+    }
+    if details.latitude.is_some_and(|latitude| !(-90.0..=90.0).contains(&latitude)) {
+        return Err(location_error("Latitude must be between -90 and 90".to_string()));
+    }
+    if details.longitude.is_some_and(|longitude| !(-180.0..=180.0).contains(&longitude)) {
+        return Err(location_error("Longitude must be between -180 and 180".to_string()));
+    }
+
+    if let Some(country_code) = &details.country_code {
+        if country_code.len() != 2 || !country_code.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(location_error(format!("{} is not an ISO 3166-1 alpha-2 country code", country_code)));
+        }
+    }
+
+    if let Some(parent_id) = details.parent_id {
+        let parent = location_by_id(conn, parent_id)?;
+        if parent.agent_id != agent_id {
+            return Err(location_error(format!("{} belongs to another agent", parent.name)));
+        }
+
+        // Walking up from the parent must not reach the location itself
+        let mut ancestor = Some(parent);
+        while let Some(current) = ancestor {
+            if Some(current.id) == location_id {
+                return Err(location_error(format!("{} can't be nested in itself", current.name)));
+            }
+            ancestor = match current.parent_id {
+                Some(parent_id) => Some(location_by_id(conn, parent_id)?),
+                None => None,
+            };
+        }
+    }
+
+    Ok(())
+}
+
+fn location_error(error_message: String) -> FieldError {
+    FieldError::new("Invalid location", graphql_value!({ "code": error_message }))
+}
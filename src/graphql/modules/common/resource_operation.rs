@@ -0,0 +1,256 @@
+use bigdecimal::{BigDecimal, Signed, Zero};
+use diesel::prelude::*;
+use juniper::{graphql_value, FieldError, FieldResult};
+use uuid::Uuid;
+
+use crate::{
+    common::{
+        economic_resource::{Decimal, EconomicResource, NewEconomicResource},
+        resource_operation::{shared_lot, split_remainder, ResourceOperation, ResourceOperationResponse, SplitPart},
+    },
+    db::schema::{economic_resources, process_executions},
+    graphql::{context::Context, modules::process::execution},
+    recipe::process::execution::{NewProcessExecution, ProcessExecution},
+    templates::recipe_flow_template::{ActionType, RoleType},
+};
+
+use super::{containment, counter, ledger};
+
+/// The run a split or a merge records its executions in
+struct OperationRun<'a> {
+    operation: ResourceOperation,
+    batch_id: Uuid,
+    agent_id: Uuid,
+    performed_by: Uuid,
+    note: Option<&'a str>,
+}
+
+/*** Mutations */
+/// Splits parts off the resource into new resources, what isn't split off stays
+/// in it. Recorded as a Consume of the resource and a Produce of each part
+pub fn split_resource(
+    context: &Context,
+    economic_resource_id: Uuid,
+    parts: Vec<SplitPart>,
+    note: Option<String>,
+) -> FieldResult<ResourceOperationResponse> {
+    let performed_by = context.user()?.id;
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    conn.transaction::<_, FieldError, _>(|conn| {
+        let resource = locked_resource(conn, economic_resource_id)?;
+        if resource.contained_in.is_some() {
+            return Err(operation_error(format!("{} is packed, unpack it before splitting it", resource.name)));
+        }
+
+        let quantities: Vec<BigDecimal> = parts.iter().map(|p| p.quantity.0.clone()).collect();
+        split_remainder(&resource.on_hand_quantity.0, &quantities).map_err(operation_error)?;
+        let total = quantities.iter().fold(BigDecimal::zero(), |total, q| total + q);
+
+        let run = OperationRun {
+            operation: ResourceOperation::Split,
+            batch_id: Uuid::new_v4(),
+            agent_id: containment::holder(conn, &resource)?,
+            performed_by,
+            note: note.as_deref().map(str::trim).filter(|n| !n.is_empty()),
+        };
+
+        let consume_id = Uuid::new_v4();
+        let consumed = execution::adjust_quantities(conn, &resource, -&total, -&total, Some(consume_id))?;
+        let mut executions = vec![record_execution(conn, &run, consume_id, ActionType::Consume, &consumed, &Decimal(total))?];
+
+        let mut produced = Vec::new();
+        for part in &parts {
+            let lot = part.lot.as_deref().map(str::trim).filter(|l| !l.is_empty()).or(resource.lot.as_deref());
+            let (child, produce) = produce_resource(conn, &run, &resource, &part.quantity, lot)?;
+            produced.push(child);
+            executions.push(produce);
+        }
+
+        Ok(ResourceOperationResponse {
+            operation: run.operation,
+            batch_id: run.batch_id,
+            executions,
+            consumed: vec![consumed],
+            produced,
+        })
+    })
+}
+
+/// Commingles the resources into a new one, each of them is consumed whole.
+/// The new resource keeps the lot they share, or takes the given or next lot
+pub fn merge_resources(
+    context: &Context,
+    economic_resource_ids: Vec<Uuid>,
+    lot: Option<String>,
+    note: Option<String>,
+) -> FieldResult<ResourceOperationResponse> {
+    let performed_by = context.user()?.id;
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let mut ids: Vec<Uuid> = Vec::new();
+    for id in economic_resource_ids {
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    if ids.len() < 2 {
+        return Err(operation_error("At least two resources are required to merge".to_string()));
+    }
+
+    conn.transaction::<_, FieldError, _>(|conn| {
+        let mut resources = Vec::new();
+        for id in &ids {
+            resources.push(locked_resource(conn, *id)?);
+        }
+
+        let first = resources[0].clone();
+        let agent_id = containment::holder(conn, &first)?;
+        for resource in &resources {
+            if resource.resource_specification_id != first.resource_specification_id {
+                return Err(operation_error(format!("{} and {} have different specifications", first.name, resource.name)));
+            }
+            if resource.current_location != first.current_location {
+                return Err(operation_error(format!("{} and {} are at different locations", first.name, resource.name)));
+            }
+            if containment::holder(conn, resource)? != agent_id {
+                return Err(operation_error(format!("{} and {} are held by different agents", first.name, resource.name)));
+            }
+            if resource.contained_in.is_some() {
+                return Err(operation_error(format!("{} is packed, unpack it before merging it", resource.name)));
+            }
+            if !resource.on_hand_quantity.0.is_positive() {
+                return Err(operation_error(format!("{} has nothing on hand", resource.name)));
+            }
+        }
+
+        let lot = match lot.as_deref().map(str::trim).filter(|l| !l.is_empty()) {
+            Some(lot) => lot.to_string(),
+            None => match shared_lot(&resources.iter().map(|r| r.lot.clone()).collect::<Vec<_>>()) {
+                Some(lot) => lot,
+                None => counter::next_lot_code(conn, &agent_id)?,
+            },
+        };
+
+        let run = OperationRun {
+            operation: ResourceOperation::Merge,
+            batch_id: Uuid::new_v4(),
+            agent_id,
+            performed_by,
+            note: note.as_deref().map(str::trim).filter(|n| !n.is_empty()),
+        };
+
+        let mut executions = Vec::new();
+        let mut consumed = Vec::new();
+        let mut total = BigDecimal::zero();
+        for resource in &resources {
+            let quantity = resource.on_hand_quantity.0.clone();
+            total += &quantity;
+
+            let consume_id = Uuid::new_v4();
+            let after = execution::adjust_quantities(conn, resource, -&quantity, -&quantity, Some(consume_id))?;
+            executions.push(record_execution(conn, &run, consume_id, ActionType::Consume, &after, &Decimal(quantity))?);
+            consumed.push(after);
+        }
+
+        let (merged, produce) = produce_resource(conn, &run, &first, &Decimal(total), Some(&lot))?;
+        executions.push(produce);
+
+        Ok(ResourceOperationResponse {
+            operation: run.operation,
+            batch_id: run.batch_id,
+            executions,
+            consumed,
+            produced: vec![merged],
+        })
+    })
+}
+
+/// Creates a resource like the given one with the quantity and lot, and its Produce
+fn produce_resource(
+    conn: &mut PgConnection,
+    run: &OperationRun,
+    like: &EconomicResource,
+    quantity: &Decimal,
+    lot: Option<&str>,
+) -> FieldResult<(EconomicResource, ProcessExecution)> {
+    let reference_number = counter::next_reference_number(conn, &run.agent_id)?;
+    let mut new_resource = NewEconomicResource::new(
+        &like.resource_specification_id,
+        &like.name,
+        quantity,
+        like.current_location.as_ref(),
+        lot,
+    );
+    new_resource.note = like.note.as_deref();
+    new_resource.custodian_id = like.custodian_id.as_ref();
+    new_resource.reference_number = Some(&reference_number);
+
+    let inserted_resource: EconomicResource = diesel::insert_into(economic_resources::table)
+        .values(new_resource)
+        .get_result(conn)?;
+
+    let produce_id = Uuid::new_v4();
+    ledger::record_change(conn, &inserted_resource, &quantity.0, &quantity.0, Some(produce_id))?;
+    let produce = record_execution(conn, run, produce_id, ActionType::Produce, &inserted_resource, quantity)?;
+
+    Ok((inserted_resource, produce))
+}
+
+fn record_execution(
+    conn: &mut PgConnection,
+    run: &OperationRun,
+    id: Uuid,
+    action: ActionType,
+    resource: &EconomicResource,
+    quantity: &Decimal,
+) -> FieldResult<ProcessExecution> {
+    let role_type = match action {
+        ActionType::Consume => RoleType::Input,
+        _ => RoleType::Output,
+    };
+
+    let mut new_execution = NewProcessExecution::new(
+        None,
+        &action,
+        &role_type,
+        &run.agent_id,
+        &run.agent_id,
+        Some(&run.batch_id),
+    );
+    new_execution.resource_specification = Some(&resource.resource_specification_id);
+    new_execution.resource_reference_number = Some(&resource.reference_number);
+    new_execution.resource_lot_number = resource.lot.as_deref();
+    new_execution.resource_quantity = Some(quantity);
+    new_execution.at_location = resource.current_location.as_ref();
+    new_execution.note = run.note;
+    new_execution.economic_resource_id = Some(&resource.id);
+    new_execution.id = Some(&id);
+    new_execution.operation = Some(&run.operation);
+    new_execution.performed_by = Some(&run.performed_by);
+
+    let inserted_execution = diesel::insert_into(process_executions::table)
+        .values(new_execution)
+        .get_result(conn)?;
+
+    Ok(inserted_execution)
+}
+
+fn locked_resource(conn: &mut PgConnection, economic_resource_id: Uuid) -> FieldResult<EconomicResource> {
+    economic_resources::table
+        .filter(economic_resources::id.eq(economic_resource_id))
+        .for_update()
+        .first::<EconomicResource>(conn)
+        .optional()?
+        .ok_or_else(|| operation_error(format!("Economic resource {} not found", economic_resource_id)))
+}
+
+fn operation_error(error_message: String) -> FieldError {
+    FieldError::new("Invalid resource operation", graphql_value!({ "code": error_message }))
+}
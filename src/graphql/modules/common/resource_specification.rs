@@ -6,13 +6,17 @@ use diesel::prelude::*;
 use juniper::FieldResult;
 use uuid::Uuid;
 
+use super::unit;
+
 pub fn all_resource_specifications(context: &Context) -> FieldResult<Vec<ResourceSpecification>> {
     let conn = &mut context
         .pool
         .get()
         .expect("Failed to get DB connection from pool");
 
-    let results = resource_specifications::table.load::<ResourceSpecification>(conn)?; // The `load` method is now available
+    let results = resource_specifications::table
+        .filter(resource_specifications::agent_id.eq_any(context.agent_ids()))
+        .load::<ResourceSpecification>(conn)?;
 
     Ok(results)
 }
@@ -62,13 +66,18 @@ pub fn create_resource_specification(
 ) -> FieldResult<ResourceSpecification> {
     let conn = &mut context.pool.get().expect("Failed to get DB connection from pool");
 
-    let new_resource_spec = NewResourceSpecification::new(
+    // Known units are stored by their symbol, anything else stays free text
+    let unit = unit::find_unit(conn, &unit_of_measure)?;
+    let unit_of_measure = unit.as_ref().map(|u| u.symbol.clone()).unwrap_or(unit_of_measure);
+
+    let mut new_resource_spec = NewResourceSpecification::new(
         &agent_id, 
         &name, 
         note.as_deref(), 
         &resource_type, 
         &unit_of_measure
     );
+    new_resource_spec.unit_id = unit.as_ref().map(|u| &u.id);
 
     // Insert the new resource specification into the database
     let inserted_resource_spec = diesel::insert_into(resource_specifications::table)
@@ -0,0 +1,169 @@
+use bigdecimal::{BigDecimal, Signed};
+use diesel::prelude::*;
+use juniper::{graphql_value, FieldError, FieldResult};
+use uuid::Uuid;
+
+use crate::{
+    common::{
+        economic_resource::Decimal,
+        resource_specification::ResourceSpecification,
+        unit::{NewUnit, Unit, UnitDimension},
+    },
+    db::schema::{resource_specifications, units},
+    graphql::context::Context,
+};
+
+/*** Queries */
+pub fn get_units(context: &Context, dimension: Option<UnitDimension>) -> FieldResult<Vec<Unit>> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let mut query = units::table.into_boxed();
+    if let Some(dimension) = dimension {
+        query = query.filter(units::dimension.eq(dimension));
+    }
+
+    let results = query
+        .order((units::dimension.asc(), units::conversion_factor.asc()))
+        .load::<Unit>(conn)?;
+
+    Ok(results)
+}
+
+/// Converts between two units named by symbol or label
+pub fn convert_quantity(context: &Context, quantity: Decimal, from: String, to: String) -> FieldResult<Decimal> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let from = find_unit(conn, &from)?.ok_or_else(|| unit_error(format!("Unknown unit {}", from)))?;
+    let to = find_unit(conn, &to)?.ok_or_else(|| unit_error(format!("Unknown unit {}", to)))?;
+
+    Ok(Decimal(convert(&quantity.0, &from, &to)?))
+}
+
+/*** Mutations */
+pub fn create_unit(
+    context: &Context,
+    label: String,
+    symbol: String,
+    dimension: UnitDimension,
+    conversion_factor: Decimal,
+    om2_uri: Option<String>,
+    unece_code: Option<String>,
+) -> FieldResult<Unit> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let (label, symbol) = (label.trim(), symbol.trim());
+    if label.is_empty() || symbol.is_empty() {
+        return Err(unit_error("A unit needs a label and a symbol".to_string()));
+    }
+    if !conversion_factor.0.is_positive() {
+        return Err(unit_error(format!("The conversion factor of {} must be positive", symbol)));
+    }
+    if let Some(existing) = find_unit(conn, symbol)?.or(find_unit(conn, label)?) {
+        return Err(unit_error(format!("{} is already registered as {}", symbol, existing.label)));
+    }
+
+    let unece_code = unece_code.map(|code| code.trim().to_uppercase()).filter(|code| !code.is_empty());
+    if let Some(code) = &unece_code {
+        if let Some(existing) = unit_by_unece_code(conn, code)? {
+            return Err(unit_error(format!("{} is already the code of {}", code, existing.label)));
+        }
+    }
+
+    let om2_uri = om2_uri.as_deref().map(str::trim).filter(|uri| !uri.is_empty());
+    let mut new_unit = NewUnit::new(label, symbol, &dimension, &conversion_factor, om2_uri);
+    new_unit.unece_code = unece_code.as_deref();
+    let inserted_unit: Unit = diesel::insert_into(units::table)
+        .values(new_unit)
+        .get_result(conn)?;
+
+    Ok(inserted_unit)
+}
+
+/// Moves the specification to a registered unit, existing quantities are not converted
+pub fn set_resource_specification_unit(
+    context: &Context,
+    resource_specification_id: Uuid,
+    unit_id: Uuid,
+) -> FieldResult<ResourceSpecification> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let unit = unit_by_id(conn, unit_id)?;
+    let updated: ResourceSpecification = diesel::update(resource_specifications::table)
+        .filter(resource_specifications::id.eq(resource_specification_id))
+        .set((
+            resource_specifications::unit_id.eq(unit.id),
+            resource_specifications::unit_of_measure.eq(&unit.symbol),
+        ))
+        .get_result(conn)?;
+
+    Ok(updated)
+}
+
+/// Registered unit named by the text, symbols win over labels
+pub fn find_unit(conn: &mut PgConnection, name: &str) -> FieldResult<Option<Unit>> {
+    let units: Vec<Unit> = units::table.load::<Unit>(conn)?;
+
+    let by_symbol = units.iter().find(|u| u.symbol == name.trim()).cloned();
+    Ok(by_symbol.or_else(|| units.into_iter().find(|u| u.matches(name))))
+}
+
+/// UN/ECE code of the registered unit named by the text, other units keep their name
+pub fn unece_code(conn: &mut PgConnection, unit_of_measure: &str) -> FieldResult<String> {
+    let unit = find_unit(conn, unit_of_measure)?;
+    Ok(unit
+        .and_then(|unit| unit.unece_code)
+        .unwrap_or_else(|| unit_of_measure.trim().to_string()))
+}
+
+/// Symbol of the unit with the UN/ECE code, unknown codes are kept as they are
+pub fn unit_symbol(conn: &mut PgConnection, code: &str) -> FieldResult<String> {
+    let unit = unit_by_unece_code(conn, code)?;
+    Ok(unit.map(|unit| unit.symbol).unwrap_or_else(|| code.trim().to_string()))
+}
+
+fn unit_by_unece_code(conn: &mut PgConnection, code: &str) -> FieldResult<Option<Unit>> {
+    let unit = units::table
+        .filter(units::unece_code.eq(code.trim().to_uppercase()))
+        .first::<Unit>(conn)
+        .optional()?;
+
+    Ok(unit)
+}
+
+pub fn unit_by_id(conn: &mut PgConnection, unit_id: Uuid) -> FieldResult<Unit> {
+    units::table
+        .filter(units::id.eq(unit_id))
+        .first::<Unit>(conn)
+        .optional()?
+        .ok_or_else(|| unit_error(format!("Unit {} not found", unit_id)))
+}
+
+/// Unit the quantities of the specification are kept in, none for free text units
+pub fn specification_unit(conn: &mut PgConnection, resource_specification_id: Uuid) -> FieldResult<Option<Unit>> {
+    let unit_id: Option<Uuid> = resource_specifications::table
+        .filter(resource_specifications::id.eq(resource_specification_id))
+        .select(resource_specifications::unit_id)
+        .first::<Option<Uuid>>(conn)?;
+
+    unit_id.map(|unit_id| unit_by_id(conn, unit_id)).transpose()
+}
+
+pub fn convert(quantity: &BigDecimal, from: &Unit, to: &Unit) -> FieldResult<BigDecimal> {
+    from.convert(quantity, to).map_err(unit_error)
+}
+
+fn unit_error(error_message: String) -> FieldError {
+    FieldError::new("Invalid unit", graphql_value!({ "code": error_message }))
+}
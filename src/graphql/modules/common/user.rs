@@ -0,0 +1,333 @@
+use std::env;
+
+use chrono::Utc;
+use diesel::prelude::*;
+use juniper::{graphql_value, FieldError, FieldResult};
+use uuid::Uuid;
+
+use crate::{
+    common::user::{
+        generate_api_key, hash_api_key, is_bootstrap_secret, AgentMember, AgentMembership, AgentRole, ApiKey, IssuedApiKey,
+        NewAgentMembership, NewApiKey, NewUser, Permission, User,
+    },
+    db::schema::{agent_memberships, agents, api_keys, users},
+    graphql::context::{access_error, Context},
+};
+
+/**** Queries */
+/// The user the API key of the request belongs to
+pub fn viewer(context: &Context) -> FieldResult<User> {
+    Ok(context.user()?.clone())
+}
+
+pub fn api_keys_of_viewer(context: &Context) -> FieldResult<Vec<ApiKey>> {
+    let user = context.user()?;
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let keys = api_keys::table
+        .filter(api_keys::user_id.eq(user.id))
+        .order(api_keys::created_at.asc())
+        .load::<ApiKey>(conn)?;
+
+    Ok(keys)
+}
+
+/// The agents the viewer is a member of, with their role in each
+pub fn memberships_of_viewer(context: &Context) -> FieldResult<Vec<AgentMembership>> {
+    context.user()?;
+    Ok(context.memberships.clone())
+}
+
+pub fn agent_members(context: &Context, agent_id: Uuid) -> FieldResult<Vec<AgentMember>> {
+    context.check_member(agent_id)?;
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let members = users::table
+        .inner_join(agent_memberships::table)
+        .filter(agent_memberships::agent_id.eq(agent_id))
+        .order(users::name.asc())
+        .select((users::all_columns, agent_memberships::all_columns))
+        .load::<(User, AgentMembership)>(conn)?
+        .into_iter()
+        .map(|(user, membership)| AgentMember { user, membership })
+        .collect();
+
+    Ok(members)
+}
+
+/*** Mutations */
+/// Signs a user up with a first API key. A user signing up with the bootstrap
+/// secret of the deployment, set as BOOTSTRAP_SECRET, while it has no deployment
+/// admin becomes one, and an admin of the agents nobody administers
+pub fn create_user(
+    context: &Context,
+    name: String,
+    email: String,
+    bootstrap_secret: Option<String>,
+) -> FieldResult<IssuedApiKey> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    // Emails are kept lowercased, so sign-ups and lookups ignore case
+    let (name, email) = (name.trim(), email.trim().to_lowercase());
+    if name.is_empty() {
+        return Err(user_error("A name is required".to_string()));
+    }
+    if !email.contains('@') {
+        return Err(user_error(format!("{} is not an email address", email)));
+    }
+    let bootstrap = match &bootstrap_secret {
+        Some(secret) if is_bootstrap_secret(env::var("BOOTSTRAP_SECRET").ok().as_deref(), secret) => true,
+        Some(_) => return Err(access_error("Invalid bootstrap secret".to_string())),
+        None => false,
+    };
+
+    conn.transaction::<_, FieldError, _>(|conn| {
+        // Sign-ups wait for each other, so only one of them can bootstrap the deployment
+        diesel::sql_query("LOCK TABLE users IN SHARE ROW EXCLUSIVE MODE").execute(conn)?;
+
+        let taken: i64 = users::table
+            .filter(users::email.eq(&email))
+            .count()
+            .get_result(conn)?;
+        if taken > 0 {
+            return Err(user_error(format!("{} is already signed up", email)));
+        }
+
+        if bootstrap {
+            let admins: i64 = users::table
+                .filter(users::deployment_admin.eq(true))
+                .count()
+                .get_result(conn)?;
+            if admins > 0 {
+                return Err(access_error("The deployment already has an admin".to_string()));
+            }
+        }
+
+        let mut new_user = NewUser::new(name, &email);
+        new_user.deployment_admin = bootstrap;
+        let user: User = diesel::insert_into(users::table)
+            .values(new_user)
+            .get_result(conn)?;
+
+        if bootstrap {
+            let administered = agent_memberships::table
+                .filter(agent_memberships::role.eq(AgentRole::Admin))
+                .select(agent_memberships::agent_id);
+            let agent_ids: Vec<Uuid> = agents::table
+                .filter(agents::id.ne_all(administered))
+                .select(agents::id)
+                .load::<Uuid>(conn)?;
+            for agent_id in &agent_ids {
+                add_membership(conn, user.id, *agent_id, AgentRole::Admin)?;
+            }
+        }
+
+        issue_api_key(conn, user, "Default")
+    })
+}
+
+pub fn create_api_key(context: &Context, name: String) -> FieldResult<IssuedApiKey> {
+    let user = context.user()?.clone();
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    issue_api_key(conn, user, name.trim())
+}
+
+pub fn revoke_api_key(context: &Context, api_key_id: Uuid) -> FieldResult<ApiKey> {
+    let user = context.user()?;
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let revoked = diesel::update(api_keys::table)
+        .filter(api_keys::id.eq(api_key_id))
+        .filter(api_keys::user_id.eq(user.id))
+        .filter(api_keys::revoked_at.is_null())
+        .set(api_keys::revoked_at.eq(Utc::now().naive_utc()))
+        .get_result::<ApiKey>(conn)
+        .optional()?
+        .ok_or_else(|| user_error(format!("No active API key {} of yours", api_key_id)))?;
+
+    Ok(revoked)
+}
+
+/// Admins add the users they work with to their agent
+pub fn add_agent_member(
+    context: &Context,
+    agent_id: Uuid,
+    email: String,
+    role: AgentRole,
+) -> FieldResult<AgentMembership> {
+    context.check_permission(agent_id, Permission::Administer)?;
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    conn.transaction::<_, FieldError, _>(|conn| {
+        let user = user_by_email(conn, &email.trim().to_lowercase())?;
+        let member: i64 = agent_memberships::table
+            .filter(agent_memberships::user_id.eq(user.id))
+            .filter(agent_memberships::agent_id.eq(agent_id))
+            .count()
+            .get_result(conn)?;
+        if member > 0 {
+            return Err(user_error(format!("{} is already a member of agent {}", user.email, agent_id)));
+        }
+
+        add_membership(conn, user.id, agent_id, role)
+    })
+}
+
+pub fn set_agent_member_role(
+    context: &Context,
+    agent_id: Uuid,
+    user_id: Uuid,
+    role: AgentRole,
+) -> FieldResult<AgentMembership> {
+    context.check_permission(agent_id, Permission::Administer)?;
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    conn.transaction::<_, FieldError, _>(|conn| {
+        let membership = admin_kept_membership(conn, agent_id, user_id, role != AgentRole::Admin)?;
+
+        let updated = diesel::update(agent_memberships::table)
+            .filter(agent_memberships::id.eq(membership.id))
+            .set(agent_memberships::role.eq(role))
+            .get_result::<AgentMembership>(conn)?;
+
+        Ok(updated)
+    })
+}
+
+pub fn remove_agent_member(context: &Context, agent_id: Uuid, user_id: Uuid) -> FieldResult<AgentMembership> {
+    context.check_permission(agent_id, Permission::Administer)?;
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    conn.transaction::<_, FieldError, _>(|conn| {
+        let membership = admin_kept_membership(conn, agent_id, user_id, true)?;
+
+        diesel::delete(agent_memberships::table.filter(agent_memberships::id.eq(membership.id))).execute(conn)?;
+
+        Ok(membership)
+    })
+}
+
+/// The user of an active API key and their memberships
+pub fn authenticate(conn: &mut PgConnection, key: &str) -> FieldResult<(User, Vec<AgentMembership>)> {
+    let api_key: ApiKey = api_keys::table
+        .filter(api_keys::key_hash.eq(hash_api_key(key)))
+        .filter(api_keys::revoked_at.is_null())
+        .first::<ApiKey>(conn)
+        .optional()?
+        .ok_or_else(|| access_error("Invalid or revoked API key".to_string()))?;
+
+    diesel::update(api_keys::table)
+        .filter(api_keys::id.eq(api_key.id))
+        .set(api_keys::last_used_at.eq(Utc::now().naive_utc()))
+        .execute(conn)?;
+
+    let user = users::table
+        .filter(users::id.eq(api_key.user_id))
+        .first::<User>(conn)?;
+    let memberships = agent_memberships::table
+        .filter(agent_memberships::user_id.eq(user.id))
+        .load::<AgentMembership>(conn)?;
+
+    Ok((user, memberships))
+}
+
+/// Makes the user a member of the agent, members already keep the role they have
+pub fn add_membership(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    agent_id: Uuid,
+    role: AgentRole,
+) -> FieldResult<AgentMembership> {
+    diesel::insert_into(agent_memberships::table)
+        .values(NewAgentMembership::new(&user_id, &agent_id, &role))
+        .on_conflict((agent_memberships::user_id, agent_memberships::agent_id))
+        .do_nothing()
+        .execute(conn)?;
+
+    let membership = agent_memberships::table
+        .filter(agent_memberships::user_id.eq(user_id))
+        .filter(agent_memberships::agent_id.eq(agent_id))
+        .first::<AgentMembership>(conn)?;
+
+    Ok(membership)
+}
+
+fn issue_api_key(conn: &mut PgConnection, user: User, name: &str) -> FieldResult<IssuedApiKey> {
+    if name.is_empty() {
+        return Err(user_error("A name is required for the API key".to_string()));
+    }
+
+    let key = generate_api_key();
+    let prefix = &key[..8];
+    let key_hash = hash_api_key(&key);
+
+    let api_key = diesel::insert_into(api_keys::table)
+        .values(NewApiKey::new(&user.id, name, prefix, &key_hash))
+        .get_result::<ApiKey>(conn)?;
+
+    Ok(IssuedApiKey { user, api_key, key })
+}
+
+/// The membership of the user, locked with the others of the agent. An agent keeps
+/// an admin, it would be out of reach otherwise, so the last one can't leave it
+fn admin_kept_membership(
+    conn: &mut PgConnection,
+    agent_id: Uuid,
+    user_id: Uuid,
+    leaves_admins: bool,
+) -> FieldResult<AgentMembership> {
+    let members: Vec<AgentMembership> = agent_memberships::table
+        .filter(agent_memberships::agent_id.eq(agent_id))
+        .for_update()
+        .load::<AgentMembership>(conn)?;
+
+    let membership = members
+        .iter()
+        .find(|m| m.user_id == user_id)
+        .cloned()
+        .ok_or_else(|| user_error(format!("User {} is not a member of agent {}", user_id, agent_id)))?;
+
+    let admins = members.iter().filter(|m| m.role == AgentRole::Admin).count();
+    if leaves_admins && membership.role == AgentRole::Admin && admins == 1 {
+        return Err(user_error(format!("User {} is the last admin of agent {}", user_id, agent_id)));
+    }
+
+    Ok(membership)
+}
+
+fn user_by_email(conn: &mut PgConnection, email: &str) -> FieldResult<User> {
+    users::table
+        .filter(users::email.eq(email))
+        .first::<User>(conn)
+        .optional()?
+        .ok_or_else(|| user_error(format!("No user signed up as {}", email)))
+}
+
+fn user_error(error_message: String) -> FieldError {
+    FieldError::new("Invalid user", graphql_value!({ "code": error_message }))
+}
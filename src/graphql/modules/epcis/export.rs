@@ -0,0 +1,344 @@
+use std::collections::{HashMap, HashSet};
+
+use bigdecimal::ToPrimitive;
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use juniper::{graphql_value, FieldError, FieldResult};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    db::schema::{
+        economic_resources, locations, process_execution_custom_values, process_executions, recipe_process_flow_data_fields,
+        recipe_process_flows, recipe_processes, recipes, resource_specifications,
+    },
+    epcis::{
+        document::{
+            BizTransaction, Destination, EpcisDocument, EpcisEvent, EpcisEventType, QuantityElement, Reference,
+            Source, SOURCE_DESTINATION_LOCATION, SOURCE_DESTINATION_OWNING_PARTY,
+        },
+        mapping::{self, EventMapping},
+    },
+    graphql::modules::{common::unit, process::correction},
+    recipe::process::execution::ProcessExecution,
+    templates::{
+        recipe_flow_template::{ActionType, RoleType},
+        recipe_flow_template_data_field::FieldClass,
+    },
+};
+
+/// Query string of the EPCIS export route, dates as YYYY-MM-DD
+#[derive(Deserialize, Debug)]
+pub struct EpcisExportParams {
+    pub recipe_process_id: Option<String>,
+    pub lot: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+/// Builds the EPCIS document of the executions of a process or of a lot
+pub fn export_events(conn: &mut PgConnection, params: &EpcisExportParams, agent_ids: &[Uuid]) -> FieldResult<EpcisDocument> {
+    let recipe_process_id = params
+        .recipe_process_id
+        .as_deref()
+        .map(|value| parse_param("recipe_process_id", value, Uuid::parse_str))
+        .transpose()?;
+    let from = params
+        .from
+        .as_deref()
+        .map(|value| parse_param("from", value, |v| v.parse::<NaiveDate>()))
+        .transpose()?;
+    let to = params
+        .to
+        .as_deref()
+        .map(|value| parse_param("to", value, |v| v.parse::<NaiveDate>()))
+        .transpose()?;
+
+    if recipe_process_id.is_none() && params.lot.is_none() {
+        return Err(export_error("Filter by recipe_process_id or lot".to_string()));
+    }
+
+    // Only executions of the given agents' recipes
+    let recipe_process_ids = recipe_processes::table
+        .filter(recipe_processes::recipe_id.eq_any(recipes::table.filter(recipes::agent_id.eq_any(agent_ids)).select(recipes::id)))
+        .select(recipe_processes::id);
+
+    let mut query = process_executions::table
+        .inner_join(recipe_process_flows::table)
+        .filter(recipe_process_flows::recipe_process_id.eq_any(recipe_process_ids))
+        .select(process_executions::all_columns)
+        .into_boxed();
+
+    if let Some(recipe_process_id) = recipe_process_id {
+        query = query.filter(recipe_process_flows::recipe_process_id.eq(recipe_process_id));
+    }
+    if let Some(lot) = &params.lot {
+        query = query.filter(
+            process_executions::resource_lot_number
+                .eq(lot)
+                .or(process_executions::to_resource_lot_number.eq(lot)),
+        );
+    }
+
+    let matching: Vec<ProcessExecution> = query.load::<ProcessExecution>(conn)?;
+
+    // A transformation is exported whole, even when the lot only matches one side of it
+    let batch_ids: Vec<Uuid> = matching.iter().filter_map(|e| e.batch_id).collect();
+    let mut executions: Vec<ProcessExecution> = process_executions::table
+        .filter(process_executions::batch_id.eq_any(&batch_ids))
+        .load::<ProcessExecution>(conn)?;
+    let loaded: HashSet<Uuid> = executions.iter().map(|e| e.id).collect();
+    executions.extend(matching.into_iter().filter(|e| !loaded.contains(&e.id)));
+    let mut executions = correction::effective_executions(conn, executions)?;
+
+    executions.retain(|e| in_range(event_time(e), from, to));
+    executions.sort_by_key(|e| (event_time(e), e.created_at));
+
+    let transformation_batches: HashSet<Uuid> = executions
+        .iter()
+        .filter_map(|e| e.batch_id)
+        .filter(|batch_id| {
+            let in_batch = |action: ActionType| {
+                executions
+                    .iter()
+                    .any(|e| e.batch_id == Some(*batch_id) && e.action == action && e.epcis_event_id.is_none())
+            };
+            in_batch(ActionType::Consume) && in_batch(ActionType::Produce)
+        })
+        .collect();
+
+    // Executions of the same EPCIS event are exported back as one event
+    let mut groups: Vec<(String, Vec<ProcessExecution>)> = Vec::new();
+    for execution in executions {
+        let key = match (&execution.epcis_event_id, execution.batch_id) {
+            (Some(event_id), _) => event_id.clone(),
+            (None, Some(batch_id))
+                if transformation_batches.contains(&batch_id)
+                    && matches!(execution.action, ActionType::Consume | ActionType::Produce) =>
+            {
+                format!("urn:uuid:{}", batch_id)
+            }
+            _ => format!("urn:uuid:{}", execution.id),
+        };
+        match groups.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, group)) => group.push(execution),
+            None => groups.push((key, vec![execution])),
+        }
+    }
+
+    let mut units: HashMap<Uuid, String> = HashMap::new();
+    let mut events = Vec::new();
+    for (event_id, group) in groups {
+        events.push(epcis_event(conn, &mut units, event_id, &group)?);
+    }
+
+    Ok(EpcisDocument::new(format_time(Utc::now().naive_utc()), events))
+}
+
+fn epcis_event(
+    conn: &mut PgConnection,
+    units: &mut HashMap<Uuid, String>,
+    event_id: String,
+    group: &[ProcessExecution],
+) -> FieldResult<EpcisEvent> {
+    let first = &group[0];
+    let is_transformation = group.iter().any(|e| e.action == ActionType::Consume)
+        && group.iter().any(|e| e.action == ActionType::Produce);
+
+    let mut event = if is_transformation {
+        let mut event = EpcisEvent::new(EpcisEventType::Transformation, format_time(event_time(first)));
+        event.transformation_id = first.batch_id.map(|batch_id| format!("urn:uuid:{}", batch_id));
+        for execution in group {
+            let element = quantity_element(conn, units, execution)?;
+            match execution.action {
+                ActionType::Consume => event.input_quantity_list.extend(element),
+                ActionType::Produce => event.output_quantity_list.extend(element),
+                _ => {}
+            }
+        }
+        event
+    } else {
+        let mapping = EventMapping::for_flow(first.action, first.role_type).ok_or_else(|| {
+            export_error(format!("{:?} {:?} has no EPCIS event", first.action, first.role_type))
+        })?;
+        let mut event = EpcisEvent::new(mapping.event_type, format_time(event_time(first)));
+        event.action = Some(mapping.epcis_action);
+        event.biz_step = Some(mapping.biz_step.to_string());
+
+        let mut elements = Vec::new();
+        for execution in group {
+            elements.extend(quantity_element(conn, units, execution)?);
+        }
+        if mapping.event_type == EpcisEventType::Aggregation {
+            event.parent_id = container(conn, first)?.map(|container| mapping::resource_urn(&container));
+            event.child_quantity_list = elements;
+        } else {
+            event.quantity_list = elements;
+        }
+        event
+    };
+
+    event.event_id = Some(event_id);
+
+    // The event happens where the recipe agent is, which is the destination when receiving
+    let location = group.iter().find_map(|e| match (e.action, e.role_type) {
+        (ActionType::Transfer, RoleType::Input) => e.to_location.or(e.at_location),
+        _ => e.at_location,
+    });
+    if let Some(location) = location {
+        let id = location_id(conn, location)?;
+        event.read_point = Some(Reference { id: id.clone() });
+        event.biz_location = Some(Reference { id });
+    }
+
+    if first.provider_agent != first.receiver_agent {
+        event.source_list.push(Source {
+            source_type: SOURCE_DESTINATION_OWNING_PARTY.to_string(),
+            source: mapping::agent_urn(&first.provider_agent),
+        });
+        event.destination_list.push(Destination {
+            destination_type: SOURCE_DESTINATION_OWNING_PARTY.to_string(),
+            destination: mapping::agent_urn(&first.receiver_agent),
+        });
+        if let Some(at_location) = first.at_location {
+            event.source_list.push(Source {
+                source_type: SOURCE_DESTINATION_LOCATION.to_string(),
+                source: location_id(conn, at_location)?,
+            });
+        }
+        if let Some(to_location) = first.to_location {
+            event.destination_list.push(Destination {
+                destination_type: SOURCE_DESTINATION_LOCATION.to_string(),
+                destination: location_id(conn, to_location)?,
+            });
+        }
+    }
+
+    let custom_values = custom_values(conn, first.id)?;
+    let custom_value = |field_class: FieldClass| {
+        custom_values
+            .iter()
+            .find(|(class, _)| *class == field_class)
+            .map(|(_, value)| value.clone())
+    };
+    if let Some(number) = custom_value(FieldClass::ReferenceDocumentNumber) {
+        event.biz_transaction_list.push(BizTransaction {
+            transaction_type: custom_value(FieldClass::ReferenceDocumentType).map(|t| t.to_lowercase()),
+            biz_transaction: number,
+        });
+    }
+
+    Ok(event)
+}
+
+/// Class, lot and quantity the execution moved, none for events without a resource
+fn quantity_element(
+    conn: &mut PgConnection,
+    units: &mut HashMap<Uuid, String>,
+    execution: &ProcessExecution,
+) -> FieldResult<Option<QuantityElement>> {
+    let resource_specification = match execution.resource_specification {
+        Some(resource_specification) => resource_specification,
+        None => return Ok(None),
+    };
+
+    let unit_of_measure = match custom_values(conn, execution.id)?
+        .into_iter()
+        .find(|(class, _)| *class == FieldClass::UnitOfMeasure)
+    {
+        Some((_, unit_of_measure)) => unit_of_measure,
+        None => unit_of_measure(conn, units, resource_specification)?,
+    };
+
+    let uom = match unit_of_measure.trim() {
+        "" => None,
+        unit_of_measure => Some(unit::unece_code(conn, unit_of_measure)?),
+    };
+
+    Ok(Some(QuantityElement {
+        epc_class: mapping::class_urn(&resource_specification, execution.resource_lot_number.as_deref()),
+        quantity: execution.resource_quantity.as_ref().and_then(|q| q.0.to_f64()).unwrap_or(0.0),
+        uom,
+    }))
+}
+
+fn container(conn: &mut PgConnection, execution: &ProcessExecution) -> FieldResult<Option<Uuid>> {
+    let resource_id = match execution.economic_resource_id {
+        Some(resource_id) => resource_id,
+        None => return Ok(None),
+    };
+
+    let contained_in: Option<Uuid> = economic_resources::table
+        .filter(economic_resources::id.eq(resource_id))
+        .select(economic_resources::contained_in)
+        .first::<Option<Uuid>>(conn)?;
+
+    Ok(contained_in)
+}
+
+/// The GLN of the location when it has one
+fn location_id(conn: &mut PgConnection, location_id: Uuid) -> FieldResult<String> {
+    let gln: Option<String> = locations::table
+        .filter(locations::id.eq(location_id))
+        .select(locations::gln)
+        .first::<Option<String>>(conn)?;
+
+    Ok(match gln {
+        Some(gln) => mapping::gln_uri(&gln),
+        None => mapping::location_urn(&location_id),
+    })
+}
+
+fn custom_values(conn: &mut PgConnection, process_execution_id: Uuid) -> FieldResult<Vec<(FieldClass, String)>> {
+    let custom_values: Vec<(FieldClass, String)> = process_execution_custom_values::table
+        .inner_join(recipe_process_flow_data_fields::table)
+        .filter(process_execution_custom_values::process_execution_id.eq(process_execution_id))
+        .select((
+            recipe_process_flow_data_fields::field_class,
+            process_execution_custom_values::field_value,
+        ))
+        .load::<(FieldClass, String)>(conn)?;
+
+    Ok(custom_values)
+}
+
+fn unit_of_measure(
+    conn: &mut PgConnection,
+    units: &mut HashMap<Uuid, String>,
+    resource_specification_id: Uuid,
+) -> FieldResult<String> {
+    if let Some(unit) = units.get(&resource_specification_id) {
+        return Ok(unit.clone());
+    }
+    let unit: String = resource_specifications::table
+        .filter(resource_specifications::id.eq(resource_specification_id))
+        .select(resource_specifications::unit_of_measure)
+        .first::<String>(conn)?;
+    units.insert(resource_specification_id, unit.clone());
+    Ok(unit)
+}
+
+fn event_time(execution: &ProcessExecution) -> NaiveDateTime {
+    execution.has_point_in_time.unwrap_or(execution.created_at)
+}
+
+/// Event times are stored in UTC
+fn format_time(time: NaiveDateTime) -> String {
+    time.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+fn in_range(event_time: NaiveDateTime, from: Option<NaiveDate>, to: Option<NaiveDate>) -> bool {
+    let date = event_time.date();
+    from.is_none_or(|from| date >= from) && to.is_none_or(|to| date <= to)
+}
+
+fn parse_param<T, E>(name: &str, value: &str, parse: impl Fn(&str) -> Result<T, E>) -> FieldResult<T> {
+    parse(value).map_err(|_| export_error(format!("Invalid {}: {}", name, value)))
+}
+
+fn export_error(error_message: String) -> FieldError {
+    FieldError::new(
+        "Unable to export EPCIS events",
+        graphql_value!({ "code": error_message }),
+    )
+}
@@ -0,0 +1,117 @@
+use diesel::prelude::*;
+use juniper::{graphql_value, FieldError, FieldResult};
+use uuid::Uuid;
+
+use crate::{
+    db::schema::{map_templates, recipe_templates},
+    fda::cte::{CriticalTrackingEvent, CteFlow, CRITICAL_TRACKING_EVENTS, REFERENCE_DOCUMENT_GROUP},
+    graphql::{
+        context::Context,
+        modules::templates::template::{
+            self, FieldInheritance, RecipeFlowTemplateArg, RecipeFlowTemplateDataFieldArg,
+            RecipeFlowTemplateGroup,
+        },
+    },
+    templates::{
+        map_template::{MapTemplate, MapTemplateResponse, TemplateType},
+        recipe_flow_template::EventType,
+        recipe_flow_template_data_field::FieldClass,
+        recipe_flow_template_group_data_fields::FieldGroupClass,
+        recipe_template::NewRecipeTemplate,
+    },
+};
+
+/*** Mutations */
+/// Installs the Critical Tracking Event templates missing from an FDA map
+pub fn install_fda_templates(context: &Context, map_template_id: Uuid) -> FieldResult<MapTemplateResponse> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    conn.transaction::<_, FieldError, _>(|conn| {
+        let map_template: MapTemplate = map_templates::table
+            .filter(map_templates::id.eq(map_template_id))
+            .filter(map_templates::deleted_at.is_null())
+            .first::<MapTemplate>(conn)?;
+
+        if map_template.type_ != TemplateType::FDA {
+            return Err(FieldError::new(
+                "FDA templates can only be installed on FDA maps",
+                graphql_value!({ "code": "INVALID_MAP_TYPE" }),
+            ));
+        }
+
+        let installed: Vec<String> = recipe_templates::table
+            .filter(recipe_templates::map_template_id.eq(map_template_id))
+            .filter(recipe_templates::deleted_at.is_null())
+            .select(recipe_templates::identifier)
+            .load::<String>(conn)?;
+
+        for cte in CRITICAL_TRACKING_EVENTS.iter().filter(|cte| !installed.iter().any(|i| i == cte.identifier)) {
+            let mut new_template = NewRecipeTemplate::new(&map_template_id, cte.identifier, cte.name, None, None, None);
+            new_template.created_by_agent = map_template.created_by_agent.as_ref();
+            template::insert_recipe_template(conn, &new_template, recipe_flow_template_args(cte))?;
+        }
+
+        Ok(())
+    })?;
+
+    template::get_map_template_by_id(context, map_template_id)
+}
+
+fn recipe_flow_template_args(cte: &CriticalTrackingEvent) -> Vec<RecipeFlowTemplateArg> {
+    cte.flows.iter().map(recipe_flow_template_arg).collect()
+}
+
+fn recipe_flow_template_arg(flow: &CteFlow) -> RecipeFlowTemplateArg {
+    let reference_document_fields: Vec<String> = flow
+        .data_fields
+        .iter()
+        .filter(|f| matches!(f.class, FieldClass::ReferenceDocumentType | FieldClass::ReferenceDocumentNumber))
+        .map(|f| f.identifier.to_string())
+        .collect();
+
+    let groups = if reference_document_fields.is_empty() {
+        Vec::new()
+    } else {
+        vec![RecipeFlowTemplateGroup {
+            name: REFERENCE_DOCUMENT_GROUP.to_string(),
+            class: FieldGroupClass::ReferenceDocument,
+            fields: reference_document_fields,
+        }]
+    };
+
+    let data_fields = flow
+        .data_fields
+        .iter()
+        .map(|data_field| RecipeFlowTemplateDataFieldArg {
+            field_identifier: data_field.identifier.to_string(),
+            field_class: data_field.class.clone(),
+            field: data_field.field.to_string(),
+            field_type: data_field.field_type.clone(),
+            note: None,
+            required: data_field.required,
+            flow_through: data_field.flow_through.clone(),
+            inherits: data_field.inherits.map(|(identifier, field)| FieldInheritance {
+                identifier: identifier.to_string(),
+                field: field.to_string(),
+            }),
+            accept_default: false,
+            option_set_id: None,
+            min_value: None,
+            max_value: None,
+            rules: None,
+        })
+        .collect();
+
+    RecipeFlowTemplateArg {
+        event_type: EventType::EconomicEvent,
+        role_type: flow.role_type,
+        action: flow.action,
+        data_fields,
+        groups,
+        identifier: flow.identifier.to_string(),
+        interactions: None,
+    }
+}
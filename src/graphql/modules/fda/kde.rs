@@ -0,0 +1,123 @@
+use diesel::prelude::*;
+use juniper::{graphql_value, FieldError, FieldResult};
+use uuid::Uuid;
+
+use crate::{
+    db::schema::{
+        map_templates, process_execution_custom_values, process_executions, recipe_process_flow_data_fields,
+        recipe_templates, resource_specifications,
+    },
+    fda::kde::{missing_key_data_elements, CteRecord},
+    graphql::modules::process::correction,
+    recipe::process::{action::ResourceRequirement, execution::ProcessExecution, process::RecipeProcess},
+    templates::{map_template::TemplateType, recipe_flow_template::ActionType, recipe_flow_template_data_field::FieldClass},
+};
+
+/// True when the process was instantiated from a template of an FDA map
+pub fn is_fda_process(conn: &mut PgConnection, recipe_process: &RecipeProcess) -> FieldResult<bool> {
+    let recipe_template_id = match recipe_process.recipe_template_id {
+        Some(recipe_template_id) => recipe_template_id,
+        None => return Ok(false),
+    };
+
+    let template_type: TemplateType = recipe_templates::table
+        .inner_join(map_templates::table)
+        .filter(recipe_templates::id.eq(recipe_template_id))
+        .select(map_templates::type_)
+        .first::<TemplateType>(conn)?;
+
+    Ok(template_type == TemplateType::FDA)
+}
+
+/// Location where the lot was assigned, taken from the event that produced it.
+/// Lot codes are only unique among the specifications of one agent, so the
+/// event must have produced the same specification for the agent owning it
+pub fn lot_code_source(
+    conn: &mut PgConnection,
+    resource_specification_id: Uuid,
+    lot: &str,
+) -> FieldResult<Option<Uuid>> {
+    let agent_id: Uuid = resource_specifications::table
+        .filter(resource_specifications::id.eq(resource_specification_id))
+        .select(resource_specifications::agent_id)
+        .first::<Uuid>(conn)?;
+
+    let produced: Vec<ProcessExecution> = process_executions::table
+        .filter(process_executions::action.eq(ActionType::Produce))
+        .filter(process_executions::resource_specification.eq(resource_specification_id))
+        .filter(process_executions::provider_agent.eq(agent_id))
+        .filter(process_executions::resource_lot_number.eq(lot))
+        .filter(process_executions::at_location.is_not_null())
+        .order(process_executions::created_at.asc())
+        .load::<ProcessExecution>(conn)?;
+
+    Ok(correction::effective_executions(conn, produced)?
+        .into_iter()
+        .find_map(|e| e.at_location))
+}
+
+/// Key Data Elements an execution recorded, for runs checked again when one of
+/// their flows is replaced
+pub fn recorded_cte_record(conn: &mut PgConnection, execution: &ProcessExecution) -> FieldResult<CteRecord> {
+    let effect = execution.action.effect();
+
+    let traceability_lot_code_source = match (&effect.resource, execution.resource_specification, &execution.resource_lot_number) {
+        (ResourceRequirement::Create, _, _) => execution.at_location,
+        (_, Some(spec_id), Some(lot)) => lot_code_source(conn, spec_id, lot)?,
+        _ => None,
+    };
+
+    let custom_values: Vec<(FieldClass, String)> = process_execution_custom_values::table
+        .inner_join(recipe_process_flow_data_fields::table)
+        .filter(process_execution_custom_values::process_execution_id.eq(execution.id))
+        .select((
+            recipe_process_flow_data_fields::field_class,
+            process_execution_custom_values::field_value,
+        ))
+        .load::<(FieldClass, String)>(conn)?;
+    let custom_value = |field_class: FieldClass| {
+        custom_values
+            .iter()
+            .find(|(class, _)| *class == field_class)
+            .map(|(_, value)| value.clone())
+    };
+
+    let unit_of_measure = match (custom_value(FieldClass::UnitOfMeasure), execution.resource_specification) {
+        (Some(unit), _) => Some(unit),
+        (None, Some(spec_id)) => resource_specifications::table
+            .filter(resource_specifications::id.eq(spec_id))
+            .select(resource_specifications::unit_of_measure)
+            .first::<String>(conn)?
+            .into(),
+        (None, None) => None,
+    };
+
+    Ok(CteRecord {
+        moves_lot: effect.requires_quantity,
+        traceability_lot_code: execution.resource_lot_number.clone(),
+        traceability_lot_code_source,
+        location: execution.at_location.or(execution.to_location),
+        quantity: execution.resource_quantity.clone(),
+        unit_of_measure: unit_of_measure.filter(|unit| !unit.trim().is_empty()),
+        reference_document: custom_value(FieldClass::ReferenceDocumentType).is_some()
+            && custom_value(FieldClass::ReferenceDocumentNumber).is_some(),
+    })
+}
+
+pub fn check_key_data_elements(records: &[CteRecord]) -> FieldResult<()> {
+    let missing = missing_key_data_elements(records);
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let error_message = missing
+        .iter()
+        .map(|key_data_element| key_data_element.description())
+        .collect::<Vec<&str>>()
+        .join(", ");
+
+    Err(FieldError::new(
+        "Missing FDA key data elements",
+        graphql_value!({ "code": error_message }),
+    ))
+}
@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+use juniper::{graphql_value, FieldError, FieldResult};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    common::{location::Location, resource_specification::ResourceSpecification},
+    db::schema::{
+        agents, locations, map_templates, process_execution_custom_values, process_executions,
+        recipe_process_flow_data_fields, recipe_process_flows, recipe_processes, recipe_templates,
+        recipes, resource_specifications,
+    },
+    fda::spreadsheet::{to_csv, SpreadsheetRow},
+    graphql::modules::process::correction,
+    recipe::process::execution::ProcessExecution,
+    templates::{map_template::TemplateType, recipe_flow_template_data_field::FieldClass},
+};
+
+use super::kde;
+
+/// Query string of the spreadsheet route, dates as YYYY-MM-DD
+#[derive(Deserialize, Debug)]
+pub struct SpreadsheetParams {
+    pub map_template_id: String,
+    pub lot: Option<String>,
+    pub resource_specification_id: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+/// Builds the FDA sortable spreadsheet of an FDA map as CSV, of the executions of
+/// the given agents' recipes
+pub fn fda_spreadsheet(conn: &mut PgConnection, params: &SpreadsheetParams, agent_ids: &[Uuid]) -> FieldResult<String> {
+    let map_template_id = parse_param("map_template_id", &params.map_template_id, Uuid::parse_str)?;
+    let resource_specification_id = params
+        .resource_specification_id
+        .as_deref()
+        .map(|value| parse_param("resource_specification_id", value, Uuid::parse_str))
+        .transpose()?;
+    let from = params
+        .from
+        .as_deref()
+        .map(|value| parse_param("from", value, |v| v.parse::<NaiveDate>()))
+        .transpose()?;
+    let to = params
+        .to
+        .as_deref()
+        .map(|value| parse_param("to", value, |v| v.parse::<NaiveDate>()))
+        .transpose()?;
+
+    let template_type: TemplateType = map_templates::table
+        .filter(map_templates::id.eq(map_template_id))
+        .select(map_templates::type_)
+        .first::<TemplateType>(conn)?;
+
+    if template_type != TemplateType::FDA {
+        return Err(FieldError::new(
+            "The FDA spreadsheet is only available for FDA maps",
+            graphql_value!({ "code": "INVALID_MAP_TYPE" }),
+        ));
+    }
+
+    let mut query = process_executions::table
+        .inner_join(recipe_process_flows::table.inner_join(recipe_processes::table.inner_join(recipe_templates::table)))
+        .filter(recipe_templates::map_template_id.eq(map_template_id))
+        .filter(recipe_processes::recipe_id.eq_any(recipes::table.filter(recipes::agent_id.eq_any(agent_ids)).select(recipes::id)))
+        .select((process_executions::all_columns, recipe_templates::name))
+        .into_boxed();
+
+    if let Some(lot) = &params.lot {
+        query = query.filter(
+            process_executions::resource_lot_number
+                .eq(lot)
+                .or(process_executions::to_resource_lot_number.eq(lot)),
+        );
+    }
+    if let Some(resource_specification_id) = resource_specification_id {
+        query = query.filter(
+            process_executions::resource_specification
+                .eq(resource_specification_id)
+                .or(process_executions::to_resource_specification.eq(resource_specification_id)),
+        );
+    }
+
+    let mut executions: Vec<(ProcessExecution, String)> = query.load::<(ProcessExecution, String)>(conn)?;
+    // Corrected events are reported as their replacements
+    let ids: Vec<Uuid> = executions.iter().map(|(e, _)| e.id).collect();
+    let corrected = correction::corrected_ids(conn, &ids)?;
+    executions.retain(|(e, _)| !e.reversal && !corrected.contains(&e.id));
+
+    let mut specs: HashMap<Uuid, ResourceSpecification> = HashMap::new();
+    let mut agent_names: HashMap<Uuid, String> = HashMap::new();
+    let mut location_descriptions: HashMap<Uuid, String> = HashMap::new();
+    let mut rows = Vec::new();
+
+    for (execution, critical_tracking_event) in executions {
+        if !execution.action.effect().requires_quantity {
+            continue;
+        }
+
+        let event_date = execution.has_point_in_time.unwrap_or(execution.created_at);
+        if !in_range(event_date, from, to) {
+            continue;
+        }
+
+        let traceability_lot_code = execution.resource_lot_number.clone().unwrap_or_default();
+        let lot_code_source = match (execution.resource_specification, execution.resource_lot_number.as_deref()) {
+            (Some(spec_id), Some(lot)) => kde::lot_code_source(conn, spec_id, lot)?,
+            _ => None,
+        };
+
+        let spec = match execution.resource_specification {
+            Some(spec_id) => Some(resource_specification(conn, &mut specs, spec_id)?),
+            None => None,
+        };
+
+        let custom_values: Vec<(FieldClass, String)> = process_execution_custom_values::table
+            .inner_join(recipe_process_flow_data_fields::table)
+            .filter(process_execution_custom_values::process_execution_id.eq(execution.id))
+            .select((
+                recipe_process_flow_data_fields::field_class,
+                process_execution_custom_values::field_value,
+            ))
+            .load::<(FieldClass, String)>(conn)?;
+        let custom_value = |field_class: FieldClass| {
+            custom_values
+                .iter()
+                .find(|(class, _)| *class == field_class)
+                .map(|(_, value)| value.clone())
+        };
+
+        rows.push(SpreadsheetRow {
+            traceability_lot_code,
+            traceability_lot_code_source: location_description(conn, &mut location_descriptions, lot_code_source)?,
+            product_description: spec.as_ref().map(|s| s.name.clone()).unwrap_or_default(),
+            critical_tracking_event,
+            event_date: Some(event_date),
+            quantity: execution.resource_quantity,
+            unit_of_measure: custom_value(FieldClass::UnitOfMeasure)
+                .or_else(|| spec.as_ref().map(|s| s.unit_of_measure.clone()))
+                .unwrap_or_default(),
+            location_description: location_description(conn, &mut location_descriptions, execution.at_location)?,
+            destination_location_description: location_description(
+                conn,
+                &mut location_descriptions,
+                execution.to_location,
+            )?,
+            immediate_previous_source: agent_name(conn, &mut agent_names, execution.provider_agent)?,
+            immediate_subsequent_recipient: agent_name(conn, &mut agent_names, execution.receiver_agent)?,
+            reference_document_type: custom_value(FieldClass::ReferenceDocumentType).unwrap_or_default(),
+            reference_document_number: custom_value(FieldClass::ReferenceDocumentNumber).unwrap_or_default(),
+            event_id: execution.id,
+        });
+    }
+
+    rows.sort_by(|a, b| {
+        a.event_date
+            .cmp(&b.event_date)
+            .then_with(|| a.traceability_lot_code.cmp(&b.traceability_lot_code))
+    });
+
+    Ok(to_csv(&rows))
+}
+
+fn in_range(event_date: NaiveDateTime, from: Option<NaiveDate>, to: Option<NaiveDate>) -> bool {
+    let date = event_date.date();
+    from.is_none_or(|from| date >= from) && to.is_none_or(|to| date <= to)
+}
+
+fn parse_param<T, E>(name: &str, value: &str, parse: impl Fn(&str) -> Result<T, E>) -> FieldResult<T> {
+    parse(value).map_err(|_| {
+        let error_message = format!("Invalid {}: {}", name, value);
+        FieldError::new("Invalid spreadsheet filter", graphql_value!({ "code": error_message }))
+    })
+}
+
+fn resource_specification(
+    conn: &mut PgConnection,
+    specs: &mut HashMap<Uuid, ResourceSpecification>,
+    spec_id: Uuid,
+) -> FieldResult<ResourceSpecification> {
+    if let Some(spec) = specs.get(&spec_id) {
+        return Ok(spec.clone());
+    }
+    let spec: ResourceSpecification = resource_specifications::table
+        .filter(resource_specifications::id.eq(spec_id))
+        .first::<ResourceSpecification>(conn)?;
+    specs.insert(spec_id, spec.clone());
+    Ok(spec)
+}
+
+fn agent_name(conn: &mut PgConnection, agent_names: &mut HashMap<Uuid, String>, agent_id: Uuid) -> FieldResult<String> {
+    if let Some(name) = agent_names.get(&agent_id) {
+        return Ok(name.clone());
+    }
+    let name: String = agents::table
+        .filter(agents::id.eq(agent_id))
+        .select(agents::name)
+        .first::<String>(conn)?;
+    agent_names.insert(agent_id, name.clone());
+    Ok(name)
+}
+
+fn location_description(
+    conn: &mut PgConnection,
+    location_descriptions: &mut HashMap<Uuid, String>,
+    location_id: Option<Uuid>,
+) -> FieldResult<String> {
+    let location_id = match location_id {
+        Some(location_id) => location_id,
+        None => return Ok(String::new()),
+    };
+    if let Some(description) = location_descriptions.get(&location_id) {
+        return Ok(description.clone());
+    }
+    let location: Location = locations::table
+        .filter(locations::id.eq(location_id))
+        .first::<Location>(conn)?;
+    let description = location.description();
+    location_descriptions.insert(location_id, description.clone());
+    Ok(description)
+}
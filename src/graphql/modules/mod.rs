@@ -1,4 +1,7 @@
 pub mod recipe;
 pub mod common;
 pub mod templates;
-pub mod process;
\ No newline at end of file
+pub mod process;
+pub mod traceability;
+pub mod fda;
+pub mod epcis;
\ No newline at end of file
@@ -0,0 +1,342 @@
+use std::collections::HashSet;
+
+use bigdecimal::BigDecimal;
+use diesel::prelude::*;
+use juniper::{graphql_value, FieldError, FieldResult};
+use uuid::Uuid;
+
+use crate::{
+    common::{economic_resource::EconomicResource, unit::Unit},
+    db::schema::{economic_resources, process_executions, recipe_process_flows, recipe_processes, recipes},
+    graphql::{
+        context::Context,
+        modules::{
+            common::{containment, ledger, unit},
+            fda::kde,
+        },
+    },
+    recipe::{
+        process::{
+            action::LocationEffect,
+            execution::{effective, CorrectionResponse, NewProcessExecution, ProcessExecution, ProcessExecutionResponse},
+            flow::RecipeProcessFlow,
+            process::RecipeProcess,
+        },
+        recipe::Recipe,
+    },
+    templates::recipe_flow_template::ActionType,
+};
+
+use super::execution::{self, DataFieldValue, ProcessFlowExecution, ProcessRun};
+
+/** Queries */
+/// Every execution of the correction chain the execution belongs to, from the
+/// corrected original to its latest replacement, reversals included
+pub fn execution_corrections(context: &Context, process_execution_id: Uuid) -> FieldResult<Vec<ProcessExecutionResponse>> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let mut root = execution_by_id(conn, process_execution_id)?;
+    while let Some(corrects) = root.corrects {
+        root = execution_by_id(conn, corrects)?;
+    }
+
+    let mut chain = vec![root.clone()];
+    let mut frontier = vec![root.id];
+    while !frontier.is_empty() {
+        let corrections: Vec<ProcessExecution> = process_executions::table
+            .filter(process_executions::corrects.eq_any(&frontier))
+            .load::<ProcessExecution>(conn)?;
+        frontier = corrections.iter().filter(|c| !c.reversal).map(|c| c.id).collect();
+        chain.extend(corrections);
+    }
+    chain.sort_by_key(|e| e.created_at);
+
+    let mut res = Vec::new();
+    for execution in chain {
+        res.push(execution::build_process_execution_response(conn, execution)?);
+    }
+
+    Ok(res)
+}
+
+/// Ids among the given executions that a later correction reversed
+pub fn corrected_ids(conn: &mut PgConnection, execution_ids: &[Uuid]) -> FieldResult<HashSet<Uuid>> {
+    let corrected: Vec<Option<Uuid>> = process_executions::table
+        .filter(process_executions::corrects.eq_any(execution_ids))
+        .select(process_executions::corrects)
+        .load::<Option<Uuid>>(conn)?;
+
+    Ok(corrected.into_iter().flatten().collect())
+}
+
+/// What the records currently say: reversals and the executions they
+/// reversed are left out, replacements stand in for the originals
+pub fn effective_executions(conn: &mut PgConnection, executions: Vec<ProcessExecution>) -> FieldResult<Vec<ProcessExecution>> {
+    let ids: Vec<Uuid> = executions.iter().map(|e| e.id).collect();
+    let corrected = corrected_ids(conn, &ids)?;
+
+    Ok(effective(executions, &corrected))
+}
+
+/*** Mutations */
+/// Corrects an execution without touching it: a reversal compensates its effect
+/// on the resources and, unless the execution is voided, a replacement records
+/// it again with the corrected values. Values left out keep their original value
+pub fn correct_event(
+    context: &Context,
+    process_execution_id: Uuid,
+    data_field_values: Option<Vec<DataFieldValue>>,
+    lot: Option<String>,
+    reason: Option<String>,
+) -> FieldResult<CorrectionResponse> {
+    let performed_by = context.user()?.id;
+    let agent_ids = context.agent_ids();
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    conn.transaction::<_, FieldError, _>(|conn| {
+        let original: ProcessExecution = process_executions::table
+            .filter(process_executions::id.eq(process_execution_id))
+            .for_update()
+            .first::<ProcessExecution>(conn)
+            .optional()?
+            .ok_or_else(|| correction_error(format!("Process execution {} not found", process_execution_id)))?;
+
+        if original.reversal {
+            return Err(correction_error("A reversal can't be corrected".to_string()));
+        }
+        if let Some(operation) = original.operation {
+            return Err(correction_error(format!(
+                "Process execution {} is part of a {:?}, which can't be corrected",
+                original.id, operation
+            )));
+        }
+        if !corrected_ids(conn, &[original.id])?.is_empty() {
+            return Err(correction_error(format!(
+                "Process execution {} was already corrected, correct its replacement instead",
+                original.id
+            )));
+        }
+
+        let reason = reason.as_deref().map(str::trim).filter(|r| !r.is_empty());
+        let reversal = reverse_execution(conn, &original, reason, performed_by)?;
+
+        let replacement = match data_field_values {
+            Some(data_field_values) => {
+                let original = execution::build_process_execution_response(conn, original)?;
+                Some(replace_execution(conn, &original, data_field_values, lot, performed_by, &agent_ids)?)
+            }
+            None => None,
+        };
+
+        Ok(CorrectionResponse { reversal, replacement })
+    })
+}
+
+/// Records the reversal and takes back what the execution added to, or
+/// removed from, its resources
+fn reverse_execution(
+    conn: &mut PgConnection,
+    original: &ProcessExecution,
+    reason: Option<&str>,
+    performed_by: Uuid,
+) -> FieldResult<ProcessExecutionResponse> {
+    let effect = original.action.effect();
+    let unit = original.unit_id.map(|unit_id| unit::unit_by_id(conn, unit_id)).transpose()?;
+    let reversal_id = Uuid::new_v4();
+
+    if let Some(resource_id) = original.economic_resource_id {
+        let resource = resource_by_id(conn, resource_id)?;
+        let quantity = resource_quantity(conn, original, unit.as_ref(), &resource)?;
+        let (accounting_from, _) = effect.accounting_effect.deltas(&quantity);
+        let (onhand_from, _) = effect.onhand_effect.deltas(&quantity);
+        execution::adjust_quantities(conn, &resource, -accounting_from, -onhand_from, Some(reversal_id))?;
+
+        if effect.location_effect == LocationEffect::Update {
+            restore_location(conn, original, resource_id, reversal_id)?;
+        }
+
+        // A Load is undone by unpacking the resource, an Unload by packing it back
+        if let Some(container_id) = original.container_id {
+            let resource = resource_by_id(conn, resource_id)?;
+            match original.action {
+                ActionType::Load => containment::unpack(conn, &resource)?,
+                _ => containment::pack(conn, &resource, container_id, Some(reversal_id))?,
+            };
+        }
+    }
+    if let Some(resource_id) = original.to_economic_resource_id {
+        let resource = resource_by_id(conn, resource_id)?;
+        let quantity = resource_quantity(conn, original, unit.as_ref(), &resource)?;
+        let (_, accounting_to) = effect.accounting_effect.deltas(&quantity);
+        let (_, onhand_to) = effect.onhand_effect.deltas(&quantity);
+        execution::adjust_quantities(conn, &resource, -accounting_to, -onhand_to, Some(reversal_id))?;
+    }
+
+    let mut new_execution = NewProcessExecution::new(
+        original.process_flow_id.as_ref(),
+        &original.action,
+        &original.role_type,
+        &original.provider_agent,
+        &original.receiver_agent,
+        original.batch_id.as_ref(),
+    );
+    new_execution.resource_specification = original.resource_specification.as_ref();
+    new_execution.resource_reference_number = original.resource_reference_number.as_ref();
+    new_execution.resource_lot_number = original.resource_lot_number.as_deref();
+    new_execution.resource_quantity = original.resource_quantity.as_ref();
+    new_execution.to_resource_specification = original.to_resource_specification.as_ref();
+    new_execution.to_resource_reference_number = original.to_resource_reference_number.as_ref();
+    new_execution.to_resource_lot_number = original.to_resource_lot_number.as_deref();
+    new_execution.at_location = original.at_location.as_ref();
+    new_execution.to_location = original.to_location.as_ref();
+    new_execution.has_point_in_time = original.has_point_in_time.as_ref();
+    new_execution.corrects = Some(&original.id);
+    new_execution.note = reason;
+    new_execution.economic_resource_id = original.economic_resource_id.as_ref();
+    new_execution.to_economic_resource_id = original.to_economic_resource_id.as_ref();
+    new_execution.id = Some(&reversal_id);
+    new_execution.reversal = true;
+    new_execution.performed_by = Some(&performed_by);
+    new_execution.unit_id = original.unit_id.as_ref();
+    new_execution.container_id = original.container_id.as_ref();
+
+    let inserted_execution: ProcessExecution = diesel::insert_into(process_executions::table)
+        .values(new_execution)
+        .get_result(conn)?;
+
+    Ok(ProcessExecutionResponse::new(inserted_execution))
+}
+
+/// Quantity of the execution in the unit the resource is kept in
+fn resource_quantity(
+    conn: &mut PgConnection,
+    original: &ProcessExecution,
+    unit: Option<&Unit>,
+    resource: &EconomicResource,
+) -> FieldResult<BigDecimal> {
+    let quantity = original.resource_quantity.clone().unwrap_or_default().0;
+
+    match (unit, unit::specification_unit(conn, resource.resource_specification_id)?) {
+        (Some(recorded), Some(specification_unit)) => unit::convert(&quantity, recorded, &specification_unit),
+        _ => Ok(quantity),
+    }
+}
+
+/// Moves the resource back where the last effective execution before the
+/// corrected one left it
+fn restore_location(
+    conn: &mut PgConnection,
+    original: &ProcessExecution,
+    resource_id: Uuid,
+    reversal_id: Uuid,
+) -> FieldResult<()> {
+    let earlier: Vec<ProcessExecution> = process_executions::table
+        .filter(process_executions::economic_resource_id.eq(resource_id))
+        .filter(process_executions::at_location.is_not_null())
+        .filter(process_executions::created_at.lt(original.created_at))
+        .filter(process_executions::id.ne(original.id))
+        .order(process_executions::created_at.desc())
+        .load::<ProcessExecution>(conn)?;
+
+    let previous = effective_executions(conn, earlier)?.into_iter().find_map(|e| e.at_location);
+    if let Some(location) = previous {
+        let resource = resource_by_id(conn, resource_id)?;
+        let moved: EconomicResource = diesel::update(economic_resources::table)
+            .filter(economic_resources::id.eq(resource_id))
+            .set(economic_resources::current_location.eq(location))
+            .get_result(conn)?;
+        ledger::record_move(conn, &resource, &moved, Some(reversal_id))?;
+        containment::move_contents(conn, resource_id, Some(location), Some(reversal_id))?;
+    }
+
+    Ok(())
+}
+
+/// Executes the flow again in the run of the original, its custom values
+/// pointing to the values they correct
+fn replace_execution(
+    conn: &mut PgConnection,
+    original: &ProcessExecutionResponse,
+    data_field_values: Vec<DataFieldValue>,
+    lot: Option<String>,
+    performed_by: Uuid,
+    agent_ids: &[Uuid],
+) -> FieldResult<ProcessExecutionResponse> {
+    let process_flow_id = original
+        .execution
+        .process_flow_id
+        .ok_or_else(|| correction_error(format!("Process execution {} has no flow to execute again", original.execution.id)))?;
+    let flow: RecipeProcessFlow = recipe_process_flows::table
+        .filter(recipe_process_flows::id.eq(process_flow_id))
+        .first::<RecipeProcessFlow>(conn)?;
+    let recipe_process: RecipeProcess = recipe_processes::table
+        .filter(recipe_processes::id.eq(flow.recipe_process_id))
+        .first::<RecipeProcess>(conn)?;
+    let recipe: Recipe = recipes::table
+        .filter(recipes::id.eq(recipe_process.recipe_id))
+        .first::<Recipe>(conn)?;
+
+    let mut values: Vec<DataFieldValue> = original
+        .custom_values
+        .iter()
+        .filter(|v| !data_field_values.iter().any(|d| d.id == v.field_id))
+        .map(|v| DataFieldValue { id: v.field_id, value: v.field_value.clone() })
+        .collect();
+    values.extend(data_field_values);
+
+    let process_flow = ProcessFlowExecution {
+        process_flow_id: flow.id,
+        data_field_values: values,
+        // A corrected creation keeps the lot it already assigned
+        lot: lot.or_else(|| original.execution.resource_lot_number.clone()),
+    };
+    let run = ProcessRun {
+        batch_id: original.execution.batch_id.unwrap_or_else(Uuid::new_v4),
+        fda: kde::is_fda_process(conn, &recipe_process)?,
+        performed_by,
+        agent_ids: agent_ids.to_vec(),
+    };
+    let (replacement, cte_record) = execution::execute_flow(conn, &recipe, &recipe_process, &run, &process_flow, Some(original))?;
+
+    // Key Data Elements are checked against the whole run, the flows not
+    // replaced with what they recorded
+    if let Some(cte_record) = cte_record {
+        let executions: Vec<ProcessExecution> = process_executions::table
+            .filter(process_executions::batch_id.eq(run.batch_id))
+            .filter(process_executions::id.ne(replacement.execution.id))
+            .load::<ProcessExecution>(conn)?;
+        let mut cte_records = vec![cte_record];
+        for execution in effective_executions(conn, executions)? {
+            cte_records.push(kde::recorded_cte_record(conn, &execution)?);
+        }
+        kde::check_key_data_elements(&cte_records)?;
+    }
+
+    Ok(replacement)
+}
+
+fn execution_by_id(conn: &mut PgConnection, id: Uuid) -> FieldResult<ProcessExecution> {
+    process_executions::table
+        .filter(process_executions::id.eq(id))
+        .first::<ProcessExecution>(conn)
+        .optional()?
+        .ok_or_else(|| correction_error(format!("Process execution {} not found", id)))
+}
+
+fn resource_by_id(conn: &mut PgConnection, id: Uuid) -> FieldResult<EconomicResource> {
+    economic_resources::table
+        .filter(economic_resources::id.eq(id))
+        .first::<EconomicResource>(conn)
+        .optional()?
+        .ok_or_else(|| correction_error(format!("Economic resource {} not found", id)))
+}
+
+fn correction_error(error_message: String) -> FieldError {
+    FieldError::new("Unable to correct event", graphql_value!({ "code": error_message }))
+}
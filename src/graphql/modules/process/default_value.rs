@@ -0,0 +1,160 @@
+use std::collections::HashSet;
+
+use chrono::Utc;
+use diesel::prelude::*;
+use juniper::{graphql_value, FieldError, FieldResult};
+use uuid::Uuid;
+
+use crate::{
+    db::schema::{agents, recipe_flow_template_data_fields, recipe_process_flow_data_fields},
+    graphql::{context::Context, modules::common::counter},
+    recipe::{
+        process::{
+            action::ResourceRequirement, data_field::RecipeFlowDataField, default_value::DefaultValue,
+            flow::RecipeProcessFlow,
+        },
+        recipe::Recipe,
+    },
+};
+
+use super::execution::DataFieldValue;
+
+/*** Mutations */
+/// Sets, or clears, the default of a process data field
+pub fn set_data_field_default(
+    context: &Context,
+    data_field_id: Uuid,
+    default_value: Option<String>,
+) -> FieldResult<RecipeFlowDataField> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let data_field: RecipeFlowDataField = recipe_process_flow_data_fields::table
+        .filter(recipe_process_flow_data_fields::id.eq(data_field_id))
+        .first::<RecipeFlowDataField>(conn)
+        .optional()?
+        .ok_or_else(|| default_error(format!("Data field {} not found", data_field_id)))?;
+
+    let default_value = default_value.map(|d| d.trim().to_string()).filter(|d| !d.is_empty());
+    if let Some(default_value) = &default_value {
+        check_default(conn, data_field.recipe_flow_template_data_field_id, &data_field.field_identifier, default_value)?;
+    }
+
+    let updated: RecipeFlowDataField = diesel::update(recipe_process_flow_data_fields::table)
+        .filter(recipe_process_flow_data_fields::id.eq(data_field_id))
+        .set(recipe_process_flow_data_fields::default_value.eq(default_value))
+        .get_result(conn)?;
+
+    Ok(updated)
+}
+
+/// Defaults are only allowed on fields whose template accepts them, fields
+/// added to the recipe only are the recipe's own and always do
+pub fn check_default(
+    conn: &mut PgConnection,
+    template_field_id: Option<Uuid>,
+    field_identifier: &str,
+    default_value: &str,
+) -> FieldResult<()> {
+    if !accepts_default(conn, template_field_id)? {
+        return Err(default_error(format!("The template of {} doesn't accept defaults", field_identifier)));
+    }
+    DefaultValue::parse(default_value).map_err(|e| default_error(format!("{}: {}", field_identifier, e)))?;
+
+    Ok(())
+}
+
+/// Fills the fields left empty with their defaults and returns the ids of the
+/// fields that took one, with the lot of the flow. Templates that stopped
+/// accepting defaults since the default was set win, their fields are left empty.
+/// nextLot() takes the lot submitted with the flow, or draws a single code from
+/// the counter that becomes the lot of the resource the flow creates
+pub fn apply_default_values(
+    conn: &mut PgConnection,
+    recipe: &Recipe,
+    flow: &RecipeProcessFlow,
+    data_fields: &[RecipeFlowDataField],
+    submitted: Vec<DataFieldValue>,
+    lot: Option<String>,
+) -> FieldResult<(Vec<DataFieldValue>, HashSet<Uuid>, Option<String>)> {
+    let mut values = submitted;
+    let mut defaulted = HashSet::new();
+    let mut lot = lot;
+
+    for data_field in data_fields {
+        let Some(default_value) = data_field.default_value.as_deref().filter(|d| !d.trim().is_empty()) else {
+            continue;
+        };
+        if values.iter().any(|v| v.id == data_field.id && !v.value.trim().is_empty()) {
+            continue;
+        }
+        if !accepts_default(conn, data_field.recipe_flow_template_data_field_id)? {
+            continue;
+        }
+
+        let default_value = DefaultValue::parse(default_value)
+            .map_err(|e| execution_error(format!("{} of flow {}: {}", data_field.field_identifier, flow.identifier, e)))?;
+        if default_value == DefaultValue::NextLot && flow.action.effect().resource != ResourceRequirement::Create {
+            return Err(execution_error(format!(
+                "{} of flow {}: nextLot() only applies to flows creating a resource",
+                data_field.field_identifier, flow.identifier
+            )));
+        }
+        let value = resolve_default(conn, recipe, &default_value, &mut lot)
+            .map_err(|e| execution_error(format!("{} of flow {}: {}", data_field.field_identifier, flow.identifier, e)))?;
+
+        values.retain(|v| v.id != data_field.id);
+        values.push(DataFieldValue { id: data_field.id, value });
+        defaulted.insert(data_field.id);
+    }
+
+    Ok((values, defaulted, lot))
+}
+
+fn resolve_default(
+    conn: &mut PgConnection,
+    recipe: &Recipe,
+    default_value: &DefaultValue,
+    lot: &mut Option<String>,
+) -> Result<String, String> {
+    match default_value {
+        DefaultValue::Literal(value) => Ok(value.clone()),
+        DefaultValue::Today => Ok(Utc::now().date_naive().to_string()),
+        DefaultValue::AgentPrimaryLocation => agents::table
+            .filter(agents::id.eq(recipe.agent_id))
+            .select(agents::primary_location_id)
+            .first::<Option<Uuid>>(conn)
+            .map_err(|e| e.to_string())?
+            .map(|id| id.to_string())
+            .ok_or_else(|| "The agent has no primary location".to_string()),
+        DefaultValue::NextLot => match lot {
+            Some(lot) => Ok(lot.clone()),
+            None => counter::next_lot_code(conn, &recipe.agent_id)
+                .map(|code| lot.insert(code).clone())
+                .map_err(|e| e.message().to_string()),
+        },
+    }
+}
+
+fn accepts_default(conn: &mut PgConnection, template_field_id: Option<Uuid>) -> FieldResult<bool> {
+    let Some(template_field_id) = template_field_id else {
+        return Ok(true);
+    };
+
+    let accept_default: bool = recipe_flow_template_data_fields::table
+        .filter(recipe_flow_template_data_fields::id.eq(template_field_id))
+        .select(recipe_flow_template_data_fields::accept_default)
+        .first::<bool>(conn)?;
+
+    Ok(accept_default)
+}
+
+fn default_error(error_message: String) -> FieldError {
+    FieldError::new("Invalid default value", graphql_value!({ "code": error_message }))
+}
+
+fn execution_error(error_message: String) -> FieldError {
+    FieldError::new("Unable to execute event", graphql_value!({ "code": error_message }))
+}
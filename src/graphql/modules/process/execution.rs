@@ -0,0 +1,846 @@
+use bigdecimal::{BigDecimal, Signed};
+use chrono::NaiveDateTime;
+use diesel::{prelude::*, PgExpressionMethods};
+use juniper::{graphql_value, FieldError, FieldResult, GraphQLInputObject};
+use uuid::Uuid;
+
+use crate::{
+    common::{
+        economic_resource::{Decimal, EconomicResource, NewEconomicResource},
+        location::Location,
+        resource_specification::ResourceSpecification,
+        unit::Unit,
+    },
+    db::schema::{
+        agents, economic_resources, locations, process_execution_custom_values, process_executions,
+        recipe_process_flow_data_fields, recipe_process_flows, recipe_processes, recipes,
+        resource_specifications,
+    },
+    fda::kde::CteRecord,
+    graphql::{
+        context::Context,
+        modules::{common::{containment, counter, ledger, unit}, fda::kde, process::{correction, default_value, inheritance, validation}},
+    },
+    recipe::{
+        process::{
+            action::{ActionEffect, CustodyEffect, LocationEffect, ResourceRequirement},
+            data_field::RecipeFlowDataField,
+            execution::{
+                NewProcessExecution, NewProcessExecutionCustomValue, ProcessExecution,
+                ProcessExecutionCustomValue, ProcessExecutionResponse,
+            },
+            flow::RecipeProcessFlow,
+            process::RecipeProcess,
+            validation::parse_date,
+        },
+        recipe::Recipe,
+    },
+    templates::{
+        recipe_flow_template::{ActionType, RoleType},
+        recipe_flow_template_data_field::{FieldClass, FlowThrough},
+    },
+};
+
+#[derive(GraphQLInputObject, Debug)]
+pub struct DataFieldValue {
+    pub id: Uuid,
+    pub value: String,
+}
+
+#[derive(GraphQLInputObject, Debug)]
+pub struct ProcessFlowExecution {
+    pub process_flow_id: Uuid,
+    pub data_field_values: Vec<DataFieldValue>,
+    /// Lot code of the created resource, allocated from the agent counter when omitted
+    pub lot: Option<String>,
+}
+
+/// Submitted values of a flow, matched against the flow data fields
+struct FlowValues<'a> {
+    values: Vec<(&'a RecipeFlowDataField, &'a str)>,
+}
+
+impl<'a> FlowValues<'a> {
+    fn new(
+        flow: &RecipeProcessFlow,
+        data_fields: &'a [RecipeFlowDataField],
+        submitted: &'a [DataFieldValue],
+    ) -> FieldResult<Self> {
+        let mut values = Vec::new();
+
+        for data_field_value in submitted {
+            let data_field = data_fields
+                .iter()
+                .find(|f| f.id == data_field_value.id)
+                .ok_or_else(|| {
+                    execution_error(format!("Field {} is not part of flow {}", data_field_value.id, flow.identifier))
+                })?;
+
+            let value = data_field_value.value.trim();
+            if !value.is_empty() {
+                values.push((data_field, value));
+            }
+        }
+
+        let missing: Vec<&str> = data_fields
+            .iter()
+            .filter(|f| f.required && !values.iter().any(|(v, _)| v.id == f.id))
+            .map(|f| f.field_identifier.as_str())
+            .collect();
+
+        if !missing.is_empty() {
+            return Err(execution_error(format!(
+                "Missing required fields in flow {}: {}",
+                flow.identifier,
+                missing.join(", ")
+            )));
+        }
+
+        Ok(FlowValues { values })
+    }
+
+    fn get(&self, field_class: &FieldClass) -> Option<(&'a RecipeFlowDataField, &'a str)> {
+        self.values
+            .iter()
+            .find(|(field, _)| field.field_class == *field_class)
+            .copied()
+    }
+
+    /// Value of the field of the given class that flows through the given side
+    fn get_through(&self, field_class: &FieldClass, external: bool) -> Option<(&'a RecipeFlowDataField, &'a str)> {
+        self.values
+            .iter()
+            .find(|(field, _)| {
+                field.field_class == *field_class
+                    && (field.flow_through == Some(FlowThrough::External)) == external
+            })
+            .copied()
+    }
+}
+
+/// The run of the process the flows are executed in
+pub struct ProcessRun {
+    /// Executions submitted together belong to the same run of the process
+    pub batch_id: Uuid,
+    /// Key Data Elements are recorded for runs of processes of FDA maps
+    pub fda: bool,
+    pub performed_by: Uuid,
+    /// Agents the user is a member of, the only ones submitted values can refer to
+    pub agent_ids: Vec<Uuid>,
+}
+
+/// Everything an execution needs, resolved from the submitted values
+struct EventData {
+    /// Id the execution is inserted with, so ledger entries can refer to it
+    execution_id: Uuid,
+    provider_agent: Uuid,
+    receiver_agent: Uuid,
+    at_location: Option<Location>,
+    to_location: Option<Location>,
+    quantity: Option<Decimal>,
+    /// Unit the quantity was recorded in as submitted, and the registered unit it names
+    unit_of_measure: Option<String>,
+    unit: Option<Unit>,
+    has_point_in_time: Option<NaiveDateTime>,
+    note: Option<String>,
+    tracking_identifier: Option<String>,
+    resource: Option<EconomicResource>,
+    /// Container a Load packs the resource into
+    container: Option<EconomicResource>,
+    resource_specification: Option<ResourceSpecification>,
+    to_resource_specification: Option<Uuid>,
+    lot: Option<String>,
+}
+
+/** Queries */
+/// Effective executions of the process, or every execution with its
+/// reversals and replacements when corrections are included
+pub fn process_executions_by_recipe_process(
+    context: &Context,
+    recipe_process_id: Uuid,
+    include_corrections: Option<bool>,
+) -> FieldResult<Vec<ProcessExecutionResponse>> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let executions: Vec<ProcessExecution> = process_executions::table
+        .inner_join(recipe_process_flows::table)
+        .filter(recipe_process_flows::recipe_process_id.eq(recipe_process_id))
+        .select(process_executions::all_columns)
+        .order(process_executions::created_at.asc())
+        .load::<ProcessExecution>(conn)?;
+    let executions = match include_corrections {
+        Some(true) => executions,
+        _ => correction::effective_executions(conn, executions)?,
+    };
+
+    let mut res = Vec::new();
+    for execution in executions {
+        res.push(build_process_execution_response(conn, execution)?);
+    }
+
+    Ok(res)
+}
+
+pub fn build_process_execution_response(
+    conn: &mut PgConnection,
+    execution: ProcessExecution,
+) -> FieldResult<ProcessExecutionResponse> {
+    let custom_values: Vec<ProcessExecutionCustomValue> = process_execution_custom_values::table
+        .filter(process_execution_custom_values::process_execution_id.eq(execution.id))
+        .load::<ProcessExecutionCustomValue>(conn)?;
+
+    let mut response = ProcessExecutionResponse::new(execution);
+    for custom_value in custom_values {
+        response.add_custom_value(custom_value);
+    }
+
+    Ok(response)
+}
+
+/** Mutations */
+pub fn execute_events(
+    context: &Context,
+    recipe_process_id: Uuid,
+    process_flows: Vec<ProcessFlowExecution>,
+) -> FieldResult<Vec<ProcessExecutionResponse>> {
+    let performed_by = context.user()?.id;
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let agent_ids = context.agent_ids();
+    conn.transaction::<_, FieldError, _>(|conn| execute_process_flows(conn, recipe_process_id, &process_flows, performed_by, &agent_ids))
+}
+
+/// Executes the flows as one run of the process by the user, callers own the transaction
+pub fn execute_process_flows(
+    conn: &mut PgConnection,
+    recipe_process_id: Uuid,
+    process_flows: &[ProcessFlowExecution],
+    performed_by: Uuid,
+    agent_ids: &[Uuid],
+) -> FieldResult<Vec<ProcessExecutionResponse>> {
+    let recipe_process: RecipeProcess = recipe_processes::table
+        .filter(recipe_processes::id.eq(recipe_process_id))
+        .first::<RecipeProcess>(conn)?;
+
+    let recipe: Recipe = recipes::table
+        .filter(recipes::id.eq(recipe_process.recipe_id))
+        .first::<Recipe>(conn)?;
+
+    let run = ProcessRun {
+        batch_id: Uuid::new_v4(),
+        fda: kde::is_fda_process(conn, &recipe_process)?,
+        performed_by,
+        agent_ids: agent_ids.to_vec(),
+    };
+
+    let mut res = Vec::new();
+    let mut cte_records = Vec::new();
+    for process_flow in process_flows {
+        let (execution, cte_record) = execute_flow(conn, &recipe, &recipe_process, &run, process_flow, None)?;
+        res.push(execution);
+        cte_records.extend(cte_record);
+    }
+
+    if run.fda {
+        kde::check_key_data_elements(&cte_records)?;
+    }
+
+    Ok(res)
+}
+
+/// Executes one flow, as a replacement of the execution it corrects if any
+pub fn execute_flow(
+    conn: &mut PgConnection,
+    recipe: &Recipe,
+    recipe_process: &RecipeProcess,
+    run: &ProcessRun,
+    process_flow: &ProcessFlowExecution,
+    corrects: Option<&ProcessExecutionResponse>,
+) -> FieldResult<(ProcessExecutionResponse, Option<CteRecord>)> {
+    let flow: RecipeProcessFlow = recipe_process_flows::table
+        .filter(recipe_process_flows::recipe_process_id.eq(recipe_process.id))
+        .filter(recipe_process_flows::id.eq(process_flow.process_flow_id))
+        .first::<RecipeProcessFlow>(conn)?;
+
+    let effect = flow.action.effect();
+    if !flow.action.allows_role(flow.role_type) {
+        return Err(execution_error(format!("Action {:?} is not valid as {:?}", flow.action, flow.role_type)));
+    }
+
+    let data_fields: Vec<RecipeFlowDataField> = recipe_process_flow_data_fields::table
+        .filter(recipe_process_flow_data_fields::recipe_process_flow_id.eq(flow.id))
+        .load::<RecipeFlowDataField>(conn)?;
+
+    let submitted = inheritance::apply_inherited_values(conn, &flow, &data_fields, &process_flow.data_field_values, &run.batch_id, process_flow.lot.as_deref())?;
+    let lot = process_flow.lot.as_deref().map(str::trim).filter(|lot| !lot.is_empty()).map(String::from);
+    let (submitted, defaulted, lot) = default_value::apply_default_values(conn, recipe, &flow, &data_fields, submitted, lot)?;
+    validation::check_flow_values(conn, &flow, &data_fields, &submitted, &run.agent_ids)?;
+    let values = FlowValues::new(&flow, &data_fields, &submitted)?;
+    let mut event = resolve_event_data(conn, recipe, &flow, effect, &values)?;
+    event.lot = lot;
+    // Whatever the action, the resources it touches are held by the recipe
+    // agent, or by the provider of a Transfer the recipe agent receives
+    for resource in event.resource.iter().chain(event.container.iter()) {
+        check_custody(conn, resource, event.provider_agent)?;
+    }
+    if let (ResourceRequirement::Create, Some(lot), Some(spec)) = (effect.resource, &event.lot, &event.resource_specification) {
+        // A replacement recreates the lot of the execution it corrects
+        let corrected_lot = corrects.and_then(|c| c.execution.resource_lot_number.as_ref());
+        if corrected_lot != Some(lot) && lot_exists(conn, spec.id, lot)? {
+            return Err(execution_error(format!("Lot {} of {} already exists", lot, spec.name)));
+        }
+    }
+
+    let (mut resource, to_resource) = apply_action_effect(conn, effect, &event)?;
+    let container_id = match resource.as_mut() {
+        Some(resource) => apply_containment(conn, flow.action, &event, resource)?,
+        None => None,
+    };
+
+    let cte_record = if run.fda {
+        Some(cte_record(conn, effect, &event, &values, resource.as_ref())?)
+    } else {
+        None
+    };
+
+    let resource_specification = resource
+        .as_ref()
+        .map(|r| r.resource_specification_id)
+        .or(event.resource_specification.as_ref().map(|s| s.id));
+
+    let mut new_execution = NewProcessExecution::new(
+        Some(&flow.id),
+        &flow.action,
+        &flow.role_type,
+        &event.provider_agent,
+        &event.receiver_agent,
+        Some(&run.batch_id),
+    );
+    new_execution.resource_specification = resource_specification.as_ref();
+    new_execution.resource_reference_number = resource.as_ref().map(|r| &r.reference_number);
+    new_execution.resource_lot_number = resource.as_ref().and_then(|r| r.lot.as_deref());
+    new_execution.resource_quantity = event.quantity.as_ref();
+    new_execution.to_resource_specification = to_resource.as_ref().map(|r| &r.resource_specification_id);
+    new_execution.to_resource_reference_number = to_resource.as_ref().map(|r| &r.reference_number);
+    new_execution.to_resource_lot_number = to_resource.as_ref().and_then(|r| r.lot.as_deref());
+    new_execution.at_location = event.at_location.as_ref().map(|l| &l.id);
+    new_execution.to_location = event.to_location.as_ref().map(|l| &l.id);
+    new_execution.has_point_in_time = event.has_point_in_time.as_ref();
+    new_execution.corrects = corrects.map(|c| &c.execution.id);
+    new_execution.note = event.note.as_deref();
+    new_execution.economic_resource_id = resource.as_ref().map(|r| &r.id);
+    new_execution.to_economic_resource_id = to_resource.as_ref().map(|r| &r.id);
+    new_execution.id = Some(&event.execution_id);
+    new_execution.performed_by = Some(&run.performed_by);
+    new_execution.unit_id = event.unit.as_ref().map(|u| &u.id);
+    new_execution.container_id = container_id.as_ref();
+
+    let inserted_execution: ProcessExecution = diesel::insert_into(process_executions::table)
+        .values(new_execution)
+        .get_result(conn)?;
+
+    let mut res = ProcessExecutionResponse::new(inserted_execution);
+
+    for (data_field, value) in &values.values {
+        let corrected_value = corrects.and_then(|c| c.custom_values.iter().find(|v| v.field_id == data_field.id));
+        let mut new_custom_value = NewProcessExecutionCustomValue::new(
+            &res.execution.id,
+            &data_field.id,
+            value,
+            corrected_value.map(|v| &v.id),
+        );
+        new_custom_value.from_default = defaulted.contains(&data_field.id);
+
+        let inserted_custom_value: ProcessExecutionCustomValue =
+            diesel::insert_into(process_execution_custom_values::table)
+                .values(new_custom_value)
+                .get_result(conn)?;
+
+        res.add_custom_value(inserted_custom_value);
+    }
+
+    Ok((res, cte_record))
+}
+
+/// Key Data Elements recorded by the flow, for events against FDA maps
+fn cte_record(
+    conn: &mut PgConnection,
+    effect: &ActionEffect,
+    event: &EventData,
+    values: &FlowValues,
+    resource: Option<&EconomicResource>,
+) -> FieldResult<CteRecord> {
+    let traceability_lot_code = resource.and_then(|r| r.lot.clone());
+
+    let traceability_lot_code_source = match (&effect.resource, resource, &traceability_lot_code) {
+        (ResourceRequirement::Create, _, _) => event.at_location.as_ref().map(|l| l.id),
+        (_, Some(resource), Some(lot)) => kde::lot_code_source(conn, resource.resource_specification_id, lot)?,
+        _ => None,
+    };
+
+    let unit_of_measure = match values.get(&FieldClass::UnitOfMeasure) {
+        Some((_, value)) => Some(value.to_string()),
+        None => {
+            let spec = match (resource, &event.resource_specification) {
+                (_, Some(spec)) => Some(spec.unit_of_measure.clone()),
+                (Some(resource), None) => {
+                    Some(resource_specification_by_id(conn, resource.resource_specification_id)?.unit_of_measure)
+                }
+                (None, None) => None,
+            };
+            spec.filter(|unit| !unit.trim().is_empty())
+        }
+    };
+
+    Ok(CteRecord {
+        moves_lot: effect.requires_quantity,
+        traceability_lot_code,
+        traceability_lot_code_source,
+        location: event.at_location.as_ref().or(event.to_location.as_ref()).map(|l| l.id),
+        quantity: event.quantity.clone(),
+        unit_of_measure,
+        reference_document: values.get(&FieldClass::ReferenceDocumentType).is_some()
+            && values.get(&FieldClass::ReferenceDocumentNumber).is_some(),
+    })
+}
+
+fn resolve_event_data(
+    conn: &mut PgConnection,
+    recipe: &Recipe,
+    flow: &RecipeProcessFlow,
+    effect: &ActionEffect,
+    values: &FlowValues,
+) -> FieldResult<EventData> {
+    // The counterparty only matters when custody changes hands, every other
+    // action happens within the recipe agent
+    let counterparty = match values.get_through(&FieldClass::Agent, true).or(values.get(&FieldClass::Agent)) {
+        Some((field, value)) if effect.custody_effect == CustodyEffect::Transfer => {
+            let agent_id = parse_uuid(field, value)?;
+            agents::table
+                .filter(agents::id.eq(agent_id))
+                .select(agents::id)
+                .first::<Uuid>(conn)
+                .optional()?
+                .ok_or_else(|| execution_error(format!("Agent {} not found", value)))?
+        }
+        _ => recipe.agent_id,
+    };
+
+    let (provider_agent, receiver_agent) = match flow.role_type {
+        RoleType::Output => (recipe.agent_id, counterparty),
+        RoleType::Input => (counterparty, recipe.agent_id),
+    };
+
+    let own_location = match values.get_through(&FieldClass::Location, false) {
+        Some((field, value)) => Some(location_by_id(conn, parse_uuid(field, value)?)?),
+        None => None,
+    };
+    let other_location = match values.get_through(&FieldClass::Location, true) {
+        Some((field, value)) => Some(location_by_id(conn, parse_uuid(field, value)?)?),
+        None => None,
+    };
+
+    let (at_location, to_location) = match (effect.custody_effect, flow.role_type) {
+        (CustodyEffect::Transfer, RoleType::Output) => (own_location, other_location),
+        (CustodyEffect::Transfer, RoleType::Input) => (other_location, own_location),
+        (CustodyEffect::NoEffect, _) => (own_location.or(other_location), None),
+    };
+
+    let quantity = match values.get(&FieldClass::Quantity) {
+        Some((field, value)) => Some(parse_quantity(field, value)?),
+        None if effect.requires_quantity => {
+            return Err(execution_error(format!("Flow {} requires a quantity", flow.identifier)));
+        }
+        None => None,
+    };
+
+    let unit_of_measure = values.get(&FieldClass::UnitOfMeasure).map(|(_, v)| v.to_string());
+    let unit = match &unit_of_measure {
+        Some(unit_of_measure) => unit::find_unit(conn, unit_of_measure)?,
+        None => None,
+    };
+
+    let has_point_in_time = match values.get(&FieldClass::HasPointInTime) {
+        Some((field, value)) => Some(parse_point_in_time(field, value)?),
+        None => None,
+    };
+
+    let own_resource = values.get_through(&FieldClass::EconomicResource, false);
+    let resource = match own_resource.or(values.get(&FieldClass::EconomicResource)) {
+        Some((field, value)) => Some(economic_resource_by_id(conn, field, value)?),
+        None => None,
+    };
+
+    // Next to the resource, an external resource is the container it goes in
+    let container = match values.get_through(&FieldClass::EconomicResource, true) {
+        Some((field, value)) if own_resource.is_some() => Some(economic_resource_by_id(conn, field, value)?),
+        _ => None,
+    };
+
+    let resource_specification = match values.get_through(&FieldClass::ResourceSpecification, false) {
+        Some((field, value)) => Some(resource_specification_by_id(conn, parse_uuid(field, value)?)?),
+        None => None,
+    };
+
+    let to_resource_specification = match values.get_through(&FieldClass::ResourceSpecification, true) {
+        Some((field, value)) => Some(resource_specification_by_id(conn, parse_uuid(field, value)?)?.id),
+        None => None,
+    };
+
+    match effect.resource {
+        ResourceRequirement::Create if resource_specification.is_none() => {
+            return Err(execution_error(format!("Flow {} requires a resource specification", flow.identifier)));
+        }
+        ResourceRequirement::Existing if resource.is_none() => {
+            return Err(execution_error(format!("Flow {} requires an economic resource", flow.identifier)));
+        }
+        ResourceRequirement::Specification if resource.is_none() && resource_specification.is_none() => {
+            return Err(execution_error(format!(
+                "Flow {} requires an economic resource or a resource specification",
+                flow.identifier
+            )));
+        }
+        _ => {}
+    }
+
+    Ok(EventData {
+        execution_id: Uuid::new_v4(),
+        provider_agent,
+        receiver_agent,
+        at_location,
+        to_location,
+        quantity,
+        unit_of_measure,
+        unit,
+        has_point_in_time,
+        note: values.get(&FieldClass::Note).map(|(_, v)| v.to_string()),
+        tracking_identifier: values.get(&FieldClass::TrackingIdentifier).map(|(_, v)| v.to_string()),
+        resource,
+        container,
+        resource_specification,
+        to_resource_specification,
+        lot: None,
+    })
+}
+
+/// Applies the action effects to the economic resources, returning the
+/// provider resource and, for custody changes, the receiver resource
+fn apply_action_effect(
+    conn: &mut PgConnection,
+    effect: &ActionEffect,
+    event: &EventData,
+) -> FieldResult<(Option<EconomicResource>, Option<EconomicResource>)> {
+    let resource = match effect.resource {
+        ResourceRequirement::Create => {
+            let spec = event
+                .resource_specification
+                .as_ref()
+                .expect("Resource specification is checked when resolving the event");
+            let quantity = specification_quantity(conn, event, spec.id)?;
+            let (accounting_from, _) = effect.accounting_effect.deltas(&quantity);
+            let (onhand_from, _) = effect.onhand_effect.deltas(&quantity);
+            let location = event
+                .at_location
+                .as_ref()
+                .ok_or_else(|| execution_error(format!("A location is required to create {}", spec.name)))?;
+            let lot = match &event.lot {
+                Some(lot) => lot.clone(),
+                // Lots entered by hand may match a code the counter comes to later
+                None => loop {
+                    let lot = counter::next_lot_code(conn, &event.provider_agent)?;
+                    if !lot_exists(conn, spec.id, &lot)? {
+                        break lot;
+                    }
+                },
+            };
+
+            let reference_number = counter::next_reference_number(conn, &event.provider_agent)?;
+            let (accounting_quantity, on_hand_quantity) = (Decimal(accounting_from), Decimal(onhand_from));
+            let mut new_resource =
+                NewEconomicResource::new(&spec.id, &spec.name, &accounting_quantity, Some(&location.id), Some(&lot));
+            new_resource.on_hand_quantity = &on_hand_quantity;
+            new_resource.tracking_identifier = event.tracking_identifier.as_deref();
+            new_resource.reference_number = Some(&reference_number);
+
+            let inserted_resource: EconomicResource = diesel::insert_into(economic_resources::table)
+                .values(new_resource)
+                .get_result(conn)?;
+            ledger::record_change(
+                conn,
+                &inserted_resource,
+                &inserted_resource.accounting_quantity.0,
+                &inserted_resource.on_hand_quantity.0,
+                Some(event.execution_id),
+            )?;
+
+            Some(inserted_resource)
+        }
+        ResourceRequirement::Existing | ResourceRequirement::Specification => match &event.resource {
+            Some(resource) => {
+                let quantity = specification_quantity(conn, event, resource.resource_specification_id)?;
+                let (accounting_from, _) = effect.accounting_effect.deltas(&quantity);
+                let (onhand_from, _) = effect.onhand_effect.deltas(&quantity);
+
+                let mut resource = adjust_quantities(conn, resource, accounting_from, onhand_from, Some(event.execution_id))?;
+
+                if effect.location_effect == LocationEffect::Update {
+                    let location = event.at_location.as_ref().ok_or_else(|| {
+                        execution_error(format!("A location is required to move {}", resource.name))
+                    })?;
+                    let moved: EconomicResource = diesel::update(economic_resources::table)
+                        .filter(economic_resources::id.eq(resource.id))
+                        .set(economic_resources::current_location.eq(location.id))
+                        .get_result(conn)?;
+                    ledger::record_move(conn, &resource, &moved, Some(event.execution_id))?;
+                    resource = moved;
+                    containment::move_contents(conn, resource.id, resource.current_location, Some(event.execution_id))?;
+                }
+
+                Some(resource)
+            }
+            None => None,
+        },
+    };
+
+    let to_resource = match &resource {
+        Some(resource) if effect.has_receiver_resource() => {
+            Some(receive_resource(conn, effect, event, resource)?)
+        }
+        _ => None,
+    };
+
+    Ok((resource, to_resource))
+}
+
+/// Load packs the resource into the container of the flow, Unload takes it out
+/// of the container it is in. Returns the container
+fn apply_containment(
+    conn: &mut PgConnection,
+    action: ActionType,
+    event: &EventData,
+    resource: &mut EconomicResource,
+) -> FieldResult<Option<Uuid>> {
+    match (action, &event.container, resource.contained_in) {
+        (ActionType::Load, Some(_), Some(current)) => Err(execution_error(format!(
+            "{} is already packed in {}, unload it first",
+            resource.name, current
+        ))),
+        (ActionType::Load, Some(container), None) => {
+            *resource = containment::pack(conn, resource, container.id, Some(event.execution_id))?;
+            Ok(Some(container.id))
+        }
+        (ActionType::Unload, _, Some(current)) => {
+            *resource = containment::unpack(conn, resource)?;
+            Ok(Some(current))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Finds or creates the resource receiving the quantity of a custody change
+fn receive_resource(
+    conn: &mut PgConnection,
+    effect: &ActionEffect,
+    event: &EventData,
+    from: &EconomicResource,
+) -> FieldResult<EconomicResource> {
+    let resource_specification_id = event
+        .to_resource_specification
+        .unwrap_or(from.resource_specification_id);
+    let quantity = specification_quantity(conn, event, resource_specification_id)?;
+    let (_, accounting_delta) = effect.accounting_effect.deltas(&quantity);
+    let (_, onhand_delta) = effect.onhand_effect.deltas(&quantity);
+
+    let location = event
+        .to_location
+        .as_ref()
+        .or(event.at_location.as_ref())
+        .ok_or_else(|| execution_error(format!("A destination location is required for {}", from.name)))?;
+
+    let custodian_id = match effect.custody_effect {
+        CustodyEffect::Transfer => Some(event.receiver_agent),
+        CustodyEffect::NoEffect => from.custodian_id,
+    };
+
+    let existing: Option<EconomicResource> = economic_resources::table
+        .filter(economic_resources::resource_specification_id.eq(resource_specification_id))
+        .filter(economic_resources::lot.is_not_distinct_from(from.lot.clone()))
+        .filter(economic_resources::current_location.eq(location.id))
+        .filter(economic_resources::custodian_id.is_not_distinct_from(custodian_id))
+        .filter(economic_resources::contained_in.is_null())
+        .filter(economic_resources::id.ne(from.id))
+        .first::<EconomicResource>(conn)
+        .optional()?;
+
+    if let Some(existing) = existing {
+        return adjust_quantities(conn, &existing, accounting_delta, onhand_delta, Some(event.execution_id));
+    }
+
+    let reference_number = counter::next_reference_number(conn, &custodian_id.unwrap_or(event.provider_agent))?;
+    let (accounting_quantity, on_hand_quantity) = (Decimal(accounting_delta), Decimal(onhand_delta));
+    let mut new_resource = NewEconomicResource::new(
+        &resource_specification_id,
+        &from.name,
+        &accounting_quantity,
+        Some(&location.id),
+        from.lot.as_deref(),
+    );
+    new_resource.on_hand_quantity = &on_hand_quantity;
+    new_resource.note = from.note.as_deref();
+    new_resource.tracking_identifier = from.tracking_identifier.as_deref();
+    new_resource.custodian_id = custodian_id.as_ref();
+    new_resource.reference_number = Some(&reference_number);
+
+    let inserted_resource: EconomicResource = diesel::insert_into(economic_resources::table)
+        .values(new_resource)
+        .get_result(conn)?;
+    ledger::record_change(
+        conn,
+        &inserted_resource,
+        &inserted_resource.accounting_quantity.0,
+        &inserted_resource.on_hand_quantity.0,
+        Some(event.execution_id),
+    )?;
+
+    Ok(inserted_resource)
+}
+
+/// Quantity of the event in the unit of the resource specification, quantities
+/// recorded without a unit are taken to be in it already
+fn specification_quantity(
+    conn: &mut PgConnection,
+    event: &EventData,
+    resource_specification_id: Uuid,
+) -> FieldResult<BigDecimal> {
+    let quantity = event.quantity.clone().unwrap_or_default().0;
+    let Some(unit_of_measure) = &event.unit_of_measure else {
+        return Ok(quantity);
+    };
+    let Some(specification_unit) = unit::specification_unit(conn, resource_specification_id)? else {
+        return Ok(quantity);
+    };
+
+    let recorded = event.unit.as_ref().ok_or_else(|| {
+        execution_error(format!(
+            "Unknown unit {}, the resource is kept in {}",
+            unit_of_measure, specification_unit.symbol
+        ))
+    })?;
+    unit::convert(&quantity, recorded, &specification_unit)
+}
+
+/// Applies quantity deltas in a single statement, so concurrent events can't overdraw a resource,
+/// and records them in the inventory ledger
+pub fn adjust_quantities(
+    conn: &mut PgConnection,
+    resource: &EconomicResource,
+    accounting_delta: BigDecimal,
+    onhand_delta: BigDecimal,
+    process_execution_id: Option<Uuid>,
+) -> FieldResult<EconomicResource> {
+    let updated: EconomicResource = diesel::update(economic_resources::table)
+        .filter(economic_resources::id.eq(resource.id))
+        .set((
+            economic_resources::accounting_quantity.eq(economic_resources::accounting_quantity + &accounting_delta),
+            economic_resources::on_hand_quantity.eq(economic_resources::on_hand_quantity + &onhand_delta),
+        ))
+        .get_result(conn)?;
+
+    // The update locked the row, what it held before is the quantity available to this execution
+    let shortage = if updated.on_hand_quantity.0.is_negative() {
+        Some(("on hand", &onhand_delta, &updated.on_hand_quantity.0 - &onhand_delta))
+    } else if updated.accounting_quantity.0.is_negative() {
+        Some(("in accounting", &accounting_delta, &updated.accounting_quantity.0 - &accounting_delta))
+    } else {
+        None
+    };
+    if let Some((quantity, delta, available)) = shortage {
+        let error_message = format!(
+            "{} requested {}, available {} {}",
+            resource.name,
+            -delta,
+            available,
+            quantity
+        );
+        return Err(FieldError::new(
+            "Insufficient quantity",
+            graphql_value!({ "code": error_message }),
+        ));
+    }
+
+    ledger::record_change(conn, &updated, &accounting_delta, &onhand_delta, process_execution_id)?;
+
+    Ok(updated)
+}
+
+fn check_custody(conn: &mut PgConnection, resource: &EconomicResource, provider_agent: Uuid) -> FieldResult<()> {
+    if containment::holder(conn, resource)? != provider_agent {
+        return Err(execution_error(format!("{} is not held by the providing agent", resource.name)));
+    }
+
+    Ok(())
+}
+
+/// Lots are told apart by their specification, which belongs to a single agent
+fn lot_exists(conn: &mut PgConnection, resource_specification_id: Uuid, lot: &str) -> FieldResult<bool> {
+    let resources: i64 = economic_resources::table
+        .filter(economic_resources::resource_specification_id.eq(resource_specification_id))
+        .filter(economic_resources::lot.eq(lot))
+        .count()
+        .get_result(conn)?;
+
+    Ok(resources > 0)
+}
+
+fn location_by_id(conn: &mut PgConnection, location_id: Uuid) -> FieldResult<Location> {
+    locations::table
+        .filter(locations::id.eq(location_id))
+        .first::<Location>(conn)
+        .optional()?
+        .ok_or_else(|| execution_error(format!("Location {} not found", location_id)))
+}
+
+fn economic_resource_by_id(conn: &mut PgConnection, field: &RecipeFlowDataField, value: &str) -> FieldResult<EconomicResource> {
+    economic_resources::table
+        .filter(economic_resources::id.eq(parse_uuid(field, value)?))
+        .first::<EconomicResource>(conn)
+        .optional()?
+        .ok_or_else(|| execution_error(format!("Economic resource {} not found", value)))
+}
+
+fn resource_specification_by_id(conn: &mut PgConnection, id: Uuid) -> FieldResult<ResourceSpecification> {
+    resource_specifications::table
+        .filter(resource_specifications::id.eq(id))
+        .first::<ResourceSpecification>(conn)
+        .optional()?
+        .ok_or_else(|| execution_error(format!("Resource specification {} not found", id)))
+}
+
+fn parse_uuid(field: &RecipeFlowDataField, value: &str) -> FieldResult<Uuid> {
+    Uuid::parse_str(value)
+        .map_err(|_| execution_error(format!("{} must be an id, got {}", field.field_identifier, value)))
+}
+
+fn parse_quantity(field: &RecipeFlowDataField, value: &str) -> FieldResult<Decimal> {
+    match value.parse::<Decimal>() {
+        Ok(quantity) if quantity.0.is_positive() => Ok(quantity),
+        _ => Err(execution_error(format!("{} must be a positive quantity, got {}", field.field_identifier, value))),
+    }
+}
+
+fn parse_point_in_time(field: &RecipeFlowDataField, value: &str) -> FieldResult<NaiveDateTime> {
+    parse_date(value).map_err(|_| execution_error(format!("{} must be a date, got {}", field.field_identifier, value)))
+}
+
+fn execution_error(error_message: String) -> FieldError {
+    FieldError::new(
+        "Unable to execute event",
+        graphql_value!({ "code": error_message }),
+    )
+}
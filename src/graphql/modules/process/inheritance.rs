@@ -0,0 +1,299 @@
+use std::collections::HashSet;
+
+use diesel::prelude::*;
+use juniper::{graphql_value, FieldError, FieldResult};
+use uuid::Uuid;
+
+use crate::{
+    db::schema::{
+        process_execution_custom_values, process_executions, recipe_process_flow_data_fields, recipe_process_flows,
+        recipe_process_relations,
+    },
+    graphql::{context::Context, modules::process::correction},
+    recipe::process::{
+        data_field::{InheritedValue, RecipeFlowDataField},
+        execution::ProcessExecution,
+        flow::RecipeProcessFlow,
+    },
+    templates::recipe_flow_template_data_field::FieldClass,
+};
+
+use super::execution::DataFieldValue;
+
+/** Queries */
+/// Values the inheriting fields of the flow would be prefilled with
+pub fn get_inherited_values(context: &Context, process_flow_id: Uuid) -> FieldResult<Vec<InheritedValue>> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let data_fields: Vec<RecipeFlowDataField> = recipe_process_flow_data_fields::table
+        .filter(recipe_process_flow_data_fields::recipe_process_flow_id.eq(process_flow_id))
+        .filter(recipe_process_flow_data_fields::inherits.is_not_null())
+        .load::<RecipeFlowDataField>(conn)?;
+
+    let mut res = Vec::new();
+    for data_field in &data_fields {
+        res.extend(inherited_value(conn, data_field, &Source::Latest)?);
+    }
+
+    Ok(res)
+}
+
+/*** Mutations */
+/// Links a process data field to a field of an earlier flow, either in the
+/// same process or in a process it is, directly or not, an output of
+pub fn set_data_field_inheritance(
+    context: &Context,
+    data_field_id: Uuid,
+    inherits: Option<Uuid>,
+) -> FieldResult<RecipeFlowDataField> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    conn.transaction::<_, FieldError, _>(|conn| {
+        let data_field = data_field_by_id(conn, data_field_id)?;
+
+        if let Some(inherits) = inherits {
+            let parent = data_field_by_id(conn, inherits)?;
+            check_inheritance(conn, &data_field, &parent)?;
+        }
+
+        let updated: RecipeFlowDataField = diesel::update(recipe_process_flow_data_fields::table)
+            .filter(recipe_process_flow_data_fields::id.eq(data_field_id))
+            .set(recipe_process_flow_data_fields::inherits.eq(inherits))
+            .get_result(conn)?;
+
+        Ok(updated)
+    })
+}
+
+/// Prefills the inheriting fields of the flow that were left empty and
+/// rejects submitted values that differ from the inherited ones. Values are
+/// inherited from the same run of the process or, failing that, from the
+/// execution that recorded a resource or the lot the event refers to
+pub fn apply_inherited_values(
+    conn: &mut PgConnection,
+    flow: &RecipeProcessFlow,
+    data_fields: &[RecipeFlowDataField],
+    submitted: &[DataFieldValue],
+    batch_id: &Uuid,
+    lot: Option<&str>,
+) -> FieldResult<Vec<DataFieldValue>> {
+    let mut values: Vec<DataFieldValue> = submitted
+        .iter()
+        .map(|v| DataFieldValue { id: v.id, value: v.value.clone() })
+        .collect();
+
+    let resources: Vec<Uuid> = data_fields
+        .iter()
+        .filter(|f| f.field_class == FieldClass::EconomicResource)
+        .filter_map(|f| values.iter().find(|v| v.id == f.id))
+        .filter_map(|v| Uuid::parse_str(v.value.trim()).ok())
+        .collect();
+    let lot = lot.map(str::trim).filter(|lot| !lot.is_empty());
+    let source = Source::Run { batch_id, resources: &resources, lot };
+
+    let mut mismatches = Vec::new();
+    for data_field in data_fields.iter().filter(|f| f.inherits.is_some()) {
+        let Some(expected) = inherited_value(conn, data_field, &source)?.and_then(|i| i.value) else {
+            continue;
+        };
+
+        match values.iter().find(|v| v.id == data_field.id && !v.value.trim().is_empty()) {
+            Some(value) if !same_value(&value.value, &expected) => mismatches.push(format!(
+                "{} is {} but inherits {}",
+                data_field.field_identifier,
+                value.value.trim(),
+                expected
+            )),
+            Some(_) => {}
+            None => {
+                values.retain(|v| v.id != data_field.id);
+                values.push(DataFieldValue { id: data_field.id, value: expected });
+            }
+        }
+    }
+
+    if !mismatches.is_empty() {
+        let error_message = format!(
+            "Inherited values don't match in flow {}: {}",
+            flow.identifier,
+            mismatches.join("; ")
+        );
+        return Err(FieldError::new(
+            "Unable to execute event",
+            graphql_value!({ "code": error_message }),
+        ));
+    }
+
+    Ok(values)
+}
+
+/// Executions of the inherited flow a value is taken from
+enum Source<'a> {
+    /// The last one, whatever the run
+    Latest,
+    /// The last one of the run or else the last one that recorded a resource
+    /// or the lot of the event, none when the event isn't related to any
+    Run {
+        batch_id: &'a Uuid,
+        resources: &'a [Uuid],
+        lot: Option<&'a str>,
+    },
+}
+
+/// Value recorded by the execution of the inherited field the source points
+/// to, none when the field doesn't inherit
+fn inherited_value(
+    conn: &mut PgConnection,
+    data_field: &RecipeFlowDataField,
+    source: &Source,
+) -> FieldResult<Option<InheritedValue>> {
+    let Some(inherits) = data_field.inherits else {
+        return Ok(None);
+    };
+    let parent = data_field_by_id(conn, inherits)?;
+
+    // Corrected executions no longer hold, their replacement does
+    let executions: Vec<ProcessExecution> = process_executions::table
+        .filter(process_executions::process_flow_id.eq(parent.recipe_process_flow_id))
+        .order(process_executions::created_at.desc())
+        .load::<ProcessExecution>(conn)?;
+    let executions = correction::effective_executions(conn, executions)?;
+
+    let execution = match source {
+        Source::Latest => executions.into_iter().next(),
+        Source::Run { batch_id, resources, lot } => executions
+            .iter()
+            .find(|e| e.batch_id.as_ref() == Some(*batch_id))
+            .or_else(|| executions.iter().find(|e| e.records(resources, *lot)))
+            .cloned(),
+    };
+
+    let value = match &execution {
+        Some(execution) => {
+            let recorded: Option<String> = process_execution_custom_values::table
+                .filter(process_execution_custom_values::process_execution_id.eq(execution.id))
+                .filter(process_execution_custom_values::field_id.eq(parent.id))
+                .select(process_execution_custom_values::field_value)
+                .first::<String>(conn)
+                .optional()?;
+            recorded.or_else(|| execution_value(execution, &parent.field_class))
+        }
+        None => None,
+    };
+
+    Ok(Some(InheritedValue {
+        data_field_id: data_field.id,
+        inherits,
+        value,
+        process_execution_id: execution.map(|e| e.id),
+    }))
+}
+
+/// Fields the execution resolved without a submitted value, like the resource a flow created
+fn execution_value(execution: &ProcessExecution, field_class: &FieldClass) -> Option<String> {
+    match field_class {
+        FieldClass::EconomicResource => execution.economic_resource_id.map(|id| id.to_string()),
+        FieldClass::ResourceSpecification => execution.resource_specification.map(|id| id.to_string()),
+        FieldClass::Quantity => execution.resource_quantity.as_ref().map(|quantity| quantity.to_string()),
+        FieldClass::Location => execution.at_location.map(|id| id.to_string()),
+        _ => None,
+    }
+}
+
+/// Ids are compared regardless of case
+fn same_value(submitted: &str, inherited: &str) -> bool {
+    match (Uuid::parse_str(submitted.trim()), Uuid::parse_str(inherited.trim())) {
+        (Ok(submitted), Ok(inherited)) => submitted == inherited,
+        _ => submitted.trim() == inherited.trim(),
+    }
+}
+
+fn check_inheritance(
+    conn: &mut PgConnection,
+    data_field: &RecipeFlowDataField,
+    parent: &RecipeFlowDataField,
+) -> FieldResult<()> {
+    if parent.field_class != data_field.field_class {
+        return Err(inheritance_error(format!(
+            "{} ({:?}) can't inherit {} ({:?})",
+            data_field.field_identifier, data_field.field_class, parent.field_identifier, parent.field_class
+        )));
+    }
+
+    if parent.recipe_process_flow_id == data_field.recipe_process_flow_id {
+        return Err(inheritance_error(format!(
+            "{} can't inherit a field of its own flow",
+            data_field.field_identifier
+        )));
+    }
+
+    let process_id = process_of_flow(conn, data_field.recipe_process_flow_id)?;
+    let parent_process_id = process_of_flow(conn, parent.recipe_process_flow_id)?;
+    if !upstream_processes(conn, process_id)?.contains(&parent_process_id) {
+        return Err(inheritance_error(format!(
+            "{} can only inherit fields of its process or of the processes it is an output of",
+            data_field.field_identifier
+        )));
+    }
+
+    // A field can't end up inheriting, through other fields, from itself
+    let mut ancestor = parent.inherits;
+    let mut visited: HashSet<Uuid> = HashSet::new();
+    while let Some(ancestor_id) = ancestor {
+        if ancestor_id == data_field.id || !visited.insert(ancestor_id) {
+            return Err(inheritance_error(format!(
+                "{} would inherit its own value",
+                data_field.field_identifier
+            )));
+        }
+        ancestor = data_field_by_id(conn, ancestor_id)?.inherits;
+    }
+
+    Ok(())
+}
+
+/// The process itself and every process it is, directly or not, an output of
+fn upstream_processes(conn: &mut PgConnection, recipe_process_id: Uuid) -> FieldResult<HashSet<Uuid>> {
+    let mut upstream: HashSet<Uuid> = HashSet::from([recipe_process_id]);
+    let mut frontier = vec![recipe_process_id];
+
+    while !frontier.is_empty() {
+        let output_of: Vec<Uuid> = recipe_process_relations::table
+            .filter(recipe_process_relations::recipe_process_id.eq_any(&frontier))
+            .select(recipe_process_relations::output_of)
+            .load::<Uuid>(conn)?;
+        frontier = output_of.into_iter().filter(|id| upstream.insert(*id)).collect();
+    }
+
+    Ok(upstream)
+}
+
+fn process_of_flow(conn: &mut PgConnection, recipe_process_flow_id: Uuid) -> FieldResult<Uuid> {
+    let recipe_process_id: Uuid = recipe_process_flows::table
+        .filter(recipe_process_flows::id.eq(recipe_process_flow_id))
+        .select(recipe_process_flows::recipe_process_id)
+        .first::<Uuid>(conn)?;
+
+    Ok(recipe_process_id)
+}
+
+fn data_field_by_id(conn: &mut PgConnection, data_field_id: Uuid) -> FieldResult<RecipeFlowDataField> {
+    recipe_process_flow_data_fields::table
+        .filter(recipe_process_flow_data_fields::id.eq(data_field_id))
+        .first::<RecipeFlowDataField>(conn)
+        .optional()?
+        .ok_or_else(|| inheritance_error(format!("Data field {} not found", data_field_id)))
+}
+
+fn inheritance_error(error_message: String) -> FieldError {
+    FieldError::new(
+        "Unable to set field inheritance",
+        graphql_value!({ "code": error_message }),
+    )
+}
@@ -1 +1,8 @@
-// pub mod process;
\ No newline at end of file
+#![allow(clippy::module_inception)]
+
+pub mod process;
+pub mod execution;
+pub mod inheritance;
+pub mod validation;
+pub mod default_value;
+pub mod correction;
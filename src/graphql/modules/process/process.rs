@@ -1,625 +1,604 @@
-
-// use diesel::prelude::*;
-// use juniper::{graphql_value, FieldError, FieldResult, GraphQLInputObject, GraphQLObject};
-// use uuid::Uuid;
-
-// use crate::{
-//     common::resource_specification::ResourceSpecification,
-//     db::schema::{
-//         recipe_process_flow_data_fields, recipe_process_flows, recipe_process_relations,
-//         recipe_processes, recipe_resources, recipe_templates, recipes,
-//     },
-//     graphql::{
-//         context::Context, modules::common::resource_specification::resource_specification_by_id,
-//     },
-//     recipe::{
-//         process::{
-//             data_field::{NewRecipeFlowDataField, RecipeFlowDataField},
-//             flow::{NewRecipeProcessFlow, RecipeProcessFlow, RecipeProcessFlowResponse},
-//             process::{
-//                 NewOutpuOf, NewRecipeProcess, OutputOf, ProcessFlow, RecipeProcess,
-//                 RecipeProcessResponse,
-//             },
-//         },
-//         recipe::{Recipe, RecipeResource},
-//     },
-//     templates::{
-//         recipe_flow_template::{
-//             ActionType, 
-//             EventType, 
-//             RoleType}, 
-//             recipe_flow_template_data_field::{FieldClass, FieldType, FlowThrough}, 
-//             recipe_flow_template_group_data_fields::FieldGroupClass,
-//             recipe_template::{RecipeTemplate}
-//     },
-// };
-
-// #[derive(GraphQLInputObject)]
-// pub struct RecipeProcessWithRelation {
-//     pub recipe_process: RecipeWithRecipeFlows,
-//     pub output_of: Vec<RecipeWithRecipeFlows>,
-// }
-
-// #[derive(GraphQLInputObject)]
-// pub struct RecipeWithRecipeFlows {
-//     pub id: Uuid,
-//     pub name: String,
-//     pub commitment: Option<ActionType>,
-//     pub fulfills: Option<Uuid>,
-//     pub recipe_flows: Vec<RecipeFlowWithDataFields>,
-//     pub identifier: String,
-//     pub trigger: Option<ActionType>
-// }
-
-// #[derive(GraphQLInputObject)]
-// pub struct RecipeFlowGroupDataField {
-//     pub id: Uuid,
-//     pub name: String,
-//     pub group_class: FieldGroupClass
-// }
-
-// #[derive(GraphQLInputObject)]
-// pub struct RecipeFlowWithDataFields {
-//     pub id: Uuid,
-//     pub recipe_template_id: Uuid,
-//     pub event_type: EventType,
-//     pub role_type: RoleType,
-//     pub action: ActionType,
-//     pub identifier: String,
-//     pub groups: Vec<RecipeFlowGroupDataField>,
-//     pub data_fields: Vec<RecipeFlowDataFieldInput>,
-// }
-
-
-// #[derive(GraphQLInputObject)]
-// pub struct RecipeFlowDataFieldInput {
-//     pub id: Option<Uuid>,
-//     pub recipe_flow_template_id: Uuid,
-//     pub group_id: Option<Uuid>,
-//     pub field_identifier: String,
-//     pub field_class: FieldClass,
-//     pub field: String,
-//     pub field_type: FieldType,
-//     pub note: Option<String>,
-//     pub required: bool,
-    
-//     pub flow_through: Option<FlowThrough>,
-//     pub default_value: Option<String>
-// }
-
-// #[derive(GraphQLObject)]
-// pub struct CreateRecipeProcessesResponse {
-//     pub recipe: Recipe,
-//     pub resources: Vec<ResourceSpecification>,
-//     pub recipe_processes: Vec<RecipeProcessResponse>,
-// }
-
-// impl CreateRecipeProcessesResponse {
-//     pub fn new(recipe: Recipe, resources: Vec<ResourceSpecification>) -> Self {
-//         CreateRecipeProcessesResponse {
-//             recipe,
-//             resources,
-//             recipe_processes: Vec::new(),
-//         }
-//     }
-
-//     pub fn add_recipe_process(&mut self, recipe_process: RecipeProcessResponse) {
-//         self.recipe_processes.push(recipe_process);
-//     }
-// }
-
-// #[derive(GraphQLObject)]
-// pub struct RecipeProcessesResponse {
-//     pub recipe: Recipe,
-//     pub resources: Vec<ResourceSpecification>,
-//     pub recipe_processes: Vec<RecipeProcessResponse>,
-// }
-
-// impl RecipeProcessesResponse {
-//     pub fn new(recipe: Recipe, resources: Vec<ResourceSpecification>) -> Self {
-//         RecipeProcessesResponse {
-//             recipe,
-//             resources,
-//             recipe_processes: Vec::new(),
-//         }
-//     }
-
-//     pub fn add_recipe_process(&mut self, recipe_process: RecipeProcessResponse) {
-//         self.recipe_processes.push(recipe_process);
-//     }
-// }
-
-// /** Queries */
-// pub fn get_recipe_processes(
-//     context: &Context,
-//     recipe_id: Uuid,
-// ) -> FieldResult<RecipeProcessesResponse> {
-//     let conn = &mut context
-//         .pool
-//         .get()
-//         .expect("Failed to get DB connection from pool");
-
-//     let recipe: Recipe = recipes::table
-//         .filter(recipes::id.eq(recipe_id))
-//         .first::<Recipe>(conn)?;
-
-//     let recipe_resources: Vec<RecipeResource> = recipe_resources::table
-//         .filter(recipe_resources::recipe_id.eq(recipe_id))
-//         .load::<RecipeResource>(conn)?;
-
-//     let mut resources: Vec<ResourceSpecification> = Vec::new();
-
-//     for resource in recipe_resources {
-//         let spec = resource_specification_by_id(context, resource.resource_specification_id)?;
-//         resources.push(spec)
-//     }
-
-//     let mut res: RecipeProcessesResponse = RecipeProcessesResponse::new(recipe.clone(), resources);
-
-//     let recipe_processes: Vec<RecipeProcess> = recipe_processes::table
-//         .filter(recipe_processes::recipe_id.eq(recipe_id))
-//         .load::<RecipeProcess>(conn)?;
-
-//     for recipe_process in recipe_processes {
-//         let mut recipe_process_response: RecipeProcessResponse =
-//             RecipeProcessResponse::new(recipe_process.clone());
-
-//         let recipe_process_flows: Vec<RecipeProcessFlow> = recipe_process_flows::table
-//             .filter(recipe_process_flows::recipe_process_id.eq(recipe_process.id))
-//             .load::<RecipeProcessFlow>(conn)?;
-
-//         for recipe_process_flow in recipe_process_flows {
-//             let mut recipe_process_flow_response =
-//                 RecipeProcessFlowResponse::new(recipe_process_flow.clone());
-
-//             let recipe_process_flow_data_fields: Vec<RecipeFlowDataField> =
-//                 recipe_process_flow_data_fields::table
-//                     .filter(
-//                         recipe_process_flow_data_fields::recipe_process_flow_id
-//                             .eq(recipe_process_flow.id),
-//                     )
-//                     .load::<RecipeFlowDataField>(conn)?;
-
-//             for recipe_process_flow_data_field in recipe_process_flow_data_fields {
-//                 recipe_process_flow_response.add_data_field(recipe_process_flow_data_field);
-//             }
-
-//             recipe_process_response.add_recipe_process_flow(recipe_process_flow_response);
-//         }
-
-//         let output_of_values: Vec<OutputOf> = recipe_process_relations::table
-//             .filter(recipe_process_relations::recipe_process_id.eq(recipe_process.id))
-//             .load::<OutputOf>(conn)?;
-
-//         for output_of in output_of_values {
-//             recipe_process_response.add_output_of(output_of.id);
-//         }
-
-//         res.add_recipe_process(recipe_process_response);
-//     }
-
-//     Ok(res)
-// }
-
-// /** Mutations */
-// //TODO: should check for inheritance, commitment, trigger, and fulfillment
-// pub fn create_recipe_processes(
-//     context: &Context,
-//     recipe_id: Uuid,
-//     data: Vec<RecipeProcessWithRelation>,
-// ) -> FieldResult<CreateRecipeProcessesResponse> {
-//     let conn = &mut context
-//         .pool
-//         .get()
-//         .expect("Failed to get DB connection from pool");
-
-//     conn.transaction::<_, FieldError, _>(|conn| {
-//         let recipe: Recipe = recipes::table
-//             .filter(recipes::id.eq(recipe_id))
-//             .first::<Recipe>(conn)?;
-
-//         let recipe_resources: Vec<RecipeResource> = recipe_resources::table 
-//             .filter(recipe_resources::recipe_id.eq(recipe_id))
-//             .load::<RecipeResource>(conn)?;
-
-//         let mut resources: Vec<ResourceSpecification> = Vec::new();
-
-//         for resource in recipe_resources {
-//             let spec = resource_specification_by_id(context, resource.resource_specification_id)?;
-//             resources.push(spec)
-//         }
-
-//         let mut res: CreateRecipeProcessesResponse =
-//             CreateRecipeProcessesResponse::new(recipe.clone(), resources);
-
-//         for recipe_process in data {
-//             let fulfills: Option<Uuid> =
-//                 if let Some(fulfills_value) = recipe_process.recipe_process.fulfills {
-//                     //get from recipe_templates the data that fulfills this
-//                     //get the identifier
-//                     let recipe_process_template: RecipeTemplate = recipe_templates::table
-//                         .filter(recipe_templates::id.eq(fulfills_value))
-//                         .first::<RecipeTemplate>(conn)?; 
-
-//                     //search for the identifier in the recipe_process table
-//                     let recipe_process: RecipeProcess = recipe_processes::table
-//                         .filter(recipe_processes::recipe_id.eq(recipe_id))
-//                         .filter(recipe_processes::identifier.eq(recipe_process_template.identifier))
-//                         .first(conn)?;
-
-//                     Some(recipe_process.id)
-//                 } else {
-//                     None
-//                 };
-
-//             let new_recipe_process = NewRecipeProcess::new(
-//                 &recipe_id,
-//                 &recipe_process.recipe_process.id,
-//                 &recipe_process.recipe_process.name,
-//                 recipe_process.recipe_process.commitment.as_ref(),
-//                 fulfills.as_ref(),
-//                 &recipe_process.recipe_process.identifier,
-//                 recipe_process.recipe_process.trigger.as_ref()
-//             );
-
-//             let inserted_recipe_process: RecipeProcess =
-//                 diesel::insert_into(recipe_processes::table)
-//                     .values(new_recipe_process)
-//                     .get_result(conn)?;
-
-//             let mut recipe_process_response: RecipeProcessResponse =
-//                 RecipeProcessResponse::new(inserted_recipe_process.clone());
-
-//             //Insert in relations table
-//             for output_of in recipe_process.output_of {
-//                 let process_output_of: RecipeProcess = recipe_processes::table
-//                     .filter(recipe_processes::recipe_id.eq(&recipe_id))
-//                     .filter(recipe_processes::recipe_template_id.eq(output_of.id))
-//                     .first::<RecipeProcess>(conn)?;
-
-//                 let new_output_of =
-//                     NewOutpuOf::new(&inserted_recipe_process.id, &process_output_of.id);
-
-//                 let inserted_relation: OutputOf =
-//                     diesel::insert_into(recipe_process_relations::table)
-//                         .values(new_output_of)
-//                         .get_result(conn)?;
-
-//                 recipe_process_response.add_output_of(inserted_relation.output_of);
-//             }
-
-//             //Iterate over flows
-//             for flow in recipe_process.recipe_process.recipe_flows {
-//                 let new_recipe_flow = NewRecipeProcessFlow::new(
-//                     &inserted_recipe_process.id,
-//                     &flow.id,
-//                     &flow.event_type,
-//                     &flow.role_type,
-//                     &flow.action,
-//                     &flow.identifier
-//                 );
-
-//                 let inserted_recipe_flow: RecipeProcessFlow =
-//                     diesel::insert_into(recipe_process_flows::table)
-//                         .values(new_recipe_flow)
-//                         .get_result(conn)?;
-
-//                 let mut recipe_process_flow_response =
-//                     RecipeProcessFlowResponse::new(inserted_recipe_flow.clone());
-
-//                 //Iterate over data fields, if data field comes from recipe_flow_template_data_fields so data_field_id should be defined
-//                 for data_field in flow.data_fields {
-//                     let data_field_id: Option<&Uuid> = data_field.id.as_ref();
-
-//                     let flow_through_ref: Option<&FlowThrough> = data_field.flow_through.as_ref();
-
-//                     //TODO: check inheritance
-
-//                     //check this, check on default value
-//                     let new_data_field = NewRecipeFlowDataField::new(
-//                         &inserted_recipe_flow.id,
-//                         data_field_id, 
-//                         &data_field.field_identifier,
-//                         &data_field.field_class,
-//                         &data_field.field,
-//                         &data_field.field_type,
-//                         data_field.note.as_deref(), 
-//                         data_field.required,
-//                         data_field.default_value.as_deref(), 
-//                         flow_through_ref,
-//                         None
-//                     );
-
-//                     let inserted_data_field: RecipeFlowDataField =
-//                         diesel::insert_into(recipe_process_flow_data_fields::table)
-//                             .values(new_data_field)
-//                             .get_result(conn)?; 
-
-//                     println!(
-//                         "{} {:?} {} {:?}",
-//                         recipe_process.recipe_process.name,
-//                         flow.action,
-//                         data_field.field_identifier,
-//                         flow.role_type
-//                     );
-
-//                     recipe_process_flow_response.add_data_field(inserted_data_field);
-//                 }
-
-//                 recipe_process_response.add_recipe_process_flow(recipe_process_flow_response);
-//             }
-
-//             res.add_recipe_process(recipe_process_response)
-//         }
-
-//         //diesel::sql_types::Uuid, diesel::sql_types::Uuid, diesel::sql_types::Nullable<diesel::sql_types::Uuid>, diesel::sql_types::Text, FieldClassEnum, diesel::sql_types::Text, FieldTypeEnum, diesel::sql_types::Nullable<diesel::sql_types::Text>, diesel::sql_types::Bool, diesel::sql_types::Nullable<diesel::sql_types::Text>, diesel::sql_types::Nullable<FlowThroughEnum>
-//         Ok(res)
-//     })
-// }
-
-// #[derive(GraphQLInputObject, Debug)]
-// pub struct DataFieldValue {
-//     id: Uuid,
-//     value: String,
-// }
-
-// #[derive(GraphQLInputObject)]
-// pub struct ProcessExecution {
-//     process_flow_id: Uuid,
-//     data_field_values: Vec<DataFieldValue>,
-// }
-
-// //TODO: iterate over process_flows, get process flow info from db, get action and role_type
-// //check action of the process flow, to apply the logic depending on action and role_type
-// //also iterate over data fields and complete the info to create the data in process_executions
-// //and process_execution_custom_values tables
-// pub fn execute_events(
-//     context: &Context,
-//     recipe_process_id: Uuid,
-//     process_flows: Vec<ProcessExecution>,
-// ) -> FieldResult<String> {
-//     let conn = &mut context
-//         .pool
-//         .get()
-//         .expect("Failed to get DB connection from pool");
-
-//     // Begin transaction
-//     conn.transaction::<_, FieldError, _>(|conn| {
-//         //get the recipe process
-//         let recipe_process: RecipeProcess = recipe_processes::table
-//             .filter(recipe_processes::id.eq(recipe_process_id))
-//             .first::<RecipeProcess>(conn)?;
-
-//         if let Some(fulfills) = recipe_process.fulfills {
-//             execute_fulfillment(context, fulfills)?;
-//         }
-
-//         for process_flow in process_flows {
-//             let process_flow_id = process_flow.process_flow_id;
-//             let process_flow_info: ProcessFlow = recipe_process_flows::table
-//                 .filter(recipe_process_flows::recipe_process_id.eq(recipe_process_id))
-//                 .filter(recipe_process_flows::id.eq(process_flow_id))
-//                 .first::<ProcessFlow>(conn)?;
-
-//             let action = process_flow_info.action;
-//             let role_type = process_flow_info.role_type;
-//             let error_message = format!("Invalid Action {:?}", action.clone());
-
-//             let process_flow_data_fields: Vec<RecipeFlowDataField> =
-//                 recipe_process_flow_data_fields::table
-//                     .filter(
-//                         recipe_process_flow_data_fields::recipe_process_flow_id.eq(process_flow_id),
-//                     )
-//                     .load::<RecipeFlowDataField>(conn)?;
-
-//             // Handle invalid action based on role_type
-//             if let RoleType::Input = role_type {
-//                 match action {
-//                     _ => {
-//                         return Err(FieldError::new(
-//                             "Action is not valid as Output",
-//                             graphql_value!({ "code": error_message }),
-//                         ));
-//                     }
-//                 }
-//             } else if let RoleType::Output = role_type {
-//                 match action {
-//                     ActionType::Produce => {
-//                         let product_field_value = get_field_value(
-//                             &process_flow,
-//                             &process_flow_data_fields,
-//                             FieldClass::ResourceSpecification,
-//                         )?;
-//                         println!("Product {:?}", product_field_value.1);
-
-//                         let quantity_field_value = get_field_value(
-//                             &process_flow,
-//                             &process_flow_data_fields,
-//                             FieldClass::Quantity,
-//                         )?;
-//                         println!("Quantity {:?}", quantity_field_value.1);
-
-//                         let extra_fields = get_extra_fields(&process_flow, &process_flow_data_fields)?;
-                       
-//                         for extra_field in extra_fields {
-//                             println!("{:?} {:?}", extra_field.field_class, extra_field.value);
-//                         };
-//                     }
-//                     _ => {
-//                         return Err(FieldError::new(
-//                             "Action is not valid as Output",
-//                             graphql_value!({ "code": error_message }),
-//                         ));
-//                     }
-//                 }
-//             }
-//         }
-//         Ok(())
-//     })?;
-
-//     // If the transaction is successful, return the result
-//     Ok(format!("All events executed successfully"))
-// }
-
-// fn execute_fulfillment(context: &Context, fulfills: Uuid) -> FieldResult<bool> {
-//     let conn = &mut context
-//         .pool
-//         .get()
-//         .expect("Failed to get DB connection from pool");
-
-//     let recipe_process_result = recipe_processes::table
-//         .filter(recipe_processes::id.eq(fulfills))
-//         .first::<RecipeProcess>(conn);
-
-//     // Check if the query was successful
-//     match recipe_process_result {
-//         Ok(res) => {
-//             match res.commitment {
-//                 Some(commitment) => {
-//                     match commitment {
-//                         ActionType::Transfer => {
-//                             //get the product and quantity
-//                             Ok(true)
-//                         }
-//                         _ => {
-//                             let error_message =
-//                                 format!("Commitment action not supported {:?}", res.name);
-//                             Err(FieldError::new(
-//                                 "Unable to execute fulfillment",
-//                                 graphql_value!({ "code": error_message }),
-//                             ))
-//                         }
-//                     }
-//                 }
-//                 None => {
-//                     let error_message =
-//                         format!("Unable to find commitment action in {:?}", res.name);
-//                     Err(FieldError::new(
-//                         "Unable to execute fulfillment",
-//                         graphql_value!({ "code": error_message }),
-//                     ))
-//                 }
-//             }
-//         } // If the query succeeds, return true
-//         Err(e) => {
-//             let error_message = format!("Commitment not found {:?}", e);
-//             Err(FieldError::new(
-//                 "Unable to execute fulfillment",
-//                 graphql_value!({ "code": error_message }),
-//             ))
-//         }
-//     }
-// }
-
-// fn get_field_value(
-//     process_flow: &ProcessExecution,
-//     data_fields: &Vec<RecipeFlowDataField>,
-//     field_class: FieldClass,
-// ) -> FieldResult<(RecipeFlowDataField, String)> {
-//     // Find the product field
-//     let product_field = if let Some(product_field) = data_fields
-//         .iter()
-//         .find(|field| field.field_class == field_class)
-//     {
-//         (*product_field).clone() // Dereference the reference and then clone
-//     } else {
-//         let error_message = format!("Cannot Find Product {:?} Data Field", field_class); 
-//         return Err(FieldError::new(
-//             "Unable to find Data Field",
-//             graphql_value!({ "code": error_message }),
-//         ));
-//     };
-
-//     // Find the field value
-//     let field_value = if let Some(field_value) = process_flow
-//         .data_field_values
-//         .iter()
-//         .find(|df| df.id == product_field.id)
-//     {
-//         field_value.value.clone()
-//     } else {
-//         let error_message = format!("Cannot Find Product {:?} Data Field", field_class); 
-//         return Err(FieldError::new(
-//             "Unable to find Data Field Value",
-//             graphql_value!({ "code": error_message }),
-//         ));
-//     };
-
-//     // Return the cloned product field and field value
-//     Ok((product_field, field_value))
-// }
-
-// #[derive(Debug)]
-// struct ExtraField {
-//     field: RecipeFlowDataField,
-//     field_class: FieldClass,
-//     value: String,
-// }
-
-// fn get_extra_fields(
-//     process_flow: &ProcessExecution,
-//     data_fields: &Vec<RecipeFlowDataField>,
-// ) -> FieldResult<Vec<ExtraField>> {
-//     let mut res = Vec::new();
-    
-//     let location_val = get_field(&process_flow, &data_fields, FieldClass::Location)?;
-//     let has_point_in_time_val = get_field(&process_flow, &data_fields, FieldClass::HasPointInTime)?;
-//     let note_val = get_field(&process_flow, &data_fields, FieldClass::Note)?;
-    
-//     if let Some(location) = location_val {
-//         res.push(location);
-//     };
-
-//     if let Some(has_point_in_time) = has_point_in_time_val {
-//         res.push(has_point_in_time);
-//     };
-
-//     if let Some(note) = note_val {
-//         res.push(note);
-//     }
-    
-//     Ok(res)
-// }
-
-// fn get_custom_fields(
-//     process_flow: &ProcessExecution,
-//     data_fields: &Vec<RecipeFlowDataField>
-// ) -> FieldResult<Vec<ExtraField>> {
-    
-//     Ok(Vec::new())
-// }
-
-// fn get_field(
-//     process_flow: &ProcessExecution,
-//     data_fields: &Vec<RecipeFlowDataField>,
-//     field_class: FieldClass,
-// ) -> FieldResult<Option<ExtraField>> {
-//     if let Some(product_field) = data_fields
-//         .iter()
-//         .find(|field| field.field_class == field_class) {
-        
-//         let field =(*product_field).clone();
-        
-//         let value = if let Some(field_value) = process_flow
-//             .data_field_values
-//             .iter()
-//             .find(|df| df.id == product_field.id) {
-//             field_value.value.clone()
-//         } else {
-//             let error_message = format!("Field Value {:?} Not Found ", field_class);
-//             return Err(FieldError::new(
-//                 "Unable to find Data Field Value",
-//                 graphql_value!({ "code": error_message }),
-//             ));
-//         };
-
-//         let res: ExtraField = ExtraField {
-//             field_class,
-//             field,
-//             value
-//         };
-//         return Ok(Some(res));
-//     };
-//     Ok(None)
-
-// }
+use std::collections::HashMap;
+
+use diesel::prelude::*;
+use juniper::{graphql_value, FieldError, FieldResult, GraphQLInputObject};
+use uuid::Uuid;
+
+use crate::{
+    common::{economic_resource::Decimal, resource_specification::ResourceSpecification},
+    db::schema::{
+        recipe_flow_template_data_fields, recipe_flow_templates, recipe_process_flow_data_fields,
+        recipe_process_flow_group_data_fields, recipe_process_flows, recipe_process_relations,
+        recipe_processes, recipe_resources, recipe_templates, recipe_templates_access, recipes,
+    },
+    graphql::{
+        context::Context,
+        modules::{
+            common::resource_specification::resource_specification_by_id, process::default_value,
+            templates::template,
+        },
+    },
+    recipe::{
+        process::{
+            data_field::{
+                NewRecipeFlowDataField, NewRecipeFlowGroupDataField, RecipeFlowDataField,
+                RecipeFlowGroupDataField,
+            },
+            flow::{NewRecipeProcessFlow, RecipeProcessFlow, RecipeProcessFlowResponse},
+            graph::{self, RecipeProcessNode},
+            process::{
+                NewOutpuOf, NewRecipeProcess, OutputOf, RecipeProcess, RecipeProcessResponse,
+                RecipeProcessesResponse,
+            },
+        },
+        recipe::{Recipe, RecipeResource},
+    },
+    templates::{
+        recipe_flow_template::{ActionType, RecipeFlowTemplate},
+        recipe_flow_template_data_field::{
+            FieldClass, FieldType, FlowThrough, RecipeFlowTemplateDataField,
+        },
+        recipe_flow_template_group_data_fields::FieldGroupClass,
+        recipe_template::RecipeTemplate,
+        recipe_template_access::RecipeTemplateAccess,
+    },
+};
+
+/// A recipe template chosen for the recipe, and the templates whose processes feed into it
+#[derive(GraphQLInputObject)]
+pub struct RecipeProcessWithRelation {
+    pub recipe_process: RecipeWithRecipeFlows,
+    /// Recipe template ids of the processes this one is an output of
+    pub output_of: Vec<Uuid>,
+}
+
+#[derive(GraphQLInputObject)]
+pub struct RecipeWithRecipeFlows {
+    /// Recipe template id
+    pub id: Uuid,
+    pub name: String,
+    pub commitment: Option<ActionType>,
+    /// Recipe template id of the process this one fulfills
+    pub fulfills: Option<Uuid>,
+    pub recipe_flows: Vec<RecipeFlowWithDataFields>,
+    pub identifier: String,
+    pub trigger: Option<ActionType>
+}
+
+#[derive(GraphQLInputObject)]
+pub struct RecipeFlowGroupDataFieldInput {
+    pub id: Uuid,
+    pub name: String,
+    pub group_class: FieldGroupClass
+}
+
+#[derive(GraphQLInputObject)]
+pub struct RecipeFlowWithDataFields {
+    /// Recipe flow template id
+    pub id: Uuid,
+    pub identifier: String,
+    pub groups: Vec<RecipeFlowGroupDataFieldInput>,
+    pub data_fields: Vec<RecipeFlowDataFieldInput>,
+}
+
+#[derive(GraphQLInputObject)]
+pub struct RecipeFlowDataFieldInput {
+    /// Recipe flow template data field id, None for fields added to the recipe only
+    pub id: Option<Uuid>,
+    pub group_id: Option<Uuid>,
+    pub field_identifier: String,
+    pub field_class: FieldClass,
+    pub field: String,
+    pub field_type: FieldType,
+    pub note: Option<String>,
+    pub required: bool,
+    pub flow_through: Option<FlowThrough>,
+    pub default_value: Option<String>,
+    /// Overrides the option set of the template field
+    pub option_set_id: Option<Uuid>,
+    /// Override the range of the template field
+    pub min_value: Option<Decimal>,
+    pub max_value: Option<Decimal>
+}
+
+/** Queries */
+pub fn get_recipe_processes(
+    context: &Context,
+    recipe_id: Uuid,
+) -> FieldResult<RecipeProcessesResponse> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let recipe: Recipe = recipes::table
+        .filter(recipes::id.eq(recipe_id))
+        .first::<Recipe>(conn)?;
+
+    build_recipe_processes_response(context, conn, recipe)
+}
+
+/// Processes of the recipe in execution order, stage by stage
+pub fn get_recipe_process_graph(context: &Context, recipe_id: Uuid) -> FieldResult<Vec<RecipeProcessNode>> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    recipe_process_graph(conn, recipe_id)
+}
+
+fn recipe_process_graph(conn: &mut PgConnection, recipe_id: Uuid) -> FieldResult<Vec<RecipeProcessNode>> {
+    let recipe_processes: Vec<RecipeProcess> = recipe_processes::table
+        .filter(recipe_processes::recipe_id.eq(recipe_id))
+        .order(recipe_processes::identifier.asc())
+        .load::<RecipeProcess>(conn)?;
+
+    let process_ids: Vec<Uuid> = recipe_processes.iter().map(|p| p.id).collect();
+    let relations: Vec<(Uuid, Uuid)> = recipe_process_relations::table
+        .filter(recipe_process_relations::recipe_process_id.eq_any(&process_ids))
+        .select((recipe_process_relations::recipe_process_id, recipe_process_relations::output_of))
+        .load::<(Uuid, Uuid)>(conn)?;
+
+    let identifier = |id: &Uuid| {
+        recipe_processes
+            .iter()
+            .find(|p| p.id == *id)
+            .map(|p| p.identifier.clone())
+            .unwrap_or_else(|| id.to_string())
+    };
+
+    let order = graph::topological_order(&process_ids, &relations).map_err(|cycle| {
+        let mut names: Vec<String> = cycle.iter().map(identifier).collect();
+        names.push(identifier(&cycle[0]));
+        let error_message = format!("Processes form a cycle: {}", names.join(" -> "));
+        FieldError::new(
+            "Recipe processes can't depend on each other in a loop",
+            graphql_value!({ "code": error_message }),
+        )
+    })?;
+
+    let mut res = Vec::new();
+    for (id, depth) in order {
+        let recipe_process = recipe_processes.iter().find(|p| p.id == id).expect("Ordered process of the recipe");
+        res.push(RecipeProcessNode {
+            recipe_process_id: id,
+            identifier: recipe_process.identifier.clone(),
+            name: recipe_process.name.clone(),
+            depth,
+            output_of: relations
+                .iter()
+                .filter(|(recipe_process_id, _)| *recipe_process_id == id)
+                .map(|(_, output_of)| *output_of)
+                .collect(),
+        });
+    }
+
+    Ok(res)
+}
+
+fn build_recipe_processes_response(
+    context: &Context,
+    conn: &mut PgConnection,
+    recipe: Recipe,
+) -> FieldResult<RecipeProcessesResponse> {
+    let recipe_resources: Vec<RecipeResource> = recipe_resources::table
+        .filter(recipe_resources::recipe_id.eq(recipe.id))
+        .load::<RecipeResource>(conn)?;
+
+    let mut resources: Vec<ResourceSpecification> = Vec::new();
+
+    for resource in recipe_resources {
+        let spec = resource_specification_by_id(context, resource.resource_specification_id)?;
+        resources.push(spec)
+    }
+
+    let recipe_processes: Vec<RecipeProcess> = recipe_processes::table
+        .filter(recipe_processes::recipe_id.eq(recipe.id))
+        .load::<RecipeProcess>(conn)?;
+
+    let mut res: RecipeProcessesResponse = RecipeProcessesResponse::new(recipe, resources);
+
+    for recipe_process in recipe_processes {
+        let recipe_process_response = build_recipe_process_response(conn, recipe_process)?;
+        res.add_recipe_process(recipe_process_response);
+    }
+
+    Ok(res)
+}
+
+fn build_recipe_process_response(
+    conn: &mut PgConnection,
+    recipe_process: RecipeProcess,
+) -> FieldResult<RecipeProcessResponse> {
+    let recipe_process_id = recipe_process.id;
+    let mut recipe_process_response: RecipeProcessResponse =
+        RecipeProcessResponse::new(recipe_process);
+
+    let recipe_process_flows: Vec<RecipeProcessFlow> = recipe_process_flows::table
+        .filter(recipe_process_flows::recipe_process_id.eq(recipe_process_id))
+        .load::<RecipeProcessFlow>(conn)?;
+
+    for recipe_process_flow in recipe_process_flows {
+        let recipe_process_flow_id = recipe_process_flow.id;
+        let mut recipe_process_flow_response = RecipeProcessFlowResponse::new(recipe_process_flow);
+
+        let recipe_process_flow_data_fields: Vec<RecipeFlowDataField> =
+            recipe_process_flow_data_fields::table
+                .filter(recipe_process_flow_data_fields::recipe_process_flow_id.eq(recipe_process_flow_id))
+                .load::<RecipeFlowDataField>(conn)?;
+
+        for recipe_process_flow_data_field in recipe_process_flow_data_fields {
+            recipe_process_flow_response.add_data_field(recipe_process_flow_data_field);
+        }
+
+        recipe_process_response.add_recipe_process_flow(recipe_process_flow_response);
+    }
+
+    let output_of_values: Vec<OutputOf> = recipe_process_relations::table
+        .filter(recipe_process_relations::recipe_process_id.eq(recipe_process_id))
+        .load::<OutputOf>(conn)?;
+
+    for output_of in output_of_values {
+        recipe_process_response.add_output_of(output_of.output_of);
+    }
+
+    Ok(recipe_process_response)
+}
+
+/** Mutations */
+pub fn create_recipe_processes(
+    context: &Context,
+    recipe_id: Uuid,
+    data: Vec<RecipeProcessWithRelation>,
+) -> FieldResult<RecipeProcessesResponse> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    conn.transaction::<_, FieldError, _>(|conn| {
+        let recipe: Recipe = recipes::table
+            .filter(recipes::id.eq(recipe_id))
+            .first::<Recipe>(conn)?;
+
+        // Processes are inserted first, so that fulfillments and relations
+        // can point to any template of the recipe regardless of the input order
+        let mut inserted_processes: Vec<RecipeProcess> = Vec::new();
+
+        for recipe_process in &data {
+            let inserted_recipe_process =
+                insert_recipe_process(conn, &recipe, &recipe_process.recipe_process)?;
+            inserted_processes.push(inserted_recipe_process);
+        }
+
+        for (recipe_process, inserted_recipe_process) in data.iter().zip(&inserted_processes) {
+            if let Some(fulfills) = recipe_process.recipe_process.fulfills {
+                let fulfilled_process = recipe_process_by_template(conn, recipe_id, fulfills)?;
+
+                diesel::update(recipe_processes::table)
+                    .filter(recipe_processes::id.eq(inserted_recipe_process.id))
+                    .set(recipe_processes::fulfills.eq(fulfilled_process.id))
+                    .execute(conn)?;
+            }
+
+            //Insert in relations table
+            for output_of in &recipe_process.output_of {
+                let process_output_of = recipe_process_by_template(conn, recipe_id, *output_of)?;
+                check_blacklist(conn, inserted_recipe_process, &process_output_of)?;
+
+                let new_output_of =
+                    NewOutpuOf::new(&inserted_recipe_process.id, &process_output_of.id);
+
+                diesel::insert_into(recipe_process_relations::table)
+                    .values(new_output_of)
+                    .execute(conn)?;
+            }
+        }
+
+        // Rejects relations that close a loop
+        recipe_process_graph(conn, recipe_id)?;
+
+        build_recipe_processes_response(context, conn, recipe)
+    })
+}
+
+fn insert_recipe_process(
+    conn: &mut PgConnection,
+    recipe: &Recipe,
+    recipe_process: &RecipeWithRecipeFlows,
+) -> FieldResult<RecipeProcess> {
+    let recipe_template: RecipeTemplate = recipe_templates::table
+        .filter(recipe_templates::id.eq(recipe_process.id))
+        .filter(recipe_templates::deleted_at.is_null())
+        .first::<RecipeTemplate>(conn)
+        .optional()?
+        .ok_or_else(|| {
+            let error_message = format!("Template {} does not exist or was deleted", recipe_process.identifier);
+            FieldError::new(
+                "Unable to create recipe process",
+                graphql_value!({ "code": error_message }),
+            )
+        })?;
+
+    let access: Option<RecipeTemplateAccess> = recipe_templates_access::table
+        .filter(recipe_templates_access::agent_id.eq(recipe.agent_id))
+        .filter(recipe_templates_access::recipe_template_id.eq(recipe_template.id))
+        .first::<RecipeTemplateAccess>(conn)
+        .optional()?;
+
+    if access.is_none() {
+        let error_message = format!("Template {} is not assigned to the recipe agent", recipe_template.name);
+        return Err(FieldError::new(
+            "Unable to create recipe process",
+            graphql_value!({ "code": error_message }),
+        ));
+    }
+
+    // Commitment and trigger always come from the template
+    if !recipe_template.keeps_actions(recipe_process.commitment, recipe_process.trigger) {
+        let error_message = format!(
+            "Process {} must keep the commitment and trigger of template {}",
+            recipe_process.identifier, recipe_template.name
+        );
+        return Err(FieldError::new(
+            "Unable to create recipe process",
+            graphql_value!({ "code": error_message }),
+        ));
+    }
+
+    let new_recipe_process = NewRecipeProcess::new(
+        &recipe.id,
+        &recipe_template.id,
+        &recipe_process.name,
+        recipe_template.commitment.as_ref(),
+        None,
+        &recipe_process.identifier,
+        recipe_template.trigger.as_ref()
+    );
+
+    let inserted_recipe_process: RecipeProcess = diesel::insert_into(recipe_processes::table)
+        .values(new_recipe_process)
+        .get_result(conn)?;
+
+    //Iterate over flows
+    for flow in &recipe_process.recipe_flows {
+        insert_recipe_process_flow(conn, &recipe_template, &inserted_recipe_process, flow)?;
+    }
+
+    resolve_template_inheritance(conn, inserted_recipe_process.id)?;
+
+    Ok(inserted_recipe_process)
+}
+
+fn insert_recipe_process_flow(
+    conn: &mut PgConnection,
+    recipe_template: &RecipeTemplate,
+    recipe_process: &RecipeProcess,
+    flow: &RecipeFlowWithDataFields,
+) -> FieldResult<RecipeProcessFlow> {
+    let recipe_flow_template: RecipeFlowTemplate = recipe_flow_templates::table
+        .filter(recipe_flow_templates::id.eq(flow.id))
+        .filter(recipe_flow_templates::recipe_template_id.eq(recipe_template.id))
+        .filter(recipe_flow_templates::deleted_at.is_null())
+        .first::<RecipeFlowTemplate>(conn)
+        .optional()?
+        .ok_or_else(|| {
+            let error_message = format!("Flow {} is not part of template {}", flow.identifier, recipe_template.name);
+            FieldError::new(
+                "Unable to create recipe process flow",
+                graphql_value!({ "code": error_message }),
+            )
+        })?;
+
+    // Event type, role and action always come from the template
+    let new_recipe_flow = NewRecipeProcessFlow::new(
+        &recipe_process.id,
+        &recipe_flow_template.id,
+        &recipe_flow_template.event_type,
+        &recipe_flow_template.role_type,
+        &recipe_flow_template.action,
+        &flow.identifier
+    );
+
+    let inserted_recipe_flow: RecipeProcessFlow = diesel::insert_into(recipe_process_flows::table)
+        .values(new_recipe_flow)
+        .get_result(conn)?;
+
+    let mut groups: HashMap<Uuid, Uuid> = HashMap::new();
+    for group in &flow.groups {
+        let new_group = NewRecipeFlowGroupDataField::new(&group.name, &group.group_class);
+        let inserted_group: RecipeFlowGroupDataField =
+            diesel::insert_into(recipe_process_flow_group_data_fields::table)
+                .values(new_group)
+                .get_result(conn)?;
+        groups.insert(group.id, inserted_group.id);
+    }
+
+    //Iterate over data fields, if data field comes from recipe_flow_template_data_fields so data_field_id should be defined
+    for data_field in &flow.data_fields {
+        let mut required = data_field.required;
+        let mut flow_through = data_field.flow_through.clone();
+        let mut option_set_id = data_field.option_set_id;
+        let mut min_value = data_field.min_value.clone();
+        let mut max_value = data_field.max_value.clone();
+
+        if let Some(template_field_id) = data_field.id {
+            let template_field: RecipeFlowTemplateDataField = recipe_flow_template_data_fields::table
+                .filter(recipe_flow_template_data_fields::id.eq(template_field_id))
+                .filter(recipe_flow_template_data_fields::recipe_flow_template_id.eq(recipe_flow_template.id))
+                .filter(recipe_flow_template_data_fields::deleted_at.is_null())
+                .first::<RecipeFlowTemplateDataField>(conn)
+                .optional()?
+                .ok_or_else(|| {
+                    let error_message = format!("Field {} is not part of flow {}", data_field.field_identifier, flow.identifier);
+                    FieldError::new(
+                        "Unable to create recipe process data field",
+                        graphql_value!({ "code": error_message }),
+                    )
+                })?;
+
+            if template_field.field_class != data_field.field_class || template_field.field_type != data_field.field_type {
+                let error_message = format!("Field {} does not match its template", data_field.field_identifier);
+                return Err(FieldError::new(
+                    "Unable to create recipe process data field",
+                    graphql_value!({ "code": error_message }),
+                ));
+            }
+
+            // A recipe can make a template field required, never optional
+            required = required || template_field.required;
+            // Which side of the flow the field describes comes from the template unless overridden
+            flow_through = flow_through.or(template_field.flow_through);
+            option_set_id = option_set_id.or(template_field.option_set_id);
+            min_value = min_value.or(template_field.min_value);
+            max_value = max_value.or(template_field.max_value);
+        }
+
+        if let Some(default_value) = data_field.default_value.as_deref().filter(|d| !d.trim().is_empty()) {
+            default_value::check_default(conn, data_field.id, &data_field.field_identifier, default_value)?;
+        }
+
+        let group_id = match data_field.group_id {
+            Some(group_id) => Some(*groups.get(&group_id).ok_or_else(|| {
+                let error_message = format!("Group of field {} not found in flow {}", data_field.field_identifier, flow.identifier);
+                FieldError::new(
+                    "Unable to create recipe process data field",
+                    graphql_value!({ "code": error_message }),
+                )
+            })?),
+            None => None,
+        };
+
+        let mut new_data_field = NewRecipeFlowDataField::new(
+            &inserted_recipe_flow.id,
+            &data_field.field_identifier,
+            &data_field.field_class,
+            &data_field.field,
+            &data_field.field_type,
+            required
+        );
+        new_data_field.recipe_flow_template_data_field_id = data_field.id.as_ref();
+        new_data_field.group_id = group_id.as_ref();
+        new_data_field.note = data_field.note.as_deref();
+        new_data_field.default_value = data_field.default_value.as_deref();
+        new_data_field.flow_through = flow_through.as_ref();
+        new_data_field.option_set_id = option_set_id.as_ref();
+        new_data_field.min_value = min_value.as_ref();
+        new_data_field.max_value = max_value.as_ref();
+
+        diesel::insert_into(recipe_process_flow_data_fields::table)
+            .values(new_data_field)
+            .execute(conn)?;
+    }
+
+    Ok(inserted_recipe_flow)
+}
+
+/// Points process data fields to the process field created from the template field their template inherits
+fn resolve_template_inheritance(conn: &mut PgConnection, recipe_process_id: Uuid) -> FieldResult<()> {
+    let data_fields: Vec<RecipeFlowDataField> = recipe_process_flow_data_fields::table
+        .inner_join(recipe_process_flows::table)
+        .filter(recipe_process_flows::recipe_process_id.eq(recipe_process_id))
+        .select(recipe_process_flow_data_fields::all_columns)
+        .load::<RecipeFlowDataField>(conn)?;
+
+    for data_field in &data_fields {
+        let Some(template_field_id) = data_field.recipe_flow_template_data_field_id else {
+            continue;
+        };
+
+        let inherits: Option<Uuid> = recipe_flow_template_data_fields::table
+            .filter(recipe_flow_template_data_fields::id.eq(template_field_id))
+            .select(recipe_flow_template_data_fields::inherits)
+            .first::<Option<Uuid>>(conn)?;
+
+        let Some(inherits) = inherits else {
+            continue;
+        };
+
+        let parent = data_fields
+            .iter()
+            .find(|f| f.recipe_flow_template_data_field_id == Some(inherits));
+
+        if let Some(parent) = parent {
+            diesel::update(recipe_process_flow_data_fields::table)
+                .filter(recipe_process_flow_data_fields::id.eq(data_field.id))
+                .set(recipe_process_flow_data_fields::inherits.eq(parent.id))
+                .execute(conn)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The map of the templates may forbid a template from following another
+fn check_blacklist(
+    conn: &mut PgConnection,
+    recipe_process: &RecipeProcess,
+    output_of: &RecipeProcess,
+) -> FieldResult<()> {
+    let (Some(successor_id), Some(predecessor_id)) = (recipe_process.recipe_template_id, output_of.recipe_template_id) else {
+        return Ok(());
+    };
+
+    let successor: RecipeTemplate = recipe_templates::table
+        .filter(recipe_templates::id.eq(successor_id))
+        .first::<RecipeTemplate>(conn)?;
+    let predecessor: RecipeTemplate = recipe_templates::table
+        .filter(recipe_templates::id.eq(predecessor_id))
+        .first::<RecipeTemplate>(conn)?;
+
+    if template::is_blacklisted(conn, &successor, &predecessor)? {
+        let error_message = format!(
+            "Template {} may not follow template {}",
+            successor.identifier, predecessor.identifier
+        );
+        return Err(FieldError::new(
+            "Unable to relate recipe processes",
+            graphql_value!({ "code": error_message }),
+        ));
+    }
+
+    Ok(())
+}
+
+/// The process of the recipe created from any version of the template
+fn recipe_process_by_template(
+    conn: &mut PgConnection,
+    recipe_id: Uuid,
+    recipe_template_id: Uuid,
+) -> FieldResult<RecipeProcess> {
+    let recipe_template: RecipeTemplate = recipe_templates::table
+        .filter(recipe_templates::id.eq(recipe_template_id))
+        .first::<RecipeTemplate>(conn)
+        .optional()?
+        .ok_or_else(|| process_lookup_error(format!("Template {} not found", recipe_template_id)))?;
+
+    let mut processes: Vec<RecipeProcess> = recipe_processes::table
+        .inner_join(recipe_templates::table)
+        .filter(recipe_processes::recipe_id.eq(recipe_id))
+        .filter(recipe_templates::map_template_id.eq(recipe_template.map_template_id))
+        .filter(recipe_templates::identifier.eq(&recipe_template.identifier))
+        .select(recipe_processes::all_columns)
+        .load::<RecipeProcess>(conn)?;
+
+    match processes.len() {
+        0 => Err(process_lookup_error(format!(
+            "No process created from template {} in this recipe",
+            recipe_template.identifier
+        ))),
+        1 => Ok(processes.remove(0)),
+        count => Err(process_lookup_error(format!(
+            "{} processes were created from template {} in this recipe",
+            count, recipe_template.identifier
+        ))),
+    }
+}
+
+fn process_lookup_error(error_message: String) -> FieldError {
+    FieldError::new(
+        "Unable to find recipe process",
+        graphql_value!({ "code": error_message }),
+    )
+}
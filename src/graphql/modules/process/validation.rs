@@ -0,0 +1,288 @@
+use std::collections::HashSet;
+
+use diesel::prelude::*;
+use juniper::{FieldError, FieldResult, Object, Value};
+use uuid::Uuid;
+
+use crate::{
+    common::economic_resource::EconomicResource,
+    db::schema::{agents, economic_resources, locations, recipe_process_flow_data_fields, recipe_process_flows, resource_specifications},
+    graphql::{
+        context::Context,
+        modules::{
+            common::containment,
+            templates::{option_set, rule},
+        },
+    },
+    recipe::process::{
+        action::CustodyEffect,
+        data_field::RecipeFlowDataField,
+        flow::RecipeProcessFlow,
+        validation::{self, FieldViolation, ValueReference},
+    },
+    templates::{
+        data_field_rule::{DataFieldRule, RuleKind},
+        recipe_flow_template::RoleType,
+        recipe_flow_template_data_field::{FieldType, FlowThrough},
+        rule_expression,
+    },
+};
+
+use super::execution::DataFieldValue;
+
+/** Queries */
+/// Checks values before they are submitted, an empty list means the flow can be executed with them
+pub fn validate_flow_values(
+    context: &Context,
+    process_flow_id: Uuid,
+    data_field_values: Vec<DataFieldValue>,
+) -> FieldResult<Vec<FieldViolation>> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let flow: RecipeProcessFlow = recipe_process_flows::table
+        .filter(recipe_process_flows::id.eq(process_flow_id))
+        .first::<RecipeProcessFlow>(conn)?;
+
+    let data_fields: Vec<RecipeFlowDataField> = recipe_process_flow_data_fields::table
+        .filter(recipe_process_flow_data_fields::recipe_process_flow_id.eq(process_flow_id))
+        .load::<RecipeFlowDataField>(conn)?;
+
+    flow_violations(conn, &flow, &data_fields, &data_field_values, &context.agent_ids())
+}
+
+/// Rejects the flow with every violation listed in the error extensions, values
+/// on the side of the recipe agent can only refer to records of the agents the
+/// user is a member of
+pub fn check_flow_values(
+    conn: &mut PgConnection,
+    flow: &RecipeProcessFlow,
+    data_fields: &[RecipeFlowDataField],
+    submitted: &[DataFieldValue],
+    agent_ids: &[Uuid],
+) -> FieldResult<()> {
+    let violations = flow_violations(conn, flow, data_fields, submitted, agent_ids)?;
+    if violations.is_empty() {
+        return Ok(());
+    }
+
+    let list: Vec<Value> = violations
+        .iter()
+        .map(|violation| {
+            let mut object = Object::with_capacity(3);
+            object.add_field("dataFieldId", Value::scalar(violation.data_field_id.to_string()));
+            object.add_field("fieldIdentifier", Value::scalar(violation.field_identifier.clone()));
+            object.add_field("message", Value::scalar(violation.message.clone()));
+            Value::object(object)
+        })
+        .collect();
+
+    let mut extensions = Object::with_capacity(2);
+    extensions.add_field("code", Value::scalar("INVALID_FIELD_VALUES"));
+    extensions.add_field("fields", Value::list(list));
+
+    Err(FieldError::new(
+        format!("Invalid values in flow {}", flow.identifier),
+        Value::object(extensions),
+    ))
+}
+
+fn flow_violations(
+    conn: &mut PgConnection,
+    flow: &RecipeProcessFlow,
+    data_fields: &[RecipeFlowDataField],
+    submitted: &[DataFieldValue],
+    agent_ids: &[Uuid],
+) -> FieldResult<Vec<FieldViolation>> {
+    let mut violations = Vec::new();
+    // Dynamic options and rules are resolved for the owner of the recipe, looked up once
+    let mut agent_id: Option<Uuid> = None;
+    // What a Transfer brings in is still held by the providing agent
+    let receives = flow.role_type == RoleType::Input && flow.action.effect().custody_effect == CustodyEffect::Transfer;
+
+    for data_field_value in submitted {
+        let Some(data_field) = data_fields.iter().find(|f| f.id == data_field_value.id) else {
+            violations.push(FieldViolation::new(
+                data_field_value.id,
+                "",
+                "Not a field of this flow".to_string(),
+            ));
+            continue;
+        };
+
+        if data_field_value.value.trim().is_empty() {
+            continue;
+        }
+
+        let message = match validation::check_value(&data_field.field_class, &data_field.field_type, &data_field_value.value) {
+            Ok(Some(reference)) => {
+                // Counterparties and the records on their side only have to exist
+                let other_side = matches!(reference, ValueReference::Agent(_))
+                    || data_field.flow_through == Some(FlowThrough::External)
+                    || (receives && matches!(reference, ValueReference::EconomicResource(_)));
+                missing_reference(conn, &reference, (!other_side).then_some(agent_ids))?
+            }
+            Ok(None) => validation::check_range(&data_field_value.value, data_field.min_value.as_ref(), data_field.max_value.as_ref()).err(),
+            Err(message) => Some(message),
+        };
+        let message = match (message, data_field.option_set_id) {
+            (None, Some(option_set_id)) if data_field.field_type == FieldType::Select => {
+                let agent_id = recipe_agent(conn, &mut agent_id, data_field.recipe_process_flow_id)?;
+                let set = option_set::option_set_by_id(conn, option_set_id)?;
+                let options = option_set::resolve_options(conn, &set, agent_id)?;
+                (!option_set::is_option(&options, &data_field_value.value))
+                    .then(|| format!("{} is not one of the options of {}", data_field_value.value.trim(), set.name))
+            }
+            (message, _) => message,
+        };
+        if let Some(message) = message {
+            violations.push(FieldViolation::new(data_field.id, &data_field.field_identifier, message));
+        }
+    }
+
+    let hidden = rule_violations(conn, data_fields, submitted, &mut agent_id, &mut violations)?;
+
+    for data_field in data_fields.iter().filter(|f| f.required && !hidden.contains(&f.id)) {
+        let submitted = submitted
+            .iter()
+            .any(|v| v.id == data_field.id && !v.value.trim().is_empty());
+        if !submitted {
+            violations.push(FieldViolation::new(data_field.id, &data_field.field_identifier, "Required".to_string()));
+        }
+    }
+
+    Ok(violations)
+}
+
+/// Evaluates the rules of the template fields against the submitted values and
+/// returns the fields hidden by them. Rules that don't parse any longer are
+/// reported when the template is validated, not here
+fn rule_violations(
+    conn: &mut PgConnection,
+    data_fields: &[RecipeFlowDataField],
+    submitted: &[DataFieldValue],
+    agent_id: &mut Option<Uuid>,
+    violations: &mut Vec<FieldViolation>,
+) -> FieldResult<HashSet<Uuid>> {
+    let mut hidden = HashSet::new();
+
+    let template_field_ids: Vec<Uuid> = data_fields
+        .iter()
+        .filter_map(|f| f.recipe_flow_template_data_field_id)
+        .collect();
+    let rules = rule::rules_of_template_fields(conn, &template_field_ids)?;
+    let Some(first) = data_fields.first().filter(|_| !rules.is_empty()) else {
+        return Ok(hidden);
+    };
+
+    let agent = recipe_agent(conn, agent_id, first.recipe_process_flow_id)?.to_string();
+    let value_of = |name: &str| -> Option<String> {
+        if name == "$agent" {
+            return Some(agent.clone());
+        }
+        let data_field = data_fields.iter().find(|f| f.field_identifier == name)?;
+        submitted
+            .iter()
+            .find(|v| v.id == data_field.id && !v.value.trim().is_empty())
+            .map(|v| v.value.clone())
+    };
+
+    for data_field in data_fields {
+        let field_rules: Vec<(&DataFieldRule, rule_expression::Expr)> = rules
+            .iter()
+            .filter(|r| Some(r.data_field_id) == data_field.recipe_flow_template_data_field_id)
+            .filter_map(|r| rule_expression::parse(&r.expression).ok().map(|expr| (r, expr)))
+            .collect();
+        if field_rules.is_empty() {
+            continue;
+        }
+
+        let has_value = value_of(&data_field.field_identifier).is_some();
+        let hidden_by = field_rules
+            .iter()
+            .find(|(r, expr)| r.kind == RuleKind::VisibleIf && !expr.evaluate(&value_of));
+        if let Some((r, _)) = hidden_by {
+            hidden.insert(data_field.id);
+            if has_value {
+                let message = format!("Must be left empty unless {}", r.expression);
+                violations.push(FieldViolation::new(data_field.id, &data_field.field_identifier, r.message.clone().unwrap_or(message)));
+            }
+            continue;
+        }
+
+        for (r, expr) in &field_rules {
+            let message = match r.kind {
+                // Fields required anyway are reported once, as Required
+                RuleKind::RequiredIf if !has_value && !data_field.required && expr.evaluate(&value_of) => {
+                    Some(format!("Required when {}", r.expression))
+                }
+                RuleKind::Constraint if has_value && !expr.evaluate(&value_of) => Some(format!("Must satisfy {}", r.expression)),
+                _ => None,
+            };
+            if let Some(message) = message {
+                violations.push(FieldViolation::new(data_field.id, &data_field.field_identifier, r.message.clone().unwrap_or(message)));
+            }
+        }
+    }
+
+    Ok(hidden)
+}
+
+fn recipe_agent(conn: &mut PgConnection, agent_id: &mut Option<Uuid>, process_flow_id: Uuid) -> FieldResult<Uuid> {
+    if let Some(agent_id) = agent_id {
+        return Ok(*agent_id);
+    }
+    Ok(*agent_id.insert(option_set::recipe_agent_of_flow(conn, process_flow_id)?))
+}
+
+/// Message for a reference to a record that doesn't exist or, when the agents
+/// are given, belongs to none of them. Both are reported alike so ids of other
+/// agents can't be probed
+fn missing_reference(conn: &mut PgConnection, reference: &ValueReference, agent_ids: Option<&[Uuid]>) -> FieldResult<Option<String>> {
+    let (agent_id, kind): (Option<Uuid>, &str) = match reference {
+        ValueReference::Agent(id) => (
+            agents::table
+                .filter(agents::id.eq(id))
+                .select(agents::id)
+                .first::<Uuid>(conn)
+                .optional()?,
+            "Agent",
+        ),
+        ValueReference::Location(id) => (
+            locations::table
+                .filter(locations::id.eq(id))
+                .select(locations::agent_id)
+                .first::<Uuid>(conn)
+                .optional()?,
+            "Location",
+        ),
+        ValueReference::ResourceSpecification(id) => (
+            resource_specifications::table
+                .filter(resource_specifications::id.eq(id))
+                .select(resource_specifications::agent_id)
+                .first::<Uuid>(conn)
+                .optional()?,
+            "Resource specification",
+        ),
+        ValueReference::EconomicResource(id) => {
+            let resource: Option<EconomicResource> = economic_resources::table
+                .filter(economic_resources::id.eq(id))
+                .first::<EconomicResource>(conn)
+                .optional()?;
+            let holder = match resource {
+                Some(resource) => Some(containment::holder(conn, &resource)?),
+                None => None,
+            };
+            (holder, "Economic resource")
+        }
+    };
+
+    let found = match (agent_id, agent_ids) {
+        (Some(agent_id), Some(agent_ids)) => agent_ids.contains(&agent_id),
+        (Some(_), None) => true,
+        (None, _) => false,
+    };
+    Ok((!found).then(|| format!("{} not found", kind)))
+}
@@ -1 +1,3 @@
+#![allow(clippy::module_inception)]
+
 pub mod recipe;
\ No newline at end of file
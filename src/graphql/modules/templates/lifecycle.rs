@@ -0,0 +1,567 @@
+use std::collections::{BTreeMap, HashSet};
+
+use chrono::Utc;
+use diesel::prelude::*;
+use juniper::{graphql_value, FieldError, FieldResult, InputValue, Object, ToInputValue, Value};
+use uuid::Uuid;
+
+use crate::{
+    db::schema::{
+        map_templates, process_executions, recipe_flow_template_data_fields, recipe_flow_templates,
+        recipe_process_flow_data_fields, recipe_process_flows, recipe_processes, recipe_template_blacklists,
+        recipe_templates, recipes,
+    },
+    common::user::Permission,
+    graphql::context::Context,
+    templates::{
+        map_template::{MapTemplate, TemplateType},
+        recipe_flow_template::RecipeFlowTemplate,
+        recipe_flow_template_data_field::RecipeFlowTemplateDataField,
+        recipe_template::RecipeTemplate,
+        template_blocker::{BlockerKind, TemplateBlocker, TemplateDeletion, TemplateItem},
+    },
+};
+
+/// Live items of the template tree below, and including, an item
+#[derive(Default)]
+struct TemplateTree {
+    map_templates: Vec<Uuid>,
+    recipe_templates: Vec<Uuid>,
+    recipe_flow_templates: Vec<Uuid>,
+    data_fields: Vec<Uuid>,
+}
+
+impl TemplateTree {
+    fn ids(&self) -> impl Iterator<Item = &Uuid> {
+        self.map_templates
+            .iter()
+            .chain(&self.recipe_templates)
+            .chain(&self.recipe_flow_templates)
+            .chain(&self.data_fields)
+    }
+}
+
+/** Queries */
+/// What keeps the item from being deleted without cascading, processes of
+/// other agents are listed without their recipe
+pub fn template_blockers(context: &Context, item: TemplateItem, id: Uuid) -> FieldResult<Vec<TemplateBlocker>> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let tree = template_tree(conn, item, id)?;
+    Ok(visible_blockers(context, blockers(conn, &tree)?))
+}
+
+/*** Mutations */
+/// Name changes are always allowed, a type change also changes how the
+/// processes created from the map are validated
+pub fn update_map_template(
+    context: &Context,
+    map_template_id: Uuid,
+    name: Option<String>,
+    type_: Option<TemplateType>,
+    cascade: Option<bool>,
+) -> FieldResult<MapTemplate> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    conn.transaction::<_, FieldError, _>(|conn| {
+        let map_template: MapTemplate = map_templates::table
+            .filter(map_templates::id.eq(map_template_id))
+            .filter(map_templates::deleted_at.is_null())
+            .for_update()
+            .first::<MapTemplate>(conn)
+            .optional()?
+            .ok_or_else(|| not_found(TemplateItem::MapTemplate, map_template_id))?;
+
+        if type_.as_ref().is_some_and(|type_| *type_ != map_template.type_) {
+            let tree = template_tree(conn, TemplateItem::MapTemplate, map_template_id)?;
+            check_process_blockers(context, conn, &tree, cascade)?;
+        }
+
+        let updated: MapTemplate = diesel::update(map_templates::table)
+            .filter(map_templates::id.eq(map_template_id))
+            .set((
+                map_templates::name.eq(name.unwrap_or(map_template.name)),
+                map_templates::type_.eq(type_.unwrap_or(map_template.type_)),
+            ))
+            .get_result(conn)?;
+
+        Ok(updated)
+    })
+}
+
+/// Renaming a flow in use renames the process flows created from it when cascading
+pub fn update_recipe_flow_template(
+    context: &Context,
+    recipe_flow_template_id: Uuid,
+    identifier: Option<String>,
+    interactions: Option<i32>,
+    cascade: Option<bool>,
+) -> FieldResult<RecipeFlowTemplate> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    conn.transaction::<_, FieldError, _>(|conn| {
+        let flow: RecipeFlowTemplate = recipe_flow_templates::table
+            .filter(recipe_flow_templates::id.eq(recipe_flow_template_id))
+            .filter(recipe_flow_templates::deleted_at.is_null())
+            .for_update()
+            .first::<RecipeFlowTemplate>(conn)
+            .optional()?
+            .ok_or_else(|| not_found(TemplateItem::RecipeFlowTemplate, recipe_flow_template_id))?;
+        check_latest_version(conn, flow.recipe_template_id)?;
+
+        let identifier = identifier.unwrap_or_else(|| flow.identifier.clone());
+        if identifier != flow.identifier {
+            let taken: i64 = recipe_flow_templates::table
+                .filter(recipe_flow_templates::recipe_template_id.eq(flow.recipe_template_id))
+                .filter(recipe_flow_templates::identifier.eq(&identifier))
+                .filter(recipe_flow_templates::deleted_at.is_null())
+                .count()
+                .get_result(conn)?;
+            if taken > 0 {
+                let error_message = format!("The template already has a flow {}", identifier);
+                return Err(FieldError::new(
+                    "Unable to update template item",
+                    graphql_value!({ "code": error_message }),
+                ));
+            }
+
+            let tree = template_tree(conn, TemplateItem::RecipeFlowTemplate, recipe_flow_template_id)?;
+            check_process_blockers(context, conn, &tree, cascade)?;
+
+            diesel::update(recipe_process_flows::table)
+                .filter(recipe_process_flows::recipe_flow_template_id.eq(recipe_flow_template_id))
+                .set(recipe_process_flows::identifier.eq(&identifier))
+                .execute(conn)?;
+        }
+
+        let updated: RecipeFlowTemplate = diesel::update(recipe_flow_templates::table)
+            .filter(recipe_flow_templates::id.eq(recipe_flow_template_id))
+            .set((
+                recipe_flow_templates::identifier.eq(&identifier),
+                recipe_flow_templates::interactions.eq(interactions.or(flow.interactions)),
+            ))
+            .get_result(conn)?;
+
+        Ok(updated)
+    })
+}
+
+/// Label, note and required are copied into processes, changing them on a
+/// field in use updates the copies when cascading. A note given as None
+/// clears it
+pub fn update_recipe_flow_template_data_field(
+    context: &Context,
+    data_field_id: Uuid,
+    field: Option<String>,
+    note: Option<Option<String>>,
+    required: Option<bool>,
+    accept_default: Option<bool>,
+    cascade: Option<bool>,
+) -> FieldResult<RecipeFlowTemplateDataField> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    conn.transaction::<_, FieldError, _>(|conn| {
+        let data_field: RecipeFlowTemplateDataField = recipe_flow_template_data_fields::table
+            .filter(recipe_flow_template_data_fields::id.eq(data_field_id))
+            .filter(recipe_flow_template_data_fields::deleted_at.is_null())
+            .for_update()
+            .first::<RecipeFlowTemplateDataField>(conn)
+            .optional()?
+            .ok_or_else(|| not_found(TemplateItem::DataField, data_field_id))?;
+        let recipe_template_id: Uuid = recipe_flow_templates::table
+            .filter(recipe_flow_templates::id.eq(data_field.recipe_flow_template_id))
+            .select(recipe_flow_templates::recipe_template_id)
+            .first::<Uuid>(conn)?;
+        check_latest_version(conn, recipe_template_id)?;
+
+        let field = field.unwrap_or_else(|| data_field.field.clone());
+        let note = note.unwrap_or_else(|| data_field.note.clone());
+        let required = required.unwrap_or(data_field.required);
+
+        if field != data_field.field || note != data_field.note || required != data_field.required {
+            let tree = template_tree(conn, TemplateItem::DataField, data_field_id)?;
+            check_process_blockers(context, conn, &tree, cascade)?;
+
+            diesel::update(recipe_process_flow_data_fields::table)
+                .filter(recipe_process_flow_data_fields::recipe_flow_template_data_field_id.eq(data_field_id))
+                .set((
+                    recipe_process_flow_data_fields::field.eq(&field),
+                    recipe_process_flow_data_fields::note.eq(&note),
+                    recipe_process_flow_data_fields::required.eq(required),
+                ))
+                .execute(conn)?;
+        }
+
+        let updated: RecipeFlowTemplateDataField = diesel::update(recipe_flow_template_data_fields::table)
+            .filter(recipe_flow_template_data_fields::id.eq(data_field_id))
+            .set((
+                recipe_flow_template_data_fields::field.eq(&field),
+                recipe_flow_template_data_fields::note.eq(&note),
+                recipe_flow_template_data_fields::required.eq(required),
+                recipe_flow_template_data_fields::accept_default.eq(accept_default.unwrap_or(data_field.accept_default)),
+            ))
+            .get_result(conn)?;
+
+        Ok(updated)
+    })
+}
+
+/// Soft deletes the item with everything below it. Items still referenced
+/// are only deleted when cascading, processes and executions are kept as
+/// history while fulfillments and inheritance pointing at the deleted items
+/// are cleared
+pub fn delete_template_item(
+    context: &Context,
+    item: TemplateItem,
+    id: Uuid,
+    cascade: Option<bool>,
+) -> FieldResult<TemplateDeletion> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    conn.transaction::<_, FieldError, _>(|conn| {
+        let tree = template_tree(conn, item, id)?;
+        let blockers = blockers(conn, &tree)?;
+
+        if !blockers.is_empty() && !cascade.unwrap_or(false) {
+            return Err(blocked_error(&visible_blockers(context, blockers)));
+        }
+        check_cascade_permission(context, &blockers)?;
+
+        let now = Utc::now().naive_utc();
+        diesel::update(map_templates::table)
+            .filter(map_templates::id.eq_any(&tree.map_templates))
+            .set(map_templates::deleted_at.eq(now))
+            .execute(conn)?;
+        diesel::update(recipe_templates::table)
+            .filter(recipe_templates::id.eq_any(&tree.recipe_templates))
+            .set(recipe_templates::deleted_at.eq(now))
+            .execute(conn)?;
+        diesel::update(recipe_flow_templates::table)
+            .filter(recipe_flow_templates::id.eq_any(&tree.recipe_flow_templates))
+            .set(recipe_flow_templates::deleted_at.eq(now))
+            .execute(conn)?;
+        diesel::update(recipe_flow_template_data_fields::table)
+            .filter(recipe_flow_template_data_fields::id.eq_any(&tree.data_fields))
+            .set(recipe_flow_template_data_fields::deleted_at.eq(now))
+            .execute(conn)?;
+
+        // Blacklists only make sense between live templates
+        diesel::delete(recipe_template_blacklists::table)
+            .filter(
+                recipe_template_blacklists::recipe_template_id
+                    .eq_any(&tree.recipe_templates)
+                    .or(recipe_template_blacklists::recipe_template_predecesor_id.eq_any(&tree.recipe_templates)),
+            )
+            .execute(conn)?;
+
+        let mut res = TemplateDeletion::new(item, id);
+        for deleted in tree.ids() {
+            res.add_deleted(*deleted);
+        }
+
+        for blocker in blockers {
+            match blocker.kind {
+                BlockerKind::Fulfillment => {
+                    diesel::update(recipe_templates::table)
+                        .filter(recipe_templates::id.eq(blocker.id))
+                        .set(recipe_templates::fulfills.eq(None::<Uuid>))
+                        .execute(conn)?;
+                }
+                BlockerKind::Inheritance => {
+                    diesel::update(recipe_flow_template_data_fields::table)
+                        .filter(recipe_flow_template_data_fields::id.eq(blocker.id))
+                        .set(recipe_flow_template_data_fields::inherits.eq(None::<Uuid>))
+                        .execute(conn)?;
+                }
+                BlockerKind::RecipeProcess | BlockerKind::ProcessExecution => {}
+            }
+            res.add_detached(blocker);
+        }
+
+        Ok(res)
+    })
+}
+
+fn template_tree(conn: &mut PgConnection, item: TemplateItem, id: Uuid) -> FieldResult<TemplateTree> {
+    let mut tree = TemplateTree::default();
+
+    match item {
+        TemplateItem::MapTemplate => {
+            map_templates::table
+                .filter(map_templates::id.eq(id))
+                .filter(map_templates::deleted_at.is_null())
+                .select(map_templates::id)
+                .first::<Uuid>(conn)
+                .optional()?
+                .ok_or_else(|| not_found(item, id))?;
+
+            tree.map_templates.push(id);
+            tree.recipe_templates = recipe_templates::table
+                .filter(recipe_templates::map_template_id.eq(id))
+                .filter(recipe_templates::deleted_at.is_null())
+                .select(recipe_templates::id)
+                .load::<Uuid>(conn)?;
+        }
+        TemplateItem::RecipeTemplate => {
+            let recipe_template: RecipeTemplate = recipe_templates::table
+                .filter(recipe_templates::id.eq(id))
+                .filter(recipe_templates::deleted_at.is_null())
+                .first::<RecipeTemplate>(conn)
+                .optional()?
+                .ok_or_else(|| not_found(item, id))?;
+
+            // A template goes away with all its versions
+            tree.recipe_templates = recipe_templates::table
+                .filter(recipe_templates::map_template_id.eq(recipe_template.map_template_id))
+                .filter(recipe_templates::identifier.eq(&recipe_template.identifier))
+                .filter(recipe_templates::deleted_at.is_null())
+                .select(recipe_templates::id)
+                .load::<Uuid>(conn)?;
+        }
+        TemplateItem::RecipeFlowTemplate => {
+            recipe_flow_templates::table
+                .filter(recipe_flow_templates::id.eq(id))
+                .filter(recipe_flow_templates::deleted_at.is_null())
+                .select(recipe_flow_templates::id)
+                .first::<Uuid>(conn)
+                .optional()?
+                .ok_or_else(|| not_found(item, id))?;
+
+            tree.recipe_flow_templates.push(id);
+        }
+        TemplateItem::DataField => {
+            recipe_flow_template_data_fields::table
+                .filter(recipe_flow_template_data_fields::id.eq(id))
+                .filter(recipe_flow_template_data_fields::deleted_at.is_null())
+                .select(recipe_flow_template_data_fields::id)
+                .first::<Uuid>(conn)
+                .optional()?
+                .ok_or_else(|| not_found(item, id))?;
+
+            tree.data_fields.push(id);
+        }
+    }
+
+    let flows: Vec<Uuid> = recipe_flow_templates::table
+        .filter(recipe_flow_templates::recipe_template_id.eq_any(&tree.recipe_templates))
+        .filter(recipe_flow_templates::deleted_at.is_null())
+        .select(recipe_flow_templates::id)
+        .load::<Uuid>(conn)?;
+    tree.recipe_flow_templates.extend(flows);
+
+    let data_fields: Vec<Uuid> = recipe_flow_template_data_fields::table
+        .filter(recipe_flow_template_data_fields::recipe_flow_template_id.eq_any(&tree.recipe_flow_templates))
+        .filter(recipe_flow_template_data_fields::deleted_at.is_null())
+        .select(recipe_flow_template_data_fields::id)
+        .load::<Uuid>(conn)?;
+    tree.data_fields.extend(data_fields);
+
+    Ok(tree)
+}
+
+/// Processes and executions created from the tree, then live templates and
+/// data fields outside the tree pointing into it
+fn blockers(conn: &mut PgConnection, tree: &TemplateTree) -> FieldResult<Vec<TemplateBlocker>> {
+    let mut res = process_blockers(conn, tree)?;
+
+    let fulfillments: Vec<(Uuid, String, i32)> = recipe_templates::table
+        .filter(recipe_templates::fulfills.eq_any(&tree.recipe_templates))
+        .filter(recipe_templates::id.ne_all(&tree.recipe_templates))
+        .filter(recipe_templates::deleted_at.is_null())
+        .select((recipe_templates::id, recipe_templates::identifier, recipe_templates::version))
+        .load::<(Uuid, String, i32)>(conn)?;
+
+    for (id, identifier, version) in fulfillments {
+        res.push(TemplateBlocker::new(
+            BlockerKind::Fulfillment,
+            id,
+            format!("Template {} version {} fulfills it", identifier, version),
+        ));
+    }
+
+    let inheritances: Vec<(Uuid, String)> = recipe_flow_template_data_fields::table
+        .filter(recipe_flow_template_data_fields::inherits.eq_any(&tree.data_fields))
+        .filter(recipe_flow_template_data_fields::id.ne_all(&tree.data_fields))
+        .filter(recipe_flow_template_data_fields::deleted_at.is_null())
+        .select((recipe_flow_template_data_fields::id, recipe_flow_template_data_fields::field_identifier))
+        .load::<(Uuid, String)>(conn)?;
+
+    for (id, field_identifier) in inheritances {
+        res.push(TemplateBlocker::new(
+            BlockerKind::Inheritance,
+            id,
+            format!("Field {} inherits from it", field_identifier),
+        ));
+    }
+
+    Ok(res)
+}
+
+fn process_blockers(conn: &mut PgConnection, tree: &TemplateTree) -> FieldResult<Vec<TemplateBlocker>> {
+    let from_templates: Vec<Uuid> = recipe_processes::table
+        .filter(recipe_processes::recipe_template_id.eq_any(&tree.recipe_templates))
+        .select(recipe_processes::id)
+        .load::<Uuid>(conn)?;
+
+    let from_data_fields: Vec<Uuid> = recipe_process_flow_data_fields::table
+        .filter(recipe_process_flow_data_fields::recipe_flow_template_data_field_id.eq_any(&tree.data_fields))
+        .select(recipe_process_flow_data_fields::recipe_process_flow_id)
+        .load::<Uuid>(conn)?;
+
+    // Process flows built from the tree, executions anywhere else in the process don't depend on it
+    let process_flows: Vec<(Uuid, Uuid)> = recipe_process_flows::table
+        .filter(
+            recipe_process_flows::recipe_process_id
+                .eq_any(&from_templates)
+                .or(recipe_process_flows::recipe_flow_template_id.eq_any(&tree.recipe_flow_templates))
+                .or(recipe_process_flows::id.eq_any(&from_data_fields)),
+        )
+        .select((recipe_process_flows::id, recipe_process_flows::recipe_process_id))
+        .load::<(Uuid, Uuid)>(conn)?;
+
+    let mut process_ids: HashSet<Uuid> = from_templates.into_iter().collect();
+    process_ids.extend(process_flows.iter().map(|(_, process_id)| *process_id));
+
+    let processes: Vec<(Uuid, String, String, Uuid)> = recipe_processes::table
+        .inner_join(recipes::table)
+        .filter(recipe_processes::id.eq_any(process_ids))
+        .order(recipe_processes::identifier.asc())
+        .select((recipe_processes::id, recipe_processes::identifier, recipes::name, recipes::agent_id))
+        .load::<(Uuid, String, String, Uuid)>(conn)?;
+
+    let flow_ids: Vec<Uuid> = process_flows.iter().map(|(flow_id, _)| *flow_id).collect();
+    let executed_flows: Vec<Option<Uuid>> = process_executions::table
+        .filter(process_executions::process_flow_id.eq_any(&flow_ids))
+        .select(process_executions::process_flow_id)
+        .load::<Option<Uuid>>(conn)?;
+
+    let mut executions: BTreeMap<Uuid, usize> = BTreeMap::new();
+    for flow_id in executed_flows.into_iter().flatten() {
+        if let Some((_, process_id)) = process_flows.iter().find(|(id, _)| *id == flow_id) {
+            *executions.entry(*process_id).or_default() += 1;
+        }
+    }
+
+    let mut res = Vec::new();
+    for (process_id, identifier, recipe_name, agent_id) in processes {
+        res.push(
+            TemplateBlocker::new(
+                BlockerKind::RecipeProcess,
+                process_id,
+                format!("Process {} of recipe {}", identifier, recipe_name),
+            )
+            .of_agent(agent_id),
+        );
+        if let Some(count) = executions.get(&process_id) {
+            res.push(
+                TemplateBlocker::new(
+                    BlockerKind::ProcessExecution,
+                    process_id,
+                    format!("{} executions of process {}", count, identifier),
+                )
+                .of_agent(agent_id),
+            );
+        }
+    }
+
+    Ok(res)
+}
+
+fn check_process_blockers(
+    context: &Context,
+    conn: &mut PgConnection,
+    tree: &TemplateTree,
+    cascade: Option<bool>,
+) -> FieldResult<()> {
+    let blockers = process_blockers(conn, tree)?;
+    if !blockers.is_empty() && !cascade.unwrap_or(false) {
+        return Err(blocked_error(&visible_blockers(context, blockers)));
+    }
+    check_cascade_permission(context, &blockers)
+}
+
+/// Cascading reaches into the processes of every agent using the item, which
+/// takes a role allowing design in each of them
+fn check_cascade_permission(context: &Context, blockers: &[TemplateBlocker]) -> FieldResult<()> {
+    let agent_ids: HashSet<Uuid> = blockers.iter().filter_map(|blocker| blocker.agent_id).collect();
+    for agent_id in agent_ids {
+        context.check_permission(agent_id, Permission::Design)?;
+    }
+    Ok(())
+}
+
+fn visible_blockers(context: &Context, blockers: Vec<TemplateBlocker>) -> Vec<TemplateBlocker> {
+    blockers
+        .into_iter()
+        .map(|blocker| match blocker.agent_id {
+            Some(agent_id) if !context.is_member(agent_id) => blocker.redacted(),
+            _ => blocker,
+        })
+        .collect()
+}
+
+/// Lists every blocker in the error extensions, so clients can show what to resolve
+fn blocked_error(blockers: &[TemplateBlocker]) -> FieldError {
+    let list: Vec<Value> = blockers
+        .iter()
+        .map(|blocker| {
+            let mut object = Object::with_capacity(3);
+            let kind: InputValue = blocker.kind.to_input_value();
+            object.add_field("kind", Value::scalar(kind.as_string_value().unwrap_or_default().to_string()));
+            object.add_field("id", Value::scalar(blocker.id.to_string()));
+            object.add_field("description", Value::scalar(blocker.description.clone()));
+            Value::object(object)
+        })
+        .collect();
+
+    let mut extensions = Object::with_capacity(2);
+    extensions.add_field("code", Value::scalar("IN_USE"));
+    extensions.add_field("blockers", Value::list(list));
+
+    FieldError::new(
+        "Template item is still referenced, pass cascade to proceed",
+        Value::object(extensions),
+    )
+}
+
+/// Older versions stay as the processes created from them were instantiated,
+/// their items change through a new version of the template
+fn check_latest_version(conn: &mut PgConnection, recipe_template_id: Uuid) -> FieldResult<()> {
+    let recipe_template: RecipeTemplate = recipe_templates::table
+        .filter(recipe_templates::id.eq(recipe_template_id))
+        .first::<RecipeTemplate>(conn)?;
+
+    match recipe_template.overriden_by {
+        Some(overriden_by) => {
+            let error_message = format!(
+                "Version {} of {} was overridden by {}",
+                recipe_template.version, recipe_template.identifier, overriden_by
+            );
+            Err(FieldError::new(
+                "Only the latest version of a template can be edited",
+                graphql_value!({ "code": error_message }),
+            ))
+        }
+        None => Ok(()),
+    }
+}
+
+fn not_found(item: TemplateItem, id: Uuid) -> FieldError {
+    let error_message = format!("{:?} {} not found", item, id);
+    FieldError::new("Template item not found", graphql_value!({ "code": error_message }))
+}
@@ -1 +1,4 @@
-pub mod template;
\ No newline at end of file
+pub mod lifecycle;
+pub mod option_set;
+pub mod rule;
+pub mod template;
@@ -0,0 +1,331 @@
+use bigdecimal::{BigDecimal, Zero};
+use diesel::prelude::*;
+use juniper::{graphql_value, FieldError, FieldResult};
+use uuid::Uuid;
+
+use crate::{
+    common::resource_specification::ResourceType,
+    db::schema::{
+        economic_resources, locations, option_set_values, option_sets, recipe_flow_template_data_fields,
+        recipe_process_flow_data_fields, recipe_process_flows, recipe_processes, recipes, resource_specifications,
+    },
+    graphql::context::Context,
+    recipe::process::data_field::RecipeFlowDataField,
+    templates::{
+        option_set::{
+            FieldOption, FieldOptions, NewOptionSet, NewOptionSetValue, OptionSet, OptionSetResponse, OptionSetValue,
+            OptionSetValueInput, OptionSource,
+        },
+        recipe_flow_template_data_field::{FieldType, RecipeFlowTemplateDataField},
+    },
+};
+
+/** Queries */
+pub fn get_option_sets(context: &Context) -> FieldResult<Vec<OptionSetResponse>> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let option_sets: Vec<OptionSet> = option_sets::table
+        .order(option_sets::name.asc())
+        .load::<OptionSet>(conn)?;
+
+    let mut res = Vec::new();
+    for option_set in option_sets {
+        res.push(option_set_response(conn, option_set)?);
+    }
+
+    Ok(res)
+}
+
+/// Concrete choices of the Select fields of a process flow, dynamic sets are
+/// resolved for the agent, the owner of the recipe when none is given
+pub fn get_field_options(
+    context: &Context,
+    process_flow_id: Uuid,
+    agent_id: Option<Uuid>,
+) -> FieldResult<Vec<FieldOptions>> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let data_fields: Vec<RecipeFlowDataField> = recipe_process_flow_data_fields::table
+        .filter(recipe_process_flow_data_fields::recipe_process_flow_id.eq(process_flow_id))
+        .filter(recipe_process_flow_data_fields::option_set_id.is_not_null())
+        .load::<RecipeFlowDataField>(conn)?;
+
+    let agent_id = match agent_id {
+        Some(agent_id) => agent_id,
+        None => recipe_agent_of_flow(conn, process_flow_id)?,
+    };
+
+    let mut res = Vec::new();
+    for data_field in data_fields {
+        let Some(option_set_id) = data_field.option_set_id else {
+            continue;
+        };
+        let option_set = option_set_by_id(conn, option_set_id)?;
+        res.push(FieldOptions {
+            data_field_id: data_field.id,
+            field_identifier: data_field.field_identifier,
+            option_set_id,
+            options: resolve_options(conn, &option_set, agent_id)?,
+        });
+    }
+
+    Ok(res)
+}
+
+/*** Mutations */
+/// Static sets list their values, dynamic sets are resolved when the field is filled in
+pub fn create_option_set(
+    context: &Context,
+    name: String,
+    source: OptionSource,
+    resource_type: Option<ResourceType>,
+    values: Option<Vec<OptionSetValueInput>>,
+) -> FieldResult<OptionSetResponse> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let values = values.unwrap_or_default();
+    if source == OptionSource::Static && values.is_empty() {
+        return Err(option_set_error("A static option set needs at least one value".to_string()));
+    }
+    if source != OptionSource::Static && !values.is_empty() {
+        return Err(option_set_error(format!("{:?} option sets are resolved, they can't list values", source)));
+    }
+    if source != OptionSource::ResourceSpecifications && resource_type.is_some() {
+        return Err(option_set_error("Only resource specification option sets filter by resource type".to_string()));
+    }
+
+    conn.transaction::<_, FieldError, _>(|conn| {
+        let new_option_set = NewOptionSet::new(&name, &source, resource_type.as_ref());
+        let option_set: OptionSet = diesel::insert_into(option_sets::table)
+            .values(new_option_set)
+            .get_result(conn)?;
+
+        let mut res = OptionSetResponse::new(option_set.clone());
+        for (position, value) in values.iter().enumerate() {
+            let position = position as i32;
+            let label = value.label.as_deref().unwrap_or(&value.value);
+            let new_value = NewOptionSetValue::new(&option_set.id, value.value.trim(), label, &position);
+            let inserted: OptionSetValue = diesel::insert_into(option_set_values::table)
+                .values(new_value)
+                .get_result(conn)
+                .map_err(|_| option_set_error(format!("Value {} is listed twice", value.value.trim())))?;
+            res.add_value(inserted);
+        }
+
+        Ok(res)
+    })
+}
+
+/// Processes created afterwards copy the set, existing processes keep theirs
+pub fn set_template_field_option_set(
+    context: &Context,
+    data_field_id: Uuid,
+    option_set_id: Option<Uuid>,
+) -> FieldResult<RecipeFlowTemplateDataField> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let data_field: RecipeFlowTemplateDataField = recipe_flow_template_data_fields::table
+        .filter(recipe_flow_template_data_fields::id.eq(data_field_id))
+        .filter(recipe_flow_template_data_fields::deleted_at.is_null())
+        .first::<RecipeFlowTemplateDataField>(conn)
+        .optional()?
+        .ok_or_else(|| option_set_error(format!("Data field {} not found", data_field_id)))?;
+
+    check_select_field(conn, &data_field.field_identifier, &data_field.field_type, option_set_id)?;
+
+    let updated: RecipeFlowTemplateDataField = diesel::update(recipe_flow_template_data_fields::table)
+        .filter(recipe_flow_template_data_fields::id.eq(data_field_id))
+        .set(recipe_flow_template_data_fields::option_set_id.eq(option_set_id))
+        .get_result(conn)?;
+
+    Ok(updated)
+}
+
+/// Overrides, or clears, the set the process field copied from its template
+pub fn set_process_field_option_set(
+    context: &Context,
+    data_field_id: Uuid,
+    option_set_id: Option<Uuid>,
+) -> FieldResult<RecipeFlowDataField> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let data_field: RecipeFlowDataField = recipe_process_flow_data_fields::table
+        .filter(recipe_process_flow_data_fields::id.eq(data_field_id))
+        .first::<RecipeFlowDataField>(conn)
+        .optional()?
+        .ok_or_else(|| option_set_error(format!("Data field {} not found", data_field_id)))?;
+
+    check_select_field(conn, &data_field.field_identifier, &data_field.field_type, option_set_id)?;
+
+    let updated: RecipeFlowDataField = diesel::update(recipe_process_flow_data_fields::table)
+        .filter(recipe_process_flow_data_fields::id.eq(data_field_id))
+        .set(recipe_process_flow_data_fields::option_set_id.eq(option_set_id))
+        .get_result(conn)?;
+
+    Ok(updated)
+}
+
+/// Choices of the set for the agent, ids are the values of dynamic sets
+pub fn resolve_options(conn: &mut PgConnection, option_set: &OptionSet, agent_id: Uuid) -> FieldResult<Vec<FieldOption>> {
+    let options = match option_set.source {
+        OptionSource::Static => option_set_values::table
+            .filter(option_set_values::option_set_id.eq(option_set.id))
+            .order(option_set_values::position.asc())
+            .select((option_set_values::value, option_set_values::label))
+            .load::<(String, String)>(conn)?
+            .into_iter()
+            .map(|(value, label)| FieldOption { value, label })
+            .collect(),
+        OptionSource::AgentLocations => locations::table
+            .filter(locations::agent_id.eq(agent_id))
+            .order(locations::name.asc())
+            .select((locations::id, locations::name))
+            .load::<(Uuid, String)>(conn)?
+            .into_iter()
+            .map(|(id, name)| FieldOption { value: id.to_string(), label: name })
+            .collect(),
+        OptionSource::ResourceSpecifications => {
+            let mut query = resource_specifications::table
+                .filter(resource_specifications::agent_id.eq(agent_id))
+                .into_boxed();
+            if let Some(resource_type) = &option_set.resource_type {
+                query = query.filter(resource_specifications::resource_type.eq(resource_type));
+            }
+            query
+                .order(resource_specifications::name.asc())
+                .select((resource_specifications::id, resource_specifications::name))
+                .load::<(Uuid, String)>(conn)?
+                .into_iter()
+                .map(|(id, name)| FieldOption { value: id.to_string(), label: name })
+                .collect()
+        }
+        // Resources without a custodian are held by the owner of their specification
+        OptionSource::LotsOnHand => economic_resources::table
+            .inner_join(resource_specifications::table)
+            .filter(economic_resources::on_hand_quantity.gt(BigDecimal::zero()))
+            .filter(economic_resources::lot.is_not_null())
+            .filter(
+                economic_resources::custodian_id.eq(agent_id).or(economic_resources::custodian_id
+                    .is_null()
+                    .and(resource_specifications::agent_id.eq(agent_id))),
+            )
+            .order((economic_resources::lot.asc(), economic_resources::name.asc()))
+            .select((economic_resources::id, economic_resources::lot, economic_resources::name))
+            .load::<(Uuid, Option<String>, String)>(conn)?
+            .into_iter()
+            .map(|(id, lot, name)| FieldOption {
+                value: id.to_string(),
+                label: format!("{} ({})", lot.unwrap_or_default(), name),
+            })
+            .collect(),
+    };
+
+    Ok(options)
+}
+
+/// Ids are compared regardless of case
+pub fn is_option(options: &[FieldOption], value: &str) -> bool {
+    let value = value.trim();
+    options.iter().any(|option| match (Uuid::parse_str(&option.value), Uuid::parse_str(value)) {
+        (Ok(option), Ok(value)) => option == value,
+        _ => option.value == value,
+    })
+}
+
+/// Agent owning the recipe the flow belongs to
+pub fn recipe_agent_of_flow(conn: &mut PgConnection, process_flow_id: Uuid) -> FieldResult<Uuid> {
+    recipe_process_flows::table
+        .inner_join(recipe_processes::table.inner_join(recipes::table))
+        .filter(recipe_process_flows::id.eq(process_flow_id))
+        .select(recipes::agent_id)
+        .first::<Uuid>(conn)
+        .optional()?
+        .ok_or_else(|| option_set_error(format!("Process flow {} not found", process_flow_id)))
+}
+
+pub fn option_set_by_id(conn: &mut PgConnection, option_set_id: Uuid) -> FieldResult<OptionSet> {
+    option_sets::table
+        .filter(option_sets::id.eq(option_set_id))
+        .first::<OptionSet>(conn)
+        .optional()?
+        .ok_or_else(|| option_set_error(format!("Option set {} not found", option_set_id)))
+}
+
+fn option_set_response(conn: &mut PgConnection, option_set: OptionSet) -> FieldResult<OptionSetResponse> {
+    let values: Vec<OptionSetValue> = option_set_values::table
+        .filter(option_set_values::option_set_id.eq(option_set.id))
+        .order(option_set_values::position.asc())
+        .load::<OptionSetValue>(conn)?;
+
+    let mut res = OptionSetResponse::new(option_set);
+    for value in values {
+        res.add_value(value);
+    }
+
+    Ok(res)
+}
+
+fn check_select_field(
+    conn: &mut PgConnection,
+    field_identifier: &str,
+    field_type: &FieldType,
+    option_set_id: Option<Uuid>,
+) -> FieldResult<()> {
+    let Some(option_set_id) = option_set_id else {
+        return Ok(());
+    };
+    if *field_type != FieldType::Select {
+        return Err(option_set_error(format!(
+            "{} is a {:?} field, only Select fields take options",
+            field_identifier, field_type
+        )));
+    }
+    option_set_by_id(conn, option_set_id)?;
+
+    Ok(())
+}
+
+fn option_set_error(error_message: String) -> FieldError {
+    FieldError::new("Invalid option set", graphql_value!({ "code": error_message }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn option(value: &str) -> FieldOption {
+        FieldOption {
+            value: value.to_string(),
+            label: value.to_uppercase(),
+        }
+    }
+
+    #[test]
+    fn matches_values_and_ids() {
+        let id = Uuid::new_v4();
+        let options = [option("organic"), option(&id.to_string())];
+
+        assert!(is_option(&options, " organic "));
+        assert!(!is_option(&options, "ORGANIC"));
+        assert!(!is_option(&options, "conventional"));
+        // Ids match whatever their case or form
+        assert!(is_option(&options, &id.to_string().to_uppercase()));
+        assert!(is_option(&options, &id.to_simple().to_string()));
+        assert!(!is_option(&[], "organic"));
+    }
+}
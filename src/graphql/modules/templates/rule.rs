@@ -0,0 +1,148 @@
+use diesel::prelude::*;
+use juniper::{graphql_value, FieldError, FieldResult};
+use uuid::Uuid;
+
+use crate::{
+    db::schema::{recipe_flow_template_data_field_rules, recipe_flow_template_data_fields, recipe_flow_templates},
+    graphql::context::Context,
+    recipe::process::validation::FieldViolation,
+    templates::{
+        data_field_rule::{DataFieldRule, DataFieldRuleArg, NewDataFieldRule, RuleKind},
+        recipe_flow_template_data_field::RecipeFlowTemplateDataField,
+        rule_expression::{self, VARIABLES},
+    },
+};
+
+/** Queries */
+/// Rules of the template that no longer hold up, for instance after a field they read was deleted
+pub fn validate_template_rules(context: &Context, recipe_template_id: Uuid) -> FieldResult<Vec<FieldViolation>> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let flow_ids: Vec<Uuid> = recipe_flow_templates::table
+        .filter(recipe_flow_templates::recipe_template_id.eq(recipe_template_id))
+        .filter(recipe_flow_templates::deleted_at.is_null())
+        .select(recipe_flow_templates::id)
+        .load::<Uuid>(conn)?;
+
+    let mut violations = Vec::new();
+    for flow_id in flow_ids {
+        let data_fields = live_data_fields(conn, flow_id)?;
+        let flow_fields: Vec<String> = data_fields.iter().map(|f| f.field_identifier.clone()).collect();
+
+        let ids: Vec<Uuid> = data_fields.iter().map(|f| f.id).collect();
+        for rule in rules_of_template_fields(conn, &ids)? {
+            let Some(data_field) = data_fields.iter().find(|f| f.id == rule.data_field_id) else {
+                continue;
+            };
+            if let Err(problem) = check_rule(&data_field.field_identifier, &flow_fields, &rule.kind, &rule.expression) {
+                violations.push(FieldViolation::new(data_field.id, &data_field.field_identifier, problem));
+            }
+        }
+    }
+
+    Ok(violations)
+}
+
+/*** Mutations */
+/// Replaces the rules of a template field, processes created from the
+/// template read the rules of their template fields when executed
+pub fn set_data_field_rules(
+    context: &Context,
+    data_field_id: Uuid,
+    rules: Vec<DataFieldRuleArg>,
+) -> FieldResult<Vec<DataFieldRule>> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    conn.transaction::<_, FieldError, _>(|conn| {
+        let data_field: RecipeFlowTemplateDataField = recipe_flow_template_data_fields::table
+            .filter(recipe_flow_template_data_fields::id.eq(data_field_id))
+            .filter(recipe_flow_template_data_fields::deleted_at.is_null())
+            .first::<RecipeFlowTemplateDataField>(conn)
+            .optional()?
+            .ok_or_else(|| {
+                let error_message = format!("Data field {} not found", data_field_id);
+                FieldError::new("Invalid data field rule", graphql_value!({ "code": error_message }))
+            })?;
+
+        let flow_fields: Vec<String> = live_data_fields(conn, data_field.recipe_flow_template_id)?
+            .into_iter()
+            .map(|f| f.field_identifier)
+            .collect();
+
+        diesel::delete(recipe_flow_template_data_field_rules::table)
+            .filter(recipe_flow_template_data_field_rules::data_field_id.eq(data_field_id))
+            .execute(conn)?;
+
+        insert_data_field_rules(conn, &data_field.id, &data_field.field_identifier, &flow_fields, &rules)
+    })
+}
+
+/// Checks the rules against the fields of the flow before inserting them
+pub fn insert_data_field_rules(
+    conn: &mut PgConnection,
+    data_field_id: &Uuid,
+    field_identifier: &str,
+    flow_fields: &[String],
+    rules: &[DataFieldRuleArg],
+) -> FieldResult<Vec<DataFieldRule>> {
+    let mut inserted = Vec::new();
+
+    for rule in rules {
+        check_rule(field_identifier, flow_fields, &rule.kind, &rule.expression).map_err(|problem| {
+            let error_message = format!("{}: {}", field_identifier, problem);
+            FieldError::new("Invalid data field rule", graphql_value!({ "code": error_message }))
+        })?;
+
+        let new_rule = NewDataFieldRule::new(data_field_id, &rule.kind, rule.expression.trim(), rule.message.as_deref());
+        let rule: DataFieldRule = diesel::insert_into(recipe_flow_template_data_field_rules::table)
+            .values(new_rule)
+            .get_result(conn)?;
+        inserted.push(rule);
+    }
+
+    Ok(inserted)
+}
+
+pub fn rules_of_template_fields(conn: &mut PgConnection, data_field_ids: &[Uuid]) -> FieldResult<Vec<DataFieldRule>> {
+    let rules: Vec<DataFieldRule> = recipe_flow_template_data_field_rules::table
+        .filter(recipe_flow_template_data_field_rules::data_field_id.eq_any(data_field_ids))
+        .order(recipe_flow_template_data_field_rules::created_at.asc())
+        .load::<DataFieldRule>(conn)?;
+
+    Ok(rules)
+}
+
+/// Rules may only read fields of their own flow, and the condition of a
+/// field can't depend on the field itself
+fn check_rule(field_identifier: &str, flow_fields: &[String], kind: &RuleKind, expression: &str) -> Result<(), String> {
+    let expr = rule_expression::parse(expression).map_err(|e| format!("{} in {}", e, expression))?;
+
+    for field in expr.fields() {
+        if !flow_fields.iter().any(|f| f == field) {
+            return Err(format!("{} is not a field of the flow", field));
+        }
+        if field == field_identifier && *kind != RuleKind::Constraint {
+            return Err(format!("A {:?} rule can't depend on the field itself", kind));
+        }
+    }
+    if let Some(variable) = expr.variables().into_iter().find(|v| !VARIABLES.contains(v)) {
+        return Err(format!("Unknown variable {}, expected one of {}", variable, VARIABLES.join(", ")));
+    }
+
+    Ok(())
+}
+
+fn live_data_fields(conn: &mut PgConnection, recipe_flow_template_id: Uuid) -> FieldResult<Vec<RecipeFlowTemplateDataField>> {
+    let data_fields: Vec<RecipeFlowTemplateDataField> = recipe_flow_template_data_fields::table
+        .filter(recipe_flow_template_data_fields::recipe_flow_template_id.eq(recipe_flow_template_id))
+        .filter(recipe_flow_template_data_fields::deleted_at.is_null())
+        .load::<RecipeFlowTemplateDataField>(conn)?;
+
+    Ok(data_fields)
+}
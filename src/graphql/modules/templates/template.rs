@@ -1,11 +1,13 @@
 use crate::{
+    common::economic_resource::Decimal,
     db::schema::{
         map_templates, recipe_flow_template_data_fields, recipe_flow_template_group_data_fields,
         recipe_flow_templates, recipe_template_blacklists, recipe_templates,
         recipe_templates_access,
     },
-    graphql::context::Context,
+    graphql::{context::Context, modules::templates::rule},
     templates::{
+        data_field_rule::DataFieldRuleArg,
         map_template::{MapTemplate, MapTemplateResponse, NewMapTemplate, TemplateType},
         recipe_flow_template::{
             ActionType, EventType, NewRecipeFlowTemplate, RecipeFlowTemplate,
@@ -18,9 +20,9 @@ use crate::{
         recipe_flow_template_group_data_fields::{
             FieldGroupClass, NewRecipeFlowTemplateGroupDataField, RecipeFlowTemplateGroupDataField,
         },
-        recipe_template::{NewRecipeTemplate, RecipeTemplate, RecipeTemplateWithRecipeFlows},
+        recipe_template::{latest_version, NewRecipeTemplate, RecipeTemplate, RecipeTemplateWithRecipeFlows},
         recipe_template_access::{NewRecipeTemplateAccess, RecipeTemplateAccess},
-        recipe_template_blacklist::{NewRecipeTemplateBlacklist, RecipeTemplateBlacklist},
+        recipe_template_blacklist::{self, NewRecipeTemplateBlacklist, RecipeTemplateBlacklist},
     },
 };
 use diesel::prelude::*;
@@ -30,26 +32,36 @@ use uuid::Uuid;
 
 #[derive(juniper::GraphQLInputObject)]
 pub struct RecipeFlowTemplateGroup {
-    name: String,
-    class: FieldGroupClass,
-    fields: Vec<String>,
+    pub name: String,
+    pub class: FieldGroupClass,
+    pub fields: Vec<String>,
 }
 
 #[derive(juniper::GraphQLInputObject)]
 pub struct RecipeFlowTemplateArg {
-    event_type: EventType,
-    role_type: RoleType,
-    action: ActionType,
-    data_fields: Vec<RecipeFlowTemplateDataFieldArg>,
-    groups: Vec<RecipeFlowTemplateGroup>,
-    identifier: String,
-    interactions: Option<i32>,
+    pub event_type: EventType,
+    pub role_type: RoleType,
+    pub action: ActionType,
+    pub data_fields: Vec<RecipeFlowTemplateDataFieldArg>,
+    pub groups: Vec<RecipeFlowTemplateGroup>,
+    pub identifier: String,
+    pub interactions: Option<i32>,
+}
+
+/// What a version of a recipe template is made of, given when creating and editing templates
+pub struct RecipeTemplateContent {
+    pub name: String,
+    pub recipe_flow_template_args: Vec<RecipeFlowTemplateArg>,
+    pub commitment: Option<ActionType>,
+    pub fulfills: Option<String>,
+    pub trigger: Option<ActionType>,
+    pub created_by_agent: Option<Uuid>,
 }
 
 #[derive(juniper::GraphQLInputObject)]
 pub struct FieldInheritance {
-    identifier: String,
-    field: String,
+    pub identifier: String,
+    pub field: String,
 }
 
 #[derive(juniper::GraphQLInputObject)]
@@ -63,6 +75,11 @@ pub struct RecipeFlowTemplateDataFieldArg {
     pub flow_through: Option<FlowThrough>,
     pub inherits: Option<FieldInheritance>,
     pub accept_default: bool,
+    pub option_set_id: Option<Uuid>,
+    /// Bounds of a Number field, both inclusive
+    pub min_value: Option<Decimal>,
+    pub max_value: Option<Decimal>,
+    pub rules: Option<Vec<DataFieldRuleArg>>,
 }
 
 #[derive(juniper::GraphQLInputObject, Debug)]
@@ -91,13 +108,15 @@ fn get_recipe_flow_template_data_fields(
                 recipe_flow_template_data_fields::recipe_flow_template_id
                     .eq(recipe_flow_template.id),
             )
+            .filter(recipe_flow_template_data_fields::deleted_at.is_null())
             .load::<RecipeFlowTemplateDataField>(conn)?;
 
     for rftdf in &recipe_flow_template_data_fields {
         // If possible, adjust `try_into()` to accept a reference instead
-        let recipe_flow_template_data_field_input: RecipeFlowTemplateDataFieldInput = rftdf
+        let mut recipe_flow_template_data_field_input: RecipeFlowTemplateDataFieldInput = rftdf
             .try_into() // Ensure this conversion works with a reference
             .map_err(|e| FieldError::new(e, juniper::Value::null()))?;
+        recipe_flow_template_data_field_input.rules = rule::rules_of_template_fields(conn, &[rftdf.id])?;
 
         recipe_flow_remplate_data_fields.add_data_field(recipe_flow_template_data_field_input);
 
@@ -126,11 +145,12 @@ fn get_recipe_template_with_flows(
     //get recipe flow templates by recipe template id
     let recipe_flow_templates: Vec<RecipeFlowTemplate> = recipe_flow_templates::table
         .filter(recipe_flow_templates::recipe_template_id.eq(recipe_template.id))
+        .filter(recipe_flow_templates::deleted_at.is_null())
         .load::<RecipeFlowTemplate>(conn)?;
 
     for rft in recipe_flow_templates {
         //create new instance of RecipeFlowTemplateWithDataFields
-        let recipe_flow_remplate_data_fields = get_recipe_flow_template_data_fields(&context, rft)?;
+        let recipe_flow_remplate_data_fields = get_recipe_flow_template_data_fields(context, rft)?;
 
         recipe_template_with_recipe_flows.add_recipe_flow(recipe_flow_remplate_data_fields)
     }
@@ -144,23 +164,27 @@ pub fn get_map_templates(context: &Context) -> FieldResult<Vec<MapTemplateRespon
         .get()
         .expect("Failed to get DB connection from pool");
 
-    let map_templates: Vec<MapTemplate> = map_templates::table.load::<MapTemplate>(conn)?;
+    let map_templates: Vec<MapTemplate> = map_templates::table
+        .filter(map_templates::deleted_at.is_null())
+        .load::<MapTemplate>(conn)?;
 
     let mut res: Vec<MapTemplateResponse> = Vec::new();
 
     for map_template in map_templates {
         let blacklists: Vec<RecipeTemplateBlacklist> =
-            get_blacklists_by_map_template(&context, map_template.id)?;
+            get_blacklists_by_map_template(context, map_template.id)?;
 
         let mut new_map_template = MapTemplateResponse::new(map_template, blacklists);
 
         let templates: Vec<RecipeTemplate> = recipe_templates::table
             .filter(recipe_templates::map_template_id.eq(new_map_template.map.id))
+            .filter(recipe_templates::overriden_by.is_null())
+            .filter(recipe_templates::deleted_at.is_null())
             .load::<RecipeTemplate>(conn)?;
 
         for template in templates {
             let recipe_template_with_recipe_flows =
-                get_recipe_template_with_flows(&context, template)?;
+                get_recipe_template_with_flows(context, template)?;
             new_map_template.add_template(recipe_template_with_recipe_flows);
         }
 
@@ -179,19 +203,22 @@ pub fn get_map_template_by_id(context: &Context, map_id: Uuid) -> FieldResult<Ma
 
     let map_template: MapTemplate = map_templates::table
         .filter(map_templates::id.eq(map_id))
+        .filter(map_templates::deleted_at.is_null())
         .first::<MapTemplate>(conn)?;
 
     let blacklists: Vec<RecipeTemplateBlacklist> =
-        get_blacklists_by_map_template(&context, map_template.id)?;
+        get_blacklists_by_map_template(context, map_template.id)?;
 
     let mut new_map_template = MapTemplateResponse::new(map_template, blacklists);
 
     let templates: Vec<RecipeTemplate> = recipe_templates::table
         .filter(recipe_templates::map_template_id.eq(new_map_template.map.id))
+        .filter(recipe_templates::overriden_by.is_null())
+        .filter(recipe_templates::deleted_at.is_null())
         .load::<RecipeTemplate>(conn)?;
 
     for template in templates {
-        let recipe_template_with_recipe_flows = get_recipe_template_with_flows(&context, template)?;
+        let recipe_template_with_recipe_flows = get_recipe_template_with_flows(context, template)?;
         new_map_template.add_template(recipe_template_with_recipe_flows);
     }
 
@@ -211,11 +238,154 @@ pub fn get_template_by_id(
         .filter(recipe_templates::id.eq(template_id))
         .first::<RecipeTemplate>(conn)?;
 
-    let res = get_recipe_template_with_flows(&context, recipe)?;
+    let res = get_recipe_template_with_flows(context, recipe)?;
 
     Ok(res)
 }
 
+/// Latest version of a template, or the given version
+pub fn get_template_version(
+    context: &Context,
+    map_template_id: Uuid,
+    identifier: String,
+    version: Option<i32>,
+) -> FieldResult<RecipeTemplateWithRecipeFlows> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let mut query = recipe_templates::table
+        .filter(recipe_templates::map_template_id.eq(map_template_id))
+        .filter(recipe_templates::identifier.eq(&identifier))
+        .filter(recipe_templates::deleted_at.is_null())
+        .into_boxed();
+
+    query = match version {
+        Some(version) => query.filter(recipe_templates::version.eq(version)),
+        None => query.filter(recipe_templates::overriden_by.is_null()),
+    };
+
+    let recipe: RecipeTemplate = query.first::<RecipeTemplate>(conn).optional()?.ok_or_else(|| {
+        let error_message = match version {
+            Some(version) => format!("{} has no version {}", identifier, version),
+            None => format!("{} not found", identifier),
+        };
+        FieldError::new("Template version not found", graphql_value!({ "code": error_message }))
+    })?;
+
+    get_recipe_template_with_flows(context, recipe)
+}
+
+/// Every version of the template, oldest first
+pub fn get_template_versions(
+    context: &Context,
+    template_id: Uuid,
+) -> FieldResult<Vec<RecipeTemplateWithRecipeFlows>> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let recipe: RecipeTemplate = recipe_templates::table
+        .filter(recipe_templates::id.eq(template_id))
+        .first::<RecipeTemplate>(conn)?;
+
+    let versions: Vec<RecipeTemplate> = recipe_templates::table
+        .filter(recipe_templates::map_template_id.eq(recipe.map_template_id))
+        .filter(recipe_templates::identifier.eq(&recipe.identifier))
+        .order(recipe_templates::version.asc())
+        .load::<RecipeTemplate>(conn)?;
+
+    let mut res = Vec::new();
+    for version in versions {
+        res.push(get_recipe_template_with_flows(context, version)?);
+    }
+
+    Ok(res)
+}
+
+/// Latest templates of the map that may follow the template, blacklisted successors left out
+pub fn get_allowed_successors(
+    context: &Context,
+    template_id: Uuid,
+) -> FieldResult<Vec<RecipeTemplateWithRecipeFlows>> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let predecessor: RecipeTemplate = recipe_templates::table
+        .filter(recipe_templates::id.eq(template_id))
+        .first::<RecipeTemplate>(conn)?;
+
+    let forbidden: Vec<String> = recipe_template_blacklists::table
+        .inner_join(
+            recipe_templates::table
+                .on(recipe_templates::id.eq(recipe_template_blacklists::recipe_template_id)),
+        )
+        .filter(recipe_template_blacklists::map_template_id.eq(predecessor.map_template_id))
+        .filter(recipe_template_blacklists::recipe_template_predecesor_id.eq(latest_version_id(conn, &predecessor)?))
+        .filter(recipe_templates::overriden_by.is_null())
+        .select(recipe_templates::identifier)
+        .load::<String>(conn)?;
+
+    let candidates: Vec<RecipeTemplate> = recipe_templates::table
+        .filter(recipe_templates::map_template_id.eq(predecessor.map_template_id))
+        .filter(recipe_templates::identifier.ne(&predecessor.identifier))
+        .filter(recipe_templates::identifier.ne_all(forbidden))
+        .filter(recipe_templates::overriden_by.is_null())
+        .filter(recipe_templates::deleted_at.is_null())
+        .order(recipe_templates::identifier.asc())
+        .load::<RecipeTemplate>(conn)?;
+
+    let mut res = Vec::new();
+    for candidate in candidates {
+        res.push(get_recipe_template_with_flows(context, candidate)?);
+    }
+
+    Ok(res)
+}
+
+/// Blacklists are carried over to every new version, so a pair is forbidden
+/// when the latest version of the successor is blacklisted after the latest
+/// version of the predecessor
+pub fn is_blacklisted(
+    conn: &mut PgConnection,
+    successor: &RecipeTemplate,
+    predecessor: &RecipeTemplate,
+) -> FieldResult<bool> {
+    if successor.map_template_id != predecessor.map_template_id {
+        return Ok(false);
+    }
+
+    let successor_id = latest_version_id(conn, successor)?;
+    let predecessor_id = latest_version_id(conn, predecessor)?;
+    let blacklists: Vec<RecipeTemplateBlacklist> = recipe_template_blacklists::table
+        .filter(recipe_template_blacklists::map_template_id.eq(successor.map_template_id))
+        .filter(recipe_template_blacklists::recipe_template_id.eq(successor_id))
+        .load::<RecipeTemplateBlacklist>(conn)?;
+
+    Ok(recipe_template_blacklist::is_blacklisted(&blacklists, successor_id, predecessor_id))
+}
+
+fn latest_version_id_by_id(conn: &mut PgConnection, recipe_template_id: Uuid) -> FieldResult<Uuid> {
+    let recipe_template: RecipeTemplate = recipe_templates::table
+        .filter(recipe_templates::id.eq(recipe_template_id))
+        .first::<RecipeTemplate>(conn)?;
+
+    latest_version_id(conn, &recipe_template)
+}
+
+fn latest_version_id(conn: &mut PgConnection, recipe_template: &RecipeTemplate) -> FieldResult<Uuid> {
+    let versions: Vec<RecipeTemplate> = recipe_templates::table
+        .filter(recipe_templates::map_template_id.eq(recipe_template.map_template_id))
+        .filter(recipe_templates::identifier.eq(&recipe_template.identifier))
+        .load::<RecipeTemplate>(conn)?;
+
+    Ok(latest_version(&versions, recipe_template.id).map_or(recipe_template.id, |v| v.id))
+}
+
 pub fn get_templates_access_by_agent(
     context: &Context,
     agent_id: Uuid,
@@ -225,15 +395,20 @@ pub fn get_templates_access_by_agent(
         .get()
         .expect("Failed to get DB connection from pool");
 
+    // Access is copied to every new version, so older versions are left out
     let accesses: Vec<RecipeTemplateAccess> = recipe_templates_access::table
+        .inner_join(recipe_templates::table)
         .filter(recipe_templates_access::agent_id.eq(agent_id))
+        .filter(recipe_templates::overriden_by.is_null())
+        .filter(recipe_templates::deleted_at.is_null())
+        .select(recipe_templates_access::all_columns)
         .load::<RecipeTemplateAccess>(conn)?;
 
     let mut res: Vec<RecipeTemplateWithRecipeFlows> = Vec::new();
 
     for a in accesses {
         let template_id = a.recipe_template_id;
-        let mut recipe = get_template_by_id(context, template_id)?;
+        let recipe = get_template_by_id(context, template_id)?;
         res.push(recipe)
     }
 
@@ -249,10 +424,18 @@ fn get_blacklists_by_map_template(
         .get()
         .expect("Failed to get DB connection from pool");
 
-    let blacklists: Vec<RecipeTemplateBlacklist> = recipe_template_blacklists::table
+    let mut blacklists: Vec<RecipeTemplateBlacklist> = recipe_template_blacklists::table
         .filter(recipe_template_blacklists::map_template_id.eq(map_template_id))
         .load::<RecipeTemplateBlacklist>(conn)?;
 
+    // Rows of older versions are kept for history, only the latest ones apply
+    let latest: Vec<Uuid> = recipe_templates::table
+        .filter(recipe_templates::map_template_id.eq(map_template_id))
+        .filter(recipe_templates::overriden_by.is_null())
+        .select(recipe_templates::id)
+        .load::<Uuid>(conn)?;
+    blacklists.retain(|b| latest.contains(&b.recipe_template_id) && latest.contains(&b.recipe_template_predecesor_id));
+
     Ok(blacklists)
 }
 
@@ -260,6 +443,7 @@ pub fn create_map_template(
     context: &Context,
     name: String,
     type_: TemplateType,
+    created_by_agent: Option<Uuid>,
 ) -> FieldResult<MapTemplate> {
     let conn = &mut context
         .pool
@@ -267,7 +451,8 @@ pub fn create_map_template(
         .expect("Failed to get DB connection from pool");
 
     conn.transaction::<_, FieldError, _>(|conn| {
-        let new_map_template = NewMapTemplate::new(&name, &type_);
+        let mut new_map_template = NewMapTemplate::new(&name, &type_);
+        new_map_template.created_by_agent = created_by_agent.as_ref();
 
         let inserted_map_template: MapTemplate = diesel::insert_into(map_templates::table)
             .values(&new_map_template)
@@ -282,12 +467,17 @@ pub fn create_recipe_template(
     context: &Context,
     map_template_id: Uuid,
     identifier: String,
-    name: String,
-    recipe_flow_template_args: Vec<RecipeFlowTemplateArg>,
-    commitment: Option<ActionType>,
-    fulfills: Option<String>,
-    trigger: Option<ActionType>,
+    content: RecipeTemplateContent,
 ) -> FieldResult<RecipeTemplateWithRecipeFlows> {
+    let RecipeTemplateContent {
+        name,
+        recipe_flow_template_args,
+        commitment,
+        fulfills,
+        trigger,
+        created_by_agent,
+    } = content;
+    let created_by = context.user()?.id;
     let conn = &mut context
         .pool
         .get()
@@ -296,20 +486,9 @@ pub fn create_recipe_template(
     // Start a transaction
     conn.transaction::<_, FieldError, _>(|conn| {
         // Create the new recipe template
-        let fulfills_id = if let Some(process_identifier) = fulfills {
-            // Attempt to get the recipe by identifier
-            let recipe: RecipeTemplate = recipe_templates::table
-                .filter(recipe_templates::identifier.eq(process_identifier))
-                .first::<RecipeTemplate>(conn)?;
-
-            // Return the recipe's id wrapped in Some
-            Some(recipe.id)
-        } else {
-            // If fulfills is None, return None
-            None
-        };
+        let fulfills_id = fulfilled_template_id(conn, map_template_id, fulfills)?;
 
-        let new_template = NewRecipeTemplate::new(
+        let mut new_template = NewRecipeTemplate::new(
             &map_template_id,
             &identifier,
             &name,
@@ -317,129 +496,291 @@ pub fn create_recipe_template(
             fulfills_id.as_ref(),
             trigger.as_ref(),
         );
+        new_template.created_by_agent = created_by_agent.as_ref();
+        new_template.created_by = Some(&created_by);
 
-        let inserted_template: RecipeTemplate = diesel::insert_into(recipe_templates::table)
-            .values(&new_template)
-            .get_result(conn)
-            .map_err(|e| FieldError::new(e, juniper::Value::null()))?; // Map diesel::result::Error to FieldError
-
-        // Initialize the result struct
-        let mut res: RecipeTemplateWithRecipeFlows =
-            RecipeTemplateWithRecipeFlows::new(&inserted_template);
-
-        // Iterate over each `RecipeFlowTemplateArg`
-        for r in recipe_flow_template_args {
-            // Create and insert a new recipe flow template
-            let new_recipe_flow_template = NewRecipeFlowTemplate::new(
-                &inserted_template.id,
-                &r.event_type,
-                &r.role_type,
-                &r.action,
-                &r.identifier,
-                r.interactions.as_ref(),
+        insert_recipe_template(conn, &new_template, recipe_flow_template_args)
+    })
+}
+
+/// Editing a template creates its next version, the edited version stays
+/// untouched for the processes created from it
+pub fn update_recipe_template(
+    context: &Context,
+    recipe_template_id: Uuid,
+    content: RecipeTemplateContent,
+) -> FieldResult<RecipeTemplateWithRecipeFlows> {
+    let RecipeTemplateContent {
+        name,
+        recipe_flow_template_args,
+        commitment,
+        fulfills,
+        trigger,
+        created_by_agent,
+    } = content;
+    let created_by = context.user()?.id;
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    conn.transaction::<_, FieldError, _>(|conn| {
+        // Locked so concurrent edits can't both branch from the same version
+        let previous: RecipeTemplate = recipe_templates::table
+            .filter(recipe_templates::id.eq(recipe_template_id))
+            .for_update()
+            .first::<RecipeTemplate>(conn)?;
+
+        if let Some(overriden_by) = previous.overriden_by {
+            let error_message = format!(
+                "Version {} of {} was overridden by {}",
+                previous.version, previous.identifier, overriden_by
             );
+            return Err(FieldError::new(
+                "Only the latest version of a template can be edited",
+                graphql_value!({ "code": error_message }),
+            ));
+        }
 
-            let inserted_recipe_flow_template: RecipeFlowTemplate =
-                diesel::insert_into(recipe_flow_templates::table)
-                    .values(&new_recipe_flow_template)
-                    .get_result::<RecipeFlowTemplate>(conn)?;
+        if previous.deleted_at.is_some() {
+            let error_message = format!("Template {} was deleted", previous.identifier);
+            return Err(FieldError::new(
+                "Deleted templates can't be edited",
+                graphql_value!({ "code": error_message }),
+            ));
+        }
+
+        let fulfills_id = fulfilled_template_id(conn, previous.map_template_id, fulfills)?;
+        let version = previous.version + 1;
+
+        let mut new_template = NewRecipeTemplate::new(
+            &previous.map_template_id,
+            &previous.identifier,
+            &name,
+            commitment.as_ref(),
+            fulfills_id.as_ref(),
+            trigger.as_ref(),
+        );
+        new_template.version = &version;
+        new_template.created_by_agent = created_by_agent.as_ref();
+        new_template.created_by = Some(&created_by);
 
-            // Initialize `RecipeFlowTemplateWithDataFields` struct
-            let mut recipe_flow_res =
-                RecipeFlowTemplateWithDataFields::new(&inserted_recipe_flow_template);
+        let inserted = insert_recipe_template(conn, &new_template, recipe_flow_template_args)?;
 
-            let mut groups: Vec<(Uuid, Vec<String>)> = Vec::new();
+        diesel::update(recipe_templates::table)
+            .filter(recipe_templates::id.eq(previous.id))
+            .set(recipe_templates::overriden_by.eq(inserted.id))
+            .execute(conn)?;
 
-            for group in r.groups {
-                let new_group = NewRecipeFlowTemplateGroupDataField::new(&group.name, &group.class);
+        // Latest versions fulfilling the edited one go on fulfilling the template,
+        // older versions stay as they were
+        diesel::update(recipe_templates::table)
+            .filter(recipe_templates::fulfills.eq(previous.id))
+            .filter(recipe_templates::overriden_by.is_null())
+            .set(recipe_templates::fulfills.eq(inserted.id))
+            .execute(conn)?;
 
-                let inserted_group: RecipeFlowTemplateGroupDataField =
-                    diesel::insert_into(recipe_flow_template_group_data_fields::table)
-                        .values(new_group)
-                        .get_result::<RecipeFlowTemplateGroupDataField>(conn)?;
+        copy_template_relations(conn, previous.id, inserted.id)?;
 
-                let separated_fields: Vec<String> = group
-                    .fields
-                    .iter()
-                    .flat_map(|field| field.split(", ").map(String::from).collect::<Vec<String>>()) // Split by ", " and collect into a Vec<String>
-                    .collect();
+        Ok(inserted)
+    })
+}
 
-                groups.push((inserted_group.id, separated_fields));
-            }
+/// Latest version of the template with the given identifier in the map
+fn fulfilled_template_id(
+    conn: &mut PgConnection,
+    map_template_id: Uuid,
+    fulfills: Option<String>,
+) -> FieldResult<Option<Uuid>> {
+    let process_identifier = match fulfills {
+        Some(process_identifier) => process_identifier,
+        None => return Ok(None),
+    };
 
-            // Iterate over each data field and add it to the recipe flow
-            for rd in r.data_fields {
-                let group_id = groups
-                    .iter()
-                    .find(|g| g.1.contains(&rd.field_identifier))
-                    .map(|group| group.0);
-
-                let inherits: Option<Uuid> = if let Some(inherits) = rd.inherits {
-                    //search for the recipe flow template with the identifier
-                    let recipe_flow_template: RecipeFlowTemplate = recipe_flow_templates::table
-                        .filter(recipe_flow_templates::recipe_template_id.eq(&inserted_template.id))
-                        .filter(recipe_flow_templates::identifier.eq(inherits.identifier))
-                        .first::<RecipeFlowTemplate>(conn)?;
-
-                    let field: RecipeFlowTemplateDataField =
-                        recipe_flow_template_data_fields::table
-                            .filter(
-                                recipe_flow_template_data_fields::recipe_flow_template_id
-                                    .eq(recipe_flow_template.id),
-                            )
-                            .filter(
-                                recipe_flow_template_data_fields::field_identifier
-                                    .eq(inherits.field),
-                            )
-                            .first::<RecipeFlowTemplateDataField>(conn)?;
-
-                    Some(field.id)
-                } else {
-                    None
-                };
-
-                let new_recipe_flow_template_data_field = NewRecipeFlowTemplateDataField::new(
-                    &inserted_recipe_flow_template.id,
-                    group_id.as_ref(),
-                    &rd.field_identifier,
-                    &rd.field_class,
-                    &rd.field,
-                    &rd.field_type,
-                    rd.note.as_deref(),
-                    &rd.required,
-                    rd.flow_through.as_ref(),
-                    inherits.as_ref(),
-                    &rd.accept_default,
-                );
-
-                let inserted_recipe_flow_template_data_field: RecipeFlowTemplateDataField =
-                    diesel::insert_into(recipe_flow_template_data_fields::table)
-                        .values(new_recipe_flow_template_data_field)
-                        .get_result(conn)
-                        .map_err(|e| FieldError::new(e, juniper::Value::null()))?; // Map diesel::result::Error to FieldError
-
-                let recipe_flow_template_data_field_input: RecipeFlowTemplateDataFieldInput =
-                    (&inserted_recipe_flow_template_data_field)
-                        .try_into()
-                        .map_err(|e| FieldError::new(e, juniper::Value::null()))?;
-
-                // Add the data field to the recipe flow
-                recipe_flow_res.add_data_field(recipe_flow_template_data_field_input);
-
-                if let Some(group_id) = group_id {
-                    let group: RecipeFlowTemplateGroupDataField =
-                        recipe_flow_template_group_data_fields::table
-                            .filter(recipe_flow_template_group_data_fields::id.eq(group_id))
-                            .first::<RecipeFlowTemplateGroupDataField>(conn)?;
-
-                    recipe_flow_res.add_group(group);
-                }
+    let recipe: RecipeTemplate = recipe_templates::table
+        .filter(recipe_templates::map_template_id.eq(map_template_id))
+        .filter(recipe_templates::identifier.eq(process_identifier))
+        .filter(recipe_templates::overriden_by.is_null())
+        .filter(recipe_templates::deleted_at.is_null())
+        .first::<RecipeTemplate>(conn)?;
+
+    Ok(Some(recipe.id))
+}
+
+/// Agents with access to a version and its blacklists carry over to the next version
+fn copy_template_relations(conn: &mut PgConnection, previous_id: Uuid, next_id: Uuid) -> FieldResult<()> {
+    let accesses: Vec<RecipeTemplateAccess> = recipe_templates_access::table
+        .filter(recipe_templates_access::recipe_template_id.eq(previous_id))
+        .load::<RecipeTemplateAccess>(conn)?;
+
+    for access in accesses {
+        let new_access = NewRecipeTemplateAccess::new(&access.agent_id, &next_id);
+        diesel::insert_into(recipe_templates_access::table)
+            .values(&new_access)
+            .execute(conn)?;
+    }
+
+    let blacklists: Vec<RecipeTemplateBlacklist> = recipe_template_blacklists::table
+        .filter(
+            recipe_template_blacklists::recipe_template_id
+                .eq(previous_id)
+                .or(recipe_template_blacklists::recipe_template_predecesor_id.eq(previous_id)),
+        )
+        .load::<RecipeTemplateBlacklist>(conn)?;
+
+    let next = |id: Uuid| if id == previous_id { next_id } else { id };
+    for blacklist in blacklists {
+        let template_id = next(blacklist.recipe_template_id);
+        let predecessor_id = next(blacklist.recipe_template_predecesor_id);
+        let new_blacklist = NewRecipeTemplateBlacklist::new(&blacklist.map_template_id, &template_id, &predecessor_id);
+        diesel::insert_into(recipe_template_blacklists::table)
+            .values(&new_blacklist)
+            .execute(conn)?;
+    }
+
+    Ok(())
+}
+
+/// Inserts a recipe template with its flows, data fields and groups
+pub fn insert_recipe_template(
+    conn: &mut PgConnection,
+    new_template: &NewRecipeTemplate,
+    recipe_flow_template_args: Vec<RecipeFlowTemplateArg>,
+) -> FieldResult<RecipeTemplateWithRecipeFlows> {
+    let inserted_template: RecipeTemplate = diesel::insert_into(recipe_templates::table)
+        .values(new_template)
+        .get_result(conn)
+        .map_err(|e| FieldError::new(e, juniper::Value::null()))?; // Map diesel::result::Error to FieldError
+
+    // Initialize the result struct
+    let mut res: RecipeTemplateWithRecipeFlows =
+        RecipeTemplateWithRecipeFlows::new(&inserted_template);
+
+    // Iterate over each `RecipeFlowTemplateArg`
+    for r in recipe_flow_template_args {
+        // Create and insert a new recipe flow template
+        let new_recipe_flow_template = NewRecipeFlowTemplate::new(
+            &inserted_template.id,
+            &r.event_type,
+            &r.role_type,
+            &r.action,
+            &r.identifier,
+            r.interactions.as_ref(),
+        );
+
+        let inserted_recipe_flow_template: RecipeFlowTemplate =
+            diesel::insert_into(recipe_flow_templates::table)
+                .values(&new_recipe_flow_template)
+                .get_result::<RecipeFlowTemplate>(conn)?;
+
+        // Initialize `RecipeFlowTemplateWithDataFields` struct
+        let mut recipe_flow_res =
+            RecipeFlowTemplateWithDataFields::new(&inserted_recipe_flow_template);
+
+        let mut groups: Vec<(Uuid, Vec<String>)> = Vec::new();
+
+        for group in r.groups {
+            let new_group = NewRecipeFlowTemplateGroupDataField::new(&group.name, &group.class);
+
+            let inserted_group: RecipeFlowTemplateGroupDataField =
+                diesel::insert_into(recipe_flow_template_group_data_fields::table)
+                    .values(new_group)
+                    .get_result::<RecipeFlowTemplateGroupDataField>(conn)?;
+
+            let separated_fields: Vec<String> = group
+                .fields
+                .iter()
+                .flat_map(|field| field.split(", ").map(String::from).collect::<Vec<String>>()) // Split by ", " and collect into a Vec<String>
+                .collect();
+
+            groups.push((inserted_group.id, separated_fields));
+        }
+
+        // Rules can read any field of the flow, including the ones inserted after them
+        let flow_fields: Vec<String> = r.data_fields.iter().map(|f| f.field_identifier.clone()).collect();
+
+        // Iterate over each data field and add it to the recipe flow
+        for rd in r.data_fields {
+            let group_id = groups
+                .iter()
+                .find(|g| g.1.contains(&rd.field_identifier))
+                .map(|group| group.0);
+
+            let inherits: Option<Uuid> = if let Some(inherits) = rd.inherits {
+                //search for the recipe flow template with the identifier
+                let recipe_flow_template: RecipeFlowTemplate = recipe_flow_templates::table
+                    .filter(recipe_flow_templates::recipe_template_id.eq(&inserted_template.id))
+                    .filter(recipe_flow_templates::identifier.eq(inherits.identifier))
+                    .first::<RecipeFlowTemplate>(conn)?;
+
+                let field: RecipeFlowTemplateDataField =
+                    recipe_flow_template_data_fields::table
+                        .filter(
+                            recipe_flow_template_data_fields::recipe_flow_template_id
+                                .eq(recipe_flow_template.id),
+                        )
+                        .filter(
+                            recipe_flow_template_data_fields::field_identifier
+                                .eq(inherits.field),
+                        )
+                        .first::<RecipeFlowTemplateDataField>(conn)?;
+
+                Some(field.id)
+            } else {
+                None
+            };
+
+            let mut new_recipe_flow_template_data_field = NewRecipeFlowTemplateDataField::new(
+                &inserted_recipe_flow_template.id,
+                &rd.field_identifier,
+                &rd.field_class,
+                &rd.field,
+                &rd.field_type,
+                &rd.required,
+                &rd.accept_default,
+            );
+            new_recipe_flow_template_data_field.group_id = group_id.as_ref();
+            new_recipe_flow_template_data_field.note = rd.note.as_deref();
+            new_recipe_flow_template_data_field.flow_through = rd.flow_through.as_ref();
+            new_recipe_flow_template_data_field.inherits = inherits.as_ref();
+            new_recipe_flow_template_data_field.option_set_id = rd.option_set_id.as_ref();
+            new_recipe_flow_template_data_field.min_value = rd.min_value.as_ref();
+            new_recipe_flow_template_data_field.max_value = rd.max_value.as_ref();
+
+            let inserted_recipe_flow_template_data_field: RecipeFlowTemplateDataField =
+                diesel::insert_into(recipe_flow_template_data_fields::table)
+                    .values(new_recipe_flow_template_data_field)
+                    .get_result(conn)
+                    .map_err(|e| FieldError::new(e, juniper::Value::null()))?; // Map diesel::result::Error to FieldError
+
+            let mut recipe_flow_template_data_field_input: RecipeFlowTemplateDataFieldInput =
+                (&inserted_recipe_flow_template_data_field)
+                    .try_into()
+                    .map_err(|e| FieldError::new(e, juniper::Value::null()))?;
+            recipe_flow_template_data_field_input.rules = rule::insert_data_field_rules(
+                conn,
+                &inserted_recipe_flow_template_data_field.id,
+                &rd.field_identifier,
+                &flow_fields,
+                rd.rules.as_deref().unwrap_or_default(),
+            )?;
+
+            // Add the data field to the recipe flow
+            recipe_flow_res.add_data_field(recipe_flow_template_data_field_input);
+
+            if let Some(group_id) = group_id {
+                let group: RecipeFlowTemplateGroupDataField =
+                    recipe_flow_template_group_data_fields::table
+                        .filter(recipe_flow_template_group_data_fields::id.eq(group_id))
+                        .first::<RecipeFlowTemplateGroupDataField>(conn)?;
+
+                recipe_flow_res.add_group(group);
             }
-            res.add_recipe_flow(recipe_flow_res);
         }
+        res.add_recipe_flow(recipe_flow_res);
+    }
 
-        Ok(res)
-    })
+    Ok(res)
 }
 
 pub fn set_map_template_blacklists(
@@ -454,6 +795,8 @@ pub fn set_map_template_blacklists(
         .expect("Failed to get DB connection from pool");
 
     conn.transaction::<_, FieldError, _>(|conn| {
+        // Blacklists are only read on the latest versions, older ids are moved up to them
+        let selected_template_id = latest_version_id_by_id(conn, selected_template_id)?;
 
         //remove all related to selected_template_id
         diesel::delete(recipe_template_blacklists::table)
@@ -467,8 +810,8 @@ pub fn set_map_template_blacklists(
         .execute(conn)?;
 
         for blacklist in blacklists {
-            let template_id = blacklist.recipe_template_id;
-            let predecessor_id = blacklist.recipe_template_predecesor_id;
+            let template_id = latest_version_id_by_id(conn, blacklist.recipe_template_id)?;
+            let predecessor_id = latest_version_id_by_id(conn, blacklist.recipe_template_predecesor_id)?;
 
             let new_blacklist = NewRecipeTemplateBlacklist::new(
                 &map_template_id,
@@ -500,7 +843,7 @@ pub fn assign_template_to_agent(
         .first::<RecipeTemplateAccess>(conn);
 
     // If it exists, return an error
-    if let Ok(_) = existing_access {
+    if existing_access.is_ok() {
         return Err(FieldError::new(
             "Template access already exists for this agent.",
             graphql_value!({ "code": "ALREADY_EXISTS" }),
@@ -519,10 +862,7 @@ pub fn assign_template_to_agent(
 
 #[cfg(test)]
 mod tests {
-    use std::env;
-
     use super::*;
-    use diesel::prelude::*;
     use diesel::r2d2;
     use diesel::r2d2::ConnectionManager;
     use diesel::result::Error as DieselError;
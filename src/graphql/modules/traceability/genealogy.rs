@@ -0,0 +1,338 @@
+use std::collections::{HashMap, HashSet};
+
+use diesel::prelude::*;
+use juniper::{graphql_value, FieldError, FieldResult};
+use uuid::Uuid;
+
+use crate::{
+    common::{agent::Agent, economic_resource::EconomicResource, location::Location},
+    db::schema::{
+        agents, economic_resources, locations, process_executions, recipe_process_flows,
+        recipe_process_relations, resource_specifications,
+    },
+    graphql::{
+        context::Context,
+        modules::{common::containment, process::correction},
+    },
+    recipe::process::execution::ProcessExecution,
+    templates::recipe_flow_template::RoleType,
+    traceability::genealogy::{self, GenealogyLink, GenealogyResponse, TraceDirection, TraceMode},
+};
+
+/** Queries */
+pub fn trace_lot(
+    context: &Context,
+    lot: Option<String>,
+    economic_resource_id: Option<Uuid>,
+    direction: TraceDirection,
+    mode: TraceMode,
+) -> FieldResult<GenealogyResponse> {
+    let conn = &mut context
+        .pool
+        .get()
+        .expect("Failed to get DB connection from pool");
+
+    let origin: Vec<EconomicResource> = match (economic_resource_id, lot) {
+        (Some(economic_resource_id), _) => economic_resources::table
+            .filter(economic_resources::id.eq(economic_resource_id))
+            .load::<EconomicResource>(conn)?,
+        (None, Some(lot)) => economic_resources::table
+            .filter(economic_resources::lot.eq(lot))
+            .order(economic_resources::created_at.asc())
+            .load::<EconomicResource>(conn)?,
+        (None, None) => {
+            return Err(FieldError::new(
+                "Unable to trace",
+                graphql_value!({ "code": "Either a lot or an economic resource is required" }),
+            ));
+        }
+    };
+
+    // A trace starts from what the caller's agents hold, and follows it through
+    // the executions wherever they lead
+    let mut held = Vec::new();
+    for resource in origin {
+        if context.is_member(containment::holder(conn, &resource)?) {
+            held.push(resource);
+        }
+    }
+    let origin = held;
+
+    if origin.is_empty() {
+        return Err(FieldError::new(
+            "Unable to trace",
+            graphql_value!({ "code": "No economic resource matches the traced lot" }),
+        ));
+    }
+
+    let origin_ids: Vec<Uuid> = origin.iter().map(|r| r.id).collect();
+    let walk = genealogy::walk(&origin_ids, mode, |resource_id| trace_step(conn, resource_id, direction))?;
+
+    let mut links: Vec<GenealogyLink> = Vec::new();
+    let mut agent_ids: HashSet<Uuid> = HashSet::new();
+    let mut location_ids: HashSet<Uuid> = HashSet::new();
+
+    for (resource_id, next_id, execution) in walk.steps {
+        agent_ids.insert(execution.provider_agent);
+        agent_ids.insert(execution.receiver_agent);
+        location_ids.extend(execution.at_location);
+        location_ids.extend(execution.to_location);
+
+        let (from_resource_id, to_resource_id) = match direction {
+            TraceDirection::Forward => (resource_id, next_id),
+            TraceDirection::Backward => (next_id, resource_id),
+        };
+        links.push(GenealogyLink {
+            from_resource_id,
+            to_resource_id,
+            process_execution_id: execution.id,
+            action: execution.action,
+        });
+    }
+
+    let order: Vec<Uuid> = walk.reached.iter().map(|(id, _)| *id).collect();
+    let mut resources: HashMap<Uuid, EconomicResource> = economic_resources::table
+        .filter(economic_resources::id.eq_any(&order))
+        .load::<EconomicResource>(conn)?
+        .into_iter()
+        .map(|resource| (resource.id, resource))
+        .collect();
+
+    // Whoever holds the resource, the custodian or else the owner of its specification
+    let spec_ids: Vec<Uuid> = resources.values().map(|r| r.resource_specification_id).collect();
+    let spec_agents: HashMap<Uuid, Uuid> = resource_specifications::table
+        .filter(resource_specifications::id.eq_any(&spec_ids))
+        .select((resource_specifications::id, resource_specifications::agent_id))
+        .load::<(Uuid, Uuid)>(conn)?
+        .into_iter()
+        .collect();
+
+    for resource in resources.values() {
+        agent_ids.extend(
+            resource
+                .custodian_id
+                .or_else(|| spec_agents.get(&resource.resource_specification_id).copied()),
+        );
+        if let Some(location_id) = resource.current_location {
+            location_ids.insert(location_id);
+        }
+    }
+
+    let mut res = GenealogyResponse::new(direction, mode);
+
+    for (resource_id, depth) in walk.reached {
+        if let Some(resource) = resources.remove(&resource_id) {
+            res.add_node(resource, depth);
+        }
+    }
+
+    for link in links {
+        res.add_link(link);
+    }
+
+    let agent_ids: Vec<Uuid> = agent_ids.into_iter().collect();
+    for agent in agents::table
+        .filter(agents::id.eq_any(agent_ids))
+        .order(agents::name.asc())
+        .load::<Agent>(conn)?
+    {
+        res.add_agent(agent);
+    }
+
+    let location_ids: Vec<Uuid> = location_ids.into_iter().collect();
+    for location in locations::table
+        .filter(locations::id.eq_any(location_ids))
+        .order(locations::name.asc())
+        .load::<Location>(conn)?
+    {
+        res.add_location(location);
+    }
+
+    Ok(res)
+}
+
+/// Resources one step away from the given one, with the execution linking them
+fn trace_step(
+    conn: &mut PgConnection,
+    resource_id: Uuid,
+    direction: TraceDirection,
+) -> FieldResult<Vec<(Uuid, ProcessExecution)>> {
+    let mut steps = Vec::new();
+
+    match direction {
+        TraceDirection::Forward => {
+            let executions: Vec<ProcessExecution> = process_executions::table
+                .filter(process_executions::economic_resource_id.eq(resource_id))
+                .load::<ProcessExecution>(conn)?;
+            let executions = correction::effective_executions(conn, executions)?;
+
+            for execution in executions {
+                // Custody changes move the quantity into another resource
+                if let Some(to_resource_id) = execution.to_economic_resource_id {
+                    steps.push((to_resource_id, execution));
+                    continue;
+                }
+
+                match execution.role_type {
+                    RoleType::Input if execution.action.is_ingredient() => {
+                        steps.extend(batch_executions(conn, &execution, RoleType::Output)?);
+                    }
+                    RoleType::Output if execution.action.is_product() => {
+                        steps.extend(downstream_by_lot(conn, &execution)?);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        TraceDirection::Backward => {
+            let received: Vec<ProcessExecution> = process_executions::table
+                .filter(process_executions::to_economic_resource_id.eq(resource_id))
+                .load::<ProcessExecution>(conn)?;
+            let received = correction::effective_executions(conn, received)?;
+
+            for execution in received {
+                if let Some(from_resource_id) = execution.economic_resource_id {
+                    steps.push((from_resource_id, execution));
+                }
+            }
+
+            let produced: Vec<ProcessExecution> = process_executions::table
+                .filter(process_executions::economic_resource_id.eq(resource_id))
+                .filter(process_executions::role_type.eq(RoleType::Output))
+                .load::<ProcessExecution>(conn)?;
+            let produced = correction::effective_executions(conn, produced)?;
+
+            for execution in produced.iter().filter(|e| e.action.is_product()) {
+                steps.extend(batch_executions(conn, execution, RoleType::Input)?);
+                steps.extend(upstream_by_lot(conn, execution)?);
+            }
+        }
+    }
+
+    steps.retain(|(next_id, _)| *next_id != resource_id);
+    Ok(steps)
+}
+
+/// Executions of the same process run on the other side of the transformation
+fn batch_executions(
+    conn: &mut PgConnection,
+    execution: &ProcessExecution,
+    role_type: RoleType,
+) -> FieldResult<Vec<(Uuid, ProcessExecution)>> {
+    let batch_id = match execution.batch_id {
+        Some(batch_id) => batch_id,
+        None => return Ok(Vec::new()),
+    };
+
+    let executions: Vec<ProcessExecution> = process_executions::table
+        .filter(process_executions::batch_id.eq(batch_id))
+        .filter(process_executions::role_type.eq(role_type))
+        .load::<ProcessExecution>(conn)?;
+    let executions = correction::effective_executions(conn, executions)?;
+
+    Ok(executions
+        .into_iter()
+        .filter(|e| match role_type {
+            RoleType::Input => e.action.is_ingredient(),
+            RoleType::Output => e.action.is_product(),
+        })
+        .filter_map(|e| e.economic_resource_id.map(|id| (id, e)))
+        .collect())
+}
+
+/// Inputs recorded only by lot number, matched through the processes that take
+/// the output of the producing process
+fn downstream_by_lot(
+    conn: &mut PgConnection,
+    execution: &ProcessExecution,
+) -> FieldResult<Vec<(Uuid, ProcessExecution)>> {
+    // Splits and merges belong to no process, their batch links them already
+    let (lot, process_flow_id) = match (&execution.resource_lot_number, execution.process_flow_id) {
+        (Some(lot), Some(process_flow_id)) => (lot, process_flow_id),
+        _ => return Ok(Vec::new()),
+    };
+
+    let recipe_process_id = recipe_process_flows::table
+        .filter(recipe_process_flows::id.eq(process_flow_id))
+        .select(recipe_process_flows::recipe_process_id)
+        .first::<Uuid>(conn)?;
+
+    let downstream: Vec<Uuid> = recipe_process_relations::table
+        .filter(recipe_process_relations::output_of.eq(recipe_process_id))
+        .select(recipe_process_relations::recipe_process_id)
+        .load::<Uuid>(conn)?;
+
+    let inputs: Vec<ProcessExecution> = process_executions::table
+        .inner_join(recipe_process_flows::table)
+        .filter(recipe_process_flows::recipe_process_id.eq_any(downstream))
+        .filter(process_executions::role_type.eq(RoleType::Input))
+        .filter(process_executions::economic_resource_id.is_null())
+        .filter(process_executions::resource_lot_number.eq(lot))
+        .filter(process_executions::resource_specification.eq(execution.resource_specification))
+        .select(process_executions::all_columns)
+        .load::<ProcessExecution>(conn)?;
+    let inputs = correction::effective_executions(conn, inputs)?;
+
+    let mut steps = Vec::new();
+    for input in inputs.iter().filter(|e| e.action.is_ingredient()) {
+        steps.extend(batch_executions(conn, input, RoleType::Output)?);
+    }
+
+    Ok(steps)
+}
+
+/// Producing executions of the lots an input recorded only by lot number, looked
+/// up in the processes this process takes the output of
+fn upstream_by_lot(
+    conn: &mut PgConnection,
+    execution: &ProcessExecution,
+) -> FieldResult<Vec<(Uuid, ProcessExecution)>> {
+    let (batch_id, process_flow_id) = match (execution.batch_id, execution.process_flow_id) {
+        (Some(batch_id), Some(process_flow_id)) => (batch_id, process_flow_id),
+        _ => return Ok(Vec::new()),
+    };
+
+    let inputs: Vec<ProcessExecution> = process_executions::table
+        .filter(process_executions::batch_id.eq(batch_id))
+        .filter(process_executions::role_type.eq(RoleType::Input))
+        .filter(process_executions::economic_resource_id.is_null())
+        .filter(process_executions::resource_lot_number.is_not_null())
+        .load::<ProcessExecution>(conn)?;
+    let inputs = correction::effective_executions(conn, inputs)?;
+
+    if inputs.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let recipe_process_id = recipe_process_flows::table
+        .filter(recipe_process_flows::id.eq(process_flow_id))
+        .select(recipe_process_flows::recipe_process_id)
+        .first::<Uuid>(conn)?;
+
+    let upstream: Vec<Uuid> = recipe_process_relations::table
+        .filter(recipe_process_relations::recipe_process_id.eq(recipe_process_id))
+        .select(recipe_process_relations::output_of)
+        .load::<Uuid>(conn)?;
+
+    let mut steps = Vec::new();
+    for input in inputs.iter().filter(|e| e.action.is_ingredient()) {
+        let outputs: Vec<ProcessExecution> = process_executions::table
+            .inner_join(recipe_process_flows::table)
+            .filter(recipe_process_flows::recipe_process_id.eq_any(&upstream))
+            .filter(process_executions::role_type.eq(RoleType::Output))
+            .filter(process_executions::resource_lot_number.eq(&input.resource_lot_number))
+            .filter(process_executions::resource_specification.eq(input.resource_specification))
+            .select(process_executions::all_columns)
+            .load::<ProcessExecution>(conn)?;
+        let outputs = correction::effective_executions(conn, outputs)?;
+
+        steps.extend(
+            outputs
+                .into_iter()
+                .filter(|e| e.action.is_product())
+                .filter_map(|e| e.economic_resource_id.map(|id| (id, e))),
+        );
+    }
+
+    Ok(steps)
+}
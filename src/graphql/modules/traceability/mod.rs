@@ -0,0 +1 @@
+pub mod genealogy;
\ No newline at end of file
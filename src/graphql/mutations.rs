@@ -1,25 +1,66 @@
-use juniper::{graphql_object, FieldResult};
+use juniper::{graphql_object, FieldResult, Nullable};
 use uuid::Uuid;
 
 use crate::{
     common::{
-        agent::Agent, economic_resource::EconomicResource, location::Location, resource_specification::{ResourceSpecification, ResourceType}
-    }, graphql::context::Context, recipe::recipe::RecipeWithResources, templates::{map_template::{MapTemplate, MapTemplateResponse, TemplateType}, recipe_flow_template::ActionType, recipe_template::RecipeTemplateWithRecipeFlows, recipe_template_access::RecipeTemplateAccess}
+        agent::Agent, counter::Counter, economic_resource::{Decimal, EconomicResource, NewEconomicResource}, location::{Location, LocationDetails}, resource_operation::{ResourceOperationResponse, SplitPart}, resource_specification::{ResourceSpecification, ResourceType}, unit::{Unit, UnitDimension}, user::{AgentMembership, AgentRole, ApiKey, IssuedApiKey, Permission}
+    }, graphql::context::Context, recipe::{process::{data_field::RecipeFlowDataField, execution::{CorrectionResponse, ProcessExecutionResponse}, process::RecipeProcessesResponse}, recipe::RecipeWithResources}, templates::{data_field_rule::{DataFieldRule, DataFieldRuleArg}, map_template::{MapTemplate, MapTemplateResponse, TemplateType}, option_set::{OptionSetResponse, OptionSetValueInput, OptionSource}, recipe_flow_template::{ActionType, RecipeFlowTemplate}, recipe_flow_template_data_field::RecipeFlowTemplateDataField, recipe_template::RecipeTemplateWithRecipeFlows, recipe_template_access::RecipeTemplateAccess, template_blocker::{TemplateDeletion, TemplateItem}}
 };
 
 use super::modules::{
-    common::{agent, economic_resource, location, resource_specification}, 
-    // process::process::{self, CreateRecipeProcessesResponse, ProcessExecution, RecipeProcessWithRelation}, 
-    recipe::recipe, templates::template::{self, MapTemplateBlacklist, RecipeFlowTemplateArg}
+    common::{agent, authorization, containment, counter, economic_resource, location, resource_operation, resource_specification, unit, user}, 
+    fda::cte, 
+    process::{correction, default_value, execution::{self, DataFieldValue, ProcessFlowExecution}, inheritance, process::{self, RecipeProcessWithRelation}}, 
+    recipe::recipe, templates::{lifecycle, option_set, rule, template::{self, MapTemplateBlacklist, RecipeFlowTemplateArg, RecipeTemplateContent}}
 };
 
 pub struct MutationRoot;
 
 #[graphql_object(Context = Context)]
 impl MutationRoot {
+    /*** Users */
+    /// Signs up without a key, every other field requires one. The bootstrap
+    /// secret of the deployment makes its first deployment admin
+    fn create_user(
+        context: &Context,
+        name: String,
+        email: String,
+        bootstrap_secret: Option<String>,
+    ) -> FieldResult<IssuedApiKey> {
+        user::create_user(context, name, email, bootstrap_secret)
+    }
+
+    /// The key is only returned here, it is stored hashed
+    fn create_api_key(context: &Context, name: String) -> FieldResult<IssuedApiKey> {
+        user::create_api_key(context, name)
+    }
+
+    fn revoke_api_key(context: &Context, api_key_id: Uuid) -> FieldResult<ApiKey> {
+        user::revoke_api_key(context, api_key_id)
+    }
+
+    /// Admins add users who signed up, with the role they have in the agent
+    fn add_agent_member(context: &Context, agent_id: Uuid, email: String, role: AgentRole) -> FieldResult<AgentMembership> {
+        user::add_agent_member(context, agent_id, email, role)
+    }
+
+    /// An agent keeps at least one admin
+    fn set_agent_member_role(context: &Context, agent_id: Uuid, user_id: Uuid, role: AgentRole) -> FieldResult<AgentMembership> {
+        user::set_agent_member_role(context, agent_id, user_id, role)
+    }
+
+    fn remove_agent_member(context: &Context, agent_id: Uuid, user_id: Uuid) -> FieldResult<AgentMembership> {
+        user::remove_agent_member(context, agent_id, user_id)
+    }
+
     /*** Agents */
     fn create_agent(context: &Context, name: String, note: Option<String>) -> FieldResult<Agent> {
-        agent::create_agent(&context, name, note)
+        agent::create_agent(context, name, note)
+    }
+
+    fn set_agent_primary_location(context: &Context, agent_id: Uuid, location_id: Option<Uuid>) -> FieldResult<Agent> {
+        authorization::agent(context, agent_id, Permission::Administer)?;
+        agent::set_agent_primary_location(context, agent_id, location_id)
     }
 
     /** Resource Specifications */
@@ -31,8 +72,9 @@ impl MutationRoot {
         resource_type: ResourceType,
         unit_of_measure: String,
     ) -> FieldResult<ResourceSpecification> {
+        authorization::agent(context, agent_id, Permission::Administer)?;
         resource_specification::create_resource_specification(
-            &context,
+            context,
             agent_id,
             name,
             note,
@@ -41,44 +83,140 @@ impl MutationRoot {
         )
     }
 
+    /// Existing quantities of the specification are not converted
+    fn set_resource_specification_unit(
+        context: &Context,
+        resource_specification_id: Uuid,
+        unit_id: Uuid,
+    ) -> FieldResult<ResourceSpecification> {
+        authorization::resource_specification(context, resource_specification_id, Permission::Administer)?;
+        unit::set_resource_specification_unit(context, resource_specification_id, unit_id)
+    }
+
+    /** Units */
+    /// Units are shared by all agents, deployment admins add them
+    fn create_unit(
+        context: &Context,
+        label: String,
+        symbol: String,
+        dimension: UnitDimension,
+        conversion_factor: Decimal,
+        om2_uri: Option<String>,
+        unece_code: Option<String>,
+    ) -> FieldResult<Unit> {
+        context.check_deployment_admin()?;
+        unit::create_unit(context, label, symbol, dimension, conversion_factor, om2_uri, unece_code)
+    }
+
     /** Economic Resource */
     fn create_economic_resource(
         context: &Context,
         resource_specification_id: Uuid,
         name: String,
         note: Option<String>,
-        accounting_quantity: i32,
+        accounting_quantity: Decimal,
         tracking_identifier: Option<String>,
-        current_location: String,
+        current_location: Uuid,
         lot: Option<String>,
         contained_in: Option<Uuid>,
     ) -> FieldResult<EconomicResource> {
-        economic_resource::create_economic_resource(
-            &context,
-            resource_specification_id,
-            name,
-            note,
-            accounting_quantity,
-            tracking_identifier,
-            current_location,
-            lot,
-            contained_in,
-        )
+        authorization::resource_specification(context, resource_specification_id, Permission::Operate)?;
+        authorization::location(context, current_location, Permission::Operate)?;
+        if let Some(contained_in) = contained_in {
+            authorization::economic_resource(context, contained_in, Permission::Operate)?;
+        }
+        let mut new_economic_resource = NewEconomicResource::new(
+            &resource_specification_id,
+            &name,
+            &accounting_quantity,
+            Some(&current_location),
+            lot.as_deref(),
+        );
+        new_economic_resource.note = note.as_deref();
+        new_economic_resource.tracking_identifier = tracking_identifier.as_deref();
+        new_economic_resource.contained_in = contained_in.as_ref();
+        economic_resource::create_economic_resource(context, &new_economic_resource)
+    }
+
+    /// Packs the resource into the container, it moves to the container's location with its contents
+    fn pack_resource(context: &Context, economic_resource_id: Uuid, container_id: Uuid) -> FieldResult<EconomicResource> {
+        authorization::economic_resource(context, economic_resource_id, Permission::Operate)?;
+        authorization::economic_resource(context, container_id, Permission::Operate)?;
+        containment::pack_resource(context, economic_resource_id, container_id)
+    }
+
+    fn unpack_resource(context: &Context, economic_resource_id: Uuid) -> FieldResult<EconomicResource> {
+        authorization::economic_resource(context, economic_resource_id, Permission::Operate)?;
+        containment::unpack_resource(context, economic_resource_id)
+    }
+
+    fn repack_resource(context: &Context, economic_resource_id: Uuid, container_id: Uuid) -> FieldResult<EconomicResource> {
+        authorization::economic_resource(context, economic_resource_id, Permission::Operate)?;
+        authorization::economic_resource(context, container_id, Permission::Operate)?;
+        containment::repack_resource(context, economic_resource_id, container_id)
+    }
+
+    /// Splits parts off a lot into new resources, a partial shipment for instance
+    fn split_resource(
+        context: &Context,
+        economic_resource_id: Uuid,
+        parts: Vec<SplitPart>,
+        note: Option<String>,
+    ) -> FieldResult<ResourceOperationResponse> {
+        authorization::economic_resource(context, economic_resource_id, Permission::Operate)?;
+        resource_operation::split_resource(context, economic_resource_id, parts, note)
+    }
+
+    /// Commingles resources of one specification, holder and location into a new one
+    fn merge_resources(
+        context: &Context,
+        economic_resource_ids: Vec<Uuid>,
+        lot: Option<String>,
+        note: Option<String>,
+    ) -> FieldResult<ResourceOperationResponse> {
+        for economic_resource_id in &economic_resource_ids {
+            authorization::economic_resource(context, *economic_resource_id, Permission::Operate)?;
+        }
+        resource_operation::merge_resources(context, economic_resource_ids, lot, note)
     }
 
     /** Map Templates */
+    /// A map without an owning agent is left to the deployment admins
     fn create_map_template(
         context: &Context,
         name: String,
-        type_: TemplateType
+        type_: TemplateType,
+        created_by_agent: Option<Uuid>
     ) -> FieldResult<MapTemplate> {
+        context.check_any_permission(Permission::Design)?;
+        if let Some(agent_id) = created_by_agent {
+            authorization::agent(context, agent_id, Permission::Design)?;
+        }
         template::create_map_template(
             context, 
             name, 
-            type_
+            type_,
+            created_by_agent
         )
     }
 
+    /// A type change on a map in use needs cascade
+    fn update_map_template(
+        context: &Context,
+        map_template_id: Uuid,
+        name: Option<String>,
+        type_: Option<TemplateType>,
+        cascade: Option<bool>,
+    ) -> FieldResult<MapTemplate> {
+        authorization::map_template(context, map_template_id, Permission::Design)?;
+        lifecycle::update_map_template(context, map_template_id, name, type_, cascade)
+    }
+
+    fn delete_map_template(context: &Context, map_template_id: Uuid, cascade: Option<bool>) -> FieldResult<TemplateDeletion> {
+        authorization::map_template(context, map_template_id, Permission::Design)?;
+        lifecycle::delete_template_item(context, TemplateItem::MapTemplate, map_template_id, cascade)
+    }
+
     /** Recipe Templates */
     fn create_recipe_template(
         context: &Context,
@@ -88,35 +226,168 @@ impl MutationRoot {
         recipe_flow_template_args: Vec<RecipeFlowTemplateArg>,
         commitment: Option<ActionType>,
         fulfills: Option<String>,
-        trigger: Option<ActionType>
+        trigger: Option<ActionType>,
+        created_by_agent: Option<Uuid>
     ) -> FieldResult<RecipeTemplateWithRecipeFlows> {
-        template::create_recipe_template(
-            context, 
-            map_template_id,
-            identifier,
-            name, 
+        authorization::map_template(context, map_template_id, Permission::Design)?;
+        if let Some(agent_id) = created_by_agent {
+            authorization::agent(context, agent_id, Permission::Design)?;
+        }
+        let content = RecipeTemplateContent {
+            name,
             recipe_flow_template_args,
             commitment,
             fulfills,
-            trigger
+            trigger,
+            created_by_agent,
+        };
+        template::create_recipe_template(context, map_template_id, identifier, content)
+    }
+
+    fn update_recipe_template(
+        context: &Context,
+        recipe_template_id: Uuid,
+        name: String,
+        recipe_flow_template_args: Vec<RecipeFlowTemplateArg>,
+        commitment: Option<ActionType>,
+        fulfills: Option<String>,
+        trigger: Option<ActionType>,
+        created_by_agent: Option<Uuid>
+    ) -> FieldResult<RecipeTemplateWithRecipeFlows> {
+        authorization::recipe_template(context, recipe_template_id, Permission::Design)?;
+        if let Some(agent_id) = created_by_agent {
+            authorization::agent(context, agent_id, Permission::Design)?;
+        }
+        let content = RecipeTemplateContent {
+            name,
+            recipe_flow_template_args,
+            commitment,
+            fulfills,
+            trigger,
+            created_by_agent,
+        };
+        template::update_recipe_template(context, recipe_template_id, content)
+    }
+
+    /// Deletes every version of the template
+    fn delete_recipe_template(
+        context: &Context,
+        recipe_template_id: Uuid,
+        cascade: Option<bool>,
+    ) -> FieldResult<TemplateDeletion> {
+        authorization::recipe_template(context, recipe_template_id, Permission::Design)?;
+        lifecycle::delete_template_item(context, TemplateItem::RecipeTemplate, recipe_template_id, cascade)
+    }
+
+    /** Recipe Flow Templates */
+    fn update_recipe_flow_template(
+        context: &Context,
+        recipe_flow_template_id: Uuid,
+        identifier: Option<String>,
+        interactions: Option<i32>,
+        cascade: Option<bool>,
+    ) -> FieldResult<RecipeFlowTemplate> {
+        authorization::recipe_flow_template(context, recipe_flow_template_id, Permission::Design)?;
+        lifecycle::update_recipe_flow_template(context, recipe_flow_template_id, identifier, interactions, cascade)
+    }
+
+    fn delete_recipe_flow_template(
+        context: &Context,
+        recipe_flow_template_id: Uuid,
+        cascade: Option<bool>,
+    ) -> FieldResult<TemplateDeletion> {
+        authorization::recipe_flow_template(context, recipe_flow_template_id, Permission::Design)?;
+        lifecycle::delete_template_item(context, TemplateItem::RecipeFlowTemplate, recipe_flow_template_id, cascade)
+    }
+
+    /// A null note clears it, a note left out is kept
+    fn update_recipe_flow_template_data_field(
+        context: &Context,
+        data_field_id: Uuid,
+        field: Option<String>,
+        note: Nullable<String>,
+        required: Option<bool>,
+        accept_default: Option<bool>,
+        cascade: Option<bool>,
+    ) -> FieldResult<RecipeFlowTemplateDataField> {
+        authorization::template_data_field(context, data_field_id, Permission::Design)?;
+        lifecycle::update_recipe_flow_template_data_field(
+            context,
+            data_field_id,
+            field,
+            note.explicit(),
+            required,
+            accept_default,
+            cascade
         )
     }
 
+    fn delete_recipe_flow_template_data_field(
+        context: &Context,
+        data_field_id: Uuid,
+        cascade: Option<bool>,
+    ) -> FieldResult<TemplateDeletion> {
+        authorization::template_data_field(context, data_field_id, Permission::Design)?;
+        lifecycle::delete_template_item(context, TemplateItem::DataField, data_field_id, cascade)
+    }
+
+    /// Replaces the RequiredIf, VisibleIf and Constraint rules of a data field
+    fn set_data_field_rules(
+        context: &Context,
+        data_field_id: Uuid,
+        rules: Vec<DataFieldRuleArg>,
+    ) -> FieldResult<Vec<DataFieldRule>> {
+        authorization::template_data_field(context, data_field_id, Permission::Design)?;
+        rule::set_data_field_rules(context, data_field_id, rules)
+    }
+
     fn set_map_template_blacklists(
         context: &Context,
         map_template_id: Uuid,
         selected_template_id: Uuid,
         blacklists: Vec<MapTemplateBlacklist>,
     ) -> FieldResult<MapTemplateResponse> {
+        authorization::map_template(context, map_template_id, Permission::Design)?;
         template::set_map_template_blacklists(context, map_template_id, selected_template_id, blacklists)
     }
 
+    /** Option Sets */
+    /// Option sets are shared by all agents, deployment admins add them
+    fn create_option_set(
+        context: &Context,
+        name: String,
+        source: OptionSource,
+        resource_type: Option<ResourceType>,
+        values: Option<Vec<OptionSetValueInput>>,
+    ) -> FieldResult<OptionSetResponse> {
+        context.check_deployment_admin()?;
+        option_set::create_option_set(context, name, source, resource_type, values)
+    }
+
+    /// Processes created from the template afterwards copy the set
+    fn set_template_field_option_set(
+        context: &Context,
+        data_field_id: Uuid,
+        option_set_id: Option<Uuid>,
+    ) -> FieldResult<RecipeFlowTemplateDataField> {
+        authorization::template_data_field(context, data_field_id, Permission::Design)?;
+        option_set::set_template_field_option_set(context, data_field_id, option_set_id)
+    }
+
+    /** FDA */
+    /// The installed templates belong to the owner of the map
+    fn install_fda_templates(context: &Context, map_template_id: Uuid) -> FieldResult<MapTemplateResponse> {
+        authorization::map_template(context, map_template_id, Permission::Design)?;
+        cte::install_fda_templates(context, map_template_id)
+    }
+
     /** Recipe Template Access */
     fn assign_template_to_agent(
         context: &Context,
         recipe_template_id: Uuid,
         agent_id: Uuid,
     ) -> FieldResult<RecipeTemplateAccess> {
+        authorization::agent(context, agent_id, Permission::Administer)?;
         template::assign_template_to_agent(context, recipe_template_id, agent_id)
     }
 
@@ -128,87 +399,120 @@ impl MutationRoot {
         note: Option<String>,
         recipe_resources: Vec<Uuid>,
     ) -> FieldResult<RecipeWithResources> {
-        recipe::create_recipe(&context, agent_id, name, note, recipe_resources)
+        authorization::agent(context, agent_id, Permission::Design)?;
+        for resource_specification_id in &recipe_resources {
+            authorization::resource_specification(context, *resource_specification_id, Permission::Read)?;
+        }
+        recipe::create_recipe(context, agent_id, name, note, recipe_resources)
     }
 
     /** Locations */
-    fn create_location(context: &Context, agent_id: Uuid, name: String, value: String) -> FieldResult<Location> {
-        location::create_location(&context, agent_id, name, value)
-    }
-
-    // /** Process */
-    // fn create_recipe_processes(
-    //     context: &Context,
-    //     recipe_id: Uuid,
-    //     data: Vec<RecipeProcessWithRelation>
-    // ) -> FieldResult<CreateRecipeProcessesResponse> {
-    //     process::create_recipe_processes(&context, recipe_id, data)
-    // }
-
-    // /** Process Execution */
-    // fn execute_events(context: &Context, recipe_process_id: Uuid, process_flows: Vec<ProcessExecution>) -> FieldResult<String> {
-    //     process::execute_events(&context, recipe_process_id, process_flows)
-    // }
-}
-
+    /// Details hold the GLN, address, coordinates, type and parent of the location
+    fn create_location(
+        context: &Context,
+        agent_id: Uuid,
+        name: String,
+        value: String,
+        details: Option<LocationDetails>,
+    ) -> FieldResult<Location> {
+        authorization::agent(context, agent_id, Permission::Administer)?;
+        if let Some(parent_id) = details.as_ref().and_then(|d| d.parent_id) {
+            authorization::location(context, parent_id, Permission::Administer)?;
+        }
+        location::create_location(context, agent_id, name, value, details)
+    }
 
+    /// Replaces the details of the location, those left out are cleared
+    fn set_location_details(context: &Context, location_id: Uuid, details: LocationDetails) -> FieldResult<Location> {
+        authorization::location(context, location_id, Permission::Administer)?;
+        if let Some(parent_id) = details.parent_id {
+            authorization::location(context, parent_id, Permission::Administer)?;
+        }
+        location::set_location_details(context, location_id, details)
+    }
 
+    /** Counters */
+    fn allocate_lot_code(context: &Context, agent_id: Uuid) -> FieldResult<String> {
+        authorization::agent(context, agent_id, Permission::Operate)?;
+        counter::allocate_lot_code(context, agent_id)
+    }
 
+    fn allocate_reference_number(context: &Context, agent_id: Uuid) -> FieldResult<i32> {
+        authorization::agent(context, agent_id, Permission::Operate)?;
+        counter::allocate_reference_number(context, agent_id)
+    }
 
-/*
-fn get_latest_lot_code_for_agent(conn: &PgConnection, agent_id: Uuid) -> QueryResult<(Option<i32>, Option<i32>)> {
-    use crate::schema::lot_codes::dsl::*;
+    fn update_lot_format(
+        context: &Context,
+        agent_id: Uuid,
+        lot_format: String,
+        lot_prefix: Option<String>
+    ) -> FieldResult<Counter> {
+        authorization::agent(context, agent_id, Permission::Administer)?;
+        counter::update_lot_format(context, agent_id, lot_format, lot_prefix)
+    }
 
-    lot_codes
-        .filter(agent_id.eq(agent_id))
-        .select((diesel::dsl::max(lot_code), diesel::dsl::max(reference_number)))
-        .first::<(Option<i32>, Option<i32>)>(conn)
-}
+    /** Process */
+    fn create_recipe_processes(
+        context: &Context,
+        recipe_id: Uuid,
+        data: Vec<RecipeProcessWithRelation>
+    ) -> FieldResult<RecipeProcessesResponse> {
+        authorization::recipe(context, recipe_id, Permission::Design)?;
+        process::create_recipe_processes(context, recipe_id, data)
+    }
 
-        fn insert_lot_code(
-    conn: &PgConnection,
-    agent: Uuid,
-    increment_lot_code: bool,
-    increment_reference_number: bool,
-) -> QueryResult<LotCode> {
-    use crate::schema::lot_codes;
-    use diesel::insert_into;
-
-    // Get the latest lot_code and reference_number for the agent
-    let (latest_lot_code, latest_reference_number) = get_latest_lot_code_for_agent(conn, agent)?;
-
-    // Determine the new values for lot_code and reference_number
-    let new_lot_code = if increment_lot_code {
-        latest_lot_code.unwrap_or(0) + 1
-    } else {
-        latest_lot_code.unwrap_or(1) // Default to 1 if none found
-    };
-
-    let new_reference_number = if increment_reference_number {
-        latest_reference_number.unwrap_or(0) + 1
-    } else {
-        latest_reference_number.unwrap_or(1) // Default to 1 if none found
-    };
-
-    // Insert the new row into the table
-    let new_lot_code_row = diesel::insert_into(lot_codes::table)
-        .values((
-            lot_codes::agent_id.eq(agent),
-            lot_codes::lot_code.eq(new_lot_code),
-            lot_codes::reference_number.eq(new_reference_number),
-        ))
-        .get_result::<LotCode>(conn)?;
-
-    Ok(new_lot_code_row)
-}
+    /** Process Execution */
+    fn execute_events(
+        context: &Context,
+        recipe_process_id: Uuid,
+        process_flows: Vec<ProcessFlowExecution>
+    ) -> FieldResult<Vec<ProcessExecutionResponse>> {
+        authorization::recipe_process(context, recipe_process_id, Permission::Operate)?;
+        execution::execute_events(context, recipe_process_id, process_flows)
+    }
 
+    /// Reverses the execution and records the replacement, omitting the values voids it
+    fn correct_event(
+        context: &Context,
+        process_execution_id: Uuid,
+        data_field_values: Option<Vec<DataFieldValue>>,
+        lot: Option<String>,
+        reason: Option<String>
+    ) -> FieldResult<CorrectionResponse> {
+        authorization::process_execution(context, process_execution_id, Permission::Operate)?;
+        correction::correct_event(context, process_execution_id, data_field_values, lot, reason)
+    }
 
-// Example 1: Increment only the reference number
-let new_lot_code = insert_lot_code(&conn, agent_id, false, true)
-    .expect("Error inserting new lot code");
+    /// Inherited fields are prefilled and locked when the flow is executed
+    fn set_data_field_inheritance(
+        context: &Context,
+        data_field_id: Uuid,
+        inherits: Option<Uuid>,
+    ) -> FieldResult<RecipeFlowDataField> {
+        authorization::process_flow_data_field(context, data_field_id, Permission::Design)?;
+        if let Some(inherits) = inherits {
+            authorization::process_flow_data_field(context, inherits, Permission::Design)?;
+        }
+        inheritance::set_data_field_inheritance(context, data_field_id, inherits)
+    }
 
-// Example 2: Increment the lot code and the reference number
-let new_lot_code = insert_lot_code(&conn, agent_id, true, true)
-    .expect("Error inserting new lot code");
+    fn set_process_field_option_set(
+        context: &Context,
+        data_field_id: Uuid,
+        option_set_id: Option<Uuid>,
+    ) -> FieldResult<RecipeFlowDataField> {
+        authorization::process_flow_data_field(context, data_field_id, Permission::Design)?;
+        option_set::set_process_field_option_set(context, data_field_id, option_set_id)
+    }
 
-*/
\ No newline at end of file
+    /// A literal, or one of today(), agent.primaryLocation and nextLot(), applied when the field is left empty
+    fn set_data_field_default(
+        context: &Context,
+        data_field_id: Uuid,
+        default_value: Option<String>,
+    ) -> FieldResult<RecipeFlowDataField> {
+        authorization::process_flow_data_field(context, data_field_id, Permission::Design)?;
+        default_value::set_data_field_default(context, data_field_id, default_value)
+    }
+}
@@ -1,54 +1,91 @@
 use crate::{
     common::{
-        agent::{Agent, AgentWithLocations}, economic_resource::{EconomicResource, EconomicResourceWithSpec}, location::Location, resource_specification::ResourceSpecification
+        agent::{Agent, AgentWithLocations}, containment::ContainmentNode, counter::Counter, economic_resource::{Decimal, EconomicResource, EconomicResourceWithSpec}, ledger::{InventoryLedgerEntry, StockGrouping, StockLevel}, location::{Location, LocationNode}, resource_specification::ResourceSpecification, unit::{Unit, UnitDimension}, user::{AgentMember, AgentMembership, ApiKey, Permission, User}
     },
     graphql::context::Context,
-    recipe::recipe::RecipeWithResources,
-    templates::{map_template::MapTemplateResponse, recipe_template::RecipeTemplateWithRecipeFlows},
+    recipe::{process::{data_field::InheritedValue, execution::ProcessExecutionResponse, graph::RecipeProcessNode, process::RecipeProcessesResponse, validation::FieldViolation}, recipe::RecipeWithResources},
+    templates::{map_template::MapTemplateResponse, option_set::{FieldOptions, OptionSetResponse}, recipe_template::RecipeTemplateWithRecipeFlows, template_blocker::{TemplateBlocker, TemplateItem}},
+    traceability::genealogy::{GenealogyResponse, TraceDirection, TraceMode},
 };
 use juniper::{graphql_object, FieldResult};
 use uuid::Uuid;
 
 use super::modules::{
-    common::{agent, economic_resource, location, resource_specification}, 
-    // process::process::{self, RecipeProcessesResponse}, 
-    recipe::recipe, templates::template
+    common::{agent, authorization, containment, counter, economic_resource, ledger, location, resource_specification, unit, user}, 
+    process::{correction, execution::{self, DataFieldValue}, inheritance, process, validation}, 
+    recipe::recipe, templates::{lifecycle, option_set, rule, template}, traceability::genealogy
 };
 
 pub struct QueryRoot;
 
 #[graphql_object(Context = Context)]
 impl QueryRoot {
+    /*** Users */
+    /// The user the API key of the request belongs to
+    fn viewer(context: &Context) -> FieldResult<User> {
+        user::viewer(context)
+    }
+
+    fn api_keys(context: &Context) -> FieldResult<Vec<ApiKey>> {
+        user::api_keys_of_viewer(context)
+    }
+
+    fn memberships(context: &Context) -> FieldResult<Vec<AgentMembership>> {
+        user::memberships_of_viewer(context)
+    }
+
+    fn agent_members(context: &Context, agent_id: Uuid) -> FieldResult<Vec<AgentMember>> {
+        user::agent_members(context, agent_id)
+    }
+
     /*** Agents */
     fn all_agents(context: &Context) -> FieldResult<Vec<Agent>> {
-        agent::all_agents(&context)
+        context.user()?;
+        agent::all_agents(context)
     }
 
     fn agent_by_id(context: &Context, agent_id: Uuid) -> FieldResult<Agent> {
-        agent::agent_by_id(&context, agent_id)
+        authorization::agent(context, agent_id, Permission::Read)?;
+        agent::agent_by_id(context, agent_id)
     }
 
     fn agents_with_location(context: &Context) -> FieldResult<Vec<AgentWithLocations>> {
-        agent::agents_with_location(&context)
+        context.user()?;
+        agent::agents_with_location(context)
     }
 
     /*** Resource Specifications */
     fn all_resource_specifications(context: &Context) -> FieldResult<Vec<ResourceSpecification>> {
-        resource_specification::all_resource_specifications(&context)
+        context.user()?;
+        resource_specification::all_resource_specifications(context)
     }
 
     fn resource_specifications_by_agent(
         context: &Context,
         agent_id: Uuid,
     ) -> FieldResult<Vec<ResourceSpecification>> {
-        resource_specification::resource_specifications_by_agent(&context, agent_id)
+        authorization::agent(context, agent_id, Permission::Read)?;
+        resource_specification::resource_specifications_by_agent(context, agent_id)
     }
 
     fn resource_specification_by_id(
         context: &Context,
         resource_specification_id: Uuid,
     ) -> FieldResult<ResourceSpecification> {
-        resource_specification::resource_specification_by_id(&context, resource_specification_id)
+        authorization::resource_specification(context, resource_specification_id, Permission::Read)?;
+        resource_specification::resource_specification_by_id(context, resource_specification_id)
+    }
+
+    /*** Units */
+    fn units(context: &Context, dimension: Option<UnitDimension>) -> FieldResult<Vec<Unit>> {
+        context.user()?;
+        unit::get_units(context, dimension)
+    }
+
+    /// Units are named by symbol or label, only units of one dimension convert
+    fn convert_quantity(context: &Context, quantity: Decimal, from: String, to: String) -> FieldResult<Decimal> {
+        context.user()?;
+        unit::convert_quantity(context, quantity, from, to)
     }
 
     /*** Economic Resources */
@@ -56,8 +93,9 @@ impl QueryRoot {
         context: &Context,
         resource_specification_id: Uuid,
     ) -> FieldResult<Vec<EconomicResource>> {
+        authorization::resource_specification(context, resource_specification_id, Permission::Read)?;
         economic_resource::economic_resources_by_specification_id(
-            &context,
+            context,
             resource_specification_id,
         )
     }
@@ -66,54 +104,220 @@ impl QueryRoot {
         context: &Context,
         agent_id: Uuid,
     ) -> FieldResult<Vec<EconomicResourceWithSpec>> {
-        economic_resource::economic_resources_by_agent(&context, agent_id)
+        authorization::agent(context, agent_id, Permission::Read)?;
+        economic_resource::economic_resources_by_agent(context, agent_id)
+    }
+
+    /// The resource with everything packed into it, cases of a pallet and their units
+    fn containment_tree(context: &Context, economic_resource_id: Uuid) -> FieldResult<ContainmentNode> {
+        authorization::economic_resource(context, economic_resource_id, Permission::Read)?;
+        containment::containment_tree(context, economic_resource_id)
+    }
+
+    /*** Inventory Ledger */
+    /// Stock as the ledger had it at the time, now when no time is given
+    fn stock_as_of(
+        context: &Context,
+        as_of: Option<String>,
+        group_by: Vec<StockGrouping>,
+        agent_id: Option<Uuid>,
+        resource_specification_id: Option<Uuid>,
+        location_id: Option<Uuid>,
+    ) -> FieldResult<Vec<StockLevel>> {
+        context.user()?;
+        if let Some(agent_id) = agent_id {
+            authorization::agent(context, agent_id, Permission::Read)?;
+        }
+        ledger::stock_as_of(context, as_of, group_by, agent_id, resource_specification_id, location_id)
+    }
+
+    fn inventory_ledger(
+        context: &Context,
+        economic_resource_id: Option<Uuid>,
+        lot: Option<String>,
+        from: Option<String>,
+        to: Option<String>,
+    ) -> FieldResult<Vec<InventoryLedgerEntry>> {
+        context.user()?;
+        ledger::ledger_entries(context, economic_resource_id, lot, from, to)
     }
 
     /** Get Map Templates */
     fn get_map_templates(context: &Context) -> FieldResult<Vec<MapTemplateResponse>> {
+        context.user()?;
         template::get_map_templates(context)
     }
 
     fn get_map_template_by_id(context: &Context, map_id: Uuid) -> FieldResult<MapTemplateResponse> {
+        context.user()?;
         template::get_map_template_by_id(context, map_id)
     }
 
     /** Recipe Templates */
 
     fn get_template_by_id(context: &Context, template_id: Uuid) -> FieldResult<RecipeTemplateWithRecipeFlows> {
+        context.user()?;
         template::get_template_by_id(context, template_id)
     }
 
+    /// Latest version of the template when no version is given
+    fn get_template_version(
+        context: &Context,
+        map_template_id: Uuid,
+        identifier: String,
+        version: Option<i32>,
+    ) -> FieldResult<RecipeTemplateWithRecipeFlows> {
+        context.user()?;
+        template::get_template_version(context, map_template_id, identifier, version)
+    }
+
+    fn get_template_versions(context: &Context, template_id: Uuid) -> FieldResult<Vec<RecipeTemplateWithRecipeFlows>> {
+        context.user()?;
+        template::get_template_versions(context, template_id)
+    }
+
+    /// Templates of the same map whose processes may be an output of the template's process
+    fn get_allowed_successors(context: &Context, template_id: Uuid) -> FieldResult<Vec<RecipeTemplateWithRecipeFlows>> {
+        context.user()?;
+        template::get_allowed_successors(context, template_id)
+    }
+
+    /// What a delete of the item would have to cascade over
+    fn template_blockers(context: &Context, item: TemplateItem, id: Uuid) -> FieldResult<Vec<TemplateBlocker>> {
+        context.user()?;
+        lifecycle::template_blockers(context, item, id)
+    }
+
+    /// Rules of the template's data fields that are broken, for instance by a deleted field
+    fn validate_template_rules(context: &Context, recipe_template_id: Uuid) -> FieldResult<Vec<FieldViolation>> {
+        context.user()?;
+        rule::validate_template_rules(context, recipe_template_id)
+    }
+
+    /** Option Sets */
+    fn get_option_sets(context: &Context) -> FieldResult<Vec<OptionSetResponse>> {
+        context.user()?;
+        option_set::get_option_sets(context)
+    }
+
     /** Recipe Template Access */
     fn get_templates_access_by_agent(
         context: &Context,
         agent_id: Uuid,
     ) -> FieldResult<Vec<RecipeTemplateWithRecipeFlows>> {
+        authorization::agent(context, agent_id, Permission::Read)?;
         template::get_templates_access_by_agent(context, agent_id)
     }
 
     /*** Recipe */
     fn recipe_by_id(context: &Context, recipe_id: Uuid) -> FieldResult<RecipeWithResources> {
-        recipe::recipe_by_id(&context, recipe_id)
+        authorization::recipe(context, recipe_id, Permission::Read)?;
+        recipe::recipe_by_id(context, recipe_id)
     }
 
     /** Locations */
     fn locations_by_agent(context: &Context, agent_id: Uuid) -> FieldResult<Vec<Location>> {
-        location::locations_by_agent(&context, agent_id)
+        authorization::agent(context, agent_id, Permission::Read)?;
+        location::locations_by_agent(context, agent_id)
+    }
+
+    /// The location with its zones, bins and further nested locations
+    fn location_tree(context: &Context, location_id: Uuid) -> FieldResult<LocationNode> {
+        authorization::location(context, location_id, Permission::Read)?;
+        location::location_tree(context, location_id)
     }
 
     fn recipes_by_agent(
         context: &Context,
         agent_id: Uuid,
     ) -> FieldResult<Vec<RecipeWithResources>> {
-        recipe::recipes_by_agent(&context, agent_id)
+        authorization::agent(context, agent_id, Permission::Read)?;
+        recipe::recipes_by_agent(context, agent_id)
+    }
+
+    /** Counters */
+    fn counter_by_agent(context: &Context, agent_id: Uuid) -> FieldResult<Counter> {
+        authorization::agent(context, agent_id, Permission::Read)?;
+        counter::counter_by_agent(context, agent_id)
     }
 
 
-    // fn get_recipe_processes(
-    //     context: &Context,
-    //     recipe_id: Uuid
-    // ) -> FieldResult<RecipeProcessesResponse> {
-    //     process::get_recipe_processes(context, recipe_id)
-    // }
+    /** Process */
+    fn get_recipe_processes(
+        context: &Context,
+        recipe_id: Uuid
+    ) -> FieldResult<RecipeProcessesResponse> {
+        authorization::recipe(context, recipe_id, Permission::Read)?;
+        process::get_recipe_processes(context, recipe_id)
+    }
+
+    /// Processes in topological order with their depth
+    fn recipe_process_graph(context: &Context, recipe_id: Uuid) -> FieldResult<Vec<RecipeProcessNode>> {
+        authorization::recipe(context, recipe_id, Permission::Read)?;
+        process::get_recipe_process_graph(context, recipe_id)
+    }
+
+    /** Process Execution */
+    /// Effective executions, corrected ones and their reversals are included on request
+    fn process_executions_by_recipe_process(
+        context: &Context,
+        recipe_process_id: Uuid,
+        include_corrections: Option<bool>
+    ) -> FieldResult<Vec<ProcessExecutionResponse>> {
+        authorization::recipe_process(context, recipe_process_id, Permission::Read)?;
+        execution::process_executions_by_recipe_process(context, recipe_process_id, include_corrections)
+    }
+
+    /// The original execution followed by its reversals and replacements
+    fn execution_corrections(
+        context: &Context,
+        process_execution_id: Uuid
+    ) -> FieldResult<Vec<ProcessExecutionResponse>> {
+        authorization::process_execution(context, process_execution_id, Permission::Read)?;
+        correction::execution_corrections(context, process_execution_id)
+    }
+
+    /// Every problem with the values, without executing the flow
+    fn validate_flow_values(
+        context: &Context,
+        process_flow_id: Uuid,
+        data_field_values: Vec<DataFieldValue>,
+    ) -> FieldResult<Vec<FieldViolation>> {
+        authorization::process_flow(context, process_flow_id, Permission::Read)?;
+        validation::validate_flow_values(context, process_flow_id, data_field_values)
+    }
+
+    /// Values the inheriting fields of a flow take from the fields they inherit
+    fn get_inherited_values(context: &Context, process_flow_id: Uuid) -> FieldResult<Vec<InheritedValue>> {
+        authorization::process_flow(context, process_flow_id, Permission::Read)?;
+        inheritance::get_inherited_values(context, process_flow_id)
+    }
+
+    /// Choices of the Select fields of a flow, for the owner of the recipe when no agent is given
+    fn get_field_options(
+        context: &Context,
+        process_flow_id: Uuid,
+        agent_id: Option<Uuid>,
+    ) -> FieldResult<Vec<FieldOptions>> {
+        authorization::process_flow(context, process_flow_id, Permission::Read)?;
+        if let Some(agent_id) = agent_id {
+            authorization::agent(context, agent_id, Permission::Read)?;
+        }
+        option_set::get_field_options(context, process_flow_id, agent_id)
+    }
+
+    /** Traceability */
+    fn trace_lot(
+        context: &Context,
+        lot: Option<String>,
+        economic_resource_id: Option<Uuid>,
+        direction: TraceDirection,
+        mode: TraceMode
+    ) -> FieldResult<GenealogyResponse> {
+        context.user()?;
+        if let Some(economic_resource_id) = economic_resource_id {
+            authorization::economic_resource(context, economic_resource_id, Permission::Read)?;
+        }
+        genealogy::trace_lot(context, lot, economic_resource_id, direction, mode)
+    }
 }
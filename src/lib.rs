@@ -1,5 +1,8 @@
-mod recipe;
-mod db;
-mod graphql;
-mod templates;
-mod common;
\ No newline at end of file
+pub mod recipe;
+pub mod db;
+pub mod graphql;
+pub mod templates;
+pub mod common;
+pub mod traceability;
+pub mod fda;
+pub mod epcis;
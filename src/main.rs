@@ -1,11 +1,5 @@
-mod db;
-mod graphql;
-mod recipe;
-mod common;
-mod templates;
-
-use crate::db::conn::establish_connection_pool;
-use crate::graphql::handler;
+use vf::db::conn::establish_connection_pool;
+use vf::graphql::handler;
 use std::sync::Arc;
 
 #[actix_web::main]
@@ -1,2 +1,4 @@
+#![allow(clippy::module_inception)]
+
 pub mod recipe;
 pub mod process;
\ No newline at end of file
@@ -0,0 +1,225 @@
+use bigdecimal::{BigDecimal, Zero};
+
+use crate::templates::recipe_flow_template::{ActionType, RoleType};
+
+/// How an event changes the accounting or on hand quantity of a resource
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum QuantityEffect {
+    NoEffect,
+    Increment,
+    Decrement,
+    /// Decrements the provider resource and increments the receiver resource
+    DecrementIncrement,
+}
+
+impl QuantityEffect {
+    /// Returns the (provider resource, receiver resource) deltas for a quantity
+    pub fn deltas(&self, quantity: &BigDecimal) -> (BigDecimal, BigDecimal) {
+        match self {
+            QuantityEffect::NoEffect => (BigDecimal::zero(), BigDecimal::zero()),
+            QuantityEffect::Increment => (quantity.clone(), BigDecimal::zero()),
+            QuantityEffect::Decrement => (-quantity, BigDecimal::zero()),
+            QuantityEffect::DecrementIncrement => (-quantity, quantity.clone()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LocationEffect {
+    NoEffect,
+    /// The created or received resource is placed at the event location
+    New,
+    /// The resource is moved to the event location
+    Update,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CustodyEffect {
+    NoEffect,
+    /// The receiver agent takes custody of the received resource
+    Transfer,
+}
+
+/// What the flow has to point at for the action to be executed
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ResourceRequirement {
+    /// A new economic resource is created from a resource specification
+    Create,
+    /// An existing economic resource
+    Existing,
+    /// A resource specification or an existing economic resource
+    Specification,
+}
+
+#[derive(Debug)]
+pub struct ActionEffect {
+    pub action: ActionType,
+    pub roles: &'static [RoleType],
+    pub resource: ResourceRequirement,
+    pub requires_quantity: bool,
+    pub accounting_effect: QuantityEffect,
+    pub onhand_effect: QuantityEffect,
+    pub location_effect: LocationEffect,
+    pub custody_effect: CustodyEffect,
+}
+
+impl ActionEffect {
+    /// True when the event moves quantity from a provider resource into a receiver resource
+    pub fn has_receiver_resource(&self) -> bool {
+        self.accounting_effect == QuantityEffect::DecrementIncrement
+            || self.onhand_effect == QuantityEffect::DecrementIncrement
+    }
+}
+
+/// ValueFlows action effects, one entry per ActionType
+pub const ACTION_EFFECTS: [ActionEffect; 10] = [
+    ActionEffect {
+        action: ActionType::Cite,
+        roles: &[RoleType::Input],
+        resource: ResourceRequirement::Specification,
+        requires_quantity: false,
+        accounting_effect: QuantityEffect::NoEffect,
+        onhand_effect: QuantityEffect::NoEffect,
+        location_effect: LocationEffect::NoEffect,
+        custody_effect: CustodyEffect::NoEffect,
+    },
+    ActionEffect {
+        action: ActionType::Modify,
+        roles: &[RoleType::Output],
+        resource: ResourceRequirement::Existing,
+        requires_quantity: true,
+        accounting_effect: QuantityEffect::NoEffect,
+        onhand_effect: QuantityEffect::Increment,
+        location_effect: LocationEffect::NoEffect,
+        custody_effect: CustodyEffect::NoEffect,
+    },
+    ActionEffect {
+        action: ActionType::Produce,
+        roles: &[RoleType::Output],
+        resource: ResourceRequirement::Create,
+        requires_quantity: true,
+        accounting_effect: QuantityEffect::Increment,
+        onhand_effect: QuantityEffect::Increment,
+        location_effect: LocationEffect::New,
+        custody_effect: CustodyEffect::NoEffect,
+    },
+    ActionEffect {
+        action: ActionType::Consume,
+        roles: &[RoleType::Input],
+        resource: ResourceRequirement::Existing,
+        requires_quantity: true,
+        accounting_effect: QuantityEffect::Decrement,
+        onhand_effect: QuantityEffect::Decrement,
+        location_effect: LocationEffect::NoEffect,
+        custody_effect: CustodyEffect::NoEffect,
+    },
+    ActionEffect {
+        action: ActionType::Transfer,
+        roles: &[RoleType::Input, RoleType::Output],
+        resource: ResourceRequirement::Existing,
+        requires_quantity: true,
+        accounting_effect: QuantityEffect::DecrementIncrement,
+        onhand_effect: QuantityEffect::DecrementIncrement,
+        location_effect: LocationEffect::New,
+        custody_effect: CustodyEffect::Transfer,
+    },
+    ActionEffect {
+        action: ActionType::Use,
+        roles: &[RoleType::Input],
+        resource: ResourceRequirement::Existing,
+        requires_quantity: false,
+        accounting_effect: QuantityEffect::NoEffect,
+        onhand_effect: QuantityEffect::NoEffect,
+        location_effect: LocationEffect::NoEffect,
+        custody_effect: CustodyEffect::NoEffect,
+    },
+    ActionEffect {
+        action: ActionType::Load,
+        roles: &[RoleType::Input],
+        resource: ResourceRequirement::Existing,
+        requires_quantity: true,
+        accounting_effect: QuantityEffect::NoEffect,
+        onhand_effect: QuantityEffect::Decrement,
+        location_effect: LocationEffect::NoEffect,
+        custody_effect: CustodyEffect::NoEffect,
+    },
+    ActionEffect {
+        action: ActionType::Unload,
+        roles: &[RoleType::Output],
+        resource: ResourceRequirement::Existing,
+        requires_quantity: true,
+        accounting_effect: QuantityEffect::NoEffect,
+        onhand_effect: QuantityEffect::Increment,
+        location_effect: LocationEffect::Update,
+        custody_effect: CustodyEffect::NoEffect,
+    },
+    ActionEffect {
+        action: ActionType::Accept,
+        roles: &[RoleType::Input],
+        resource: ResourceRequirement::Existing,
+        requires_quantity: true,
+        accounting_effect: QuantityEffect::NoEffect,
+        onhand_effect: QuantityEffect::Decrement,
+        location_effect: LocationEffect::NoEffect,
+        custody_effect: CustodyEffect::NoEffect,
+    },
+    ActionEffect {
+        action: ActionType::Dispatch,
+        roles: &[RoleType::Output],
+        resource: ResourceRequirement::Existing,
+        requires_quantity: true,
+        accounting_effect: QuantityEffect::NoEffect,
+        onhand_effect: QuantityEffect::DecrementIncrement,
+        location_effect: LocationEffect::New,
+        custody_effect: CustodyEffect::Transfer,
+    },
+];
+
+impl ActionType {
+    pub fn effect(&self) -> &'static ActionEffect {
+        ACTION_EFFECTS
+            .iter()
+            .find(|effect| effect.action == *self)
+            .expect("Every action has an entry in ACTION_EFFECTS")
+    }
+
+    pub fn allows_role(&self, role_type: RoleType) -> bool {
+        self.effect().roles.contains(&role_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_action_has_one_effect() {
+        for effect in &ACTION_EFFECTS {
+            let count = ACTION_EFFECTS.iter().filter(|e| e.action == effect.action).count();
+            assert_eq!(count, 1, "{:?} is defined {} times", effect.action, count);
+            assert!(!effect.roles.is_empty());
+        }
+    }
+
+    #[test]
+    fn roles_follow_valueflows() {
+        assert!(ActionType::Produce.allows_role(RoleType::Output));
+        assert!(!ActionType::Produce.allows_role(RoleType::Input));
+        assert!(ActionType::Consume.allows_role(RoleType::Input));
+        assert!(!ActionType::Consume.allows_role(RoleType::Output));
+        assert!(ActionType::Transfer.allows_role(RoleType::Input));
+        assert!(ActionType::Transfer.allows_role(RoleType::Output));
+    }
+
+    #[test]
+    fn quantity_deltas() {
+        let quantity: BigDecimal = "12.5".parse().unwrap();
+        let (zero, minus) = (BigDecimal::zero(), -&quantity);
+        assert_eq!(QuantityEffect::NoEffect.deltas(&quantity), (zero.clone(), zero.clone()));
+        assert_eq!(QuantityEffect::Increment.deltas(&quantity), (quantity.clone(), zero.clone()));
+        assert_eq!(QuantityEffect::Decrement.deltas(&quantity), (minus.clone(), zero));
+        assert_eq!(QuantityEffect::DecrementIncrement.deltas(&quantity), (minus, quantity.clone()));
+        assert!(ActionType::Transfer.effect().has_receiver_resource());
+        assert!(!ActionType::Consume.effect().has_receiver_resource());
+    }
+}
@@ -2,7 +2,11 @@ use diesel::prelude::{Insertable, Queryable};
 use juniper::GraphQLObject;
 use uuid::Uuid;
 
-use crate::{db::schema::recipe_process_flow_data_fields, templates::recipe_flow_template_data_field::{FieldClass, FieldType, FlowThrough}};
+use crate::{
+    common::economic_resource::Decimal,
+    db::schema::{recipe_process_flow_data_fields, recipe_process_flow_group_data_fields}, 
+    templates::{recipe_flow_template_data_field::{FieldClass, FieldType, FlowThrough}, recipe_flow_template_group_data_fields::FieldGroupClass}
+};
 
 #[derive(Queryable, GraphQLObject, Debug, Clone)]
 #[diesel(table_name = recipe_process_flow_data_fields)]
@@ -21,6 +25,11 @@ pub struct RecipeFlowDataField {
     pub default_value: Option<String>,
     pub flow_through: Option<FlowThrough>,
     pub inherits: Option<Uuid>,
+    pub option_set_id: Option<Uuid>,
+    /// Lowest value of a Number field
+    pub min_value: Option<Decimal>,
+    /// Highest value of a Number field
+    pub max_value: Option<Decimal>,
 }
 
 
@@ -29,6 +38,7 @@ pub struct RecipeFlowDataField {
 pub struct NewRecipeFlowDataField<'a> {
     pub recipe_process_flow_id: &'a Uuid,
     pub recipe_flow_template_data_field_id: Option<&'a Uuid>,
+    pub group_id: Option<&'a Uuid>,
     pub field_identifier: &'a str,
     pub field_class: &'a FieldClass,
     pub field: &'a str,
@@ -37,35 +47,76 @@ pub struct NewRecipeFlowDataField<'a> {
     pub required: bool,
     pub default_value: Option<&'a str>,
     pub flow_through: Option<&'a FlowThrough>,
-    pub inherits: Option<&'a Uuid>
+    pub inherits: Option<&'a Uuid>,
+    pub option_set_id: Option<&'a Uuid>,
+    pub min_value: Option<&'a Decimal>,
+    pub max_value: Option<&'a Decimal>
 }
 
 impl<'a>  NewRecipeFlowDataField<'a> {
+    /// Template field, group, note, default, flow through, inheritance,
+    /// option set and range are set on the result when given
     pub fn new(
         recipe_process_flow_id: &'a Uuid,
-        recipe_flow_template_data_field_id: Option<&'a Uuid>,
         field_identifier: &'a str,
         field_class: &'a FieldClass,
         field: &'a str,
         field_type: &'a FieldType,
-        note: Option<&'a str>,
-        required: bool,
-        default_value: Option<&'a str>,
-        flow_through: Option<&'a FlowThrough>,
-        inherits: Option<&'a Uuid>
+        required: bool
     ) -> Self {
         NewRecipeFlowDataField {
             recipe_process_flow_id,
-            recipe_flow_template_data_field_id, 
+            recipe_flow_template_data_field_id: None,
+            group_id: None,
             field_identifier,
             field_class,
             field,
             field_type,
-            note,
+            note: None,
             required,
-            default_value,
-            flow_through,
-            inherits
+            default_value: None,
+            flow_through: None,
+            inherits: None,
+            option_set_id: None,
+            min_value: None,
+            max_value: None
         }
     }
-}
\ No newline at end of file
+}
+
+#[derive(Queryable, GraphQLObject, Debug, Clone)]
+#[diesel(table_name = recipe_process_flow_group_data_fields)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct RecipeFlowGroupDataField {
+    pub id: Uuid,
+    pub name: String,
+    pub group_class: FieldGroupClass
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = recipe_process_flow_group_data_fields)]
+pub struct NewRecipeFlowGroupDataField<'a> {
+    pub name: &'a str,
+    pub group_class: &'a FieldGroupClass,
+}
+
+impl<'a> NewRecipeFlowGroupDataField<'a> {
+    pub fn new(
+        name: &'a str,
+        group_class: &'a FieldGroupClass
+    ) -> Self {
+        NewRecipeFlowGroupDataField {
+            name,
+            group_class
+        }
+    }
+}
+
+/// Value a data field takes from the field it inherits, as recorded by the last execution of that field
+#[derive(GraphQLObject, Debug, Clone)]
+pub struct InheritedValue {
+    pub data_field_id: Uuid,
+    pub inherits: Uuid,
+    pub value: Option<String>,
+    pub process_execution_id: Option<Uuid>,
+}
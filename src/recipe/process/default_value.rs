@@ -0,0 +1,45 @@
+/// Default of a process data field, either a fixed value or one resolved when the flow is executed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DefaultValue {
+    Literal(String),
+    /// Date of the execution
+    Today,
+    /// Primary location of the agent owning the recipe
+    AgentPrimaryLocation,
+    /// Lot of the resource the flow creates, the next code of the counter of
+    /// the agent owning the recipe unless a lot is submitted with the flow
+    NextLot,
+}
+
+impl DefaultValue {
+    /// Anything that looks like a call or an `agent.` property has to be one of
+    /// the known expressions, so typos aren't recorded as values
+    pub fn parse(default_value: &str) -> Result<Self, String> {
+        let default_value = default_value.trim();
+        match default_value {
+            "today()" => Ok(DefaultValue::Today),
+            "agent.primaryLocation" => Ok(DefaultValue::AgentPrimaryLocation),
+            "nextLot()" => Ok(DefaultValue::NextLot),
+            _ if default_value.ends_with("()") || default_value.starts_with("agent.") => Err(format!(
+                "Unknown default {}, expected today(), agent.primaryLocation or nextLot()",
+                default_value
+            )),
+            _ => Ok(DefaultValue::Literal(default_value.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_expressions_and_literals() {
+        assert_eq!(DefaultValue::parse(" today() "), Ok(DefaultValue::Today));
+        assert_eq!(DefaultValue::parse("agent.primaryLocation"), Ok(DefaultValue::AgentPrimaryLocation));
+        assert_eq!(DefaultValue::parse("nextLot()"), Ok(DefaultValue::NextLot));
+        assert_eq!(DefaultValue::parse("BOL"), Ok(DefaultValue::Literal("BOL".to_string())));
+        assert!(DefaultValue::parse("tomorrow()").is_err());
+        assert!(DefaultValue::parse("agent.name").is_err());
+    }
+}
@@ -1,27 +1,34 @@
 
 
+use std::collections::HashSet;
+
 use chrono::NaiveDateTime;
 use diesel::prelude::{Insertable, Queryable};
 use juniper::GraphQLObject;
 use uuid::Uuid;
 
-use crate::{db::schema::process_executions, templates::recipe_flow_template::{ActionType, RoleType}};
+use crate::{
+    common::{economic_resource::Decimal, resource_operation::ResourceOperation},
+    db::schema::{process_execution_custom_values, process_executions}, 
+    templates::recipe_flow_template::{ActionType, RoleType}
+};
 
 #[derive(Queryable, GraphQLObject, Debug, Clone)]
 #[diesel(table_name = process_executions)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct ProcessExecution {
     pub id: Uuid,
-    pub process_flow_id: Uuid,
+    /// Flow the execution was recorded against, none for a split or a merge
+    pub process_flow_id: Option<Uuid>,
     pub action: ActionType,
     pub role_type: RoleType,
     pub resource_specification: Option<Uuid>,
     pub resource_reference_number: Option<i32>,
-    pub resource_lot_number: Option<i32>,
-    pub resource_quantity: Option<i32>,
+    pub resource_lot_number: Option<String>,
+    pub resource_quantity: Option<Decimal>,
     pub to_resource_specification: Option<Uuid>,
     pub to_resource_reference_number: Option<i32>,
-    pub to_resource_lot_number: Option<i32>,
+    pub to_resource_lot_number: Option<String>,
     pub provider_agent: Uuid,
     pub receiver_agent: Uuid,
     pub at_location: Option<Uuid>,
@@ -29,69 +36,283 @@ pub struct ProcessExecution {
     pub has_point_in_time: Option<NaiveDateTime>,
     pub created_at: NaiveDateTime,
     pub corrects: Option<Uuid>,
-    pub note: Option<String>
+    pub note: Option<String>,
+    pub economic_resource_id: Option<Uuid>,
+    pub to_economic_resource_id: Option<Uuid>,
+    pub batch_id: Option<Uuid>,
+    pub epcis_event_id: Option<String>,
+    /// Compensates the execution it corrects, reversing its effect on the resources
+    pub reversal: bool,
+    /// Unit the quantity was recorded in, the unit of the resource specification when empty
+    pub unit_id: Option<Uuid>,
+    /// Container the resource was packed into by a Load or unpacked from by an Unload
+    pub container_id: Option<Uuid>,
+    /// Split or merge the execution is part of, with the others of its batch
+    pub operation: Option<ResourceOperation>,
+    /// User who performed the execution, none for executions from before users
+    pub performed_by: Option<Uuid>
+}
+
+impl ProcessExecution {
+    /// True when the execution recorded one of the resources or the lot, on either end of the flow
+    pub fn records(&self, resources: &[Uuid], lot: Option<&str>) -> bool {
+        let resource = [self.economic_resource_id, self.to_economic_resource_id]
+            .iter()
+            .flatten()
+            .any(|id| resources.contains(id));
+        let lot = lot.is_some_and(|lot| {
+            [&self.resource_lot_number, &self.to_resource_lot_number]
+                .iter()
+                .any(|recorded| recorded.as_deref() == Some(lot))
+        });
+
+        resource || lot
+    }
+}
+
+/// What the records currently say, given the ids of the executions that were
+/// corrected: reversals and the executions they reversed are left out,
+/// replacements stand in for the originals
+pub fn effective(executions: Vec<ProcessExecution>, corrected: &HashSet<Uuid>) -> Vec<ProcessExecution> {
+    executions
+        .into_iter()
+        .filter(|e| !e.reversal && !corrected.contains(&e.id))
+        .collect()
 }
 
 #[derive(Insertable)]
 #[diesel(table_name = process_executions)]
 pub struct NewProcessExecution<'a> {
-    pub process_flow_id: &'a Uuid,
+    /// Set when ledger entries are recorded before the execution is inserted
+    pub id: Option<&'a Uuid>,
+    pub process_flow_id: Option<&'a Uuid>,
     pub action: &'a ActionType,
     pub role_type: &'a RoleType,
     pub resource_specification: Option<&'a Uuid>,
     pub resource_reference_number: Option<&'a i32>,
-    pub resource_lot_number: Option<&'a i32>,
-    pub resource_quantity: Option<&'a i32>,
+    pub resource_lot_number: Option<&'a str>,
+    pub resource_quantity: Option<&'a Decimal>,
     pub to_resource_specification: Option<&'a Uuid>,
     pub to_resource_reference_number: Option<&'a i32>,
-    pub to_resource_lot_number: Option<&'a i32>,
+    pub to_resource_lot_number: Option<&'a str>,
     pub provider_agent:&'a Uuid,
     pub receiver_agent:&'a Uuid,
     pub at_location: Option<&'a Uuid>,
     pub to_location: Option<&'a Uuid>,
     pub has_point_in_time: Option<&'a NaiveDateTime>,
     pub corrects: Option<&'a Uuid>,
-    pub note: Option<&'a String>
+    pub note: Option<&'a str>,
+    pub economic_resource_id: Option<&'a Uuid>,
+    pub to_economic_resource_id: Option<&'a Uuid>,
+    pub batch_id: Option<&'a Uuid>,
+    pub reversal: bool,
+    pub unit_id: Option<&'a Uuid>,
+    pub container_id: Option<&'a Uuid>,
+    pub operation: Option<&'a ResourceOperation>,
+    pub performed_by: Option<&'a Uuid>
 }
 
 impl<'a>  NewProcessExecution<'a> {
+    /// Resources, locations, time, correction and note are set on the result when known
     pub fn new(
-        process_flow_id: &'a Uuid,
+        process_flow_id: Option<&'a Uuid>,
         action: &'a ActionType,
         role_type: &'a RoleType,
-        resource_specification: Option<&'a Uuid>,
-        resource_reference_number: Option<&'a i32>,
-        resource_lot_number: Option<&'a i32>,
-        resource_quantity: Option<&'a i32>,
-        to_resource_specification: Option<&'a Uuid>,
-        to_resource_reference_number: Option<&'a i32>,
-        to_resource_lot_number: Option<&'a i32>,
         provider_agent:&'a Uuid,
         receiver_agent:&'a Uuid,
-        at_location: Option<&'a Uuid>,
-        to_location: Option<&'a Uuid>,
-        has_point_in_time: Option<&'a NaiveDateTime>,
-        corrects: Option<&'a Uuid>,
-        note: Option<&'a String>
+        batch_id: Option<&'a Uuid>
     ) -> Self {
         NewProcessExecution {
+            id: None,
             process_flow_id,
             action,
             role_type,
-            resource_specification,
-            resource_reference_number,
-            resource_lot_number,
-            resource_quantity,
-            to_resource_specification,
-            to_resource_reference_number,
-            to_resource_lot_number,
+            resource_specification: None,
+            resource_reference_number: None,
+            resource_lot_number: None,
+            resource_quantity: None,
+            to_resource_specification: None,
+            to_resource_reference_number: None,
+            to_resource_lot_number: None,
             provider_agent,
             receiver_agent,
-            at_location,
-            to_location,
-            has_point_in_time,
+            at_location: None,
+            to_location: None,
+            has_point_in_time: None,
+            corrects: None,
+            note: None,
+            economic_resource_id: None,
+            to_economic_resource_id: None,
+            batch_id,
+            reversal: false,
+            unit_id: None,
+            container_id: None,
+            operation: None,
+            performed_by: None
+        }
+    }
+}
+
+#[derive(Queryable, GraphQLObject, Debug, Clone)]
+#[diesel(table_name = process_execution_custom_values)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ProcessExecutionCustomValue {
+    pub id: Uuid,
+    pub process_execution_id: Uuid,
+    pub field_id: Uuid,
+    pub field_value: String,
+    pub corrects: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+    /// The value is the default of the field, nothing was submitted
+    pub from_default: bool
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = process_execution_custom_values)]
+pub struct NewProcessExecutionCustomValue<'a> {
+    pub process_execution_id: &'a Uuid,
+    pub field_id: &'a Uuid,
+    pub field_value: &'a str,
+    pub corrects: Option<&'a Uuid>,
+    pub from_default: bool
+}
+
+impl<'a> NewProcessExecutionCustomValue<'a> {
+    pub fn new(
+        process_execution_id: &'a Uuid,
+        field_id: &'a Uuid,
+        field_value: &'a str,
+        corrects: Option<&'a Uuid>
+    ) -> Self {
+        NewProcessExecutionCustomValue {
+            process_execution_id,
+            field_id,
+            field_value,
             corrects,
-            note
+            from_default: false
         }
     }
 }
+
+#[derive(GraphQLObject, Debug)]
+pub struct ProcessExecutionResponse {
+    pub execution: ProcessExecution,
+    pub custom_values: Vec<ProcessExecutionCustomValue>
+}
+
+impl ProcessExecutionResponse {
+    pub fn new(execution: ProcessExecution) -> Self {
+        ProcessExecutionResponse {
+            execution,
+            custom_values: Vec::new()
+        }
+    }
+
+    pub fn add_custom_value(&mut self, custom_value: ProcessExecutionCustomValue) {
+        self.custom_values.push(custom_value)
+    }
+}
+
+/// Reversal recorded by a correction and, unless the execution was voided, its replacement
+#[derive(GraphQLObject, Debug)]
+pub struct CorrectionResponse {
+    pub reversal: ProcessExecutionResponse,
+    pub replacement: Option<ProcessExecutionResponse>
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn execution() -> ProcessExecution {
+        ProcessExecution {
+            id: Uuid::new_v4(),
+            process_flow_id: Some(Uuid::new_v4()),
+            action: ActionType::Produce,
+            role_type: RoleType::Output,
+            resource_specification: None,
+            resource_reference_number: None,
+            resource_lot_number: None,
+            resource_quantity: None,
+            to_resource_specification: None,
+            to_resource_reference_number: None,
+            to_resource_lot_number: None,
+            provider_agent: Uuid::new_v4(),
+            receiver_agent: Uuid::new_v4(),
+            at_location: None,
+            to_location: None,
+            has_point_in_time: None,
+            created_at: NaiveDateTime::default(),
+            corrects: None,
+            note: None,
+            economic_resource_id: None,
+            to_economic_resource_id: None,
+            batch_id: None,
+            epcis_event_id: None,
+            reversal: false,
+            unit_id: None,
+            container_id: None,
+            operation: None,
+            performed_by: None,
+        }
+    }
+
+    #[test]
+    fn records_the_resource_on_either_end() {
+        let resource = Uuid::new_v4();
+        let mut produced = execution();
+        produced.economic_resource_id = Some(resource);
+        let mut transferred = execution();
+        transferred.to_economic_resource_id = Some(resource);
+
+        assert!(produced.records(&[resource], None));
+        assert!(transferred.records(&[Uuid::new_v4(), resource], None));
+        assert!(!produced.records(&[Uuid::new_v4()], None));
+    }
+
+    #[test]
+    fn records_the_lot_on_either_end() {
+        let mut produced = execution();
+        produced.resource_lot_number = Some("ACME-20261018-0001".to_string());
+        let mut transferred = execution();
+        transferred.to_resource_lot_number = Some("ACME-20261018-0001".to_string());
+
+        assert!(produced.records(&[], Some("ACME-20261018-0001")));
+        assert!(transferred.records(&[], Some("ACME-20261018-0001")));
+        assert!(!produced.records(&[], Some("ACME-20261018-0002")));
+        assert!(!produced.records(&[], None));
+    }
+
+    #[test]
+    fn replacements_stand_in_for_corrected_executions() {
+        let original = execution();
+        let mut reversal = execution();
+        reversal.reversal = true;
+        reversal.corrects = Some(original.id);
+        let mut replacement = execution();
+        replacement.corrects = Some(original.id);
+        let untouched = execution();
+
+        let corrected = HashSet::from([original.id]);
+        let ids: Vec<Uuid> = effective(vec![original, reversal, replacement.clone(), untouched.clone()], &corrected)
+            .iter()
+            .map(|e| e.id)
+            .collect();
+
+        assert_eq!(ids, vec![replacement.id, untouched.id]);
+    }
+
+    #[test]
+    fn corrected_replacements_give_way_to_theirs() {
+        let original = execution();
+        let mut first = execution();
+        first.corrects = Some(original.id);
+        let mut second = execution();
+        second.corrects = Some(first.id);
+
+        let corrected = HashSet::from([original.id, first.id]);
+        let ids: Vec<Uuid> = effective(vec![original, first, second.clone()], &corrected).iter().map(|e| e.id).collect();
+
+        assert_eq!(ids, vec![second.id]);
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+
+use juniper::GraphQLObject;
+use uuid::Uuid;
+
+/// A process of the recipe graph, depth 0 processes are not an output of any other
+#[derive(GraphQLObject, Debug, Clone)]
+pub struct RecipeProcessNode {
+    pub recipe_process_id: Uuid,
+    pub identifier: String,
+    pub name: String,
+    pub depth: i32,
+    pub output_of: Vec<Uuid>,
+}
+
+/// Processes in execution order with their depth, every process comes after
+/// the processes it is an output of. Relations are (recipe_process_id, output_of)
+/// pairs, on a cycle the processes of the loop are returned in order instead
+pub fn topological_order(processes: &[Uuid], relations: &[(Uuid, Uuid)]) -> Result<Vec<(Uuid, i32)>, Vec<Uuid>> {
+    let mut pending: HashMap<Uuid, usize> = processes.iter().map(|id| (*id, 0)).collect();
+    for (recipe_process_id, _) in relations {
+        if let Some(count) = pending.get_mut(recipe_process_id) {
+            *count += 1;
+        }
+    }
+
+    let mut depths: HashMap<Uuid, i32> = HashMap::new();
+    let mut order: Vec<(Uuid, i32)> = Vec::new();
+    let mut ready: Vec<Uuid> = processes.iter().filter(|id| pending[*id] == 0).copied().collect();
+
+    // Processes of the same stage keep the order they were given in
+    while !ready.is_empty() {
+        let mut next = Vec::new();
+        for id in ready {
+            let depth = *depths.get(&id).unwrap_or(&0);
+            order.push((id, depth));
+            for (recipe_process_id, _) in relations.iter().filter(|(_, output_of)| *output_of == id) {
+                let Some(count) = pending.get_mut(recipe_process_id) else {
+                    continue;
+                };
+                *count -= 1;
+                let successor_depth = depths.entry(*recipe_process_id).or_insert(0);
+                *successor_depth = (*successor_depth).max(depth + 1);
+                if *count == 0 {
+                    next.push(*recipe_process_id);
+                }
+            }
+        }
+        next.sort_by_key(|id| processes.iter().position(|p| p == id));
+        ready = next;
+    }
+
+    if order.len() == processes.len() {
+        return Ok(order);
+    }
+
+    Err(find_cycle(processes, relations, &order))
+}
+
+/// Every process left out of the order waits on another one left out, so
+/// walking back through those always ends up in a loop
+fn find_cycle(processes: &[Uuid], relations: &[(Uuid, Uuid)], order: &[(Uuid, i32)]) -> Vec<Uuid> {
+    let blocked = |id: &Uuid| !order.iter().any(|(ordered, _)| ordered == id);
+
+    let Some(start) = processes.iter().find(|id| blocked(id)) else {
+        return Vec::new();
+    };
+
+    let mut path = vec![*start];
+    loop {
+        let current = path[path.len() - 1];
+        let Some((_, previous)) = relations
+            .iter()
+            .find(|(recipe_process_id, output_of)| *recipe_process_id == current && blocked(output_of))
+        else {
+            return path;
+        };
+
+        if let Some(position) = path.iter().position(|id| id == previous) {
+            let mut cycle = path.split_off(position);
+            cycle.reverse();
+            return cycle;
+        }
+        path.push(*previous);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(n: usize) -> Vec<Uuid> {
+        (0..n).map(|_| Uuid::new_v4()).collect()
+    }
+
+    #[test]
+    fn orders_processes_by_depth() {
+        let p = ids(4);
+        // p0 -> p1 -> p3, p0 -> p2 -> p3, and p0 -> p3 directly
+        let relations = vec![(p[1], p[0]), (p[2], p[0]), (p[3], p[1]), (p[3], p[2]), (p[3], p[0])];
+
+        let order = topological_order(&[p[3], p[2], p[1], p[0]], &relations).unwrap();
+
+        assert_eq!(order, vec![(p[0], 0), (p[2], 1), (p[1], 1), (p[3], 2)]);
+    }
+
+    #[test]
+    fn names_the_loop() {
+        let p = ids(4);
+        // p0 -> p1 -> p2 -> p3 -> p1
+        let relations = vec![(p[1], p[0]), (p[2], p[1]), (p[3], p[2]), (p[1], p[3])];
+
+        let cycle = topological_order(&p, &relations).unwrap_err();
+
+        assert_eq!(cycle.len(), 3);
+        let start = cycle.iter().position(|id| *id == p[1]).unwrap();
+        let rotated: Vec<Uuid> = cycle.iter().cycle().skip(start).take(3).copied().collect();
+        assert_eq!(rotated, vec![p[1], p[2], p[3]]);
+    }
+}
@@ -1,4 +1,10 @@
+#![allow(clippy::module_inception)]
+
 pub mod process;
 pub mod flow;
 pub mod data_field;
-pub mod execution;
\ No newline at end of file
+pub mod execution;
+pub mod action;
+pub mod graph;
+pub mod validation;
+pub mod default_value;
@@ -3,7 +3,10 @@ use juniper::GraphQLObject;
 use uuid::Uuid;
 
 use crate::{
-    db::schema::{recipe_process_relations, recipe_processes}, templates::{recipe_flow_template::{ActionType, EventType, RoleType}}
+    common::resource_specification::ResourceSpecification, 
+    db::schema::{recipe_process_relations, recipe_processes}, 
+    recipe::recipe::Recipe, 
+    templates::recipe_flow_template::{ActionType, EventType, RoleType}
 };
 
 use super::flow::RecipeProcessFlowResponse;
@@ -120,6 +123,27 @@ impl RecipeProcessResponse {
     }
 }
 
+#[derive(GraphQLObject)]
+pub struct RecipeProcessesResponse {
+    pub recipe: Recipe,
+    pub resources: Vec<ResourceSpecification>,
+    pub recipe_processes: Vec<RecipeProcessResponse>,
+}
+
+impl RecipeProcessesResponse {
+    pub fn new(recipe: Recipe, resources: Vec<ResourceSpecification>) -> Self {
+        RecipeProcessesResponse {
+            recipe,
+            resources,
+            recipe_processes: Vec::new(),
+        }
+    }
+
+    pub fn add_recipe_process(&mut self, recipe_process: RecipeProcessResponse) {
+        self.recipe_processes.push(recipe_process);
+    }
+}
+
 
 #[derive(Queryable, GraphQLObject, Debug, Clone)]
 #[diesel(table_name = recipe_process_flows)]
@@ -0,0 +1,153 @@
+use bigdecimal::Signed;
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use juniper::GraphQLObject;
+use uuid::Uuid;
+
+use crate::{
+    common::economic_resource::Decimal,
+    templates::recipe_flow_template_data_field::{FieldClass, FieldType},
+};
+
+/// Why a submitted value was rejected, shown next to the input of the field
+#[derive(GraphQLObject, Debug, Clone, PartialEq)]
+pub struct FieldViolation {
+    pub data_field_id: Uuid,
+    pub field_identifier: String,
+    pub message: String,
+}
+
+impl FieldViolation {
+    pub fn new(data_field_id: Uuid, field_identifier: &str, message: String) -> Self {
+        FieldViolation {
+            data_field_id,
+            field_identifier: field_identifier.to_string(),
+            message,
+        }
+    }
+}
+
+/// Record a value points to, which has to exist
+#[derive(Debug, PartialEq, Eq)]
+pub enum ValueReference {
+    Agent(Uuid),
+    Location(Uuid),
+    ResourceSpecification(Uuid),
+    EconomicResource(Uuid),
+}
+
+/// Checks the value against the class and the type of its field, classes
+/// are checked first as they are stricter than the type they are shown with
+pub fn check_value(field_class: &FieldClass, field_type: &FieldType, value: &str) -> Result<Option<ValueReference>, String> {
+    let value = value.trim();
+
+    let reference = match field_class {
+        FieldClass::Agent => Some(ValueReference::Agent(parse_id(value)?)),
+        FieldClass::Location => Some(ValueReference::Location(parse_id(value)?)),
+        FieldClass::ResourceSpecification => Some(ValueReference::ResourceSpecification(parse_id(value)?)),
+        FieldClass::EconomicResource => Some(ValueReference::EconomicResource(parse_id(value)?)),
+        FieldClass::Quantity => {
+            match value.parse::<Decimal>() {
+                Ok(quantity) if quantity.0.is_positive() => {}
+                Ok(_) => return Err("Must be greater than zero".to_string()),
+                Err(_) => return Err("Must be a decimal number".to_string()),
+            }
+            None
+        }
+        FieldClass::HasPointInTime => {
+            parse_date(value)?;
+            None
+        }
+        _ => None,
+    };
+
+    match field_type {
+        FieldType::Number if reference.is_none() => match value.parse::<f64>() {
+            Ok(number) if number.is_finite() => {}
+            _ => return Err(format!("{} is not a number", value)),
+        },
+        FieldType::Date => {
+            parse_date(value)?;
+        }
+        FieldType::Select if value.is_empty() => return Err("Select one of the options".to_string()),
+        _ => {}
+    }
+
+    Ok(reference)
+}
+
+/// Checks a number against the bounds of its field, both inclusive. Values
+/// that aren't numbers are left to the check of the type
+pub fn check_range(value: &str, min_value: Option<&Decimal>, max_value: Option<&Decimal>) -> Result<(), String> {
+    let Ok(number) = value.parse::<Decimal>() else {
+        return Ok(());
+    };
+
+    match (min_value, max_value) {
+        (Some(min_value), Some(max_value)) if number < *min_value || number > *max_value => {
+            Err(format!("Must be between {} and {}", min_value, max_value))
+        }
+        (Some(min_value), _) if number < *min_value => Err(format!("Must be at least {}", min_value)),
+        (_, Some(max_value)) if number > *max_value => Err(format!("Must be at most {}", max_value)),
+        _ => Ok(()),
+    }
+}
+
+/// ISO-8601 date, or date and time with or without offset
+pub fn parse_date(value: &str) -> Result<NaiveDateTime, String> {
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(date_time.naive_utc());
+    }
+    if let Ok(date_time) = value.parse::<NaiveDateTime>() {
+        return Ok(date_time);
+    }
+    if let Ok(date) = value.parse::<NaiveDate>() {
+        return Ok(date.and_hms_opt(0, 0, 0).expect("Midnight is a valid time"));
+    }
+    Err(format!("{} is not an ISO-8601 date", value))
+}
+
+fn parse_id(value: &str) -> Result<Uuid, String> {
+    Uuid::parse_str(value).map_err(|_| format!("{} is not an id", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_classes_before_types() {
+        let id = Uuid::new_v4();
+        assert_eq!(
+            check_value(&FieldClass::Location, &FieldType::Select, &id.to_string()),
+            Ok(Some(ValueReference::Location(id)))
+        );
+        assert!(check_value(&FieldClass::Agent, &FieldType::Select, "acme").is_err());
+        assert!(check_value(&FieldClass::Quantity, &FieldType::Number, "0").is_err());
+        assert!(check_value(&FieldClass::Quantity, &FieldType::Number, "1.5.2").is_err());
+        assert_eq!(check_value(&FieldClass::Quantity, &FieldType::Number, "12.5"), Ok(None));
+        assert_eq!(check_value(&FieldClass::Quantity, &FieldType::Number, " 12 "), Ok(None));
+    }
+
+    #[test]
+    fn checks_types() {
+        assert_eq!(check_value(&FieldClass::Custom, &FieldType::Number, "-1.5"), Ok(None));
+        assert!(check_value(&FieldClass::Custom, &FieldType::Number, "NaN").is_err());
+        assert_eq!(check_value(&FieldClass::Custom, &FieldType::Date, "2026-10-18T08:00:00Z"), Ok(None));
+        assert_eq!(check_value(&FieldClass::HasPointInTime, &FieldType::Text, "2026-10-18"), Ok(None));
+        assert!(check_value(&FieldClass::Custom, &FieldType::Date, "18/10/2026").is_err());
+        assert_eq!(check_value(&FieldClass::Note, &FieldType::Text, "anything"), Ok(None));
+    }
+
+    #[test]
+    fn checks_ranges() {
+        let min = Decimal::from(0);
+        let max = Decimal::from(10);
+        assert_eq!(check_range("0", Some(&min), Some(&max)), Ok(()));
+        assert_eq!(check_range(" 10 ", Some(&min), Some(&max)), Ok(()));
+        assert!(check_range("10.5", Some(&min), Some(&max)).is_err());
+        assert!(check_range("-1", Some(&min), None).is_err());
+        assert_eq!(check_range("1000", Some(&min), None), Ok(()));
+        assert!(check_range("11", None, Some(&max)).is_err());
+        assert_eq!(check_range("not a number", Some(&min), Some(&max)), Ok(()));
+    }
+}
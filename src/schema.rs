@@ -5,6 +5,10 @@ pub mod sql_types {
     #[diesel(postgres_type(name = "action_type_enum"))]
     pub struct ActionTypeEnum;
 
+    #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "agent_role_enum"))]
+    pub struct AgentRoleEnum;
+
     #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "event_type_enum"))]
     pub struct EventTypeEnum;
@@ -25,6 +29,18 @@ pub mod sql_types {
     #[diesel(postgres_type(name = "flow_through_enum"))]
     pub struct FlowThroughEnum;
 
+    #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "location_type_enum"))]
+    pub struct LocationTypeEnum;
+
+    #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "option_source_enum"))]
+    pub struct OptionSourceEnum;
+
+    #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "resource_operation_enum"))]
+    pub struct ResourceOperationEnum;
+
     #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "resource_type_enum"))]
     pub struct ResourceTypeEnum;
@@ -33,9 +49,30 @@ pub mod sql_types {
     #[diesel(postgres_type(name = "role_type_enum"))]
     pub struct RoleTypeEnum;
 
+    #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "rule_kind_enum"))]
+    pub struct RuleKindEnum;
+
     #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "template_type_enum"))]
     pub struct TemplateTypeEnum;
+
+    #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "unit_dimension_enum"))]
+    pub struct UnitDimensionEnum;
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::AgentRoleEnum;
+
+    agent_memberships (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        agent_id -> Uuid,
+        created_at -> Timestamp,
+        role -> AgentRoleEnum,
+    }
 }
 
 diesel::table! {
@@ -46,6 +83,22 @@ diesel::table! {
         name -> Text,
         note -> Nullable<Text>,
         created_at -> Timestamp,
+        primary_location_id -> Nullable<Uuid>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+
+    api_keys (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        name -> Text,
+        prefix -> Text,
+        key_hash -> Text,
+        created_at -> Timestamp,
+        last_used_at -> Nullable<Timestamp>,
+        revoked_at -> Nullable<Timestamp>,
     }
 }
 
@@ -58,6 +111,9 @@ diesel::table! {
         lot_code -> Int4,
         reference_number -> Int4,
         created_at -> Timestamp,
+        lot_format -> Text,
+        lot_prefix -> Nullable<Text>,
+        lot_period -> Nullable<Text>,
     }
 }
 
@@ -69,25 +125,67 @@ diesel::table! {
         resource_specification_id -> Uuid,
         name -> Text,
         note -> Nullable<Text>,
-        accounting_quantity -> Int4,
-        on_hand_quantity -> Int4,
+        accounting_quantity -> Numeric,
+        on_hand_quantity -> Numeric,
         tracking_identifier -> Nullable<Text>,
-        current_location -> Text,
+        current_location -> Nullable<Uuid>,
         lot -> Nullable<Text>,
         contained_in -> Nullable<Uuid>,
         created_at -> Timestamp,
         reference_number -> Int4,
+        custodian_id -> Nullable<Uuid>,
     }
 }
 
 diesel::table! {
     use diesel::sql_types::*;
 
+    inventory_ledger_entries (id) {
+        id -> Uuid,
+        economic_resource_id -> Uuid,
+        resource_specification_id -> Uuid,
+        lot -> Nullable<Text>,
+        location_id -> Nullable<Uuid>,
+        agent_id -> Uuid,
+        accounting_delta -> Numeric,
+        on_hand_delta -> Numeric,
+        process_execution_id -> Nullable<Uuid>,
+        recorded_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::LocationTypeEnum;
+
     locations (id) {
         id -> Uuid,
         agent_id -> Uuid,
         name -> Text,
         value -> Text,
+        #[max_length = 13]
+        gln -> Nullable<Varchar>,
+        location_type -> LocationTypeEnum,
+        parent_id -> Nullable<Uuid>,
+        street_address -> Nullable<Text>,
+        locality -> Nullable<Text>,
+        region -> Nullable<Text>,
+        postal_code -> Nullable<Text>,
+        #[max_length = 2]
+        country_code -> Nullable<Bpchar>,
+        latitude -> Nullable<Float8>,
+        longitude -> Nullable<Float8>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+
+    lot_sequences (id) {
+        id -> Uuid,
+        agent_id -> Uuid,
+        period -> Text,
+        sequence -> Int4,
     }
 }
 
@@ -100,6 +198,34 @@ diesel::table! {
         name -> Text,
         #[sql_name = "type"]
         type_ -> TemplateTypeEnum,
+        deleted_at -> Nullable<Timestamp>,
+        created_by_agent -> Nullable<Uuid>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+
+    option_set_values (id) {
+        id -> Uuid,
+        option_set_id -> Uuid,
+        value -> Text,
+        label -> Text,
+        position -> Int4,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::OptionSourceEnum;
+    use super::sql_types::ResourceTypeEnum;
+
+    option_sets (id) {
+        id -> Uuid,
+        name -> Text,
+        source -> OptionSourceEnum,
+        resource_type -> Nullable<ResourceTypeEnum>,
+        created_at -> Timestamp,
     }
 }
 
@@ -113,6 +239,7 @@ diesel::table! {
         field_value -> Text,
         corrects -> Nullable<Uuid>,
         created_at -> Timestamp,
+        from_default -> Bool,
     }
 }
 
@@ -120,19 +247,20 @@ diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::ActionTypeEnum;
     use super::sql_types::RoleTypeEnum;
+    use super::sql_types::ResourceOperationEnum;
 
     process_executions (id) {
         id -> Uuid,
-        process_flow_id -> Uuid,
+        process_flow_id -> Nullable<Uuid>,
         action -> ActionTypeEnum,
         role_type -> RoleTypeEnum,
         resource_specification -> Nullable<Uuid>,
         resource_reference_number -> Nullable<Int4>,
-        resource_lot_number -> Nullable<Int4>,
-        resource_quantity -> Nullable<Int4>,
+        resource_lot_number -> Nullable<Text>,
+        resource_quantity -> Nullable<Numeric>,
         to_resource_specification -> Nullable<Uuid>,
         to_resource_reference_number -> Nullable<Int4>,
-        to_resource_lot_number -> Nullable<Int4>,
+        to_resource_lot_number -> Nullable<Text>,
         provider_agent -> Uuid,
         receiver_agent -> Uuid,
         at_location -> Nullable<Uuid>,
@@ -141,6 +269,29 @@ diesel::table! {
         created_at -> Timestamp,
         corrects -> Nullable<Uuid>,
         note -> Nullable<Text>,
+        economic_resource_id -> Nullable<Uuid>,
+        to_economic_resource_id -> Nullable<Uuid>,
+        batch_id -> Nullable<Uuid>,
+        epcis_event_id -> Nullable<Text>,
+        reversal -> Bool,
+        unit_id -> Nullable<Uuid>,
+        container_id -> Nullable<Uuid>,
+        operation -> Nullable<ResourceOperationEnum>,
+        performed_by -> Nullable<Uuid>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::RuleKindEnum;
+
+    recipe_flow_template_data_field_rules (id) {
+        id -> Uuid,
+        data_field_id -> Uuid,
+        kind -> RuleKindEnum,
+        expression -> Text,
+        message -> Nullable<Text>,
+        created_at -> Timestamp,
     }
 }
 
@@ -163,6 +314,10 @@ diesel::table! {
         flow_through -> Nullable<FlowThroughEnum>,
         inherits -> Nullable<Uuid>,
         accept_default -> Bool,
+        deleted_at -> Nullable<Timestamp>,
+        option_set_id -> Nullable<Uuid>,
+        min_value -> Nullable<Numeric>,
+        max_value -> Nullable<Numeric>,
     }
 }
 
@@ -191,6 +346,7 @@ diesel::table! {
         action -> ActionTypeEnum,
         identifier -> Text,
         interactions -> Nullable<Int4>,
+        deleted_at -> Nullable<Timestamp>,
     }
 }
 
@@ -214,6 +370,9 @@ diesel::table! {
         default_value -> Nullable<Text>,
         flow_through -> Nullable<FlowThroughEnum>,
         inherits -> Nullable<Uuid>,
+        option_set_id -> Nullable<Uuid>,
+        min_value -> Nullable<Numeric>,
+        max_value -> Nullable<Numeric>,
     }
 }
 
@@ -307,6 +466,8 @@ diesel::table! {
         trigger -> Nullable<ActionTypeEnum>,
         version -> Int4,
         overriden_by -> Nullable<Uuid>,
+        created_by_agent -> Nullable<Uuid>,
+        deleted_at -> Nullable<Timestamp>,
         created_by -> Nullable<Uuid>,
     }
 }
@@ -345,18 +506,64 @@ diesel::table! {
         created_at -> Timestamp,
         resource_type -> ResourceTypeEnum,
         unit_of_measure -> Text,
+        unit_id -> Nullable<Uuid>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::UnitDimensionEnum;
+
+    units (id) {
+        id -> Uuid,
+        label -> Text,
+        symbol -> Text,
+        dimension -> UnitDimensionEnum,
+        conversion_factor -> Numeric,
+        om2_uri -> Nullable<Text>,
+        created_at -> Timestamp,
+        unece_code -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+
+    users (id) {
+        id -> Uuid,
+        name -> Text,
+        email -> Text,
+        created_at -> Timestamp,
+        deployment_admin -> Bool,
     }
 }
 
+diesel::joinable!(agent_memberships -> agents (agent_id));
+diesel::joinable!(agent_memberships -> users (user_id));
+diesel::joinable!(api_keys -> users (user_id));
 diesel::joinable!(counters -> agents (agent_id));
+diesel::joinable!(economic_resources -> agents (custodian_id));
+diesel::joinable!(economic_resources -> locations (current_location));
 diesel::joinable!(economic_resources -> resource_specifications (resource_specification_id));
+diesel::joinable!(inventory_ledger_entries -> agents (agent_id));
+diesel::joinable!(inventory_ledger_entries -> economic_resources (economic_resource_id));
+diesel::joinable!(inventory_ledger_entries -> locations (location_id));
+diesel::joinable!(inventory_ledger_entries -> process_executions (process_execution_id));
+diesel::joinable!(inventory_ledger_entries -> resource_specifications (resource_specification_id));
 diesel::joinable!(locations -> agents (agent_id));
+diesel::joinable!(lot_sequences -> agents (agent_id));
+diesel::joinable!(option_set_values -> option_sets (option_set_id));
 diesel::joinable!(process_execution_custom_values -> process_executions (process_execution_id));
 diesel::joinable!(process_execution_custom_values -> recipe_process_flow_data_fields (field_id));
 diesel::joinable!(process_executions -> recipe_process_flows (process_flow_id));
+diesel::joinable!(process_executions -> units (unit_id));
+diesel::joinable!(process_executions -> users (performed_by));
+diesel::joinable!(recipe_flow_template_data_field_rules -> recipe_flow_template_data_fields (data_field_id));
+diesel::joinable!(recipe_flow_template_data_fields -> option_sets (option_set_id));
 diesel::joinable!(recipe_flow_template_data_fields -> recipe_flow_template_group_data_fields (group_id));
 diesel::joinable!(recipe_flow_template_data_fields -> recipe_flow_templates (recipe_flow_template_id));
 diesel::joinable!(recipe_flow_templates -> recipe_templates (recipe_template_id));
+diesel::joinable!(recipe_process_flow_data_fields -> option_sets (option_set_id));
 diesel::joinable!(recipe_process_flow_data_fields -> recipe_flow_template_data_fields (recipe_flow_template_data_field_id));
 diesel::joinable!(recipe_process_flow_data_fields -> recipe_process_flow_group_data_fields (group_id));
 diesel::joinable!(recipe_process_flow_data_fields -> recipe_process_flows (recipe_process_flow_id));
@@ -367,21 +574,29 @@ diesel::joinable!(recipe_processes -> recipes (recipe_id));
 diesel::joinable!(recipe_resources -> recipes (recipe_id));
 diesel::joinable!(recipe_resources -> resource_specifications (resource_specification_id));
 diesel::joinable!(recipe_template_blacklists -> map_templates (map_template_id));
-diesel::joinable!(recipe_templates -> agents (created_by));
+diesel::joinable!(recipe_templates -> agents (created_by_agent));
 diesel::joinable!(recipe_templates -> map_templates (map_template_id));
 diesel::joinable!(recipe_templates_access -> agents (agent_id));
 diesel::joinable!(recipe_templates_access -> recipe_templates (recipe_template_id));
 diesel::joinable!(recipes -> agents (agent_id));
 diesel::joinable!(resource_specifications -> agents (agent_id));
+diesel::joinable!(resource_specifications -> units (unit_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    agent_memberships,
     agents,
+    api_keys,
     counters,
     economic_resources,
+    inventory_ledger_entries,
     locations,
+    lot_sequences,
     map_templates,
+    option_set_values,
+    option_sets,
     process_execution_custom_values,
     process_executions,
+    recipe_flow_template_data_field_rules,
     recipe_flow_template_data_fields,
     recipe_flow_template_group_data_fields,
     recipe_flow_templates,
@@ -396,4 +611,6 @@ diesel::allow_tables_to_appear_in_same_query!(
     recipe_templates_access,
     recipes,
     resource_specifications,
+    units,
+    users,
 );
@@ -0,0 +1,88 @@
+use std::io::Write;
+
+use chrono::NaiveDateTime;
+use diesel::{
+    deserialize::{self, FromSql, FromSqlRow},
+    expression::AsExpression,
+    pg::{Pg, PgValue},
+    serialize::{self, IsNull, Output, ToSql},
+    Insertable, Queryable,
+};
+use juniper::{GraphQLEnum, GraphQLInputObject, GraphQLObject};
+use uuid::Uuid;
+
+use crate::db::schema::{recipe_flow_template_data_field_rules, sql_types::RuleKindEnum};
+
+/// What the expression of a rule decides for its field
+#[derive(Debug, PartialEq, FromSqlRow, AsExpression, Eq, GraphQLEnum, Clone, Copy)]
+#[diesel(sql_type = RuleKindEnum)]
+pub enum RuleKind {
+    /// The field is required while the expression holds
+    RequiredIf,
+    /// The field is shown, and may take a value, only while the expression holds
+    VisibleIf,
+    /// A value of the field is only accepted when the expression holds
+    Constraint,
+}
+
+impl ToSql<RuleKindEnum, Pg> for RuleKind {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        match *self {
+            RuleKind::RequiredIf => out.write_all(b"RequiredIf")?,
+            RuleKind::VisibleIf => out.write_all(b"VisibleIf")?,
+            RuleKind::Constraint => out.write_all(b"Constraint")?,
+        }
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<RuleKindEnum, Pg> for RuleKind {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"RequiredIf" => Ok(RuleKind::RequiredIf),
+            b"VisibleIf" => Ok(RuleKind::VisibleIf),
+            b"Constraint" => Ok(RuleKind::Constraint),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
+
+#[derive(Queryable, GraphQLObject, Debug, Clone)]
+#[diesel(table_name = recipe_flow_template_data_field_rules)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DataFieldRule {
+    pub id: Uuid,
+    pub data_field_id: Uuid,
+    pub kind: RuleKind,
+    pub expression: String,
+    pub message: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = recipe_flow_template_data_field_rules)]
+pub struct NewDataFieldRule<'a> {
+    pub data_field_id: &'a Uuid,
+    pub kind: &'a RuleKind,
+    pub expression: &'a str,
+    pub message: Option<&'a str>,
+}
+
+impl<'a> NewDataFieldRule<'a> {
+    pub fn new(data_field_id: &'a Uuid, kind: &'a RuleKind, expression: &'a str, message: Option<&'a str>) -> Self {
+        NewDataFieldRule {
+            data_field_id,
+            kind,
+            expression,
+            message,
+        }
+    }
+}
+
+#[derive(GraphQLInputObject, Debug, Clone)]
+pub struct DataFieldRuleArg {
+    pub kind: RuleKind,
+    /// For instance `referenceDocumentType == 'BOL'` or `buyer != $agent`
+    pub expression: String,
+    pub message: Option<String>,
+}
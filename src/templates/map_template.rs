@@ -1,3 +1,4 @@
+use chrono::NaiveDateTime;
 use std::io::Write;
 
 use diesel::{
@@ -45,28 +46,33 @@ impl FromSql<TemplateTypeEnum, Pg> for TemplateType {
 #[derive(Queryable, GraphQLObject, Debug)]
 #[diesel(table_name = map_templates)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
-
 pub struct MapTemplate {
     pub id: Uuid,
     pub name: String,
-    pub type_: TemplateType
+    pub type_: TemplateType,
+    pub deleted_at: Option<NaiveDateTime>,
+    /// Agent whose designers change the map, none for maps of the deployment
+    pub created_by_agent: Option<Uuid>
 }
 
 #[derive(Insertable)]
 #[diesel(table_name = map_templates)]
 pub struct NewMapTemplate<'a> {
     pub name: &'a str,
-    pub type_: &'a TemplateType
+    pub type_: &'a TemplateType,
+    pub created_by_agent: Option<&'a Uuid>
 }
 
 impl<'a> NewMapTemplate<'a> {
+    /// The owning agent is set on the result when given
     pub fn new(
         name: &'a str,
         type_: &'a TemplateType
     ) -> Self {
         NewMapTemplate {
             name,
-            type_
+            type_,
+            created_by_agent: None
         }
     }
 }
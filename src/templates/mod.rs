@@ -4,4 +4,7 @@ pub mod recipe_template_access;
 pub mod recipe_flow_template;
 pub mod recipe_flow_template_group_data_fields;
 pub mod recipe_flow_template_data_field;
-pub mod recipe_template_blacklist;
\ No newline at end of file
+pub mod recipe_template_blacklist;
+pub mod template_blocker;pub mod option_set;
+pub mod data_field_rule;
+pub mod rule_expression;
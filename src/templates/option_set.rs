@@ -0,0 +1,155 @@
+use std::io::Write;
+
+use chrono::NaiveDateTime;
+use diesel::{
+    deserialize::{self, FromSql, FromSqlRow},
+    expression::AsExpression,
+    pg::{Pg, PgValue},
+    serialize::{self, IsNull, Output, ToSql},
+    Insertable, Queryable,
+};
+use juniper::{GraphQLEnum, GraphQLInputObject, GraphQLObject};
+use uuid::Uuid;
+
+use crate::{
+    common::resource_specification::ResourceType,
+    db::schema::{option_set_values, option_sets, sql_types::OptionSourceEnum},
+};
+
+/// Where the choices of a Select field come from, every source but Static is resolved for an agent
+#[derive(Debug, PartialEq, FromSqlRow, AsExpression, Eq, GraphQLEnum, Clone, Copy)]
+#[diesel(sql_type = OptionSourceEnum)]
+pub enum OptionSource {
+    Static,
+    AgentLocations,
+    ResourceSpecifications,
+    LotsOnHand,
+}
+
+impl ToSql<OptionSourceEnum, Pg> for OptionSource {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        match *self {
+            OptionSource::Static => out.write_all(b"Static")?,
+            OptionSource::AgentLocations => out.write_all(b"AgentLocations")?,
+            OptionSource::ResourceSpecifications => out.write_all(b"ResourceSpecifications")?,
+            OptionSource::LotsOnHand => out.write_all(b"LotsOnHand")?,
+        }
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<OptionSourceEnum, Pg> for OptionSource {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"Static" => Ok(OptionSource::Static),
+            b"AgentLocations" => Ok(OptionSource::AgentLocations),
+            b"ResourceSpecifications" => Ok(OptionSource::ResourceSpecifications),
+            b"LotsOnHand" => Ok(OptionSource::LotsOnHand),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
+
+#[derive(Queryable, GraphQLObject, Debug, Clone)]
+#[diesel(table_name = option_sets)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct OptionSet {
+    pub id: Uuid,
+    pub name: String,
+    pub source: OptionSource,
+    /// Filters ResourceSpecifications sets, all types when empty
+    pub resource_type: Option<ResourceType>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = option_sets)]
+pub struct NewOptionSet<'a> {
+    pub name: &'a str,
+    pub source: &'a OptionSource,
+    pub resource_type: Option<&'a ResourceType>,
+}
+
+impl<'a> NewOptionSet<'a> {
+    pub fn new(name: &'a str, source: &'a OptionSource, resource_type: Option<&'a ResourceType>) -> Self {
+        NewOptionSet {
+            name,
+            source,
+            resource_type,
+        }
+    }
+}
+
+#[derive(Queryable, GraphQLObject, Debug, Clone)]
+#[diesel(table_name = option_set_values)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct OptionSetValue {
+    pub id: Uuid,
+    pub option_set_id: Uuid,
+    pub value: String,
+    pub label: String,
+    pub position: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = option_set_values)]
+pub struct NewOptionSetValue<'a> {
+    pub option_set_id: &'a Uuid,
+    pub value: &'a str,
+    pub label: &'a str,
+    pub position: &'a i32,
+}
+
+impl<'a> NewOptionSetValue<'a> {
+    pub fn new(option_set_id: &'a Uuid, value: &'a str, label: &'a str, position: &'a i32) -> Self {
+        NewOptionSetValue {
+            option_set_id,
+            value,
+            label,
+            position,
+        }
+    }
+}
+
+#[derive(GraphQLInputObject, Debug)]
+pub struct OptionSetValueInput {
+    pub value: String,
+    /// Shown instead of the value, the value itself when empty
+    pub label: Option<String>,
+}
+
+#[derive(GraphQLObject, Debug)]
+pub struct OptionSetResponse {
+    pub option_set: OptionSet,
+    /// Static options in display order, empty for dynamic sets
+    pub values: Vec<OptionSetValue>,
+}
+
+impl OptionSetResponse {
+    pub fn new(option_set: OptionSet) -> Self {
+        OptionSetResponse {
+            option_set,
+            values: Vec::new(),
+        }
+    }
+
+    pub fn add_value(&mut self, value: OptionSetValue) {
+        self.values.push(value)
+    }
+}
+
+/// One concrete choice of a Select field
+#[derive(GraphQLObject, Debug, Clone, PartialEq)]
+pub struct FieldOption {
+    pub value: String,
+    pub label: String,
+}
+
+/// Choices of a field of a process flow, resolved for an agent
+#[derive(GraphQLObject, Debug)]
+pub struct FieldOptions {
+    pub data_field_id: Uuid,
+    pub field_identifier: String,
+    pub option_set_id: Uuid,
+    pub options: Vec<FieldOption>,
+}
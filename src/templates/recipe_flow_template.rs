@@ -8,6 +8,7 @@ use diesel::{
     Insertable, 
     Queryable
 };
+use chrono::NaiveDateTime;
 use juniper::{GraphQLEnum, GraphQLObject};
 use uuid::Uuid;
 
@@ -128,7 +129,6 @@ impl FromSql<ActionTypeEnum, Pg> for ActionType {
 #[derive(Queryable, GraphQLObject, Debug)]
 #[diesel(table_name = recipe_flow_templates)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
-
 pub struct RecipeFlowTemplate {
     pub id: Uuid,
     pub recipe_template_id: Uuid,
@@ -137,6 +137,7 @@ pub struct RecipeFlowTemplate {
     pub action: ActionType,
     pub identifier: String,
     pub interactions: Option<i32>,
+    pub deleted_at: Option<NaiveDateTime>,
 }
 
 
@@ -208,7 +209,7 @@ impl RecipeFlowTemplateWithDataFields {
 
     pub fn add_group(&mut self, group: RecipeFlowTemplateGroupDataField) {
         let found_group = self.groups.iter().find(|g| g.id == group.id);
-        if let None = found_group {
+        if found_group.is_none() {
             self.groups.push(group);
         }
         
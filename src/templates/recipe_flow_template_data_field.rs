@@ -7,14 +7,20 @@ use diesel::{
     serialize::{self, IsNull, Output, ToSql},
     Insertable, Queryable,
 };
+use chrono::NaiveDateTime;
 use juniper::{GraphQLEnum, GraphQLObject};
 use uuid::Uuid;
 
-use crate::db::schema::{
-    recipe_flow_template_data_fields, 
-    sql_types::{FieldClassEnum, FieldTypeEnum, FlowThroughEnum}
+use crate::{
+    common::economic_resource::Decimal,
+    db::schema::{
+        recipe_flow_template_data_fields, 
+        sql_types::{FieldClassEnum, FieldTypeEnum, FlowThroughEnum}
+    }
 };
 
+use super::data_field_rule::DataFieldRule;
+
 #[derive(Debug, PartialEq, FromSqlRow, AsExpression, Eq, GraphQLEnum, Clone)]
 #[diesel(sql_type = FieldClassEnum)]
 pub enum FieldClass {
@@ -28,7 +34,8 @@ pub enum FieldClass {
     TrackingIdentifier,
     Custom,
     ReferenceDocumentNumber,
-    ReferenceDocumentType
+    ReferenceDocumentType,
+    UnitOfMeasure
 }
 
 impl ToSql<FieldClassEnum, Pg> for FieldClass {
@@ -45,6 +52,7 @@ impl ToSql<FieldClassEnum, Pg> for FieldClass {
             FieldClass::Custom => out.write_all(b"custom")?,
             FieldClass::ReferenceDocumentNumber => out.write_all(b"referenceDocumentNumber")?,
             FieldClass::ReferenceDocumentType => out.write_all(b"referenceDocumentType")?,
+            FieldClass::UnitOfMeasure => out.write_all(b"unitOfMeasure")?,
         }
         Ok(IsNull::No)
     }
@@ -64,6 +72,7 @@ impl FromSql<FieldClassEnum, Pg> for FieldClass {
             b"custom" => Ok(FieldClass::Custom),
             b"referenceDocumentNumber" => Ok(FieldClass::ReferenceDocumentNumber),
             b"referenceDocumentType" => Ok(FieldClass::ReferenceDocumentType),
+            b"unitOfMeasure" => Ok(FieldClass::UnitOfMeasure),
             _ => Err("Unrecognized enum variant".into()),
         }
     }
@@ -136,7 +145,6 @@ impl FromSql<FlowThroughEnum, Pg> for FlowThrough {
 #[derive(Queryable, GraphQLObject, Debug)]
 #[diesel(table_name = recipe_flow_template_data_fields)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
-
 pub struct RecipeFlowTemplateDataField {
     pub id: Uuid,
     pub recipe_flow_template_id: Uuid,
@@ -149,7 +157,13 @@ pub struct RecipeFlowTemplateDataField {
     pub required: bool,
     pub flow_through: Option<FlowThrough>,
     pub inherits: Option<Uuid>,
-    pub accept_default: bool
+    pub accept_default: bool,
+    pub deleted_at: Option<NaiveDateTime>,
+    pub option_set_id: Option<Uuid>,
+    /// Lowest value of a Number field
+    pub min_value: Option<Decimal>,
+    /// Highest value of a Number field
+    pub max_value: Option<Decimal>
 }
 
 
@@ -167,35 +181,38 @@ pub struct NewRecipeFlowTemplateDataField<'a> {
     pub required: &'a bool,
     pub flow_through: Option<&'a FlowThrough>,
     pub inherits: Option<&'a Uuid>,
-    pub accept_default: &'a bool
+    pub accept_default: &'a bool,
+    pub option_set_id: Option<&'a Uuid>,
+    pub min_value: Option<&'a Decimal>,
+    pub max_value: Option<&'a Decimal>
 }
 
 impl<'a> NewRecipeFlowTemplateDataField<'a> {
+    /// Group, note, flow through, inheritance, option set and range are set on the result when given
     pub fn new(
         recipe_flow_template_id: &'a Uuid,
-        group_id: Option<&'a Uuid>,
         field_identifier: &'a str,
         field_class: &'a FieldClass,
         field: &'a str,
         field_type: &'a FieldType,
-        note: Option<&'a str>,
         required: &'a bool,
-        flow_through: Option<&'a FlowThrough>,
-        inherits: Option<&'a Uuid>,
         accept_default: &'a bool
     ) -> Self {
         NewRecipeFlowTemplateDataField {
             recipe_flow_template_id,
-            group_id,
+            group_id: None,
             field_identifier,
             field_class,
             field,
             field_type,
-            note,
+            note: None,
             required,
-            flow_through,
-            inherits,
-            accept_default
+            flow_through: None,
+            inherits: None,
+            accept_default,
+            option_set_id: None,
+            min_value: None,
+            max_value: None
         }
     }
 }
@@ -213,7 +230,11 @@ pub struct RecipeFlowTemplateDataFieldInput {
     pub required: bool,
     pub flow_through: Option<FlowThrough>,
     pub inherits: Option<Uuid>,
-    pub accept_default: bool
+    pub accept_default: bool,
+    pub option_set_id: Option<Uuid>,
+    pub min_value: Option<Decimal>,
+    pub max_value: Option<Decimal>,
+    pub rules: Vec<DataFieldRule>
 }
 
 
@@ -233,7 +254,11 @@ impl TryFrom<&RecipeFlowTemplateDataField> for RecipeFlowTemplateDataFieldInput
             required: value.required,
             flow_through: value.flow_through.clone(),
             inherits: value.inherits,
-            accept_default: value.accept_default
+            accept_default: value.accept_default,
+            option_set_id: value.option_set_id,
+            min_value: value.min_value.clone(),
+            max_value: value.max_value.clone(),
+            rules: Vec::new()
         })
     }
 }
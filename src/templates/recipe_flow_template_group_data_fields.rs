@@ -55,7 +55,6 @@ impl FromSql<FieldGroupClassEnum, Pg> for FieldGroupClass {
 #[derive(Queryable, GraphQLObject, Debug)]
 #[diesel(table_name = recipe_flow_template_group_data_fields)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
-
 pub struct RecipeFlowTemplateGroupDataField {
     pub id: Uuid,
     pub name: String,
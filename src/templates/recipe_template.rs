@@ -1,3 +1,4 @@
+use chrono::NaiveDateTime;
 
 use diesel::{
     Insertable, 
@@ -9,13 +10,12 @@ use uuid::Uuid;
 use crate::db::schema::recipe_templates;
 
 
-use super::{recipe_flow_template::{ActionType, RecipeFlowTemplateWithDataFields}, recipe_template_blacklist::RecipeTemplateBlacklist};
+use super::recipe_flow_template::{ActionType, RecipeFlowTemplateWithDataFields};
 
 
 #[derive(Queryable, GraphQLObject, Debug)]
 #[diesel(table_name = recipe_templates)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
-
 pub struct RecipeTemplate {
     pub id: Uuid,
     pub map_template_id: Uuid,
@@ -23,10 +23,42 @@ pub struct RecipeTemplate {
     pub name: String,
     pub commitment: Option<ActionType>,
     pub fulfills: Option<Uuid>,
-    pub trigger: Option<ActionType>
+    pub trigger: Option<ActionType>,
+    pub version: i32,
+    /// Next version of the template, None for the latest version
+    pub overriden_by: Option<Uuid>,
+    /// Agent the version was created for
+    pub created_by_agent: Option<Uuid>,
+    pub deleted_at: Option<NaiveDateTime>,
+    /// User who created the version
+    pub created_by: Option<Uuid>
 }
 
 
+/// Follows the versions overriding the given one up to the latest version of
+/// its chain, None when the version isn't among the given ones
+pub fn latest_version(versions: &[RecipeTemplate], id: Uuid) -> Option<&RecipeTemplate> {
+    let mut current = versions.iter().find(|v| v.id == id)?;
+    // Bounded by the number of versions so a broken chain can't loop forever
+    for _ in 0..versions.len() {
+        match current.overriden_by.and_then(|next| versions.iter().find(|v| v.id == next)) {
+            Some(next) => current = next,
+            None => break,
+        }
+    }
+
+    Some(current)
+}
+
+impl RecipeTemplate {
+    /// Processes take the commitment and trigger of their template, one given
+    /// for the process has to be the same
+    pub fn keeps_actions(&self, commitment: Option<ActionType>, trigger: Option<ActionType>) -> bool {
+        commitment.is_none_or(|action| self.commitment == Some(action))
+            && trigger.is_none_or(|action| self.trigger == Some(action))
+    }
+}
+
 #[derive(Insertable)]
 #[diesel(table_name = recipe_templates)]
 pub struct NewRecipeTemplate<'a> {
@@ -35,7 +67,10 @@ pub struct NewRecipeTemplate<'a> {
     pub name: &'a str,
     pub commitment: Option<&'a ActionType>,
     pub fulfills: Option<&'a Uuid>,
-    pub trigger: Option<&'a ActionType>
+    pub trigger: Option<&'a ActionType>,
+    pub version: &'a i32,
+    pub created_by_agent: Option<&'a Uuid>,
+    pub created_by: Option<&'a Uuid>
 }
 
 impl<'a> NewRecipeTemplate<'a> {
@@ -53,7 +88,10 @@ impl<'a> NewRecipeTemplate<'a> {
             name,
             commitment,
             fulfills,
-            trigger
+            trigger,
+            version: &1,
+            created_by_agent: None,
+            created_by: None
         }
     }
 }
@@ -67,6 +105,13 @@ pub struct RecipeTemplateWithRecipeFlows {
     pub fulfills: Option<Uuid>,
     pub identifier: String,
     pub trigger: Option<ActionType>,
+    pub version: i32,
+    pub overriden_by: Option<Uuid>,
+    /// Agent the version was created for
+    pub created_by_agent: Option<Uuid>,
+    /// User who created the version
+    pub created_by: Option<Uuid>,
+    pub deleted_at: Option<NaiveDateTime>,
     pub recipe_flows: Vec<RecipeFlowTemplateWithDataFields>
 }
 
@@ -80,6 +125,11 @@ impl RecipeTemplateWithRecipeFlows {
             fulfills: recipe_template.fulfills,
             identifier: recipe_template.identifier.clone(),
             trigger: recipe_template.trigger,
+            version: recipe_template.version,
+            overriden_by: recipe_template.overriden_by,
+            created_by_agent: recipe_template.created_by_agent,
+            created_by: recipe_template.created_by,
+            deleted_at: recipe_template.deleted_at,
             recipe_flows: Vec::new()
         }
     }
@@ -87,4 +137,67 @@ impl RecipeTemplateWithRecipeFlows {
     pub fn add_recipe_flow(&mut self, recipe_flow: RecipeFlowTemplateWithDataFields) {
         self.recipe_flows.push(recipe_flow)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(version: i32, overriden_by: Option<Uuid>) -> RecipeTemplate {
+        RecipeTemplate {
+            id: Uuid::new_v4(),
+            map_template_id: Uuid::nil(),
+            identifier: "harvest".to_string(),
+            name: "Harvest".to_string(),
+            commitment: None,
+            fulfills: None,
+            trigger: None,
+            version,
+            overriden_by,
+            created_by_agent: None,
+            deleted_at: None,
+            created_by: None,
+        }
+    }
+
+    #[test]
+    fn follows_the_chain_to_the_latest_version() {
+        let third = version(3, None);
+        let second = version(2, Some(third.id));
+        let first = version(1, Some(second.id));
+        let other = version(1, None);
+        let (first_id, third_id, other_id) = (first.id, third.id, other.id);
+        let versions = vec![third, other, first, second];
+
+        assert_eq!(latest_version(&versions, first_id).map(|v| v.id), Some(third_id));
+        assert_eq!(latest_version(&versions, third_id).map(|v| v.id), Some(third_id));
+        assert_eq!(latest_version(&versions, other_id).map(|v| v.id), Some(other_id));
+        assert!(latest_version(&versions, Uuid::new_v4()).is_none());
+    }
+
+    #[test]
+    fn processes_keep_the_actions_of_their_template() {
+        let mut template = version(1, None);
+        template.commitment = Some(ActionType::Transfer);
+
+        assert!(template.keeps_actions(None, None));
+        assert!(template.keeps_actions(Some(ActionType::Transfer), None));
+        assert!(!template.keeps_actions(Some(ActionType::Produce), None));
+        assert!(!template.keeps_actions(None, Some(ActionType::Transfer)));
+    }
+
+    #[test]
+    fn stops_on_broken_chains() {
+        // A version overridden by one that isn't loaded is the latest known
+        let first = version(1, Some(Uuid::new_v4()));
+        let first_id = first.id;
+        assert_eq!(latest_version(&[first], first_id).map(|v| v.id), Some(first_id));
+
+        // Versions overriding each other don't loop
+        let mut second = version(2, None);
+        let first = version(1, Some(second.id));
+        second.overriden_by = Some(first.id);
+        let first_id = first.id;
+        assert!(latest_version(&[first, second], first_id).is_some());
+    }
+}
@@ -12,7 +12,6 @@ use crate::db::schema::recipe_template_blacklists;
 #[derive(Queryable, GraphQLObject, Debug)]
 #[diesel(table_name = recipe_template_blacklists)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
-
 pub struct RecipeTemplateBlacklist {
     pub id: Uuid,
     pub map_template_id: Uuid,
@@ -40,4 +39,44 @@ impl<'a> NewRecipeTemplateBlacklist<'a> {
             recipe_template_predecesor_id
         }
     }
-}
\ No newline at end of file
+}
+
+/// True when the successor is blacklisted after the predecessor. Blacklists
+/// are carried over to every new version, so only the rows of the latest
+/// versions are looked at
+pub fn is_blacklisted(blacklists: &[RecipeTemplateBlacklist], successor_id: Uuid, predecessor_id: Uuid) -> bool {
+    blacklists
+        .iter()
+        .any(|b| b.recipe_template_id == successor_id && b.recipe_template_predecesor_id == predecessor_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blacklist(successor_id: Uuid, predecessor_id: Uuid) -> RecipeTemplateBlacklist {
+        RecipeTemplateBlacklist {
+            id: Uuid::new_v4(),
+            map_template_id: Uuid::new_v4(),
+            recipe_template_id: successor_id,
+            recipe_template_predecesor_id: predecessor_id,
+        }
+    }
+
+    #[test]
+    fn matches_the_pair_in_order() {
+        let (successor, predecessor) = (Uuid::new_v4(), Uuid::new_v4());
+        let blacklists = vec![blacklist(successor, predecessor)];
+
+        assert!(is_blacklisted(&blacklists, successor, predecessor));
+        assert!(!is_blacklisted(&blacklists, predecessor, successor));
+    }
+
+    #[test]
+    fn ignores_rows_of_other_versions() {
+        let (old_successor, successor, predecessor) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let blacklists = vec![blacklist(old_successor, predecessor)];
+
+        assert!(!is_blacklisted(&blacklists, successor, predecessor));
+    }
+}
@@ -0,0 +1,395 @@
+//! Rule language of template data fields
+//!
+//! ```text
+//! expression := or
+//! or         := and ("or" and)*
+//! and        := not ("and" not)*
+//! not        := "not" not | "(" expression ")" | comparison
+//! comparison := operand (("==" | "!=" | "<" | "<=" | ">" | ">=") operand | "in" "[" operand ("," operand)* "]")?
+//! operand    := field identifier | $variable | 'text' | "text" | number
+//! ```
+//!
+//! A field on its own holds when it has a value. Empty fields equal the empty
+//! text, numbers are compared as numbers and ids regardless of case.
+
+use std::cmp::Ordering;
+
+use uuid::Uuid;
+
+/// Variables the caller provides next to the values of the fields
+pub const VARIABLES: [&str; 1] = ["$agent"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Field(String),
+    Variable(String),
+    Literal(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Operand, Comparison, Operand),
+    In(Operand, Vec<Operand>),
+    Present(Operand),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Variable(String),
+    Text(String),
+    Number(String),
+    Op(Comparison),
+    Open,
+    Close,
+    OpenList,
+    CloseList,
+    Comma,
+}
+
+pub fn parse(source: &str) -> Result<Expr, String> {
+    let tokens = tokenize(source)?;
+    if tokens.is_empty() {
+        return Err("Empty expression".to_string());
+    }
+    let mut parser = Parser { tokens, position: 0 };
+    let expr = parser.or()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(token) => Err(format!("Unexpected {}", describe(token))),
+    }
+}
+
+impl Expr {
+    /// Fields the expression reads, in order of appearance
+    pub fn fields(&self) -> Vec<&str> {
+        let mut fields = Vec::new();
+        self.visit(&mut |operand| {
+            if let Operand::Field(name) = operand {
+                fields.push(name.as_str());
+            }
+        });
+        fields
+    }
+
+    pub fn variables(&self) -> Vec<&str> {
+        let mut variables = Vec::new();
+        self.visit(&mut |operand| {
+            if let Operand::Variable(name) = operand {
+                variables.push(name.as_str());
+            }
+        });
+        variables
+    }
+
+    /// Evaluates the expression with the values of the fields and variables, variables are looked up with their `$`
+    pub fn evaluate<F: Fn(&str) -> Option<String>>(&self, value_of: &F) -> bool {
+        match self {
+            Expr::Or(left, right) => left.evaluate(value_of) || right.evaluate(value_of),
+            Expr::And(left, right) => left.evaluate(value_of) && right.evaluate(value_of),
+            Expr::Not(expr) => !expr.evaluate(value_of),
+            Expr::Present(operand) => !resolve(operand, value_of).is_empty(),
+            Expr::Compare(left, comparison, right) => {
+                let ordering = compare(&resolve(left, value_of), &resolve(right, value_of));
+                match comparison {
+                    Comparison::Eq => ordering == Some(Ordering::Equal),
+                    Comparison::Ne => ordering != Some(Ordering::Equal),
+                    Comparison::Lt => ordering == Some(Ordering::Less),
+                    Comparison::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+                    Comparison::Gt => ordering == Some(Ordering::Greater),
+                    Comparison::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+                }
+            }
+            Expr::In(operand, list) => {
+                let value = resolve(operand, value_of);
+                list.iter()
+                    .any(|item| compare(&value, &resolve(item, value_of)) == Some(Ordering::Equal))
+            }
+        }
+    }
+
+    fn visit<'a>(&'a self, f: &mut dyn FnMut(&'a Operand)) {
+        match self {
+            Expr::Or(left, right) | Expr::And(left, right) => {
+                left.visit(f);
+                right.visit(f);
+            }
+            Expr::Not(expr) => expr.visit(f),
+            Expr::Compare(left, _, right) => {
+                f(left);
+                f(right);
+            }
+            Expr::In(operand, list) => {
+                f(operand);
+                for item in list {
+                    f(item);
+                }
+            }
+            Expr::Present(operand) => f(operand),
+        }
+    }
+}
+
+fn resolve<F: Fn(&str) -> Option<String>>(operand: &Operand, value_of: &F) -> String {
+    match operand {
+        Operand::Field(name) | Operand::Variable(name) => value_of(name).unwrap_or_default().trim().to_string(),
+        Operand::Literal(value) => value.clone(),
+    }
+}
+
+/// Numbers and ids are compared as such, other values as text. Only numbers
+/// and text order, a number never orders against text
+fn compare(left: &str, right: &str) -> Option<Ordering> {
+    if let (Ok(left), Ok(right)) = (left.parse::<f64>(), right.parse::<f64>()) {
+        return left.partial_cmp(&right);
+    }
+    if let (Ok(left), Ok(right)) = (Uuid::parse_str(left), Uuid::parse_str(right)) {
+        return (left == right).then_some(Ordering::Equal);
+    }
+    if left.parse::<f64>().is_ok() != right.parse::<f64>().is_ok() && !left.is_empty() && !right.is_empty() {
+        return None;
+    }
+    Some(left.cmp(right))
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' | '[' | ']' | ',' => {
+                chars.next();
+                tokens.push(match c {
+                    '(' => Token::Open,
+                    ')' => Token::Close,
+                    '[' => Token::OpenList,
+                    ']' => Token::CloseList,
+                    _ => Token::Comma,
+                });
+            }
+            '=' | '!' | '<' | '>' => {
+                chars.next();
+                let equals = chars.next_if_eq(&'=').is_some();
+                tokens.push(Token::Op(match (c, equals) {
+                    ('=', true) => Comparison::Eq,
+                    ('!', true) => Comparison::Ne,
+                    ('<', false) => Comparison::Lt,
+                    ('<', true) => Comparison::Le,
+                    ('>', false) => Comparison::Gt,
+                    ('>', true) => Comparison::Ge,
+                    _ => return Err(format!("Unknown operator {}, compare with ==", c)),
+                }));
+            }
+            '\'' | '"' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some(next) if next == c => break,
+                        Some(next) => text.push(next),
+                        None => return Err(format!("Unterminated text {}{}", c, text)),
+                    }
+                }
+                tokens.push(Token::Text(text));
+            }
+            '$' => {
+                chars.next();
+                let name = take_word(&mut chars);
+                if name.is_empty() {
+                    return Err("Expected a variable name after $".to_string());
+                }
+                tokens.push(Token::Variable(format!("${}", name)));
+            }
+            c if c.is_ascii_digit() || c == '-' => {
+                let mut number = String::new();
+                while let Some(next) = chars.next_if(|n| n.is_ascii_digit() || *n == '.' || (*n == '-' && number.is_empty())) {
+                    number.push(next);
+                }
+                if number.parse::<f64>().is_err() {
+                    return Err(format!("{} is not a number", number));
+                }
+                tokens.push(Token::Number(number));
+            }
+            c if c.is_alphabetic() || c == '_' => tokens.push(Token::Word(take_word(&mut chars))),
+            c => return Err(format!("Unexpected character {}", c)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn take_word(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut word = String::new();
+    while let Some(next) = chars.next_if(|n| n.is_alphanumeric() || *n == '_') {
+        word.push(next);
+    }
+    word
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Word(word) => word.clone(),
+        Token::Variable(name) => name.clone(),
+        Token::Text(text) => format!("'{}'", text),
+        Token::Number(number) => number.clone(),
+        Token::Op(comparison) => format!("{:?}", comparison),
+        Token::Open => "(".to_string(),
+        Token::Close => ")".to_string(),
+        Token::OpenList => "[".to_string(),
+        Token::CloseList => "]".to_string(),
+        Token::Comma => ",".to_string(),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Word(word)) if word == keyword) {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(format!("Expected {} but found {}", describe(&expected), describe(&token))),
+            None => Err(format!("Expected {} at the end", describe(&expected))),
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.not()?;
+        while self.keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.keyword("not") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        if self.peek() == Some(&Token::Open) {
+            self.position += 1;
+            let expr = self.or()?;
+            self.expect(Token::Close)?;
+            return Ok(expr);
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let left = self.operand()?;
+        if let Some(Token::Op(comparison)) = self.peek() {
+            let comparison = *comparison;
+            self.position += 1;
+            return Ok(Expr::Compare(left, comparison, self.operand()?));
+        }
+        if self.keyword("in") {
+            self.expect(Token::OpenList)?;
+            let mut list = vec![self.operand()?];
+            while self.peek() == Some(&Token::Comma) {
+                self.position += 1;
+                list.push(self.operand()?);
+            }
+            self.expect(Token::CloseList)?;
+            return Ok(Expr::In(left, list));
+        }
+        Ok(Expr::Present(left))
+    }
+
+    fn operand(&mut self) -> Result<Operand, String> {
+        match self.next() {
+            Some(Token::Word(word)) if ["and", "or", "not", "in"].contains(&word.as_str()) => {
+                Err(format!("Expected a field or a value but found {}", word))
+            }
+            Some(Token::Word(word)) => Ok(Operand::Field(word)),
+            Some(Token::Variable(name)) => Ok(Operand::Variable(name)),
+            Some(Token::Text(text)) => Ok(Operand::Literal(text)),
+            Some(Token::Number(number)) => Ok(Operand::Literal(number)),
+            Some(token) => Err(format!("Expected a field or a value but found {}", describe(&token))),
+            None => Err("Expected a field or a value at the end".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(name: &str) -> Option<String> {
+        match name {
+            "referenceDocumentType" => Some("BOL".to_string()),
+            "quantity" => Some("12".to_string()),
+            "buyer" => Some("6F9619FF-8B86-D011-B42D-00C04FC964FF".to_string()),
+            "$agent" => Some("6f9619ff-8b86-d011-b42d-00c04fc964ff".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn evaluates_comparisons() {
+        let holds = |source: &str| parse(source).unwrap().evaluate(&values);
+        assert!(holds("referenceDocumentType == 'BOL'"));
+        assert!(holds("referenceDocumentType in ['BOL', \"ASN\"] and quantity > 9.5"));
+        assert!(!holds("quantity <= 10 or not referenceDocumentType"));
+        assert!(holds("not (note or lot != '')"));
+        assert!(!holds("buyer != $agent"));
+        assert!(!holds("quantity < 'ten'") && !holds("quantity >= 'ten'"));
+    }
+
+    #[test]
+    fn parses_fields_and_rejects_syntax_errors() {
+        let expr = parse("(a == 'x' or b) and c in [d, $agent]").unwrap();
+        assert_eq!(expr.fields(), vec!["a", "b", "c", "d"]);
+        assert_eq!(expr.variables(), vec!["$agent"]);
+
+        assert_eq!(parse("a == 'BOL' b"), Err("Unexpected b".to_string()));
+        assert!(parse("a = 'BOL'").is_err());
+        assert!(parse("(a and b").is_err());
+        assert!(parse("a in []").is_err());
+        assert!(parse("'unterminated").is_err());
+        assert!(parse("").is_err());
+    }
+}
@@ -0,0 +1,88 @@
+use juniper::{GraphQLEnum, GraphQLObject};
+use uuid::Uuid;
+
+/// Level of the template tree
+#[derive(Debug, PartialEq, Eq, GraphQLEnum, Clone, Copy)]
+pub enum TemplateItem {
+    MapTemplate,
+    RecipeTemplate,
+    RecipeFlowTemplate,
+    DataField,
+}
+
+/// What still references a template item
+#[derive(Debug, PartialEq, Eq, GraphQLEnum, Clone, Copy)]
+pub enum BlockerKind {
+    /// A recipe process created from the item
+    RecipeProcess,
+    /// Executions recorded against a process created from the item
+    ProcessExecution,
+    /// A template outside the deleted items that fulfills one of them
+    Fulfillment,
+    /// A data field outside the deleted items that inherits from one of them
+    Inheritance,
+}
+
+#[derive(GraphQLObject, Debug, Clone)]
+pub struct TemplateBlocker {
+    pub kind: BlockerKind,
+    pub id: Uuid,
+    pub description: String,
+    /// Agent whose recipe the process belongs to, none for blockers between templates
+    #[graphql(skip)]
+    pub agent_id: Option<Uuid>,
+}
+
+impl TemplateBlocker {
+    pub fn new(kind: BlockerKind, id: Uuid, description: String) -> Self {
+        TemplateBlocker {
+            kind,
+            id,
+            description,
+            agent_id: None,
+        }
+    }
+
+    pub fn of_agent(mut self, agent_id: Uuid) -> Self {
+        self.agent_id = Some(agent_id);
+        self
+    }
+
+    /// Hides what the recipe is called from users outside the agent
+    pub fn redacted(mut self) -> Self {
+        self.description = match self.kind {
+            BlockerKind::ProcessExecution => "Executions of a process of another agent".to_string(),
+            _ => "Process of a recipe of another agent".to_string(),
+        };
+        self
+    }
+}
+
+#[derive(GraphQLObject, Debug)]
+pub struct TemplateDeletion {
+    pub item: TemplateItem,
+    pub id: Uuid,
+    /// Every soft deleted map, recipe, flow and data field template
+    pub deleted: Vec<Uuid>,
+    /// References kept as history when the deletion was cascaded
+    pub detached: Vec<TemplateBlocker>,
+}
+
+impl TemplateDeletion {
+    pub fn new(item: TemplateItem, id: Uuid) -> Self {
+        TemplateDeletion {
+            item,
+            id,
+            deleted: Vec::new(),
+            detached: Vec::new(),
+        }
+    }
+
+    pub fn add_deleted(&mut self, id: Uuid) {
+        self.deleted.push(id)
+    }
+
+    pub fn add_detached(&mut self, blocker: TemplateBlocker) {
+        self.detached.push(blocker)
+    }
+}
@@ -0,0 +1,182 @@
+use std::collections::{hash_map::Entry, HashMap, VecDeque};
+
+use juniper::{GraphQLEnum, GraphQLObject};
+use uuid::Uuid;
+
+use crate::{
+    common::{agent::Agent, economic_resource::EconomicResource, location::Location},
+    templates::recipe_flow_template::ActionType,
+};
+
+#[derive(GraphQLEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceDirection {
+    /// Where did the lot go
+    Forward,
+    /// What went into the lot
+    Backward,
+}
+
+#[derive(GraphQLEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceMode {
+    /// Only the immediate trading partners and transformations, one up or one down
+    OneStep,
+    /// The whole transitive genealogy
+    Full,
+}
+
+#[derive(GraphQLObject, Debug)]
+pub struct GenealogyNode {
+    pub resource: EconomicResource,
+    /// Number of steps from the traced lot, 0 for the traced lot itself
+    pub depth: i32,
+}
+
+/// A step between two resources, backed by the execution that links them
+#[derive(GraphQLObject, Debug)]
+pub struct GenealogyLink {
+    pub from_resource_id: Uuid,
+    pub to_resource_id: Uuid,
+    pub process_execution_id: Uuid,
+    pub action: ActionType,
+}
+
+#[derive(GraphQLObject, Debug)]
+pub struct GenealogyResponse {
+    pub direction: TraceDirection,
+    pub mode: TraceMode,
+    pub nodes: Vec<GenealogyNode>,
+    pub links: Vec<GenealogyLink>,
+    /// Distinct lots found in the genealogy, traced lot included
+    pub lots: Vec<String>,
+    pub agents: Vec<Agent>,
+    pub locations: Vec<Location>,
+}
+
+impl GenealogyResponse {
+    pub fn new(direction: TraceDirection, mode: TraceMode) -> Self {
+        GenealogyResponse {
+            direction,
+            mode,
+            nodes: Vec::new(),
+            links: Vec::new(),
+            lots: Vec::new(),
+            agents: Vec::new(),
+            locations: Vec::new(),
+        }
+    }
+
+    pub fn add_node(&mut self, resource: EconomicResource, depth: i32) {
+        if let Some(lot) = &resource.lot {
+            if !self.lots.contains(lot) {
+                self.lots.push(lot.clone());
+            }
+        }
+        self.nodes.push(GenealogyNode { resource, depth })
+    }
+
+    pub fn add_link(&mut self, link: GenealogyLink) {
+        self.links.push(link)
+    }
+
+    pub fn add_agent(&mut self, agent: Agent) {
+        self.agents.push(agent)
+    }
+
+    pub fn add_location(&mut self, location: Location) {
+        self.locations.push(location)
+    }
+}
+
+impl ActionType {
+    /// Input actions whose resource ends up in the outputs of the same process run
+    pub fn is_ingredient(&self) -> bool {
+        matches!(self, ActionType::Consume | ActionType::Load | ActionType::Accept)
+    }
+
+    /// Output actions that create or add to a resource from the inputs of the process run
+    pub fn is_product(&self) -> bool {
+        matches!(self, ActionType::Produce | ActionType::Modify | ActionType::Unload)
+    }
+}
+
+/// Resources a trace reached with their depth, in the order they were found, and
+/// the steps taken from each resource to the next
+pub struct Walk<S> {
+    pub reached: Vec<(Uuid, i32)>,
+    pub steps: Vec<(Uuid, Uuid, S)>,
+}
+
+/// Walks breadth first from the origin, every resource is followed once at the
+/// depth it was first reached. One step mode only follows the origin
+pub fn walk<S, E>(
+    origin: &[Uuid],
+    mode: TraceMode,
+    mut next: impl FnMut(Uuid) -> Result<Vec<(Uuid, S)>, E>,
+) -> Result<Walk<S>, E> {
+    let mut depths: HashMap<Uuid, i32> = HashMap::new();
+    let mut reached = Vec::new();
+    let mut queue: VecDeque<(Uuid, i32)> = VecDeque::new();
+    for resource_id in origin {
+        if depths.insert(*resource_id, 0).is_none() {
+            reached.push((*resource_id, 0));
+            queue.push_back((*resource_id, 0));
+        }
+    }
+
+    let mut steps = Vec::new();
+    while let Some((resource_id, depth)) = queue.pop_front() {
+        if mode == TraceMode::OneStep && depth >= 1 {
+            continue;
+        }
+
+        for (next_id, step) in next(resource_id)? {
+            if let Entry::Vacant(entry) = depths.entry(next_id) {
+                entry.insert(depth + 1);
+                reached.push((next_id, depth + 1));
+                queue.push_back((next_id, depth + 1));
+            }
+            steps.push((resource_id, next_id, step));
+        }
+    }
+
+    Ok(Walk { reached, steps })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trace(edges: &[(Uuid, Uuid)], origin: &[Uuid], mode: TraceMode) -> Walk<()> {
+        walk(origin, mode, |resource_id| {
+            Ok::<_, ()>(
+                edges
+                    .iter()
+                    .filter(|(from, _)| *from == resource_id)
+                    .map(|(_, to)| (*to, ()))
+                    .collect(),
+            )
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn reaches_every_resource_once_at_its_shortest_depth() {
+        let [lot, blend, pallet, shipment] = [Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4()];
+        // The pallet is reached directly and through the blend, the shipment loops back to the lot
+        let edges = [(lot, blend), (lot, pallet), (blend, pallet), (pallet, shipment), (shipment, lot)];
+
+        let full = trace(&edges, &[lot], TraceMode::Full);
+        assert_eq!(full.reached, vec![(lot, 0), (blend, 1), (pallet, 1), (shipment, 2)]);
+        assert_eq!(full.steps.len(), edges.len());
+    }
+
+    #[test]
+    fn one_step_only_follows_the_origin() {
+        let [lot, blend, pallet] = [Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4()];
+        let edges = [(lot, blend), (blend, pallet)];
+
+        let one_step = trace(&edges, &[lot, lot], TraceMode::OneStep);
+        assert_eq!(one_step.reached, vec![(lot, 0), (blend, 1)]);
+        assert_eq!(one_step.steps.len(), 1);
+    }
+}